@@ -0,0 +1,85 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use tetris::game::{self, BlockType, Game, ReplayInput, TickChange};
+
+/// Returns the game with `rows` bottom rows filled except the column `hole`.
+fn game_with_rows(rows: usize, hole: usize) -> Game {
+    let mut game = Game::with_seed(game::GameRules::new(), 0);
+    for y in game::BOARD_HEIGHT - rows..game::BOARD_HEIGHT {
+        for x in (0..game::BOARD_WIDTH).filter(|&x| x != hole) {
+            game.debug_toggle_cell(game::board_position(x, y));
+        }
+    }
+    game
+}
+
+fn tick_empty_board(c: &mut Criterion) {
+    let input = ReplayInput::default();
+    let mut game = Game::with_seed(game::GameRules::new(), 0);
+    c.bench_function("tick empty board", |b| {
+        b.iter(|| {
+            if game.is_game_over() {
+                game = Game::with_seed(game::GameRules::new(), 0);
+            }
+            game.tick(&input)
+        })
+    });
+}
+
+fn tick_dense_board(c: &mut Criterion) {
+    let input = ReplayInput::default();
+    for rows in [8, 16] {
+        c.bench_function(&format!("tick {} filled rows", rows), |b| {
+            b.iter_batched_ref(
+                || game_with_rows(rows, 0),
+                |game| game.tick(&input),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+fn tick_rotations(c: &mut Criterion) {
+    let pressed = ReplayInput::new(false, false, true, false, false, false, false);
+    let released = ReplayInput::default();
+    let mut game = Game::with_seed(game::GameRules::new(), 0);
+    c.bench_function("tick rotations", |b| {
+        b.iter(|| {
+            if game.is_game_over() {
+                game = Game::with_seed(game::GameRules::new(), 0);
+            }
+            // rotation is triggered by the press, release the key every other tick
+            game.tick(&pressed);
+            game.tick(&released)
+        })
+    });
+}
+
+fn tick_line_clears(c: &mut Criterion) {
+    let input = ReplayInput::new(false, false, false, true, false, false, false);
+    c.bench_function("drop I block clearing 4 rows", |b| {
+        b.iter_batched_ref(
+            || {
+                // the vertical I block spawns in the column 4
+                let mut game = game_with_rows(4, 4);
+                game.debug_spawn_block(BlockType::I);
+                game
+            },
+            |game| loop {
+                let changes = game.tick(&input);
+                if changes.iter().any(|c| matches!(c, TickChange::BlockLocked)) {
+                    break changes;
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(
+    benches,
+    tick_empty_board,
+    tick_dense_board,
+    tick_rotations,
+    tick_line_clears
+);
+criterion_main!(benches);
@@ -0,0 +1,294 @@
+//! Headless bot which plays games using `Game::legal_placements` and `BoardMetrics`, and prints
+//! how many lines it clears per game.
+//!
+//! `cargo run --release --example bot -- [--games=<n>] [--beam=<width>] [--depth=<blocks>]`
+//!
+//! The default depth of 1 places every block greedily, deeper searches keep the `width` best
+//! boards after every block of the next blocks queue.
+
+use std::time::Instant;
+
+use tetris::game::{
+    self, board_index, board_position, BlockType, BoardMetrics, Game, GameRules, Gravity, Move,
+    Placement, Position, BOARD_HEIGHT, BOARD_WIDTH, NEXT_BLOCKS_COUNT,
+};
+
+// weights of the board evaluation, the same as of the bundled `game::Bot`
+const HEIGHT_WEIGHT: f32 = -0.51;
+const LINES_WEIGHT: f32 = 0.76;
+const HOLES_WEIGHT: f32 = -0.36;
+const BUMPINESS_WEIGHT: f32 = -0.18;
+
+/// Games are stopped after ten minutes of play.
+const MAX_TICKS: u32 = 60 * 60 * 10;
+
+type Board = [[bool; BOARD_WIDTH]; BOARD_HEIGHT];
+
+struct Options {
+    games: u64,
+    beam_width: usize,
+    depth: usize,
+}
+
+impl Options {
+    fn from_args(args: &[String]) -> Self {
+        let value = |name: &str, default: usize| {
+            args.iter()
+                .find_map(|a| a.strip_prefix(name)?.strip_prefix('='))
+                .map(|v| v.parse().expect("invalid number"))
+                .unwrap_or(default)
+        };
+        Self {
+            games: value("--games", 10) as u64,
+            beam_width: value("--beam", 8).max(1),
+            depth: value("--depth", 1).clamp(1, NEXT_BLOCKS_COUNT + 1),
+        }
+    }
+}
+
+/// Board reached by placing the searched blocks.
+#[derive(Clone)]
+struct Node {
+    board: Board,
+    lines: usize,
+    score: f32,
+    /// Placement of the active block which leads to this board.
+    first: Option<Placement>,
+}
+
+/// Searches the placements of the active block and the next blocks, returns the placement of
+/// the active block leading to the best board.
+fn find_best_placement(game: &Game, options: &Options) -> Option<Placement> {
+    let mut board = [[false; BOARD_WIDTH]; BOARD_HEIGHT];
+    for (x, y) in game.board_points().filter_map(|(_, pos)| board_index(pos)) {
+        board[y][x] = true;
+    }
+    let mut beam = vec![Node {
+        board,
+        lines: 0,
+        score: 0.0,
+        first: None,
+    }];
+
+    for depth in 0..options.depth {
+        let mut children = vec![];
+        for node in &beam {
+            let placements = match &node.first {
+                None => game.legal_placements(),
+                Some(_) => {
+                    let block_type = game.next_blocks().nth(depth - 1).unwrap();
+                    placements_on_board(game.rules(), &node.board, block_type)
+                }
+            };
+            for placement in placements {
+                if !is_reachable_by_drop(&node.board, &placement.points) {
+                    continue;
+                }
+                let board = place(&node.board, &placement);
+                let lines = node.lines + placement.cleared_rows.len();
+                children.push(Node {
+                    score: evaluate(&board, lines),
+                    board,
+                    lines,
+                    first: Some(node.first.clone().unwrap_or(placement)),
+                });
+            }
+        }
+        if children.is_empty() {
+            break;
+        }
+        children.sort_by(|a, b| b.score.total_cmp(&a.score));
+        children.truncate(options.beam_width);
+        beam = children;
+    }
+
+    beam.into_iter().next().and_then(|node| node.first)
+}
+
+/// Placements of the block on the board are enumerated by a game which starts with the board.
+fn placements_on_board(rules: &GameRules, board: &Board, block_type: BlockType) -> Vec<Placement> {
+    let mut game = Game::with_blocks(rules.clone(), &[block_type]);
+    game.prefill_board(
+        (0..BOARD_HEIGHT)
+            .flat_map(|y| (0..BOARD_WIDTH).map(move |x| (x, y)))
+            .filter(|&(x, y)| board[y][x])
+            .map(|(x, y)| board_position(x, y)),
+    );
+    if game.is_game_over() {
+        return vec![];
+    }
+    game.legal_placements()
+}
+
+/// The bot only moves and rotates the block before the hard drop, so tucks under the stack
+/// aren't reachable.
+fn is_reachable_by_drop(board: &Board, points: &[Position]) -> bool {
+    points.iter().all(|&pos| match board_index(pos) {
+        Some((x, y)) => (0..y).all(|above| !board[above][x]),
+        None => false,
+    })
+}
+
+fn place(board: &Board, placement: &Placement) -> Board {
+    let mut placed = *board;
+    for (x, y) in placement.points.iter().filter_map(|&pos| board_index(pos)) {
+        placed[y][x] = true;
+    }
+    let mut result = [[false; BOARD_WIDTH]; BOARD_HEIGHT];
+    let mut y = BOARD_HEIGHT;
+    for row in placed.iter().rev() {
+        if !row.iter().all(|&p| p) {
+            y -= 1;
+            result[y] = *row;
+        }
+    }
+    result
+}
+
+fn evaluate(board: &Board, lines: usize) -> f32 {
+    let metrics = BoardMetrics::measure(|x, y| board[y][x], Gravity::Down);
+    HEIGHT_WEIGHT * metrics.aggregate_height as f32
+        + LINES_WEIGHT * lines as f32
+        + HOLES_WEIGHT * metrics.holes as f32
+        + BUMPINESS_WEIGHT * metrics.bumpiness as f32
+}
+
+/// Presses the keys moving the active block to the target placement, a key is released for
+/// one tick between presses.
+#[derive(Default)]
+struct Driver {
+    rotations: usize,
+    x: i32,
+    released: bool,
+    move_left: bool,
+    move_right: bool,
+    rotate: bool,
+    fast_drop: bool,
+    instant_drop: bool,
+}
+
+impl Driver {
+    fn new(placement: &Placement) -> Self {
+        Self {
+            rotations: placement.rotations,
+            x: placement.position.0,
+            released: true,
+            ..Default::default()
+        }
+    }
+
+    fn think(&mut self, game: &Game) {
+        let was_released = self.released;
+        self.move_left = false;
+        self.move_right = false;
+        self.rotate = false;
+        self.fast_drop = false;
+        self.instant_drop = false;
+        self.released = true;
+        if !was_released {
+            return;
+        }
+
+        let x = game.active_block_position().0;
+        if self.rotations > 0 && game.try_rotate().is_some() {
+            self.rotations -= 1;
+            self.rotate = true;
+        } else if x > self.x && game.try_move(Move::Left).is_some() {
+            self.move_left = true;
+        } else if x < self.x && game.try_move(Move::Right).is_some() {
+            self.move_right = true;
+        } else if self.rotations > 0 && game.try_move(Move::Fall).is_some() {
+            // the block can't rotate in the hidden rows at the top yet
+            self.fast_drop = true;
+        } else {
+            self.instant_drop = true;
+        }
+        self.released = false;
+    }
+}
+
+impl game::Input for Driver {
+    fn move_left(&self) -> bool {
+        self.move_left
+    }
+
+    fn move_right(&self) -> bool {
+        self.move_right
+    }
+
+    fn rotate(&self) -> bool {
+        self.rotate
+    }
+
+    fn fast_drop(&self) -> bool {
+        self.fast_drop
+    }
+
+    fn instant_drop(&self) -> bool {
+        self.instant_drop
+    }
+
+    fn use_item(&self) -> bool {
+        false
+    }
+
+    fn hold(&self) -> bool {
+        false
+    }
+}
+
+fn play(seed: u64, options: &Options) -> game::Score {
+    let mut game = Game::with_seed(GameRules::new(), seed);
+    let mut driver = Driver::default();
+    let mut block_id = None;
+    while !game.is_game_over() && game.score().ticks < MAX_TICKS {
+        if block_id != Some(game.active_block().id) {
+            block_id = Some(game.active_block().id);
+            driver = find_best_placement(&game, options)
+                .map(|placement| Driver::new(&placement))
+                .unwrap_or_default();
+        }
+        driver.think(&game);
+        game.tick(&driver);
+    }
+    game.score()
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let options = Options::from_args(&args);
+    println!(
+        "{} games, beam width {}, depth {}",
+        options.games, options.beam_width, options.depth
+    );
+
+    let started = Instant::now();
+    let mut lines = vec![];
+    for seed in 0..options.games {
+        let score = play(seed, &options);
+        let end = if score.ticks < MAX_TICKS {
+            "game over"
+        } else {
+            "time up"
+        };
+        println!(
+            "game {:>3}: {:>5} lines, {:>7} points, {}",
+            seed, score.lines, score.points, end
+        );
+        lines.push(score.lines);
+    }
+
+    if lines.is_empty() {
+        return;
+    }
+    lines.sort();
+    let mean = lines.iter().sum::<u32>() as f32 / lines.len() as f32;
+    println!(
+        "lines per game: mean {:.1}, median {}, min {}, max {} ({:.1?})",
+        mean,
+        lines[lines.len() / 2],
+        lines[0],
+        lines[lines.len() - 1],
+        started.elapsed()
+    );
+}
@@ -0,0 +1,37 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use tetris::game::{self, BlockType, Game, GameRules, ReplayInput};
+
+mod common;
+use common::check_game;
+
+/// Arbitrary board with the active block placed near the edges, where rotation and collision
+/// checks do their raw arithmetic.
+#[derive(Arbitrary, Debug)]
+struct Setup {
+    seed: u64,
+    cells: Vec<(u8, u8)>,
+    block: u8,
+    inputs: Vec<u8>,
+}
+
+fuzz_target!(|setup: Setup| {
+    let mut game = Game::with_seed(GameRules::new(), setup.seed);
+    for (x, y) in setup.cells {
+        // keep the spawn area free, otherwise the game is over before the first tick
+        let pos = game::board_position(
+            x as usize % game::BOARD_WIDTH,
+            y as usize % (game::BOARD_HEIGHT - game::HIDDEN_BOARD_TOP) + game::HIDDEN_BOARD_TOP,
+        );
+        game.debug_toggle_cell(pos);
+    }
+    game.debug_spawn_block(BlockType::ALL[setup.block as usize % BlockType::ALL.len()]);
+    check_game(&game);
+
+    for input in setup.inputs {
+        game.tick(&ReplayInput::from_byte(input));
+        check_game(&game);
+    }
+});
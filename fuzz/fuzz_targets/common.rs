@@ -0,0 +1,8 @@
+use tetris::game::Game;
+
+/// Panics if any of the game invariants doesn't hold.
+pub fn check_game(game: &Game) {
+    if let Err(e) = game.check_invariants() {
+        panic!("game invariant violated: {}", e);
+    }
+}
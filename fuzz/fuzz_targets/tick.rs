@@ -0,0 +1,42 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use tetris::game::{Game, GameRules, Item, ReplayInput};
+
+mod common;
+use common::check_game;
+
+#[derive(Arbitrary, Debug)]
+enum Event {
+    Tick(u8),
+    Garbage(u8),
+    Item(u8),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Run {
+    seed: u64,
+    items: bool,
+    events: Vec<Event>,
+}
+
+fuzz_target!(|run: Run| {
+    let mut rules = GameRules::new();
+    rules.items = run.items;
+    let mut game = Game::with_seed(rules, run.seed);
+
+    for event in run.events {
+        match event {
+            Event::Tick(input) => {
+                game.tick(&ReplayInput::from_byte(input));
+            }
+            Event::Garbage(lines) => game.receive_garbage(lines as u32 % 30),
+            Event::Item(item) => {
+                let items = [Item::ClearBottomRows, Item::ShuffleQueue, Item::SpeedUp];
+                game.receive_item(items[item as usize % items.len()]);
+            }
+        }
+        check_game(&game);
+    }
+});
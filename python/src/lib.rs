@@ -0,0 +1,419 @@
+//! Python bindings of the core game, so agents can be trained against the same rules the game
+//! is played by. Build and install the `tetris` module with `maturin develop` in this directory.
+
+use engine::game::{
+    self, AutoRepeat, BlockType, ClearKind, GameRules, GameSnapshot, GarbageStyle, Gravity, Item,
+    Move, ReplayInput, TickChange,
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+
+/// `(rotations, cells, cleared_rows)` of a placement of the active block.
+type PlacementTuple = (usize, Vec<(i32, i32)>, usize);
+
+/// Rules of a new game, the defaults are the ones of the marathon. `gravity` is `"down"` or
+/// `"up"`, `garbage_style` is `"clean"`, `"cheese"` or `"random"`, which repeats the hole
+/// column with `garbage_repeat_chance`. Co-op games need the input of two players, so the
+/// rules are always single player.
+#[pyclass]
+#[derive(Clone)]
+struct Rules {
+    #[pyo3(get, set)]
+    speed_level: u32,
+    #[pyo3(get, set)]
+    items: bool,
+    #[pyo3(get, set)]
+    mirror: bool,
+    #[pyo3(get, set)]
+    big: bool,
+    #[pyo3(get, set)]
+    drill_interval: Option<u32>,
+    #[pyo3(get, set)]
+    garbage_percent: u32,
+    #[pyo3(get, set)]
+    preview_count: usize,
+    #[pyo3(get, set)]
+    entry_delay: u32,
+    #[pyo3(get, set)]
+    line_clear_delay: u32,
+    #[pyo3(get, set)]
+    gravity: String,
+    #[pyo3(get, set)]
+    doubles: bool,
+    #[pyo3(get, set)]
+    garbage_style: String,
+    #[pyo3(get, set)]
+    garbage_repeat_chance: f64,
+    #[pyo3(get, set)]
+    auto_repeat_delay: u32,
+    #[pyo3(get, set)]
+    auto_repeat_interval: u32,
+}
+
+#[pymethods]
+impl Rules {
+    #[new]
+    fn new() -> Self {
+        Self::from(&GameRules::new())
+    }
+}
+
+impl From<&GameRules> for Rules {
+    fn from(rules: &GameRules) -> Self {
+        let (garbage_style, garbage_repeat_chance) = match rules.garbage_style {
+            GarbageStyle::Clean => ("clean", game::DEFAULT_REPEAT_CHANCE),
+            GarbageStyle::Cheese => ("cheese", game::DEFAULT_REPEAT_CHANCE),
+            GarbageStyle::Random { repeat_chance } => ("random", repeat_chance),
+        };
+        Self {
+            speed_level: rules.speed_level,
+            items: rules.items,
+            mirror: rules.mirror,
+            big: rules.big,
+            drill_interval: rules.drill_interval,
+            garbage_percent: rules.garbage_percent,
+            preview_count: rules.preview_count,
+            entry_delay: rules.entry_delay,
+            line_clear_delay: rules.line_clear_delay,
+            gravity: match rules.gravity {
+                Gravity::Down => "down",
+                Gravity::Up => "up",
+            }
+            .to_string(),
+            doubles: rules.doubles,
+            garbage_style: garbage_style.to_string(),
+            garbage_repeat_chance,
+            auto_repeat_delay: rules.auto_repeat.delay,
+            auto_repeat_interval: rules.auto_repeat.interval,
+        }
+    }
+}
+
+impl Rules {
+    /// Returns the rules of the game, an error names the unknown gravity or garbage style.
+    fn game_rules(&self) -> PyResult<GameRules> {
+        let gravity = match self.gravity.as_str() {
+            "down" => Gravity::Down,
+            "up" => Gravity::Up,
+            gravity => {
+                return Err(PyValueError::new_err(format!(
+                    "expected gravity down or up, got {}",
+                    gravity
+                )))
+            }
+        };
+        let garbage_style = match self.garbage_style.as_str() {
+            "clean" => GarbageStyle::Clean,
+            "cheese" => GarbageStyle::Cheese,
+            "random" => GarbageStyle::Random {
+                repeat_chance: self.garbage_repeat_chance,
+            },
+            style => {
+                return Err(PyValueError::new_err(format!(
+                    "expected garbage style clean, cheese or random, got {}",
+                    style
+                )))
+            }
+        };
+        Ok(GameRules {
+            speed_level: self.speed_level,
+            items: self.items,
+            mirror: self.mirror,
+            big: self.big,
+            drill_interval: self.drill_interval,
+            garbage_percent: self.garbage_percent,
+            preview_count: self.preview_count,
+            entry_delay: self.entry_delay,
+            line_clear_delay: self.line_clear_delay,
+            gravity,
+            doubles: self.doubles,
+            garbage_style,
+            auto_repeat: AutoRepeat {
+                delay: self.auto_repeat_delay,
+                interval: self.auto_repeat_interval,
+            },
+            ..GameRules::new()
+        })
+    }
+}
+
+fn block_name(block_type: BlockType) -> &'static str {
+    match block_type {
+        BlockType::I => "I",
+        BlockType::J => "J",
+        BlockType::L => "L",
+        BlockType::O => "O",
+        BlockType::S => "S",
+        BlockType::T => "T",
+        BlockType::Z => "Z",
+    }
+}
+
+fn item_name(item: Item) -> &'static str {
+    match item {
+        Item::ClearBottomRows => "ClearBottomRows",
+        Item::ShuffleQueue => "ShuffleQueue",
+        Item::SpeedUp => "SpeedUp",
+    }
+}
+
+fn clear_kind_name(clear_kind: ClearKind) -> &'static str {
+    match clear_kind {
+        ClearKind::Single => "Single",
+        ClearKind::Double => "Double",
+        ClearKind::Triple => "Triple",
+        ClearKind::Tetris => "Tetris",
+        ClearKind::TSpinSingle => "TSpinSingle",
+        ClearKind::TSpinDouble => "TSpinDouble",
+        ClearKind::TSpinTriple => "TSpinTriple",
+    }
+}
+
+/// Returns the dict of the change, its `type` is the name of the `TickChange` variant and the
+/// other keys are the values of the variant, like the changes sent by the state server. Rows
+/// are counted from the top visible row.
+fn change_dict<'py>(
+    py: Python<'py>,
+    change: &TickChange,
+    gravity: Gravity,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    let change_type = match change {
+        TickChange::BlockShifted(direction) => {
+            let direction = match direction {
+                Move::Left => "left",
+                Move::Right => "right",
+                Move::Fall => "down",
+            };
+            dict.set_item("direction", direction)?;
+            "BlockShifted"
+        }
+        TickChange::BlockRotated { kick } => {
+            dict.set_item("kick", kick)?;
+            "BlockRotated"
+        }
+        TickChange::SoftDropped => "SoftDropped",
+        TickChange::BlockLanded => "BlockLanded",
+        TickChange::BlockLocked => "BlockLocked",
+        TickChange::NewBlock => "NewBlock",
+        TickChange::BlockHeld => "BlockHeld",
+        TickChange::RowsCleared { rows, clear_kind } => {
+            let top = gravity.visible_rows().start as i32;
+            let rows = rows.iter().map(|&y| y as i32 - top).collect::<Vec<_>>();
+            dict.set_item("rows", rows)?;
+            dict.set_item("clear_kind", clear_kind_name(*clear_kind))?;
+            "RowsCleared"
+        }
+        TickChange::PointRemoved(id) => {
+            dict.set_item("point", id)?;
+            "PointRemoved"
+        }
+        TickChange::GameOver => "GameOver",
+        TickChange::ItemGranted(item) => {
+            dict.set_item("item", item_name(*item))?;
+            "ItemGranted"
+        }
+        TickChange::ItemUsed(item) => {
+            dict.set_item("item", item_name(*item))?;
+            "ItemUsed"
+        }
+        TickChange::PointAdded(id) => {
+            dict.set_item("point", id)?;
+            "PointAdded"
+        }
+        TickChange::GarbageSent(lines) => {
+            dict.set_item("lines", lines)?;
+            "GarbageSent"
+        }
+    };
+    dict.set_item("type", change_type)?;
+    Ok(dict)
+}
+
+/// State of a game saved by `Game.snapshot`.
+#[pyclass]
+struct Snapshot(GameSnapshot);
+
+/// Game played a tick at a time. Rows of the board and the cells are counted from the top
+/// visible row, the spawned block is in the hidden rows above it, which are negative.
+#[pyclass]
+#[derive(Clone)]
+struct Game(game::Game);
+
+#[pymethods]
+impl Game {
+    /// Games with the same seed deal the same blocks.
+    #[new]
+    #[pyo3(signature = (rules = None, seed = None))]
+    fn new(rules: Option<&Rules>, seed: Option<u64>) -> PyResult<Self> {
+        let rules = match rules {
+            Some(rules) => rules.game_rules()?,
+            None => GameRules::new(),
+        };
+        Ok(Self(match seed {
+            Some(seed) => game::Game::with_seed(rules, seed),
+            None => game::Game::with_rules(rules),
+        }))
+    }
+
+    /// Plays a tick with the given keys held and returns its changes as dicts, whose `type` is
+    /// like `BlockLocked` or `RowsCleared` and whose other keys are the values of the change,
+    /// like the cleared `rows`. A key held on consecutive ticks repeats like a held key.
+    #[pyo3(signature = (
+        left = false,
+        right = false,
+        rotate = false,
+        soft_drop = false,
+        hard_drop = false,
+        use_item = false,
+        hold = false
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn tick<'py>(
+        &mut self,
+        py: Python<'py>,
+        left: bool,
+        right: bool,
+        rotate: bool,
+        soft_drop: bool,
+        hard_drop: bool,
+        use_item: bool,
+        hold: bool,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let input = ReplayInput::new(left, right, rotate, soft_drop, hard_drop, use_item, hold);
+        let gravity = self.0.rules().gravity;
+        self.0
+            .tick(&input)
+            .iter()
+            .map(|change| change_dict(py, change, gravity))
+            .collect()
+    }
+
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot(self.0.snapshot())
+    }
+
+    /// Returns the game to the snapshot of the same game.
+    fn restore(&mut self, snapshot: &Snapshot) {
+        self.0.restore(&snapshot.0);
+    }
+
+    fn rules(&self) -> Rules {
+        Rules::from(self.0.rules())
+    }
+
+    #[getter]
+    fn game_over(&self) -> bool {
+        self.0.is_game_over()
+    }
+
+    #[getter]
+    fn ticks(&self) -> u32 {
+        self.0.score().ticks
+    }
+
+    #[getter]
+    fn score(&self) -> u32 {
+        self.0.score().points
+    }
+
+    #[getter]
+    fn lines(&self) -> u32 {
+        self.0.score().lines
+    }
+
+    #[getter]
+    fn combo(&self) -> u32 {
+        self.0.score().combo
+    }
+
+    #[getter]
+    fn pending_garbage(&self) -> u32 {
+        self.0.pending_garbage()
+    }
+
+    /// Rows of the visible board from the top, filled cells are 1 and empty ones 0.
+    fn board(&self) -> Vec<Vec<u8>> {
+        let gravity = self.0.rules().gravity;
+        let mut board = vec![vec![0; self.0.board_width()]; gravity.visible_rows().len()];
+        for (x, row) in self
+            .0
+            .board_points()
+            .filter_map(|(_, pos)| gravity.visible_cell(pos))
+        {
+            board[row][x] = 1;
+        }
+        board
+    }
+
+    /// Name of the active block, `None` between the blocks.
+    fn active_block(&self) -> Option<&'static str> {
+        self.0
+            .has_active_block()
+            .then(|| block_name(self.0.view().active_block_type))
+    }
+
+    /// `(column, row)` cells of the active block.
+    fn active_cells(&self) -> Vec<(i32, i32)> {
+        let top = self.0.rules().gravity.visible_rows().start as i32;
+        self.0
+            .view()
+            .active_block
+            .iter()
+            .map(|&(_, (x, y))| (x, y - top))
+            .collect()
+    }
+
+    /// Names of the next blocks.
+    fn queue(&self) -> Vec<&'static str> {
+        self.0.next_blocks().map(block_name).collect()
+    }
+
+    /// Name of the block in the hold.
+    fn held_block(&self) -> Option<&'static str> {
+        self.0.held_block().map(block_name)
+    }
+
+    fn item(&self) -> Option<&'static str> {
+        self.0.item().map(item_name)
+    }
+
+    /// Placements of the active block it can be moved to, rotations are counted from its
+    /// current orientation.
+    fn legal_placements(&self) -> Vec<PlacementTuple> {
+        let top = self.0.rules().gravity.visible_rows().start as i32;
+        self.0
+            .legal_placements()
+            .into_iter()
+            .map(|placement| {
+                let cells = placement
+                    .points
+                    .iter()
+                    .map(|&(x, y)| (x, y - top))
+                    .collect();
+                (placement.rotations, cells, placement.cleared_rows.len())
+            })
+            .collect()
+    }
+
+    /// Queues garbage lines, they rise when the next block is locked without removing rows.
+    fn receive_garbage(&mut self, lines: u32) {
+        self.0.receive_garbage(lines);
+    }
+
+    /// Hash of the game state, equal for games in the same state.
+    fn state_hash(&self) -> u64 {
+        self.0.state_hash()
+    }
+}
+
+#[pymodule]
+fn tetris(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Rules>()?;
+    module.add_class::<Game>()?;
+    module.add_class::<Snapshot>()?;
+    Ok(())
+}
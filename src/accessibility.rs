@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::{
+    cells::{CellStyle, CellTexture},
+    game::Position,
+    player::Player,
+    pos_to_vec3,
+    settings::Settings,
+    PointComponent, UNIT_PX,
+};
+
+const OUTLINE_WIDTH: u32 = 3;
+const GHOST_OUTLINE_WIDTH: u32 = 2;
+/// Outline color, the fill is white to be tinted by the point color.
+const OUTLINE_SHADE: u8 = 16;
+const HIGH_CONTRAST_BACKGROUND_DIMMING: f32 = 0.25;
+const MIN_POINT_LIGHTNESS: f32 = 0.55;
+const GHOST_COLOR: Color = Color::WHITE;
+/// Over the board background.
+const GHOST_Z: f32 = 0.5;
+/// Every block has four points.
+const BLOCK_POINTS: usize = 4;
+
+/// Turns off the movement and flashing of the effects, set by the `reduce_motion` setting and
+/// toggled in the pause menu. Every effect system checks it.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct ReduceMotion(pub bool);
+
+impl ReduceMotion {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self(settings.get("reduce_motion") == Some("true"))
+    }
+
+    /// Turns the option on or off and saves the setting.
+    pub fn toggle(&mut self, settings: &mut Settings) {
+        self.0 = !self.0;
+        // the change still applies to this session
+        if let Err(e) = settings.set("reduce_motion", &self.0.to_string()) {
+            warn!("failed to save the reduce_motion setting: {}", e);
+        }
+    }
+}
+
+/// Draws the points with thick outlines and saturated colors on a darker background, and the
+/// ghost of the active block as hollow cells. Set by the `high_contrast` setting and toggled in
+/// the pause menu.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct HighContrast(pub bool);
+
+impl HighContrast {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self(settings.get("high_contrast") == Some("true"))
+    }
+
+    /// Turns the option on or off and saves the setting.
+    pub fn toggle(&mut self, settings: &mut Settings) {
+        self.0 = !self.0;
+        // the change still applies to this session
+        if let Err(e) = settings.set("high_contrast", &self.0.to_string()) {
+            warn!("failed to save the high_contrast setting: {}", e);
+        }
+    }
+
+    /// Returns the multiplier of the background lightness.
+    pub fn background_dimming(&self) -> f32 {
+        if self.0 {
+            HIGH_CONTRAST_BACKGROUND_DIMMING
+        } else {
+            1.
+        }
+    }
+}
+
+/// Textures of the high contrast cells.
+#[derive(Default)]
+pub struct HighContrastTextures {
+    /// White cell with a dark outline.
+    cell: Handle<Image>,
+    /// Outline of a cell with a transparent inside.
+    ghost: Handle<Image>,
+}
+
+/// Cell of the ghost of the player's active block, at the index of the block point.
+#[derive(Component)]
+pub struct GhostCell {
+    player: Entity,
+    index: usize,
+}
+
+/// Returns the cell with an outline of `outline_width` pixels.
+fn outlined_cell(style: &CellStyle, outline_width: u32, outline: [u8; 4], fill: [u8; 4]) -> Image {
+    style.cell_image(|distances| {
+        if distances.into_iter().min().unwrap() < outline_width {
+            outline
+        } else {
+            fill
+        }
+    })
+}
+
+/// Draws the high contrast cells with the gap of the cell style, when the settings are loaded or
+/// reloaded.
+pub fn update_high_contrast_textures(
+    style: Res<CellStyle>,
+    mut textures: ResMut<HighContrastTextures>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !style.is_changed() {
+        return;
+    }
+    let shade = OUTLINE_SHADE;
+    *textures = HighContrastTextures {
+        cell: images.add(outlined_cell(
+            &style,
+            OUTLINE_WIDTH,
+            [shade, shade, shade, 255],
+            [255; 4],
+        )),
+        ghost: images.add(outlined_cell(&style, GHOST_OUTLINE_WIDTH, [255; 4], [0; 4])),
+    };
+}
+
+/// Returns the point color with full saturation and raised lightness.
+fn saturated(color: Color) -> Color {
+    let [hue, _, lightness, alpha] = color.as_hsla_f32();
+    Color::hsla(hue, 1., lightness.max(MIN_POINT_LIGHTNESS), alpha)
+}
+
+/// Styles the spawned points, and all points when the option is toggled or the textures are
+/// redrawn. The alpha set by the stack visibility is kept.
+pub fn style_points(
+    mut commands: Commands,
+    high_contrast: Res<HighContrast>,
+    textures: Res<HighContrastTextures>,
+    cell_texture: Res<CellTexture>,
+    mut points: Query<(
+        Entity,
+        &PointComponent,
+        &mut Sprite,
+        ChangeTrackers<PointComponent>,
+    )>,
+) {
+    let restyled = high_contrast.is_changed() || textures.is_changed() || cell_texture.is_changed();
+    for (entity, point, mut sprite, tracker) in points.iter_mut() {
+        if !tracker.is_added() && !restyled {
+            continue;
+        }
+        let alpha = sprite.color.a();
+        let (color, texture) = if high_contrast.0 {
+            (saturated(point.color), textures.cell.clone())
+        } else {
+            (point.color, cell_texture.0.clone())
+        };
+        sprite.color = color;
+        sprite.color.set_a(alpha);
+        // the unit sized sprite is scaled to the cell size, as without the texture
+        sprite.custom_size = Some(Vec2::ONE);
+        commands.entity(entity).insert(texture);
+    }
+}
+
+/// Shows the ghosts of the active blocks in the high contrast mode.
+pub fn update_ghosts(
+    mut commands: Commands,
+    high_contrast: Res<HighContrast>,
+    textures: Res<HighContrastTextures>,
+    players: Query<(Entity, &Player)>,
+    mut ghost_cells: Query<(Entity, &GhostCell, &mut Transform, &mut Visibility)>,
+) {
+    // the cells with the redrawn texture are spawned on the next frame
+    if !high_contrast.0 || textures.is_changed() {
+        for (entity, ..) in ghost_cells.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    for (player_entity, player) in players.iter() {
+        if !ghost_cells
+            .iter()
+            .any(|(_, cell, ..)| cell.player == player_entity)
+        {
+            // the cells are shown from the next frame
+            for index in 0..BLOCK_POINTS {
+                let cell = commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: GHOST_COLOR,
+                            anchor: Anchor::BottomLeft,
+                            custom_size: Some(Vec2::ONE),
+                            ..default()
+                        },
+                        texture: textures.ghost.clone(),
+                        visibility: Visibility { is_visible: false },
+                        ..default()
+                    })
+                    .insert(GhostCell {
+                        player: player_entity,
+                        index,
+                    })
+                    .id();
+                commands.entity(player.board).add_child(cell);
+            }
+        }
+    }
+
+    let ghosts: HashMap<Entity, Vec<Position>> = players
+        .iter()
+        .map(|(entity, player)| {
+            let view = player.game.view();
+            let ghost = if view.game_over {
+                vec![]
+            } else {
+                // the ghost is drawn over the board, so the cells covered by the block are hidden
+                let block_cells: Vec<Position> =
+                    view.active_block.iter().map(|&(_, pos)| pos).collect();
+                view.ghost
+                    .into_iter()
+                    .filter(|pos| view.gravity.visible_row(pos.1).is_some())
+                    .filter(|pos| !block_cells.contains(pos))
+                    .collect()
+            };
+            (entity, ghost)
+        })
+        .collect();
+    for (_, cell, mut transform, mut visibility) in ghost_cells.iter_mut() {
+        let pos = ghosts
+            .get(&cell.player)
+            .and_then(|ghost| ghost.get(cell.index));
+        match pos {
+            Some(&pos) => {
+                transform.translation = pos_to_vec3(pos) + Vec3::Z * GHOST_Z;
+                transform.scale = Vec3::new(UNIT_PX, UNIT_PX, 1.);
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::{
+    input::gamepad::GamepadButtonType,
+    prelude::{Gamepad, KeyCode},
+};
+
+/// Action controlling the game of a player.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    FastDrop,
+    Rotate,
+    InstantDrop,
+    UseItem,
+    Hold,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::FastDrop,
+        Action::Rotate,
+        Action::InstantDrop,
+        Action::UseItem,
+        Action::Hold,
+    ];
+
+    /// Returns the name of the action in the settings file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::FastDrop => "fast_drop",
+            Action::Rotate => "rotate",
+            Action::InstantDrop => "instant_drop",
+            Action::UseItem => "use_item",
+            Action::Hold => "hold",
+        }
+    }
+}
+
+/// Key or gamepad button which activates an action.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+}
+
+/// Bindings of the actions of one player, an action may be bound to several keys and buttons
+/// and a key or button to several actions.
+#[derive(Clone, Debug)]
+pub struct ActionMap {
+    bindings: Vec<(Binding, Action)>,
+    /// Gamepad whose buttons are bound, any gamepad if `None`.
+    gamepad: Option<Gamepad>,
+}
+
+impl ActionMap {
+    /// Returns the map of the arrow keys and the gamepad.
+    pub fn arrows() -> Self {
+        let mut map = Self::without_bindings();
+        for (key, action) in [
+            (KeyCode::Left, Action::MoveLeft),
+            (KeyCode::Right, Action::MoveRight),
+            (KeyCode::Down, Action::FastDrop),
+            (KeyCode::Up, Action::Rotate),
+            (KeyCode::RShift, Action::UseItem),
+            (KeyCode::RControl, Action::Hold),
+        ] {
+            map.bind(action, Binding::Key(key));
+        }
+        map.bind_gamepad();
+        map
+    }
+
+    /// Returns the map of the WASD keys and the gamepad.
+    pub fn wasd() -> Self {
+        let mut map = Self::without_bindings();
+        for (key, action) in [
+            (KeyCode::A, Action::MoveLeft),
+            (KeyCode::D, Action::MoveRight),
+            (KeyCode::S, Action::FastDrop),
+            (KeyCode::W, Action::Rotate),
+            (KeyCode::LShift, Action::UseItem),
+            (KeyCode::LControl, Action::Hold),
+        ] {
+            map.bind(action, Binding::Key(key));
+        }
+        map.bind_gamepad();
+        map
+    }
+
+    fn without_bindings() -> Self {
+        Self {
+            bindings: vec![],
+            gamepad: None,
+        }
+    }
+
+    fn bind_gamepad(&mut self) {
+        for (button, action) in [
+            (GamepadButtonType::DPadLeft, Action::MoveLeft),
+            (GamepadButtonType::DPadRight, Action::MoveRight),
+            (GamepadButtonType::DPadDown, Action::FastDrop),
+            (GamepadButtonType::DPadUp, Action::InstantDrop),
+            (GamepadButtonType::South, Action::Rotate),
+            (GamepadButtonType::West, Action::UseItem),
+            (GamepadButtonType::North, Action::Hold),
+        ] {
+            self.bind(action, Binding::Button(button));
+        }
+    }
+
+    /// Adds the binding to the action, other bindings of the action stay.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        if !self.bindings.contains(&(binding, action)) {
+            self.bindings.push((binding, action));
+        }
+    }
+
+    /// Removes all bindings of the action.
+    pub fn unbind(&mut self, action: Action) {
+        self.bindings.retain(|&(_, a)| a != action);
+    }
+
+    /// Returns the map which reads the buttons of the gamepad only, any gamepad if `None`.
+    pub fn with_gamepad(&self, gamepad: Option<Gamepad>) -> Self {
+        Self {
+            bindings: self.bindings.clone(),
+            gamepad,
+        }
+    }
+
+    /// Returns the actions bound to the key or the button of the gamepad.
+    pub fn actions(
+        &self,
+        binding: Binding,
+        gamepad: Option<Gamepad>,
+    ) -> impl Iterator<Item = Action> + '_ {
+        let matches_gamepad = match binding {
+            Binding::Key(_) => true,
+            Binding::Button(_) => self.gamepad.is_none() || self.gamepad == gamepad,
+        };
+        self.bindings
+            .iter()
+            .filter(move |&&(b, _)| matches_gamepad && b == binding)
+            .map(|&(_, action)| action)
+    }
+
+    /// Returns the map with left and right swapped.
+    pub fn mirrored(&self) -> Self {
+        let bindings = self
+            .bindings
+            .iter()
+            .map(|&(binding, action)| {
+                let action = match action {
+                    Action::MoveLeft => Action::MoveRight,
+                    Action::MoveRight => Action::MoveLeft,
+                    action => action,
+                };
+                (binding, action)
+            })
+            .collect();
+        Self {
+            bindings,
+            gamepad: self.gamepad,
+        }
+    }
+}
+
+/// Action maps of the local players, the input of every player is read through them.
+pub struct ActionMaps {
+    /// Used by the only player of single player games and the local player of network games.
+    pub single: ActionMap,
+    /// Used by the players sharing the keyboard in versus games, indexed by the player.
+    pub versus: Vec<ActionMap>,
+}
+
+impl ActionMaps {
+    /// Returns the map of the player, `None` if the player has no local controls.
+    pub fn player(&self, index: usize, versus: bool) -> Option<&ActionMap> {
+        if versus {
+            self.versus.get(index)
+        } else {
+            Some(&self.single)
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    audio::{Channel, SoundPack, Volume},
+    game::{ClearKind, LockedBlock, SharedLog},
+    player::Player,
+    settings::Settings,
+};
+
+/// Folder of the voice lines in the sounds folder of the pack.
+const VOICE_DIR: &str = "voice";
+
+/// Voice line of the announcer, at most one is played per frame.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Callout {
+    Tetris,
+    TSpinSingle,
+    TSpinDouble,
+    TSpinTriple,
+    BackToBack,
+    LevelUp,
+}
+
+impl Callout {
+    const ALL: [Callout; 6] = [
+        Callout::Tetris,
+        Callout::TSpinSingle,
+        Callout::TSpinDouble,
+        Callout::TSpinTriple,
+        Callout::BackToBack,
+        Callout::LevelUp,
+    ];
+
+    fn file(self) -> &'static str {
+        match self {
+            Callout::Tetris => "tetris.ogg",
+            Callout::TSpinSingle => "t_spin_single.ogg",
+            Callout::TSpinDouble => "t_spin_double.ogg",
+            Callout::TSpinTriple => "t_spin_triple.ogg",
+            Callout::BackToBack => "back_to_back.ogg",
+            Callout::LevelUp => "level_up.ogg",
+        }
+    }
+
+    /// Returns the callout of the clear made by the last of the locked blocks.
+    fn from_clear(locked_blocks: &SharedLog<LockedBlock>) -> Option<Self> {
+        let mut blocks = locked_blocks.iter().rev();
+        let block = blocks.next()?;
+        let callout = match block.clear_kind()? {
+            ClearKind::Tetris => Callout::Tetris,
+            ClearKind::TSpinSingle => Callout::TSpinSingle,
+            ClearKind::TSpinDouble => Callout::TSpinDouble,
+            ClearKind::TSpinTriple => Callout::TSpinTriple,
+            _ => return None,
+        };
+        // the previous clear was difficult too, as counted by the attack
+        let back_to_back = blocks
+            .find_map(LockedBlock::clear_kind)
+            .is_some_and(ClearKind::is_difficult);
+        Some(if back_to_back {
+            Callout::BackToBack
+        } else {
+            callout
+        })
+    }
+}
+
+/// Announces difficult clears and level ups, turned off by the `announcer=false` setting and
+/// in the pause menu. Its volume is the voice channel.
+pub struct Announcer {
+    pub enabled: bool,
+    voices: HashMap<Callout, Handle<AudioSource>>,
+}
+
+impl Announcer {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            enabled: settings.get("announcer") != Some("false"),
+            voices: HashMap::new(),
+        }
+    }
+
+    /// Turns the announcer on or off and saves the setting.
+    pub fn toggle(&mut self, settings: &mut Settings) {
+        self.enabled = !self.enabled;
+        // the change still applies to this session
+        if let Err(e) = settings.set("announcer", &self.enabled.to_string()) {
+            warn!("failed to save the announcer setting: {}", e);
+        }
+    }
+}
+
+/// Loads the voice lines of the selected sound pack.
+pub fn load_voices(
+    pack: Res<SoundPack>,
+    asset_server: Res<AssetServer>,
+    mut announcer: ResMut<Announcer>,
+) {
+    if !pack.is_changed() {
+        return;
+    }
+    announcer.voices = Callout::ALL
+        .iter()
+        .map(|&callout| {
+            let path = pack.path(&format!("{}/{}", VOICE_DIR, callout.file()));
+            (callout, asset_server.load(&path))
+        })
+        .collect();
+}
+
+/// Plays the callouts of the blocks locked since the last frame, a clear is called out rather
+/// than the level up it made.
+pub fn announce(
+    audio: Res<Audio>,
+    announcer: Res<Announcer>,
+    volume: Res<Volume>,
+    players: Query<(Entity, &Player)>,
+    mut last_seen: Local<HashMap<Entity, (usize, u32)>>,
+) {
+    let mut callout = None;
+    for (entity, player) in players.iter() {
+        let locked_blocks = player.game.locked_blocks();
+        let level = player.game.score().level();
+        // a restarted game has fewer locked blocks
+        let (seen_blocks, seen_level) = last_seen.get(&entity).copied().unwrap_or_default();
+        if locked_blocks.len() > seen_blocks {
+            callout = callout.or_else(|| Callout::from_clear(locked_blocks));
+            if seen_level > 0 && level > seen_level {
+                callout = callout.or(Some(Callout::LevelUp));
+            }
+        }
+        last_seen.insert(entity, (locked_blocks.len(), level));
+    }
+
+    let gain = volume.gain(Channel::Voice);
+    let voice = callout.and_then(|callout| announcer.voices.get(&callout));
+    if let (Some(voice), true) = (voice, announcer.enabled && gain > 0.) {
+        audio.play_with_settings(voice.clone(), PlaybackSettings::ONCE.with_volume(gain));
+    }
+}
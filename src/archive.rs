@@ -0,0 +1,82 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    campaign::CAMPAIGN_FILE, paths, records::RECORDS_FILE, settings::SETTINGS_FILE,
+    stats::STATS_FILE,
+};
+
+const HEADER: &str = "tetris-profile 1";
+/// Files of the player profile, the settings include the control profiles.
+const FILES: [&str; 4] = [SETTINGS_FILE, RECORDS_FILE, STATS_FILE, CAMPAIGN_FILE];
+
+/// Bundles the settings, records, stats and campaign progress into one file. The archive
+/// starts with the header line, every file is a `<name> <length>` line followed by its
+/// contents. Missing files are left out.
+pub fn export_profile(path: &Path) -> io::Result<()> {
+    let mut archive = format!("{}\n", HEADER);
+    for name in FILES {
+        let contents = match fs::read_to_string(file_path(name)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        archive += &format!("{} {}\n{}", name, contents.len(), contents);
+    }
+    fs::write(path, archive)
+}
+
+/// Replaces the profile files by the ones in the archive, files missing in the archive are
+/// kept. Returns the names of the replaced files, nothing is replaced if the archive is
+/// invalid.
+pub fn import_profile(path: &Path) -> io::Result<Vec<&'static str>> {
+    let archive = fs::read_to_string(path)?;
+    let files = parse_archive(&archive)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid profile archive"))?;
+    for &(name, contents) in &files {
+        let path = file_path(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // the previous file can be restored by hand
+        if path.exists() {
+            fs::copy(&path, backup_path(&path))?;
+        }
+        fs::write(path, contents)?;
+    }
+    Ok(files.into_iter().map(|(name, _)| name).collect())
+}
+
+/// Returns the path of the profile file, the settings are in the config directory and the
+/// others in the data directory.
+fn file_path(name: &str) -> PathBuf {
+    if name == SETTINGS_FILE {
+        paths::config_path(name)
+    } else {
+        paths::data_path(name)
+    }
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Returns the files of the archive, `None` if it's not an archive or contains unknown files.
+fn parse_archive(archive: &str) -> Option<Vec<(&'static str, &str)>> {
+    let mut rest = archive.strip_prefix(HEADER)?.strip_prefix('\n')?;
+    let mut files = vec![];
+    while !rest.is_empty() {
+        let (line, after_line) = rest.split_once('\n')?;
+        let (name, len) = line.split_once(' ')?;
+        let name = FILES.into_iter().find(|&f| f == name)?;
+        let len = len.parse().ok()?;
+        let contents = after_line.get(..len)?;
+        files.push((name, contents));
+        rest = &after_line[len..];
+    }
+    Some(files)
+}
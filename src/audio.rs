@@ -0,0 +1,359 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{audio::AudioSink, prelude::*};
+
+use crate::{
+    game::TickChange,
+    i18n::{Key, Language},
+    settings::Settings,
+    TickChanged, UI,
+};
+
+const ASSETS_DIR: &str = "assets";
+/// Folder in the assets with the music and the sound effects, missing files are only logged.
+const SOUNDS_DIR: &str = "sounds";
+const MUSIC_FILE: &str = "music.ogg";
+const DEFAULT_VOLUME: u32 = 80;
+pub const MAX_VOLUME: u32 = 100;
+/// Step of the volume sliders in the pause menu.
+pub const VOLUME_STEP: u32 = 10;
+const MUTE_KEY: KeyCode = KeyCode::M;
+const FONT_SIZE: f32 = 14.;
+const TEXT_COLOR: Color = Color::WHITE;
+const MARGIN_SIZE: f32 = 4.;
+
+/// Sound effect played for changes of the games.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Sfx {
+    Shift,
+    Rotate,
+    Land,
+    Lock,
+    Clear,
+    Item,
+    GameOver,
+}
+
+impl Sfx {
+    const ALL: [Sfx; 7] = [
+        Sfx::Shift,
+        Sfx::Rotate,
+        Sfx::Land,
+        Sfx::Lock,
+        Sfx::Clear,
+        Sfx::Item,
+        Sfx::GameOver,
+    ];
+
+    fn file(self) -> &'static str {
+        match self {
+            Sfx::Shift => "shift.ogg",
+            Sfx::Rotate => "rotate.ogg",
+            Sfx::Land => "land.ogg",
+            Sfx::Lock => "lock.ogg",
+            Sfx::Clear => "clear.ogg",
+            Sfx::Item => "item.ogg",
+            Sfx::GameOver => "game_over.ogg",
+        }
+    }
+
+    fn from_change(change: &TickChange) -> Option<Self> {
+        match change {
+            TickChange::BlockShifted(_) => Some(Sfx::Shift),
+            TickChange::BlockRotated { .. } => Some(Sfx::Rotate),
+            TickChange::BlockLanded => Some(Sfx::Land),
+            TickChange::BlockLocked => Some(Sfx::Lock),
+            TickChange::RowsCleared { .. } => Some(Sfx::Clear),
+            TickChange::ItemGranted(_) | TickChange::ItemUsed(_) => Some(Sfx::Item),
+            TickChange::GameOver => Some(Sfx::GameOver),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Channel {
+    /// Scales the other channels.
+    Master,
+    Music,
+    Sfx,
+    /// Announcer voice lines.
+    Voice,
+}
+
+impl Channel {
+    fn setting(self) -> &'static str {
+        match self {
+            Channel::Master => "master_volume",
+            Channel::Music => "music_volume",
+            Channel::Sfx => "sfx_volume",
+            Channel::Voice => "voice_volume",
+        }
+    }
+}
+
+/// Volumes of the channels in percent given by the `master_volume`, `music_volume`,
+/// `sfx_volume` and `voice_volume` settings.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Volume {
+    master: u32,
+    music: u32,
+    sfx: u32,
+    voice: u32,
+    /// Silences all channels without changing their volumes, only for this session.
+    muted: bool,
+}
+
+impl Volume {
+    pub fn from_settings(settings: &Settings) -> Self {
+        let volume = |channel: Channel| {
+            settings
+                .get(channel.setting())
+                .and_then(|volume| volume.parse().ok())
+                .unwrap_or(DEFAULT_VOLUME)
+                .min(MAX_VOLUME)
+        };
+        Self {
+            master: volume(Channel::Master),
+            music: volume(Channel::Music),
+            sfx: volume(Channel::Sfx),
+            voice: volume(Channel::Voice),
+            muted: false,
+        }
+    }
+
+    /// Reads the volumes from the reloaded settings, the audio stays muted.
+    pub fn reload(&mut self, settings: &Settings) {
+        *self = Self {
+            muted: self.muted,
+            ..Self::from_settings(settings)
+        };
+    }
+
+    pub fn get(&self, channel: Channel) -> u32 {
+        match channel {
+            Channel::Master => self.master,
+            Channel::Music => self.music,
+            Channel::Sfx => self.sfx,
+            Channel::Voice => self.voice,
+        }
+    }
+
+    /// Changes the volume of the channel by `delta` percent and saves the setting.
+    pub fn adjust(&mut self, channel: Channel, delta: i32, settings: &mut Settings) {
+        let volume = match channel {
+            Channel::Master => &mut self.master,
+            Channel::Music => &mut self.music,
+            Channel::Sfx => &mut self.sfx,
+            Channel::Voice => &mut self.voice,
+        };
+        *volume = (*volume as i32 + delta).clamp(0, MAX_VOLUME as i32) as u32;
+        // the change still applies to this session
+        if let Err(e) = settings.set(channel.setting(), &volume.to_string()) {
+            warn!("failed to save the {} setting: {}", channel.setting(), e);
+        }
+    }
+
+    /// Returns the volume the sounds of the channel are played with.
+    pub fn gain(&self, channel: Channel) -> f32 {
+        if self.muted {
+            return 0.;
+        }
+        (self.master * self.get(channel)) as f32 / (MAX_VOLUME * MAX_VOLUME) as f32
+    }
+}
+
+/// Sound set picked by the `sound_pack` setting, its sounds are in a subfolder of the sounds
+/// folder. Files missing in the pack are taken from the sounds folder.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct SoundPack(Option<String>);
+
+impl SoundPack {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self(settings.get("sound_pack").map(String::from))
+    }
+
+    /// Returns the name of the pack, `None` for the default sounds.
+    pub fn name(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+
+    /// Selects the next pack of the sounds folder and saves the setting.
+    pub fn next(&mut self, settings: &mut Settings) {
+        let packs = available_packs();
+        let index = packs.iter().position(|pack| Some(pack) == self.0.as_ref());
+        // the default sounds come after the last pack
+        self.0 = match index {
+            Some(i) => packs.get(i + 1).cloned(),
+            None => packs.first().cloned(),
+        };
+        // the change still applies to this session
+        if let Err(e) = settings.set("sound_pack", self.name().unwrap_or_default()) {
+            warn!("failed to save the sound_pack setting: {}", e);
+        }
+    }
+
+    /// Returns the asset path of the sound file, from the pack if it has the file.
+    pub fn path(&self, file: &str) -> String {
+        match &self.0 {
+            Some(pack) if sounds_dir().join(pack).join(file).is_file() => {
+                format!("{}/{}/{}", SOUNDS_DIR, pack, file)
+            }
+            _ => format!("{}/{}", SOUNDS_DIR, file),
+        }
+    }
+}
+
+fn sounds_dir() -> PathBuf {
+    Path::new(ASSETS_DIR).join(SOUNDS_DIR)
+}
+
+/// Returns names of the subfolders of the sounds folder sorted by the name.
+fn available_packs() -> Vec<String> {
+    let mut packs: Vec<String> = fs::read_dir(sounds_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    packs.sort();
+    packs
+}
+
+/// Sounds of the selected pack.
+#[derive(Default)]
+pub struct Sounds {
+    effects: HashMap<Sfx, Handle<AudioSource>>,
+    music: Handle<AudioSource>,
+    /// Sink of the looping music, its volume follows the settings.
+    music_sink: Option<Handle<AudioSink>>,
+}
+
+impl Sounds {
+    /// Plays the music from the start, stopping the music which was playing.
+    fn play_music(&mut self, audio: &Audio, sinks: &Assets<AudioSink>, volume: &Volume) {
+        if let Some(sink) = self.music_sink.take().and_then(|sink| sinks.get(&sink)) {
+            sink.stop();
+        }
+        let sink = audio.play_with_settings(
+            self.music.clone(),
+            PlaybackSettings::LOOP.with_volume(volume.gain(Channel::Music)),
+        );
+        self.music_sink = Some(sinks.get_handle(sink));
+    }
+}
+
+/// Loads the sounds when the pack is selected and restarts the music with the pack's music.
+pub fn load_sound_pack(
+    pack: Res<SoundPack>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    sinks: Res<Assets<AudioSink>>,
+    volume: Res<Volume>,
+    mut sounds: ResMut<Sounds>,
+) {
+    if !pack.is_changed() {
+        return;
+    }
+    sounds.effects = Sfx::ALL
+        .iter()
+        .map(|&sfx| (sfx, asset_server.load(&pack.path(sfx.file()))))
+        .collect();
+    sounds.music = asset_server.load(&pack.path(MUSIC_FILE));
+    sounds.play_music(&audio, &sinks, &volume);
+}
+
+/// Restarts the music when its file is edited, the sinks keep playing the old music otherwise.
+/// The sound effects are played from the edited files without a restart.
+pub fn restart_reloaded_music(
+    mut events: EventReader<AssetEvent<AudioSource>>,
+    audio: Res<Audio>,
+    sinks: Res<Assets<AudioSink>>,
+    volume: Res<Volume>,
+    mut sounds: ResMut<Sounds>,
+) {
+    let modified = events
+        .iter()
+        .any(|event| matches!(event, AssetEvent::Modified { handle } if *handle == sounds.music));
+    if modified {
+        sounds.play_music(&audio, &sinks, &volume);
+    }
+}
+
+/// Applies the changed volume to the music, the sound effects are short enough to keep the
+/// volume they were started with.
+pub fn apply_volume(volume: Res<Volume>, sounds: Res<Sounds>, sinks: Res<Assets<AudioSink>>) {
+    if !volume.is_changed() {
+        return;
+    }
+    if let Some(sink) = sounds.music_sink.as_ref().and_then(|sink| sinks.get(sink)) {
+        sink.set_volume(volume.gain(Channel::Music));
+    }
+}
+
+/// Plays the sound effects of the game changes, every effect at most once per frame.
+pub fn play_sound_effects(
+    audio: Res<Audio>,
+    sounds: Res<Sounds>,
+    volume: Res<Volume>,
+    mut changes: EventReader<TickChanged>,
+) {
+    let effects: HashSet<Sfx> = changes
+        .iter()
+        .filter_map(|event| Sfx::from_change(&event.change))
+        .collect();
+    if volume.muted {
+        return;
+    }
+    let settings = PlaybackSettings::ONCE.with_volume(volume.gain(Channel::Sfx));
+    for sound in effects.iter().filter_map(|sfx| sounds.effects.get(sfx)) {
+        audio.play_with_settings(sound.clone(), settings.clone());
+    }
+}
+
+/// Shown in the top right corner while the audio is muted.
+#[derive(Component)]
+pub struct MuteIndicator;
+
+/// Mutes or unmutes the audio by the mute key.
+pub fn toggle_mute(
+    mut commands: Commands,
+    ui: Res<UI>,
+    language: Res<Language>,
+    keys: Res<Input<KeyCode>>,
+    mut volume: ResMut<Volume>,
+    indicators: Query<Entity, With<MuteIndicator>>,
+) {
+    if !keys.just_pressed(MUTE_KEY) {
+        return;
+    }
+    volume.muted = !volume.muted;
+    if !volume.muted {
+        for indicator in indicators.iter() {
+            commands.entity(indicator).despawn_recursive();
+        }
+        return;
+    }
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                language.get(Key::Muted),
+                ui.text_style(FONT_SIZE, TEXT_COLOR),
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(MARGIN_SIZE),
+                    top: Val::Px(MARGIN_SIZE),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(MuteIndicator);
+}
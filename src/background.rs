@@ -0,0 +1,148 @@
+use bevy::{math::vec3, prelude::*};
+
+use crate::{
+    accessibility::{HighContrast, ReduceMotion},
+    cli::Cli,
+    game::GameRules,
+    player::Player,
+    settings::Settings,
+    window_width, GameEntity, GameOptions, WINDOW_HEIGHT,
+};
+
+/// Folder in the assets with the images which can be set as the background.
+const BACKGROUNDS_DIR: &str = "backgrounds";
+
+/// Number of horizontal stripes the gradient is made of.
+const STRIPE_COUNT: usize = 22;
+/// Number of levels sharing the same background.
+const LEVELS_PER_BAND: u32 = 5;
+/// Hues of the level bands, the last one is used for all higher levels.
+const BAND_HUES: [f32; 6] = [220., 180., 130., 50., 15., 300.];
+const MIN_LIGHTNESS: f32 = 0.04;
+const MAX_LIGHTNESS: f32 = 0.16;
+const WAVE_AMPLITUDE: f32 = 0.02;
+/// Speed of the wave on the first level band in radians per second, every band is faster.
+const WAVE_SPEED: f32 = 0.5;
+
+/// Background behind the boards.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BackgroundStyle {
+    /// Gradient which changes color and speeds up with the level.
+    Animated,
+    /// Plain background color.
+    Static,
+}
+
+impl BackgroundStyle {
+    pub fn from_cli(cli: &Cli) -> Self {
+        if cli.static_background {
+            BackgroundStyle::Static
+        } else {
+            BackgroundStyle::Animated
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct BackgroundStripe(usize);
+
+/// Image set by the `background` setting, it replaces the gradient.
+#[derive(Component)]
+pub struct BackgroundImage;
+
+pub fn spawn_background(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    style: Res<BackgroundStyle>,
+    options: Res<GameOptions>,
+    rules: Res<GameRules>,
+) {
+    if let Some(image) = settings.get("background") {
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: asset_server.load(&format!("{}/{}", BACKGROUNDS_DIR, image)),
+                transform: Transform::from_xyz(0., 0., -1.),
+                // hidden until the image is loaded and fitted to the window
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(BackgroundImage)
+            .insert(GameEntity);
+        return;
+    }
+    if *style == BackgroundStyle::Static {
+        return;
+    }
+
+    let width = window_width(options.player_count, &rules);
+    let stripe_height = WINDOW_HEIGHT / STRIPE_COUNT as f32;
+    for i in 0..STRIPE_COUNT {
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform {
+                    // behind everything else, from top to bottom
+                    translation: vec3(
+                        0.,
+                        WINDOW_HEIGHT / 2. - stripe_height * (i as f32 + 0.5),
+                        -1.,
+                    ),
+                    scale: vec3(width, stripe_height, 1.),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(BackgroundStripe(i))
+            .insert(GameEntity);
+    }
+}
+
+/// Colors the gradient by the level, the gradient waves unless motion is reduced and it's darker
+/// in the high contrast mode.
+pub fn update_background(
+    time: Res<Time>,
+    reduce_motion: Res<ReduceMotion>,
+    high_contrast: Res<HighContrast>,
+    players: Query<&Player>,
+    mut stripes: Query<(&BackgroundStripe, &mut Sprite)>,
+) {
+    let level = players
+        .iter()
+        .map(|p| p.game.score().level())
+        .max()
+        .unwrap_or(1);
+    let band = ((level - 1) / LEVELS_PER_BAND) as usize;
+    let hue = BAND_HUES[band.min(BAND_HUES.len() - 1)];
+    let phase = time.seconds_since_startup() as f32 * WAVE_SPEED * (band + 1) as f32;
+
+    for (stripe, mut sprite) in stripes.iter_mut() {
+        let depth = stripe.0 as f32 / (STRIPE_COUNT - 1) as f32;
+        let wave = if reduce_motion.0 {
+            0.
+        } else {
+            (phase + depth * std::f32::consts::TAU).sin() * WAVE_AMPLITUDE
+        };
+        let lightness = (MIN_LIGHTNESS + (MAX_LIGHTNESS - MIN_LIGHTNESS) * depth + wave)
+            * high_contrast.background_dimming();
+        sprite.color = Color::hsl(hue, 0.6, lightness.max(0.));
+    }
+}
+
+/// Scales the background image to cover the whole window, parts of the image outside of the
+/// window are cropped.
+pub fn fit_background_image(
+    images: Res<Assets<Image>>,
+    options: Res<GameOptions>,
+    rules: Res<GameRules>,
+    mut backgrounds: Query<(&Handle<Image>, &mut Sprite, &mut Visibility), With<BackgroundImage>>,
+) {
+    let window_size = Vec2::new(window_width(options.player_count, &rules), WINDOW_HEIGHT);
+    for (handle, mut sprite, mut visibility) in backgrounds.iter_mut() {
+        if let Some(image) = images.get(handle) {
+            let image_size = image.size();
+            let scale = (window_size / image_size).max_element();
+            sprite.custom_size = Some(image_size * scale);
+            visibility.is_visible = true;
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Terminal frontend playing the same core game as the Bevy frontend.
+
+use std::{
+    io::{self, Stdout},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame, Terminal,
+};
+use tetris::game::{self, Game, GameRules, GameView};
+
+const TICK_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
+/// Terminals don't report key releases, a key is held until this many ticks pass since its last
+/// press or repeat event.
+const HOLD_TICKS: u32 = 6;
+const CELL: &str = "██";
+const EMPTY_CELL: &str = " .";
+/// Shows where the active block lands.
+const GHOST_CELL: &str = "░░";
+
+/// Ticks left until each key is considered released.
+#[derive(Default)]
+struct TerminalInput {
+    move_left: u32,
+    move_right: u32,
+    rotate: u32,
+    fast_drop: u32,
+    instant_drop: u32,
+    use_item: u32,
+    hold: u32,
+}
+
+impl TerminalInput {
+    fn press(&mut self, key: KeyCode) {
+        let held = match key {
+            KeyCode::Left | KeyCode::Char('a') => &mut self.move_left,
+            KeyCode::Right | KeyCode::Char('d') => &mut self.move_right,
+            KeyCode::Up | KeyCode::Char('w') => &mut self.rotate,
+            KeyCode::Down | KeyCode::Char('s') => &mut self.fast_drop,
+            KeyCode::Char(' ') => &mut self.instant_drop,
+            KeyCode::Char('e') => &mut self.use_item,
+            KeyCode::Char('c') => &mut self.hold,
+            _ => return,
+        };
+        // the first press is reported for one tick only, so that the game sees the key
+        // released between the repeats of a tapped key
+        *held = if *held == 0 { 1 } else { HOLD_TICKS };
+    }
+
+    fn tick(&mut self) {
+        for held in [
+            &mut self.move_left,
+            &mut self.move_right,
+            &mut self.rotate,
+            &mut self.fast_drop,
+            &mut self.instant_drop,
+            &mut self.use_item,
+            &mut self.hold,
+        ] {
+            *held = held.saturating_sub(1);
+        }
+    }
+}
+
+impl game::Input for TerminalInput {
+    fn move_left(&self) -> bool {
+        self.move_left > 0
+    }
+
+    fn move_right(&self) -> bool {
+        self.move_right > 0
+    }
+
+    fn rotate(&self) -> bool {
+        self.rotate > 0
+    }
+
+    fn fast_drop(&self) -> bool {
+        self.fast_drop > 0
+    }
+
+    fn instant_drop(&self) -> bool {
+        self.instant_drop > 0
+    }
+
+    fn use_item(&self) -> bool {
+        self.use_item > 0
+    }
+
+    fn hold(&self) -> bool {
+        self.hold > 0
+    }
+}
+
+/// Colors of the blocks in the game window.
+fn block_color(block_type: game::BlockType) -> Color {
+    match block_type {
+        game::BlockType::I => Color::Rgb(0, 255, 255),
+        game::BlockType::J => Color::Rgb(0, 0, 255),
+        game::BlockType::L => Color::Rgb(255, 165, 0),
+        game::BlockType::O => Color::Rgb(255, 255, 0),
+        game::BlockType::S => Color::Rgb(0, 255, 0),
+        game::BlockType::T => Color::Rgb(128, 0, 128),
+        game::BlockType::Z => Color::Rgb(255, 0, 0),
+    }
+}
+
+fn point_color(point: &game::Point) -> Color {
+    match (point.item, point.origin_block_type) {
+        (Some(_), _) => Color::White,
+        (None, Some(block_type)) => block_color(block_type),
+        (None, None) => Color::Gray,
+    }
+}
+
+fn board_lines(view: &GameView) -> Vec<Line<'static>> {
+    let mut cells = [[None; game::BOARD_WIDTH]; game::VISIBLE_BOARD_HEIGHT];
+    for &pos in &view.ghost {
+        if let Some((x, row)) = view.gravity.visible_cell(pos) {
+            cells[row][x] = Some((GHOST_CELL, Color::DarkGray));
+        }
+    }
+    for (point, pos) in view.points() {
+        if let Some((x, row)) = view.gravity.visible_cell(pos) {
+            cells[row][x] = Some((CELL, point_color(&point)));
+        }
+    }
+
+    cells
+        .iter()
+        .map(|row| {
+            Line::from(
+                row.iter()
+                    .map(|cell| match cell {
+                        Some((cell, color)) => Span::styled(*cell, Style::default().fg(*color)),
+                        None => Span::styled(EMPTY_CELL, Style::default().fg(Color::DarkGray)),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+fn hud_lines(view: &GameView) -> Vec<Line<'static>> {
+    let score = view.hud.score;
+    let next = view
+        .queue
+        .iter()
+        .map(|block_type| format!("{:?}", block_type))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let held = view
+        .held_block
+        .map_or("-".to_string(), |block_type| format!("{:?}", block_type));
+    let mut lines = vec![
+        Line::from(format!("NEXT   {}", next)),
+        Line::from(format!("HOLD   {}", held)),
+        Line::from(""),
+        Line::from(format!("SCORE  {}", score.points)),
+        Line::from(format!("LINES  {}", score.lines)),
+        Line::from(format!("LEVEL  {}", score.level())),
+    ];
+    if let Some(item) = view.hud.item {
+        lines.push(Line::from(format!("ITEM   {}", item.name())));
+    }
+    lines.push(Line::from(""));
+    if view.game_over {
+        lines.push(Line::from("GAME OVER"));
+        lines.push(Line::from("R - RESTART"));
+    }
+    lines.push(Line::from("Q - QUIT"));
+    lines
+}
+
+fn draw(frame: &mut Frame, view: &GameView) {
+    let board_width = (game::BOARD_WIDTH * CELL.chars().count()) as u16 + 2;
+    let board_height = game::VISIBLE_BOARD_HEIGHT as u16 + 2;
+    let area = Rect {
+        width: frame.size().width.min(board_width + 24),
+        height: frame.size().height.min(board_height),
+        ..frame.size()
+    };
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(board_width), Constraint::Min(0)])
+        .split(area);
+
+    frame.render_widget(
+        Paragraph::new(board_lines(view)).block(Block::default().borders(Borders::ALL)),
+        columns[0],
+    );
+    frame.render_widget(
+        Paragraph::new(hud_lines(view)).block(Block::default().title("TETRIS")),
+        columns[1],
+    );
+}
+
+fn run(terminal: &mut Terminal<CrosstermBackend<Stdout>>, rules: GameRules) -> io::Result<()> {
+    let mut game = Game::with_rules(rules.clone());
+    let mut input = TerminalInput::default();
+    let mut next_tick = Instant::now();
+
+    loop {
+        let view = game.view();
+        terminal.draw(|frame| draw(frame, &view))?;
+
+        while event::poll(next_tick.saturating_duration_since(Instant::now()))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('r') if game.is_game_over() => {
+                        game = Game::with_rules(rules.clone());
+                        input = TerminalInput::default();
+                    }
+                    code => input.press(code),
+                }
+            }
+        }
+
+        game.tick(&input);
+        input.tick();
+        next_tick += TICK_DURATION;
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let mut rules = GameRules::new();
+    rules.items = args.iter().any(|a| a == "--items");
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = run(&mut terminal, rules);
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
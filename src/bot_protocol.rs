@@ -0,0 +1,105 @@
+use std::io::{self, BufRead, Write};
+
+use crate::game::{Game, GameRules, ReplayInput};
+
+const EMPTY_CELL: char = '.';
+const FILLED_CELL: char = '#';
+/// Actions of the standard input, in the order of the `ReplayInput::new` keys.
+const ACTIONS: [&str; 7] = ["left", "right", "rotate", "soft", "drop", "item", "hold"];
+
+/// Parses the space separated actions of a line of the standard input, an empty line presses
+/// nothing. An action given on consecutive lines is held like a key. Returns the index of the
+/// first unknown action if there is one.
+fn parse_actions(line: &str) -> Result<ReplayInput, usize> {
+    let mut held = [false; ACTIONS.len()];
+    for (index, action) in line.split_whitespace().enumerate() {
+        let key = ACTIONS.iter().position(|&a| a == action).ok_or(index)?;
+        held[key] = true;
+    }
+    let [left, right, rotate, soft, drop, item, hold] = held;
+    Ok(ReplayInput::new(
+        left, right, rotate, soft, drop, item, hold,
+    ))
+}
+
+/// Plays the game without the window for an external bot. The state of the game is printed as
+/// a JSON line before the first tick and after every tick, every line of the standard input
+/// is the input of the next tick. The game ends when it's over or the input is closed, lines
+/// with unknown actions don't tick and are answered with
+/// `{"error":"unknown_action","index":<index of the action in the line>}`.
+pub fn run(rules: GameRules, seed: Option<u64>) -> io::Result<()> {
+    let mut game = match seed {
+        Some(seed) => Game::with_seed(rules, seed),
+        None => Game::with_rules(rules),
+    };
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", state_json(&game))?;
+    stdout.flush()?;
+    for line in io::stdin().lock().lines() {
+        match parse_actions(&line?) {
+            Ok(input) => {
+                game.tick(&input);
+                writeln!(stdout, "{}", state_json(&game))?;
+            }
+            Err(index) => writeln!(
+                stdout,
+                "{{\"error\":\"unknown_action\",\"index\":{}}}",
+                index
+            )?,
+        }
+        // the bot waits for the answer before sending the next line
+        stdout.flush()?;
+        if game.is_game_over() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the JSON object of the state of the game. The board is a string of every visible
+/// row from the top, the cells of the active block are `[column, row]` pairs of the same rows,
+/// the spawned block is in the hidden rows outside of them.
+pub fn state_json(game: &Game) -> String {
+    let gravity = game.rules().gravity;
+    let width = game.rules().board_width();
+    let rows = gravity.visible_rows();
+    let mut board = vec![vec![EMPTY_CELL; width]; rows.len()];
+    for (x, row) in game
+        .board_points()
+        .filter_map(|(_, pos)| gravity.visible_cell(pos))
+    {
+        board[row][x] = FILLED_CELL;
+    }
+    let board = board
+        .iter()
+        .map(|row| format!("\"{}\"", row.iter().collect::<String>()))
+        .collect::<Vec<_>>();
+
+    let view = game.view();
+    let active_cells = view
+        .active_block
+        .iter()
+        .map(|&(_, (x, y))| format!("[{},{}]", x, y - rows.start as i32))
+        .collect::<Vec<_>>();
+    let queue = game
+        .next_blocks()
+        .map(|block_type| format!("\"{:?}\"", block_type))
+        .collect::<Vec<_>>();
+    let held = game.held_block().map_or("null".to_string(), |block_type| {
+        format!("\"{:?}\"", block_type)
+    });
+    let score = game.score();
+    format!(
+        "{{\"tick\":{},\"score\":{},\"lines\":{},\"game_over\":{},\"active\":\"{:?}\",\
+        \"active_cells\":[{}],\"queue\":[{}],\"held\":{},\"board\":[{}]}}",
+        score.ticks,
+        score.points,
+        score.lines,
+        game.is_game_over(),
+        view.active_block_type,
+        active_cells.join(","),
+        queue.join(","),
+        held,
+        board.join(",")
+    )
+}
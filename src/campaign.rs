@@ -0,0 +1,306 @@
+use std::{fs, io};
+
+use bevy::prelude::*;
+
+use crate::{
+    game::{BotLevel, Game, GameRules, GarbageStyle, Gravity},
+    i18n::{Key, Language},
+    paths,
+    player::Player,
+    toast,
+    versus::Match,
+    AppState, GameOptions, TICK_DURATION, UI,
+};
+
+/// Name of the campaign progress file in the data directory.
+pub const CAMPAIGN_FILE: &str = "campaign.txt";
+
+/// Seconds the result of the stage is shown before returning to the menu.
+const RESULT_DURATION: f32 = 2.;
+const PREVIOUS_STAGE_KEY: KeyCode = KeyCode::Left;
+const NEXT_STAGE_KEY: KeyCode = KeyCode::Right;
+
+/// What the player has to do to clear the stage.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Goal {
+    /// Clear the lines before the game is over.
+    Lines(u32),
+    /// Clear the lines with garbage of the drill.
+    Dig(u32),
+    /// Win the versus match against the bot.
+    Defeat(BotLevel),
+}
+
+/// Rules and goal of a campaign stage, other rules are the default ones.
+struct Stage {
+    speed_level: u32,
+    /// Seconds between garbage rows of the drill.
+    drill_interval: Option<f32>,
+    big: bool,
+    gravity: Gravity,
+    goal: Goal,
+}
+
+const STAGES: [Stage; 10] = [
+    Stage {
+        speed_level: 1,
+        drill_interval: None,
+        big: false,
+        gravity: Gravity::Down,
+        goal: Goal::Lines(20),
+    },
+    Stage {
+        speed_level: 5,
+        drill_interval: None,
+        big: false,
+        gravity: Gravity::Down,
+        goal: Goal::Lines(30),
+    },
+    Stage {
+        speed_level: 3,
+        drill_interval: Some(4.),
+        big: false,
+        gravity: Gravity::Down,
+        goal: Goal::Dig(10),
+    },
+    Stage {
+        speed_level: 1,
+        drill_interval: None,
+        big: false,
+        gravity: Gravity::Down,
+        goal: Goal::Defeat(BotLevel::Easy),
+    },
+    Stage {
+        speed_level: 8,
+        drill_interval: None,
+        big: false,
+        gravity: Gravity::Down,
+        goal: Goal::Lines(40),
+    },
+    Stage {
+        speed_level: 4,
+        drill_interval: None,
+        big: true,
+        gravity: Gravity::Down,
+        goal: Goal::Lines(20),
+    },
+    Stage {
+        speed_level: 5,
+        drill_interval: Some(2.5),
+        big: false,
+        gravity: Gravity::Down,
+        goal: Goal::Dig(20),
+    },
+    Stage {
+        speed_level: 5,
+        drill_interval: None,
+        big: false,
+        gravity: Gravity::Up,
+        goal: Goal::Lines(30),
+    },
+    Stage {
+        speed_level: 5,
+        drill_interval: None,
+        big: false,
+        gravity: Gravity::Down,
+        goal: Goal::Defeat(BotLevel::Hard),
+    },
+    Stage {
+        speed_level: 10,
+        drill_interval: None,
+        big: false,
+        gravity: Gravity::Down,
+        goal: Goal::Defeat(BotLevel::Brutal),
+    },
+];
+
+impl Stage {
+    fn rules(&self) -> GameRules {
+        let mut rules = GameRules::new();
+        rules.speed_level = self.speed_level;
+        rules.drill_interval = self
+            .drill_interval
+            .map(|seconds| (seconds / TICK_DURATION).round() as u32);
+        rules.garbage_style = GarbageStyle::Cheese;
+        rules.big = self.big;
+        rules.gravity = self.gravity;
+        rules
+    }
+
+    fn bot_level(&self) -> Option<BotLevel> {
+        match self.goal {
+            Goal::Defeat(level) => Some(level),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the single player game reached the goal, boss stages are cleared by
+    /// winning the match instead.
+    fn is_cleared(&self, game: &Game) -> bool {
+        let score = game.score();
+        match self.goal {
+            Goal::Lines(lines) => score.lines >= lines,
+            Goal::Dig(lines) => score.garbage_lines >= lines,
+            Goal::Defeat(_) => false,
+        }
+    }
+
+    fn describe(&self, language: Language) -> String {
+        match self.goal {
+            Goal::Lines(lines) => language.format(Key::GoalLines, &[&lines]),
+            Goal::Dig(lines) => language.format(Key::GoalDig, &[&lines]),
+            Goal::Defeat(level) => {
+                language.format(Key::GoalDefeat, &[&language.bot_level_name(level)])
+            }
+        }
+    }
+}
+
+/// Progress of the single player campaign, its stages are unlocked one after another by
+/// clearing the previous one.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Campaign {
+    /// Index of the stage played next.
+    stage: usize,
+    /// Number of cleared stages, saved in the campaign file of the profile.
+    cleared: usize,
+}
+
+impl Campaign {
+    /// Loads the progress from the campaign file with a `cleared=<stages>` line, the first
+    /// stage which isn't cleared yet is selected.
+    pub fn load() -> Self {
+        let file = fs::read_to_string(paths::data_path(CAMPAIGN_FILE)).unwrap_or_default();
+        let cleared = file
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|&(name, _)| name == "cleared")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(0);
+        Self {
+            stage: Self::last_unlocked(cleared),
+            cleared,
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        fs::write(
+            paths::data_path(CAMPAIGN_FILE),
+            format!("cleared={}\n", self.cleared),
+        )
+    }
+
+    fn last_unlocked(cleared: usize) -> usize {
+        cleared.min(STAGES.len() - 1)
+    }
+
+    fn stage(&self) -> &'static Stage {
+        &STAGES[self.stage]
+    }
+
+    /// Sets the player count, the bot opponent and the rules to the ones of the selected stage,
+    /// the boss stages are matches against the bot.
+    pub fn apply(&self, options: &mut GameOptions, rules: &mut GameRules) {
+        let stage = self.stage();
+        *rules = stage.rules();
+        options.bot_opponent = stage.bot_level().is_some();
+        options.player_count = if options.bot_opponent { 2 } else { 1 };
+        if let Some(level) = stage.bot_level() {
+            options.bot_level = level;
+        }
+    }
+
+    /// Marks the selected stage as cleared, which unlocks and selects the next one.
+    fn stage_cleared(&mut self) {
+        self.cleared = self.cleared.max(self.stage + 1);
+        self.stage = Self::last_unlocked(self.stage + 1);
+        if let Err(e) = self.save() {
+            error!("failed to save the campaign: {}", e);
+        }
+    }
+
+    pub fn describe(&self, language: Language) -> String {
+        format!(
+            "{}\n{}",
+            language.format(Key::CampaignStage, &[&(self.stage + 1), &STAGES.len()]),
+            self.stage().describe(language)
+        )
+    }
+}
+
+/// Selects the previous or the next unlocked stage in the menu, returns whether the selection
+/// changed.
+pub fn select_stage(campaign: &mut Campaign, keys: &Input<KeyCode>) -> bool {
+    let stage = campaign.stage;
+    if keys.just_pressed(PREVIOUS_STAGE_KEY) {
+        campaign.stage = stage.saturating_sub(1);
+    } else if keys.just_pressed(NEXT_STAGE_KEY) {
+        campaign.stage = (stage + 1).min(Campaign::last_unlocked(campaign.cleared));
+    }
+    campaign.stage != stage
+}
+
+/// The stage being played, the result is shown for a while before returning to the menu.
+#[derive(Default)]
+pub struct CampaignSession {
+    result_timer: Option<Timer>,
+}
+
+pub fn update_campaign(
+    mut commands: Commands,
+    session: Option<ResMut<CampaignSession>>,
+    mut options: ResMut<GameOptions>,
+    players: Query<&Player>,
+    versus_match: Option<Res<Match>>,
+    ui: Res<UI>,
+    language: Res<Language>,
+    time: Res<Time>,
+    mut state: ResMut<State<AppState>>,
+) {
+    // the session is created on entering the game
+    let mut session = match session {
+        Some(session) => session,
+        None => return,
+    };
+    let campaign = match &mut options.campaign {
+        Some(campaign) => campaign,
+        None => return,
+    };
+
+    if let Some(timer) = &mut session.result_timer {
+        if timer.tick(time.delta()).finished() {
+            state.set(AppState::Menu).unwrap();
+        }
+        return;
+    }
+
+    let stage = campaign.stage();
+    let cleared = match &versus_match {
+        Some(versus_match) => match versus_match.winner() {
+            Some(winner) => winner == 0,
+            None => return,
+        },
+        None => {
+            let player = match players.iter().find(|player| player.index == 0) {
+                Some(player) => player,
+                None => return,
+            };
+            if stage.is_cleared(&player.game) {
+                true
+            } else if player.game.is_game_over() {
+                false
+            } else {
+                return;
+            }
+        }
+    };
+
+    let key = if cleared {
+        campaign.stage_cleared();
+        Key::StageCleared
+    } else {
+        Key::StageFailed
+    };
+    toast::spawn_toast(&mut commands, &ui, language.get(key));
+    session.result_timer = Some(Timer::from_seconds(RESULT_DURATION, false));
+}
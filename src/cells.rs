@@ -0,0 +1,129 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::DEFAULT_IMAGE_HANDLE,
+    },
+};
+
+use crate::{settings::Settings, UNIT_PX};
+
+/// Folder in the assets with the cell images which can be set as the skin.
+const SKINS_DIR: &str = "skins";
+/// Size of the cell textures, the cells are scaled to the unit size.
+const CELL_TEXTURE_SIZE: u32 = UNIT_PX as u32;
+const DEFAULT_GAP: u32 = 1;
+const MAX_GAP: u32 = 2;
+const BEVEL_WIDTH: u32 = 2;
+/// Shades of the cell texture which tints the point color, the fill is a bit darker than the
+/// lit top left edge.
+const HIGHLIGHT_SHADE: u8 = 255;
+const FILL_SHADE: u8 = 215;
+const SHADOW_SHADE: u8 = 140;
+/// Slightly lighter center of the shaded cell.
+const INNER_SHADE: u8 = 230;
+
+/// Look of the board points given by the `cell_gap` and `cell_shading` settings. The gap keeps
+/// the cells of a block apart and the shading draws each cell with a bevel, `cell_gap=0` with
+/// `cell_shading=false` draws the points as solid quads.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CellStyle {
+    /// Pixels between two neighbouring cells.
+    pub gap: u32,
+    pub shading: bool,
+    /// Image of the skins folder set by the `skin` setting, it replaces the drawn cell and is
+    /// tinted by the point colors.
+    pub skin: Option<String>,
+}
+
+impl CellStyle {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            gap: settings
+                .get("cell_gap")
+                .and_then(|gap| gap.parse().ok())
+                .unwrap_or(DEFAULT_GAP)
+                .min(MAX_GAP),
+            shading: settings.get("cell_shading") != Some("false"),
+            skin: settings.get("skin").map(String::from),
+        }
+    }
+
+    fn is_solid(&self) -> bool {
+        self.gap == 0 && !self.shading
+    }
+
+    /// Returns texture of a cell whose pixels are given by `shade` from their distances to the
+    /// left, top, right and bottom edge of the cell without the gap. The gap is left transparent
+    /// on the top and right side, so the cells of the whole board are spaced evenly.
+    pub fn cell_image(&self, shade: impl Fn([u32; 4]) -> [u8; 4]) -> Image {
+        let size = CELL_TEXTURE_SIZE;
+        let inner = size - self.gap;
+        let data = (0..size * size)
+            .flat_map(|i| {
+                let (x, y) = (i % size, i / size);
+                // texture rows go from the top
+                if x >= inner || y < self.gap {
+                    return [0; 4];
+                }
+                let y = y - self.gap;
+                shade([x, y, inner - 1 - x, inner - 1 - y])
+            })
+            .collect();
+        Image::new(
+            Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    fn shaded_cell(&self) -> Image {
+        self.cell_image(|[left, top, right, bottom]| {
+            let lit = left.min(top) < BEVEL_WIDTH && left.min(top) <= right.min(bottom);
+            let shade = if !self.shading || lit {
+                HIGHLIGHT_SHADE
+            } else if right.min(bottom) < BEVEL_WIDTH {
+                SHADOW_SHADE
+            } else if left.min(top).min(right).min(bottom) >= BEVEL_WIDTH * 2 {
+                INNER_SHADE
+            } else {
+                FILL_SHADE
+            };
+            [shade, shade, shade, 255]
+        })
+    }
+}
+
+/// Texture the points are drawn with outside of the high contrast mode.
+pub struct CellTexture(pub Handle<Image>);
+
+impl Default for CellTexture {
+    fn default() -> Self {
+        Self(DEFAULT_IMAGE_HANDLE.typed())
+    }
+}
+
+/// Draws the cell texture of the style, when the settings are loaded or reloaded. Skins are
+/// loaded as assets, so with the `hot-reload` feature the points follow the edits of the image.
+pub fn update_cell_texture(
+    style: Res<CellStyle>,
+    asset_server: Res<AssetServer>,
+    mut texture: ResMut<CellTexture>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !style.is_changed() {
+        return;
+    }
+    *texture = if let Some(skin) = &style.skin {
+        CellTexture(asset_server.load(&format!("{}/{}", SKINS_DIR, skin)))
+    } else if style.is_solid() {
+        CellTexture::default()
+    } else {
+        CellTexture(images.add(style.shaded_cell()))
+    };
+}
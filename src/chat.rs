@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+use crate::{
+    i18n::{Key, Language},
+    netplay::Netplay,
+    UI,
+};
+
+/// Opens the chat, sends the typed message and closes it.
+const TOGGLE_KEY: KeyCode = KeyCode::Return;
+/// Messages sent by a single key, also while the chat is open.
+const QUICK_CHAT: [(KeyCode, &str); 4] = [
+    (KeyCode::F1, "GL HF"),
+    (KeyCode::F2, "GG"),
+    (KeyCode::F3, "NICE!"),
+    (KeyCode::F4, "OOPS"),
+];
+const MAX_MESSAGE_LEN: usize = 60;
+/// Seconds the last message stays visible.
+const MESSAGE_DURATION: f32 = 5.;
+const FONT_SIZE: f32 = 12.;
+const TEXT_COLOR: Color = Color::WHITE;
+const MARGIN_SIZE: f32 = 4.;
+
+/// Text chat of online matches. The last message is shown in the margin above the boards, so
+/// the chat never covers them.
+#[derive(Default)]
+pub struct Chat {
+    open: bool,
+    line: String,
+    last_message: Option<(String, Timer)>,
+}
+
+impl Chat {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+#[derive(Component)]
+pub struct ChatText;
+
+pub fn spawn_chat_text(commands: &mut Commands, ui: &UI) -> Entity {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section("", ui.text_style(FONT_SIZE, TEXT_COLOR)).with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(MARGIN_SIZE),
+                    top: Val::Px(MARGIN_SIZE),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ChatText)
+        .id()
+}
+
+pub fn update_chat(
+    mut chat: ResMut<Chat>,
+    netplay: Option<ResMut<Netplay>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    language: Res<Language>,
+    time: Res<Time>,
+    mut texts: Query<&mut Text, With<ChatText>>,
+) {
+    let mut netplay = match netplay {
+        Some(netplay) if netplay.is_connected() => netplay,
+        _ => return,
+    };
+    let player_name = |index: usize| language.format(Key::PlayerNumber, &[&(index + 1)]);
+    let local = player_name(netplay.local_index());
+    let opponent = player_name(1 - netplay.local_index());
+
+    let mut sent = QUICK_CHAT
+        .iter()
+        .filter(|(key, _)| keys.just_pressed(*key))
+        .map(|(_, message)| message.to_string())
+        .collect::<Vec<_>>();
+    if chat.open {
+        for c in chars.iter() {
+            if c.char == '\u{8}' {
+                chat.line.pop();
+            } else if !c.char.is_control() && chat.line.chars().count() < MAX_MESSAGE_LEN {
+                chat.line.push(c.char);
+            }
+        }
+        if keys.just_pressed(TOGGLE_KEY) {
+            chat.open = false;
+            let line = std::mem::take(&mut chat.line);
+            if !line.trim().is_empty() {
+                sent.push(line.trim().to_string());
+            }
+        }
+        // keys typed to the chat shouldn't trigger other hotkeys
+        keys.clear();
+    } else {
+        chars.clear();
+        if keys.just_pressed(TOGGLE_KEY) {
+            chat.open = true;
+            keys.clear();
+        }
+    }
+
+    for message in sent {
+        netplay.send_chat(&message);
+        chat.last_message = Some((
+            format!("{}: {}", local, message),
+            Timer::from_seconds(MESSAGE_DURATION, false),
+        ));
+    }
+    for message in netplay.take_chat() {
+        chat.last_message = Some((
+            format!("{}: {}", opponent, message),
+            Timer::from_seconds(MESSAGE_DURATION, false),
+        ));
+    }
+    if let Some((_, timer)) = &mut chat.last_message {
+        if timer.tick(time.delta()).finished() {
+            chat.last_message = None;
+        }
+    }
+
+    let text = if chat.open {
+        format!("> {}_", chat.line)
+    } else {
+        chat.last_message
+            .as_ref()
+            .map_or(String::new(), |(message, _)| message.clone())
+    };
+    for mut chat_text in texts.iter_mut() {
+        chat_text.sections[0].value = text.clone();
+    }
+}
@@ -0,0 +1,235 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use tetris::game::{BotLevel, GarbageStyle, DEFAULT_REPEAT_CHANCE};
+
+/// Launch configuration given by the command line, so the game can be scripted. Modules read
+/// the flags they use from it.
+#[derive(Parser, Debug)]
+#[command(
+    name = "tetris",
+    about = "Falling blocks game with marathon, drill, practice and versus modes",
+    long_about = None
+)]
+pub struct Cli {
+    /// Mode started from the menu.
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+    /// Seed of the single player game, games with the same seed deal the same blocks.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Settings file used instead of the one in the config directory.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Overrides a setting of the settings file, may be given several times.
+    #[arg(long = "set", value_name = "NAME=VALUE", value_parser = parse_setting)]
+    pub settings: Vec<(String, String)>,
+    /// Replay played in the replay viewer instead of a game.
+    #[arg(long, value_name = "FILE", alias = "view-replay")]
+    pub replay: Option<PathBuf>,
+    /// Plays the game without the window and prints its result, the bot plays it unless a
+    /// replay is given.
+    #[arg(long)]
+    pub headless: bool,
+    /// Plays the game without the window by the actions read from the standard input and prints
+    /// its state as a JSON line after every tick, for bots written in any language.
+    #[arg(long)]
+    pub bot_protocol: bool,
+
+    /// Two players share the keyboard.
+    #[arg(long)]
+    pub versus: bool,
+    /// Versus game against the bot.
+    #[arg(long)]
+    pub vs_ai: bool,
+    /// Difficulty of the bot opponent.
+    #[arg(long, value_name = "LEVEL", value_parser = parse_bot_level)]
+    pub ai_level: Option<BotLevel>,
+    /// Number of games of a versus match, the match is won by winning most of them.
+    #[arg(long, value_name = "GAMES")]
+    pub best_of: Option<usize>,
+    /// Game of the day, everyone plays the same blocks.
+    #[arg(long)]
+    pub daily: bool,
+    /// Stages with their own rules and goals.
+    #[arg(long)]
+    pub campaign: bool,
+    /// Practice of long combos.
+    #[arg(long)]
+    pub combo_practice: bool,
+    /// Practice of perfect clear openers.
+    #[arg(long)]
+    pub pc_practice: bool,
+    /// Practice of the zig-zag pattern of holes graded by its height.
+    #[arg(long)]
+    pub secret_grade: bool,
+    /// Practice of the board and blocks of a fumen diagram, given by the diagram or a file
+    /// with it.
+    #[arg(long, value_name = "DIAGRAM")]
+    pub fumen: Option<String>,
+
+    /// Blocks may carry items.
+    #[arg(long)]
+    pub items: bool,
+    /// Shapes of all blocks are flipped horizontally.
+    #[arg(long)]
+    pub mirror: bool,
+    /// Every mino is made of 2x2 points.
+    #[arg(long)]
+    pub big: bool,
+    /// Two pilots share the board of a single player.
+    #[arg(long)]
+    pub coop: bool,
+    /// Board is two boards wide with the player spawning in its middle.
+    #[arg(long)]
+    pub doubles: bool,
+    /// Blocks fall up.
+    #[arg(long)]
+    pub flipped_gravity: bool,
+    /// Garbage row rises every this many seconds, 3 unless given.
+    #[arg(long, value_name = "SECONDS", num_args = 0..=1, require_equals = true)]
+    pub drill: Option<Option<f32>>,
+    /// Ticks between a block locking and the next spawning.
+    #[arg(long, value_name = "TICKS")]
+    pub are: Option<u32>,
+    /// Ticks the filled rows stay on the board.
+    #[arg(long, value_name = "TICKS")]
+    pub line_clear: Option<u32>,
+    /// Holes of the garbage rows: clean, cheese, random or random:<repeat chance>.
+    #[arg(long, value_name = "STYLE", value_parser = parse_garbage_style)]
+    pub garbage: Option<GarbageStyle>,
+
+    /// Hosts a network game on the port, the default one unless given.
+    #[arg(
+        long,
+        value_name = "PORT",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with = "connect"
+    )]
+    pub host: Option<Option<u16>>,
+    /// Joins the network game of the room code or the address.
+    #[arg(long, value_name = "ROOM")]
+    pub connect: Option<String>,
+
+    /// Scale of the window, chosen by the monitor height unless given.
+    #[arg(long, value_name = "SCALE")]
+    pub ui_scale: Option<f64>,
+    /// Locked points disappear right away.
+    #[arg(long, conflicts_with = "fade")]
+    pub invisible: bool,
+    /// Locked points fade out.
+    #[arg(long)]
+    pub fade: bool,
+    /// Plain background color instead of the animated gradient.
+    #[arg(long)]
+    pub static_background: bool,
+    /// Left and right controls are swapped.
+    #[arg(long)]
+    pub mirror_input: bool,
+
+    /// Exports the replay as a GIF next to it and exits.
+    #[arg(long, value_name = "REPLAY")]
+    pub export_gif: Option<PathBuf>,
+    /// Exports the frames of the replay as PNG images next to it and exits.
+    #[arg(long, value_name = "REPLAY")]
+    pub export_png: Option<PathBuf>,
+    /// Exports the settings, records, stats and campaign progress to the archive and exits.
+    #[arg(long, value_name = "ARCHIVE")]
+    pub export_profile: Option<PathBuf>,
+    /// Imports the profile exported to the archive and exits.
+    #[arg(long, value_name = "ARCHIVE")]
+    pub import_profile: Option<PathBuf>,
+    /// Rewrites the replay in the current format and exits.
+    #[arg(long, value_name = "REPLAY")]
+    pub upgrade_replay: Option<PathBuf>,
+    /// Prints the statistics of the replay and exits.
+    #[arg(long, value_name = "REPLAY")]
+    pub analyze_replay: Option<PathBuf>,
+
+    /// Online leaderboard the results are submitted to.
+    #[arg(long, value_name = "URL")]
+    pub leaderboard: Option<String>,
+    /// Name of the player on the leaderboard.
+    #[arg(long, value_name = "NAME")]
+    pub player: Option<String>,
+    /// Address of the WebSocket server sending the state of the games.
+    #[arg(long, value_name = "ADDRESS")]
+    pub state_server: Option<String>,
+}
+
+/// Mode of the `--mode` flag, the same as the flag it stands for.
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// The sprint is timed in the first lines of the marathon.
+    #[value(alias = "sprint")]
+    Marathon,
+    Drill,
+    Daily,
+    Campaign,
+    Combo,
+    #[value(alias = "pc")]
+    PerfectClear,
+    SecretGrade,
+    Versus,
+    VsAi,
+}
+
+impl Mode {
+    /// Sets the flag which starts the mode.
+    fn apply(self, cli: &mut Cli) {
+        match self {
+            Mode::Marathon => {}
+            Mode::Drill => {
+                cli.drill.get_or_insert(None);
+            }
+            Mode::Daily => cli.daily = true,
+            Mode::Campaign => cli.campaign = true,
+            Mode::Combo => cli.combo_practice = true,
+            Mode::PerfectClear => cli.pc_practice = true,
+            Mode::SecretGrade => cli.secret_grade = true,
+            Mode::Versus => cli.versus = true,
+            Mode::VsAi => cli.vs_ai = true,
+        }
+    }
+}
+
+impl Cli {
+    /// Parses the command line with the flag of the mode set. Invalid flags exit with the
+    /// usage, `--help` prints it.
+    pub fn parse_args() -> Self {
+        let mut cli = Self::parse();
+        if let Some(mode) = cli.mode {
+            mode.apply(&mut cli);
+        }
+        cli
+    }
+}
+
+fn parse_setting(arg: &str) -> Result<(String, String), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got {}", arg))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+fn parse_bot_level(name: &str) -> Result<BotLevel, String> {
+    BotLevel::from_name(name).ok_or_else(|| {
+        let names = BotLevel::ALL.map(|level| level.name());
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+fn parse_garbage_style(style: &str) -> Result<GarbageStyle, String> {
+    match style {
+        "clean" => Ok(GarbageStyle::Clean),
+        "cheese" => Ok(GarbageStyle::Cheese),
+        "random" => Ok(GarbageStyle::Random {
+            repeat_chance: DEFAULT_REPEAT_CHANCE,
+        }),
+        _ => match style.strip_prefix("random:").map(str::parse) {
+            Some(Ok(repeat_chance)) => Ok(GarbageStyle::Random { repeat_chance }),
+            _ => Err("expected clean, cheese, random or random:<repeat chance>".to_string()),
+        },
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{self, BlockType},
+    player::Player,
+    respawn_points, UI,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+const FONT_SIZE: f32 = 14.;
+const TEXT_COLOR: Color = Color::WHITE;
+
+const HELP: &str = "spawn <I|J|L|O|S|T|Z>, level <n>, clearboard, seed <n>, fumen <diagram>";
+
+/// Debug console toggled by the backtick key. Commands are applied to games of all human
+/// players.
+#[derive(Default)]
+pub struct Console {
+    open: bool,
+    line: String,
+    /// Result of the last command.
+    output: String,
+}
+
+impl Console {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+#[derive(Component)]
+pub struct ConsoleText;
+
+pub fn setup_console(mut commands: Commands, ui: Res<UI>) {
+    let text_style = ui.text_style(FONT_SIZE, TEXT_COLOR);
+    commands
+        .spawn_bundle(
+            TextBundle::from_sections([
+                TextSection::new("", text_style.clone()),
+                TextSection::new("", text_style),
+            ])
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(0.),
+                    top: Val::Px(0.),
+                    right: Val::Px(0.),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(Visibility { is_visible: false })
+        .insert(ConsoleText);
+}
+
+pub fn update_console(
+    mut commands: Commands,
+    mut console: ResMut<Console>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut players: Query<(Entity, &mut Player)>,
+    rules: Res<game::GameRules>,
+    time: Res<Time>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<ConsoleText>>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+        keys.clear();
+    }
+    for (mut text, mut visibility) in texts.iter_mut() {
+        visibility.is_visible = console.open;
+        text.sections[0].value = format!("> {}\n", console.line);
+        text.sections[1].value = console.output.clone();
+    }
+    if !console.open {
+        chars.clear();
+        return;
+    }
+
+    for c in chars.iter() {
+        if c.char == '\r' || c.char == '\n' {
+            let line = std::mem::take(&mut console.line);
+            console.output = execute(
+                &line,
+                &mut commands,
+                &mut players,
+                &rules,
+                time.seconds_since_startup(),
+            );
+        } else if c.char == '\u{8}' {
+            console.line.pop();
+        } else if !c.char.is_control() && c.char != '`' {
+            console.line.push(c.char);
+        }
+    }
+    // keys typed to the console shouldn't trigger other hotkeys
+    keys.clear();
+}
+
+/// Runs the command and returns its output.
+fn execute(
+    line: &str,
+    commands: &mut Commands,
+    players: &mut Query<(Entity, &mut Player)>,
+    rules: &game::GameRules,
+    now: f64,
+) -> String {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some(command) => command,
+        None => return String::new(),
+    };
+    let arg = words.next();
+
+    let change: Box<dyn Fn(&mut game::Game)> = match (command, arg) {
+        ("spawn", Some(arg)) => {
+            let block_type = BlockType::ALL
+                .into_iter()
+                .find(|t| format!("{:?}", t).eq_ignore_ascii_case(arg));
+            match block_type {
+                Some(block_type) => Box::new(move |game| game.debug_spawn_block(block_type)),
+                None => return format!("unknown block: {}", arg),
+            }
+        }
+        ("level", Some(arg)) => match arg.parse() {
+            Ok(level) => Box::new(move |game| game.debug_set_level(level)),
+            Err(_) => return format!("invalid level: {}", arg),
+        },
+        ("clearboard", None) => Box::new(|game| game.debug_clear_board()),
+        ("seed", Some(arg)) => match arg.parse() {
+            Ok(seed) => {
+                let rules = rules.clone();
+                Box::new(move |game| *game = game::Game::with_seed(rules.clone(), seed))
+            }
+            Err(_) => return format!("invalid seed: {}", arg),
+        },
+        ("fumen", Some(arg)) => match game::Fumen::decode(arg) {
+            Ok(fumen) => {
+                let rules = rules.clone();
+                Box::new(move |game| *game = fumen.new_game(rules.clone()))
+            }
+            Err(e) => return format!("invalid fumen: {}", e),
+        },
+        _ => return format!("unknown command, try: {}", HELP),
+    };
+
+    let mut changed = 0;
+    for (player_entity, mut player) in players.iter_mut() {
+        if player.bot.is_some() {
+            continue;
+        }
+        change(&mut player.game);
+        respawn_points(commands, player_entity, &mut player, now);
+        changed += 1;
+    }
+    if changed == 0 {
+        return "no game to change".to_string();
+    }
+    "ok".to_string()
+}
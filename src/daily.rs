@@ -0,0 +1,35 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Returns the number of days since the Unix epoch in UTC, so the day changes at the same moment
+/// for everyone.
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / SECONDS_PER_DAY
+}
+
+/// Returns the seed of the daily challenge.
+pub fn seed(day: u64) -> u64 {
+    // spread consecutive days over the whole seed range
+    day.wrapping_mul(0x9e3779b97f4a7c15)
+}
+
+/// Formats the day as `YYYY-MM-DD`.
+pub fn format_day(day: u64) -> String {
+    // civil date from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = day as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let d = day_of_year - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = year_of_era + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
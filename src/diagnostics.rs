@@ -0,0 +1,116 @@
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, EntityCountDiagnosticsPlugin,
+        FrameTimeDiagnosticsPlugin,
+    },
+    prelude::*,
+};
+
+use crate::{player::Player, TICK_DURATION, UI};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F6;
+const FONT_SIZE: f32 = 14.;
+const TEXT_COLOR: Color = Color::WHITE;
+const MARGIN_SIZE: f32 = 4.;
+/// Seconds the game ticks are counted for one measurement of the tick rate.
+const TICK_RATE_INTERVAL: f64 = 0.5;
+const TICK_RATE_HISTORY_LEN: usize = 10;
+
+/// Game ticks played per second.
+const TICK_RATE: DiagnosticId = DiagnosticId::from_u128(0x6a1f_52c4_0e9b_4d2a_9f3e_71b8_c0d4_5e21);
+
+/// Frame time, tick rate, entity count and input latency in the bottom right corner.
+#[derive(Component)]
+pub struct DiagnosticsOverlay;
+
+pub fn setup_diagnostics(mut diagnostics: ResMut<Diagnostics>) {
+    diagnostics.add(Diagnostic::new(
+        TICK_RATE,
+        "tick_rate",
+        TICK_RATE_HISTORY_LEN,
+    ));
+}
+
+/// Measures the tick rate of the players' games, paused games count as zero.
+pub fn measure_tick_rate(
+    time: Res<Time>,
+    players: Query<&Player>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut last_measurement: Local<(f64, u32)>,
+) {
+    let now = time.seconds_since_startup();
+    let ticks = match players.iter().map(|p| p.game.score().ticks).max() {
+        Some(ticks) => ticks,
+        None => return,
+    };
+    let (last_time, last_ticks) = *last_measurement;
+    let elapsed = now - last_time;
+    if elapsed < TICK_RATE_INTERVAL {
+        return;
+    }
+    // a restarted game starts counting again
+    let played = ticks.saturating_sub(last_ticks);
+    diagnostics.add_measurement(TICK_RATE, || played as f64 / elapsed);
+    *last_measurement = (now, ticks);
+}
+
+/// Shows or hides the overlay.
+pub fn toggle_diagnostics(
+    mut commands: Commands,
+    ui: Res<UI>,
+    keys: Res<Input<KeyCode>>,
+    overlays: Query<Entity, With<DiagnosticsOverlay>>,
+) {
+    if !keys.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    if !overlays.is_empty() {
+        for overlay in overlays.iter() {
+            commands.entity(overlay).despawn_recursive();
+        }
+        return;
+    }
+    commands
+        .spawn_bundle(
+            TextBundle::from_section("", ui.text_style(FONT_SIZE, TEXT_COLOR))
+                .with_text_alignment(TextAlignment::TOP_RIGHT)
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        right: Val::Px(MARGIN_SIZE),
+                        bottom: Val::Px(MARGIN_SIZE),
+                        ..default()
+                    },
+                    ..default()
+                }),
+        )
+        .insert(DiagnosticsOverlay);
+}
+
+pub fn update_diagnostics(
+    diagnostics: Res<Diagnostics>,
+    mut overlays: Query<&mut Text, With<DiagnosticsOverlay>>,
+) {
+    let average = |id| diagnostics.get(id).and_then(Diagnostic::average);
+    let frame_time = average(FrameTimeDiagnosticsPlugin::FRAME_TIME);
+    let fps = average(FrameTimeDiagnosticsPlugin::FPS);
+    let tick_rate = average(TICK_RATE);
+    let entities = average(EntityCountDiagnosticsPlugin::ENTITY_COUNT);
+    // a press waits half a tick on average for the fixed timestep and is shown on the next frame
+    let latency = frame_time.map(|frame_time| frame_time + TICK_DURATION as f64 / 2.);
+
+    let value = |value: Option<f64>, decimals: usize| match value {
+        Some(value) => format!("{:.*}", decimals, value),
+        None => "-".to_string(),
+    };
+    for mut text in overlays.iter_mut() {
+        text.sections[0].value = format!(
+            "FPS {}\nFRAME {} MS\nTICKS {}/S\nENTITIES {}\nINPUT LATENCY ~{} MS",
+            value(fps, 0),
+            value(frame_time.map(|t| t * 1000.), 1),
+            value(tick_rate, 0),
+            value(entities, 0),
+            value(latency.map(|t| t * 1000.), 1),
+        );
+    }
+}
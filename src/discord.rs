@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use discord_rich_presence::{activity::Activity, DiscordIpc, DiscordIpcClient};
+
+use crate::{
+    game::GameRules, mode_name, player::Player, stack_visibility::StackVisibility, AppState,
+    GameOptions,
+};
+
+/// Id of the Discord application the presence is published for, set at build time.
+const APP_ID: Option<&str> = option_env!("TETRIS_DISCORD_APP_ID");
+
+/// Part of the shown activity which triggers the update when changed. The score is sent along
+/// but doesn't trigger updates by itself, since Discord limits how often the activity can be set.
+#[derive(PartialEq)]
+struct ActivityKey {
+    state: AppState,
+    level: Option<u32>,
+    game_over: bool,
+}
+
+/// Connection to the local Discord client.
+pub struct Presence {
+    client: DiscordIpcClient,
+    last_key: Option<ActivityKey>,
+}
+
+impl Presence {
+    /// Returns `None` if the app id isn't set or Discord isn't running.
+    pub fn connect() -> Option<Self> {
+        let mut client = DiscordIpcClient::new(APP_ID?)
+            .map_err(|e| warn!("failed to create Discord client: {}", e))
+            .ok()?;
+        client
+            .connect()
+            .map_err(|e| warn!("failed to connect to Discord: {}", e))
+            .ok()?;
+        Some(Self {
+            client,
+            last_key: None,
+        })
+    }
+}
+
+impl Drop for Presence {
+    fn drop(&mut self) {
+        let _ = self.client.close();
+    }
+}
+
+pub fn update_presence(
+    mut presence: NonSendMut<Presence>,
+    state: Res<State<AppState>>,
+    players: Query<&Player>,
+    options: Res<GameOptions>,
+    rules: Res<GameRules>,
+    visibility: Res<StackVisibility>,
+) {
+    let state = *state.current();
+    let versus = options.player_count > 1;
+    let player = players
+        .iter()
+        .find(|p| p.bot.is_none())
+        .filter(|_| state == AppState::Game && !versus);
+
+    let key = ActivityKey {
+        state,
+        level: player.map(|p| p.game.score().level()),
+        game_over: player.map_or(false, |p| p.game.is_game_over()),
+    };
+    if presence.last_key.as_ref() == Some(&key) {
+        return;
+    }
+
+    let details = match state {
+        AppState::Menu | AppState::Demo | AppState::Stats | AppState::Saves => {
+            "In menu".to_string()
+        }
+        AppState::Lobby => "In lobby".to_string(),
+        AppState::Game if versus => "Versus".to_string(),
+        AppState::Game => format!("Playing {}", mode_name(&rules, &visibility)),
+    };
+    let activity_state = player.map(|p| {
+        let score = p.game.score();
+        let game_over = if key.game_over { ", game over" } else { "" };
+        format!(
+            "Level {}, score {}{}",
+            score.level(),
+            score.points,
+            game_over
+        )
+    });
+
+    let mut activity = Activity::new().details(&details);
+    if let Some(activity_state) = &activity_state {
+        activity = activity.state(activity_state);
+    }
+    if let Err(e) = presence.client.set_activity(activity) {
+        warn!("failed to update Discord presence: {}", e);
+    }
+    presence.last_key = Some(key);
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+
+use crate::{
+    game,
+    i18n::{Key, Language},
+    player::Player,
+    player_width, respawn_points,
+    toast::spawn_toast,
+    BORDER_SIZE, MARGIN_SIZE, UI, UNIT_PX,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F2;
+const RESPAWN_KEY: KeyCode = KeyCode::R;
+
+/// Debug board editor. While it's active the games are paused, clicking a board cell toggles
+/// its occupancy and the respawn key puts the active block back to the spawn position.
+#[derive(Default)]
+pub struct Editor {
+    active: bool,
+}
+
+impl Editor {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+/// Returns index of the player and the column and the row under the cursor, with the row
+/// counted from the top of the visible part of the board. Players play by the `rules`.
+fn cursor_to_board(
+    window: &Window,
+    cursor: Vec2,
+    rules: &game::GameRules,
+) -> Option<(usize, (usize, usize))> {
+    // cursor position starts at bottom / left of the window
+    let x = cursor.x;
+    let y = window.height() - cursor.y;
+
+    let player_width = player_width(rules);
+    let player_index = (x / player_width) as usize;
+    let board_x = x - player_width * player_index as f32 - MARGIN_SIZE - BORDER_SIZE;
+    let board_y = y - MARGIN_SIZE - BORDER_SIZE;
+    if board_x < 0. || board_y < 0. {
+        return None;
+    }
+
+    let pos = ((board_x / UNIT_PX) as usize, (board_y / UNIT_PX) as usize);
+    if pos.0 >= rules.board_width() || pos.1 >= game::VISIBLE_BOARD_HEIGHT {
+        return None;
+    }
+    Some((player_index, pos))
+}
+
+pub fn update_editor(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    ui: Res<UI>,
+    language: Res<Language>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    time: Res<Time>,
+    rules: Res<game::GameRules>,
+    mut players: Query<(Entity, &mut Player)>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        editor.active = !editor.active;
+        let message = if editor.active {
+            Key::EditorOn
+        } else {
+            Key::EditorOff
+        };
+        spawn_toast(&mut commands, &ui, language.get(message));
+    }
+    if !editor.active {
+        return;
+    }
+
+    let clicked = mouse
+        .just_pressed(MouseButton::Left)
+        .then(|| windows.get_primary())
+        .flatten()
+        .and_then(|window| cursor_to_board(window, window.cursor_position()?, &rules));
+    let respawn = keys.just_pressed(RESPAWN_KEY);
+    if clicked.is_none() && !respawn {
+        return;
+    }
+
+    for (player_entity, mut player) in players.iter_mut() {
+        let changed = match clicked {
+            Some((index, (x, row))) if index == player.index => {
+                let y = player.game.rules().gravity.board_row(row);
+                player.game.debug_toggle_cell(game::board_position(x, y))
+            }
+            _ if respawn => {
+                let block_type = player.game.active_block().block_type;
+                player.game.debug_spawn_block(block_type);
+                true
+            }
+            _ => false,
+        };
+        if changed {
+            respawn_points(
+                &mut commands,
+                player_entity,
+                &mut player,
+                time.seconds_since_startup(),
+            );
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+use bevy::{prelude::*, window::PresentMode};
+
+use crate::settings::Settings;
+
+/// Frame rate caps cycled in the pause menu, `None` renders as fast as the present mode allows.
+const FPS_CAPS: [Option<u32>; 5] = [None, Some(30), Some(60), Some(120), Some(144)];
+
+/// Vsync and frame rate cap given by the `vsync` and `fps_cap` settings. The game logic runs at
+/// a fixed timestep, so frames rendered faster than the display only burn power.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FrameRate {
+    pub vsync: bool,
+    pub fps_cap: Option<u32>,
+}
+
+impl FrameRate {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            vsync: settings.get("vsync") != Some("false"),
+            fps_cap: settings
+                .get("fps_cap")
+                .and_then(|cap| cap.parse().ok())
+                .filter(|&cap| cap > 0),
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        }
+    }
+
+    /// Turns vsync on or off and saves the setting.
+    pub fn toggle_vsync(&mut self, settings: &mut Settings) {
+        self.vsync = !self.vsync;
+        save_setting(settings, "vsync", &self.vsync.to_string());
+    }
+
+    /// Selects the next cap of `FPS_CAPS` and saves the setting.
+    pub fn next_fps_cap(&mut self, settings: &mut Settings) {
+        let index = FPS_CAPS.iter().position(|&c| c == self.fps_cap);
+        self.fps_cap = FPS_CAPS[index.map_or(0, |i| (i + 1) % FPS_CAPS.len())];
+        save_setting(settings, "fps_cap", &self.fps_cap.unwrap_or(0).to_string());
+    }
+}
+
+fn save_setting(settings: &mut Settings, name: &str, value: &str) {
+    // the change still applies to this session
+    if let Err(e) = settings.set(name, value) {
+        warn!("failed to save the {} setting: {}", name, e);
+    }
+}
+
+/// Applies the present mode to the window when vsync is switched.
+pub fn apply_present_mode(frame_rate: Res<FrameRate>, mut windows: ResMut<Windows>) {
+    if !frame_rate.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_present_mode(frame_rate.present_mode());
+    }
+}
+
+/// Sleeps at the end of the frame until the frame took the duration of the frame rate cap.
+pub fn limit_frame_rate(frame_rate: Res<FrameRate>, mut last_frame: Local<Option<Instant>>) {
+    if let (Some(cap), Some(last_frame)) = (frame_rate.fps_cap, *last_frame) {
+        let frame_duration = Duration::from_secs_f64(1. / cap as f64);
+        if let Some(remaining) = frame_duration.checked_sub(last_frame.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+    }
+    *last_frame = Some(Instant::now());
+}
@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+
+use crate::{
+    clipboard,
+    game::Fumen,
+    i18n::{Key, Language},
+    player::Player,
+    toast, UI,
+};
+
+const EXPORT_KEY: KeyCode = KeyCode::F10;
+/// Held while exporting to include the active block.
+const ACTIVE_BLOCK_KEYS: [KeyCode; 2] = [KeyCode::LShift, KeyCode::RShift];
+
+/// Copies the board of the first human player as a fumen diagram to the clipboard, so it can be
+/// shared in the fumen editor.
+pub fn export_fumen(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    ui: Res<UI>,
+    language: Res<Language>,
+    players: Query<&Player>,
+) {
+    if !keys.just_pressed(EXPORT_KEY) {
+        return;
+    }
+    let player = players
+        .iter()
+        .filter(|p| p.bot.is_none())
+        .min_by_key(|p| p.index)
+        .or_else(|| players.iter().min_by_key(|p| p.index));
+    let player = match player {
+        Some(player) => player,
+        None => return,
+    };
+
+    let diagram = Fumen::encode_game(&player.game, keys.any_pressed(ACTIVE_BLOCK_KEYS));
+    let message = match clipboard::set_text(&diagram) {
+        Ok(()) => language.get(Key::FumenCopied).to_string(),
+        Err(e) => {
+            error!("failed to copy fumen {}: {}", diagram, e);
+            language.get(Key::FumenCopyFailed).to_string()
+        }
+    };
+    toast::spawn_toast(&mut commands, &ui, message);
+}
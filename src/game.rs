@@ -1,108 +1,297 @@
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::vec;
-use std::{collections::HashMap, num::NonZeroU32};
+use std::{
+    collections::{HashMap, VecDeque},
+    mem,
+    num::NonZeroU32,
+};
+
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use tracing::{debug, debug_span};
 
 mod blocks;
+pub use blocks::BlockType;
 use blocks::*;
-pub use blocks::{get_block_color, BlockType};
 
 mod input;
-pub use input::Input;
-use input::SmartInput;
+pub use input::{AutoRepeat, Input};
+use input::{BufferedInput, SmartInput};
 
 mod utils;
-use utils::{IdGenerator, Timer};
+pub use utils::SharedLog;
+use utils::{fnv1a, IdGenerator, Timer};
 
 mod rotate;
-use rotate::rotate_block;
+use rotate::{orientations, rotate_block, Orientation};
+
+mod bot;
+pub use bot::{Bot, BotDifficulty, BotLevel};
+
+mod attack;
+use attack::Attack;
+
+mod garbage;
+use garbage::GarbageGenerator;
+pub use garbage::{GarbageStyle, DEFAULT_REPEAT_CHANCE};
+
+mod replay;
+pub use replay::{Replay, ReplayInput};
+
+mod score;
+use score::LINES_PER_LEVEL;
+pub use score::{ClearKind, LockedBlock, Score, SPRINT_LINES, SPRINT_SPLITS, SPRINT_SPLIT_LINES};
+
+mod gravity;
+pub use gravity::Gravity;
+
+mod view;
+pub use view::{GameView, HudValues, Renderer};
+
+mod metrics;
+pub use metrics::BoardMetrics;
+
+mod analysis;
+pub use analysis::{PlacedBlock, ReplayAnalysis};
+
+mod snapshot;
+pub use snapshot::GameSnapshot;
+
+mod secret_grade;
+pub use secret_grade::{grade_name as secret_grade_name, SECRET_GRADE_ROWS};
+
+mod placement;
+pub use placement::Placement;
+
+mod fumen;
+pub use fumen::Fumen;
+
+mod items;
+pub use items::Item;
+use items::{roll_item, CLEARED_BOTTOM_ROWS, SPEED_UP_DURATION};
 
 pub const BOARD_WIDTH: usize = 10;
+/// Columns of the co-op board, which is two boards wide.
+pub const MAX_BOARD_WIDTH: usize = 2 * BOARD_WIDTH;
 pub const BOARD_HEIGHT: usize = 24;
 pub const HIDDEN_BOARD_TOP: usize = 4;
 pub const VISIBLE_BOARD_HEIGHT: usize = BOARD_HEIGHT - HIDDEN_BOARD_TOP;
+pub const NEXT_BLOCKS_COUNT: usize = 3;
+/// Number of the last dealt blocks kept in the block history.
+pub const BLOCK_HISTORY_LEN: usize = BlockType::ALL.len();
 
 pub(self) const WAIT_DURATION: u32 = 30;
 pub(self) const REPEAT_DURATION: u32 = 5;
 
 pub type Id = NonZeroU32;
-pub type Position = (usize, usize);
+/// Random number generator which gives the same results on all platforms for the same seed.
+pub(self) type GameRng = ChaCha8Rng;
+/// Board column and row, positions of the block points are relative to the block position.
+/// Positions may lie outside of the board, the board is only accessed through `board_index`.
+pub type Position = (i32, i32);
 
 pub fn add_positions(a: Position, b: Position) -> Position {
     (a.0 + b.0, a.1 + b.1)
 }
 
+/// Returns column and row of the board cell at the position, `None` outside of the widest
+/// board. Games check the columns against their own `Game::board_width`.
+pub fn board_index((x, y): Position) -> Option<(usize, usize)> {
+    let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+    (x < MAX_BOARD_WIDTH && y < BOARD_HEIGHT).then_some((x, y))
+}
+
+/// Returns position of the board cell.
+pub fn board_position(x: usize, y: usize) -> Position {
+    (x as i32, y as i32)
+}
+
+#[derive(Clone, Debug)]
 pub enum TickChange {
+    /// Active block moved a step to the left or to the right.
+    BlockShifted(Move),
+    /// Active block rotated. The rotation system has no wall kicks, only a spawned block which
+    /// doesn't fit rotated is lowered, `kick` is the number of rows it was lowered by.
+    BlockRotated { kick: usize },
+    /// Active block fell a row by the soft drop.
+    SoftDropped,
+    /// Active block touched the stack or the floor and the lock delay started.
+    BlockLanded,
     /// Active block is locked to the board.
     BlockLocked,
     /// New active block has arrived.
     NewBlock,
+    /// Active block was put to the hold and the held block, or the next one when the hold was
+    /// empty, took its place.
+    BlockHeld,
+    /// Filled rows were removed, their points are removed right after. Rows are the board rows
+    /// before the removal.
+    RowsCleared {
+        rows: Vec<usize>,
+        clear_kind: ClearKind,
+    },
     /// Board point was removed.
     PointRemoved(Id),
+    /// New block can't be placed on the board, the game is over.
+    GameOver,
+    /// Item was picked up from a removed row and put to the item slot.
+    ItemGranted(Item),
+    /// Item from the item slot was used. Items targeting opponent should be passed
+    /// to `Game::receive_item` of the opponent's game.
+    ItemUsed(Item),
+    /// Garbage point was added to the board.
+    PointAdded(Id),
+    /// Garbage lines should be sent to the opponent's `Game::receive_garbage`.
+    GarbageSent(u32),
+}
+
+/// Move of the active block, see `Game::try_move`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Move {
+    Left,
+    Right,
+    /// One row towards the floor.
+    Fall,
 }
 
 #[derive(Clone, Copy)]
 pub struct Point {
     pub id: Id,
-    pub origin_block_type: BlockType,
+    /// Type of the block the point belonged to, `None` for garbage points.
+    pub origin_block_type: Option<BlockType>,
+    /// Item granted to the player when the row with this point is removed.
+    pub item: Option<Item>,
 }
 
+#[derive(Clone)]
 pub struct Block {
     pub id: Id,
     pub block_type: BlockType,
+    /// Every mino of the block is made of 2x2 points.
+    big: bool,
     points: Vec<Point>,
-    points_pos: HashMap<Id, Position>,
+    orientations: &'static [Orientation; 4],
+    /// Index of the current orientation in `orientations`.
+    orientation: usize,
 }
 
 impl Block {
-    fn new(id: Id, block_type: BlockType, gen_id: &mut IdGenerator) -> Self {
-        let block_points = get_block_points(block_type);
-
-        let mut points = Vec::with_capacity(block_points.len());
-        let mut points_pos = HashMap::with_capacity(block_points.len());
-        for &pos in block_points {
-            let point = Point {
+    fn new(
+        id: Id,
+        block_type: BlockType,
+        item: Option<Item>,
+        rules: &GameRules,
+        gen_id: &mut IdGenerator,
+        rng: &mut GameRng,
+    ) -> Self {
+        let orientations = orientations(block_type, rules.mirror, rules.big);
+        let point_count = orientations[0].points.len();
+        let item_point = rng.gen_range(0..point_count);
+        let points = (0..point_count)
+            .map(|i| Point {
                 id: gen_id(),
-                origin_block_type: block_type,
-            };
-
-            points.push(point);
-            points_pos.insert(point.id, pos);
-        }
+                origin_block_type: Some(block_type),
+                item: if i == item_point { item } else { None },
+            })
+            .collect();
 
         Self {
             id,
             block_type,
+            big: rules.big,
             points,
-            points_pos,
+            orientations,
+            orientation: 0,
         }
     }
 
-    pub(self) fn width(&self) -> usize {
-        *self.points_pos.values().map(|(x, _)| x).max().unwrap()
-    }
-
-    pub(self) fn height(&self) -> usize {
-        *self.points_pos.values().map(|(_, y)| y).max().unwrap()
+    pub(self) fn height(&self) -> i32 {
+        self.shape().iter().map(|&(_, y)| y).max().unwrap()
     }
 
     pub fn points(&self) -> &[Point] {
         &self.points
     }
 
+    /// Returns positions of the points in the current orientation, in the order of `points`.
+    pub(self) fn shape(&self) -> &'static [Position] {
+        &self.orientations[self.orientation].points
+    }
+
     pub fn get_point_position(&self, point_id: Id) -> Option<Position> {
-        self.points_pos.get(&point_id).copied()
+        let index = self.points.iter().position(|p| p.id == point_id)?;
+        Some(self.shape()[index])
     }
 }
 
-struct GameRules {}
+#[derive(Clone)]
+#[cfg_attr(feature = "debug-ui", derive(bevy_inspector_egui::Inspectable))]
+pub struct GameRules {
+    /// Blocks may carry items which are granted when their rows are removed.
+    pub items: bool,
+    /// Hole pattern of the garbage added to the board.
+    pub garbage_style: GarbageStyle,
+    /// Shapes of all blocks are flipped horizontally.
+    pub mirror: bool,
+    /// Every mino is made of 2x2 points and blocks move by two columns, which makes the board
+    /// effectively five columns wide.
+    pub big: bool,
+    pub gravity: Gravity,
+    /// A garbage row rises every this many ticks, for downstacking drills. Rows rise when the
+    /// next block is locked, so they never overlap the active block.
+    pub drill_interval: Option<u32>,
+    /// Gravity level from 1, the block falls one row every `11 - level` ticks down to one
+    /// row every tick.
+    pub speed_level: u32,
+    /// Percentage of the attack which is sent to the opponent, fractions of lines carry over to
+    /// the next attack.
+    pub garbage_percent: u32,
+    /// Number of the next blocks shown to the player, up to `NEXT_BLOCKS_COUNT`.
+    pub preview_count: usize,
+    /// Ticks between a block locking and the next block spawning (ARE), no block is active
+    /// meanwhile.
+    pub entry_delay: u32,
+    /// Ticks the filled rows stay on the board before they're removed, zero removes them on
+    /// the tick the block locks.
+    pub line_clear_delay: u32,
+    pub auto_repeat: AutoRepeat,
+    /// Two players share a board twice as wide, each controlling their own active block, see
+    /// `Game::tick_coop`. Replays record only the first player, so they aren't recorded by the
+    /// rules either.
+    pub coop: bool,
+    /// The board is two boards wide with a single player spawning in its middle, the co-op
+    /// board always is.
+    pub doubles: bool,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl GameRules {
-    fn new() -> Self {
-        GameRules {}
+    pub fn new() -> Self {
+        GameRules {
+            items: false,
+            garbage_style: GarbageStyle::default(),
+            mirror: false,
+            big: false,
+            gravity: Gravity::default(),
+            drill_interval: None,
+            speed_level: 1,
+            garbage_percent: 100,
+            preview_count: NEXT_BLOCKS_COUNT,
+            entry_delay: 0,
+            line_clear_delay: 0,
+            auto_repeat: AutoRepeat::default(),
+            coop: false,
+            doubles: false,
+        }
     }
 
     fn drop_freq(&self) -> u32 {
-        10
+        max(11u32.saturating_sub(self.speed_level), 1)
     }
 
     fn fast_drop_freq(&self) -> u32 {
@@ -112,175 +301,1511 @@ impl GameRules {
     fn lockup_duration(&self) -> u32 {
         30
     }
+
+    /// Returns number of the board columns, the co-op and doubles boards are twice as wide.
+    pub fn board_width(&self) -> usize {
+        if self.coop || self.doubles {
+            MAX_BOARD_WIDTH
+        } else {
+            BOARD_WIDTH
+        }
+    }
+
+    /// Returns number of columns the block moves by.
+    fn move_step(&self) -> i32 {
+        if self.big {
+            2
+        } else {
+            1
+        }
+    }
 }
 
-pub struct Game {
-    rules: GameRules,
-    gen_id: IdGenerator,
+/// Filled rows of the locked block waiting for the line clear delay.
+#[derive(Clone)]
+struct LineClear {
+    rows: Vec<usize>,
+    t_spin: bool,
+    lock_tick: u32,
+    /// Position where the active block was locked.
+    block_pos: Position,
+    timer: Timer,
+}
+
+/// Phase of the game, see `Game::phase`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    /// The active block spawned and hasn't played a tick yet.
+    Spawning,
+    /// The active block falls and can be moved and rotated.
+    Falling,
+    /// The active block rests on the stack and locks when the lock delay runs out, unless it's
+    /// moved where it can fall again.
+    Locking,
+    /// The filled rows of the locked block stay on the board for the line clear delay.
+    ClearingLines,
+    /// The next block waits for the entry delay.
+    EntryDelay,
+    GameOver,
+}
+
+/// Phase of the game with the timers running in it.
+#[derive(Clone)]
+enum State {
+    Spawning,
+    Falling,
+    Locking(Timer),
+    /// The active block is the locked one while the rows wait to be removed.
+    ClearingLines(LineClear),
+    /// The active block is the locked one until the next block spawns.
+    EntryDelay(Timer),
+    GameOver,
+}
+
+/// Player controlling an active block, co-op games have two of them on the same board.
+#[derive(Clone)]
+struct Pilot {
     input: SmartInput,
-    board: [[Option<Point>; BOARD_WIDTH]; BOARD_HEIGHT],
-    points_pos: HashMap<Id, Position>,
+    /// Presses made during the line clear and entry delays.
+    buffered_input: BufferedInput,
     active_block: Block,
     active_block_pos: Position,
+    active_block_spawn_tick: u32,
+    last_move_is_rotation: bool,
     drop_timer: Timer,
-    locking: bool,
-    lockup_timer: Timer,
+    state: State,
+    /// Blocks of the pilot spawn this many columns right of the spawn position.
+    spawn_column: i32,
+    held_block: Option<Block>,
+    /// The hold was used since the active block spawned, it's used once per block.
+    hold_used: bool,
+}
+
+impl Pilot {
+    fn new(
+        active_block: Block,
+        active_block_pos: Position,
+        rules: &GameRules,
+        spawn_column: i32,
+    ) -> Self {
+        Self {
+            input: SmartInput::new(rules.auto_repeat),
+            buffered_input: BufferedInput::default(),
+            active_block,
+            active_block_pos,
+            active_block_spawn_tick: 0,
+            last_move_is_rotation: false,
+            drop_timer: Timer::new(),
+            state: State::Spawning,
+            spawn_column,
+            held_block: None,
+            hold_used: false,
+        }
+    }
+
+    /// Returns `false` between a block locking and the next block spawning.
+    fn has_active_block(&self) -> bool {
+        !matches!(self.state, State::ClearingLines(_) | State::EntryDelay(_))
+    }
+
+    /// Returns board positions of the active block points.
+    fn block_cells(&self) -> impl Iterator<Item = Position> + '_ {
+        self.active_block
+            .shape()
+            .iter()
+            .map(|&pos| add_positions(self.active_block_pos, pos))
+    }
+}
+
+#[derive(Clone)]
+pub struct Game {
+    rules: GameRules,
+    rng: GameRng,
+    replay: Replay,
+    gen_id: IdGenerator,
+    /// Board cells right of `board_width` stay empty.
+    board: [[Option<Point>; MAX_BOARD_WIDTH]; BOARD_HEIGHT],
+    /// How many times a block point was locked to every board cell.
+    placements: [[u32; MAX_BOARD_WIDTH]; BOARD_HEIGHT],
+    points_pos: HashMap<Id, Position>,
+    /// Measured after every change of the board.
+    metrics: BoardMetrics,
+    pilot: Pilot,
+    /// Second pilot of the co-op game, it's swapped with `pilot` for its part of the tick.
+    partner: Option<Pilot>,
+    next_blocks: VecDeque<BlockType>,
+    /// Blocks dealt before the random ones.
+    forced_blocks: VecDeque<BlockType>,
+    /// Number of blocks at the end of the next blocks dealt by the randomizer, they are replaced
+    /// by appended blocks.
+    random_next_blocks: usize,
+    /// How many blocks of every type became active, indexed by `BlockType`.
+    dealt_blocks: [u32; BlockType::ALL.len()],
+    /// Last dealt blocks, the oldest first.
+    block_history: VecDeque<BlockType>,
+    /// How many blocks were dealt since the last block of every type, indexed by `BlockType`.
+    droughts: [u32; BlockType::ALL.len()],
+    item: Option<Item>,
+    speed_up_ticks: u32,
+    attack: Attack,
+    attack_total: u32,
+    /// Hundredths of a line left over after applying the garbage percentage.
+    attack_remainder: u32,
+    pending_garbage: u32,
+    /// Garbage rows of the drill which rise when the next block is locked.
+    pending_drill_rows: u32,
+    garbage: GarbageGenerator,
+    score: Score,
+    locked_blocks: SharedLog<LockedBlock>,
 }
 
 impl Game {
     pub fn new() -> Self {
+        Self::with_rules(GameRules::new())
+    }
+
+    pub fn with_rules(rules: GameRules) -> Self {
+        Self::with_seed(rules, thread_rng().gen())
+    }
+
+    /// Creates the game which deals the same blocks, items and garbage for the same seed and
+    /// the same input.
+    pub fn with_seed(rules: GameRules, seed: u64) -> Self {
+        let mut rng = GameRng::seed_from_u64(seed);
         let mut gen_id = IdGenerator::new();
-        let active_block = Block::new(gen_id(), get_random_block(), &mut gen_id);
-        let active_block_pos = (4, 0);
+        let item = if rules.items {
+            roll_item(&mut rng)
+        } else {
+            None
+        };
+        let block_type = get_random_block(&mut rng);
+        let active_block = Block::new(gen_id(), block_type, item, &rules, &mut gen_id, &mut rng);
+        // a single player spawns in the middle of the doubles board
+        let spawn_column = if rules.doubles && !rules.coop {
+            (BOARD_WIDTH / 2) as i32
+        } else {
+            0
+        };
+        let (x, y) = rules.gravity.spawn_position(active_block.height());
+        let active_block_pos = (x + spawn_column, y);
+        let next_blocks = (0..NEXT_BLOCKS_COUNT)
+            .map(|_| get_random_block(&mut rng))
+            .collect();
+        let boards = rules.board_width() / BOARD_WIDTH;
+        let garbage = GarbageGenerator::new(rules.garbage_style, boards, &mut rng);
+        let replay = Replay::new(seed, &rules);
+        let pilot = Pilot::new(active_block, active_block_pos, &rules, spawn_column);
+        // the partner starts in the right half of the co-op board
+        let partner = rules.coop.then(|| {
+            let block_type = get_random_block(&mut rng);
+            let block = Block::new(gen_id(), block_type, None, &rules, &mut gen_id, &mut rng);
+            let (x, y) = rules.gravity.spawn_position(block.height());
+            let spawn_column = BOARD_WIDTH as i32;
+            Pilot::new(block, (x + spawn_column, y), &rules, spawn_column)
+        });
+        debug!(
+            seed,
+            items = rules.items,
+            mirror = rules.mirror,
+            big = rules.big,
+            coop = rules.coop,
+            doubles = rules.doubles,
+            block = ?block_type,
+            "game started"
+        );
+        let partner_block_type = partner.as_ref().map(|p| p.active_block.block_type);
 
-        Self {
-            rules: GameRules::new(),
+        let mut game = Self {
+            rules: rules,
+            rng: rng,
+            replay: replay,
             gen_id: gen_id,
-            input: SmartInput::new(),
             points_pos: HashMap::new(),
-            board: [[None; BOARD_WIDTH]; BOARD_HEIGHT],
-            active_block: active_block,
-            active_block_pos: active_block_pos,
-            drop_timer: Timer::new(),
-            locking: false,
-            lockup_timer: Timer::new(),
+            metrics: BoardMetrics::default(),
+            board: [[None; MAX_BOARD_WIDTH]; BOARD_HEIGHT],
+            placements: [[0; MAX_BOARD_WIDTH]; BOARD_HEIGHT],
+            pilot: pilot,
+            partner: partner,
+            next_blocks: next_blocks,
+            forced_blocks: VecDeque::new(),
+            random_next_blocks: NEXT_BLOCKS_COUNT,
+            dealt_blocks: [0; BlockType::ALL.len()],
+            block_history: VecDeque::with_capacity(BLOCK_HISTORY_LEN),
+            droughts: [0; BlockType::ALL.len()],
+            item: None,
+            speed_up_ticks: 0,
+            attack: Attack::new(),
+            attack_total: 0,
+            attack_remainder: 0,
+            pending_garbage: 0,
+            pending_drill_rows: 0,
+            garbage: garbage,
+            score: Score::default(),
+            locked_blocks: SharedLog::new(),
+        };
+        game.block_dealt(block_type);
+        if let Some(block_type) = partner_block_type {
+            game.block_dealt(block_type);
         }
+        game
+    }
+
+    /// Creates the game which deals `blocks` in order, starting with the active block, and random
+    /// blocks after them. Replays don't record the forced blocks.
+    pub fn with_blocks(rules: GameRules, blocks: &[BlockType]) -> Self {
+        let mut game = Self::with_rules(rules);
+        game.set_queue(blocks);
+        game.dealt_blocks = [0; BlockType::ALL.len()];
+        game.block_history.clear();
+        game.droughts = [0; BlockType::ALL.len()];
+        game.spawn_block();
+        game
+    }
+
+    /// Returns snapshot of the game state for renderers.
+    pub fn view(&self) -> GameView {
+        GameView::new(self)
+    }
+
+    pub fn rules(&self) -> &GameRules {
+        &self.rules
     }
 
     pub fn active_block(&self) -> &Block {
-        &self.active_block
+        &self.pilot.active_block
     }
 
     pub fn active_block_position(&self) -> Position {
-        self.active_block_pos
+        self.pilot.active_block_pos
+    }
+
+    /// Returns the type of the block in the hold.
+    pub fn held_block(&self) -> Option<BlockType> {
+        self.pilot.held_block.as_ref().map(|block| block.block_type)
+    }
+
+    /// Returns the active block of the second co-op pilot with its position and the position
+    /// where it would land, `None` without a partner or while its next block waits.
+    pub fn partner_block(&self) -> Option<(&Block, Position, Position)> {
+        let partner = self.partner.as_ref().filter(|p| p.has_active_block())?;
+        let block = &partner.active_block;
+        let landing_pos = self.shape_landing_position(
+            Some(&self.pilot),
+            block.shape().iter(),
+            block.height(),
+            partner.active_block_pos,
+        );
+        Some((block, partner.active_block_pos, landing_pos))
+    }
+
+    pub fn board_width(&self) -> usize {
+        self.rules.board_width()
+    }
+
+    pub fn phase(&self) -> Phase {
+        // a co-op pilot topping out ends the game of both
+        if self.pilots().any(|p| matches!(p.state, State::GameOver)) {
+            return Phase::GameOver;
+        }
+        match self.pilot.state {
+            State::Spawning => Phase::Spawning,
+            State::Falling => Phase::Falling,
+            State::Locking(_) => Phase::Locking,
+            State::ClearingLines(_) => Phase::ClearingLines,
+            State::EntryDelay(_) => Phase::EntryDelay,
+            State::GameOver => Phase::GameOver,
+        }
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.phase() == Phase::GameOver
+    }
+
+    /// Returns `true` while the filled rows stay on the board for the line clear delay.
+    pub fn is_clearing_rows(&self) -> bool {
+        self.phase() == Phase::ClearingLines
+    }
+
+    /// Returns `true` while the next block waits for the entry delay.
+    pub fn is_entry_delay(&self) -> bool {
+        self.phase() == Phase::EntryDelay
+    }
+
+    /// Returns `false` between a block locking and the next block spawning, when there's no
+    /// block to move or draw.
+    pub fn has_active_block(&self) -> bool {
+        self.pilot.has_active_block()
+    }
+
+    /// Returns the next blocks shown to the player, which are limited by the preview count.
+    pub fn next_blocks(&self) -> impl Iterator<Item = BlockType> + '_ {
+        self.next_blocks
+            .iter()
+            .copied()
+            .take(self.rules.preview_count)
+    }
+
+    /// Replaces the next blocks with `blocks`, random blocks are dealt after them. The active
+    /// block isn't changed. Replays don't record the queue.
+    pub fn set_queue(&mut self, blocks: &[BlockType]) {
+        self.forced_blocks = blocks.iter().copied().collect();
+        self.next_blocks.clear();
+        self.random_next_blocks = 0;
+        self.fill_next_blocks();
+    }
+
+    /// Deals `blocks` after the blocks given before, instead of the random ones.
+    pub fn append_queue(&mut self, blocks: &[BlockType]) {
+        let len = self.next_blocks.len() - self.random_next_blocks;
+        self.next_blocks.truncate(len);
+        self.random_next_blocks = 0;
+        self.forced_blocks.extend(blocks);
+        self.fill_next_blocks();
+    }
+
+    /// Returns how many blocks of the type were dealt in this game, including the active block.
+    pub fn dealt_blocks(&self, block_type: BlockType) -> u32 {
+        self.dealt_blocks[block_type as usize]
+    }
+
+    /// Returns the last `BLOCK_HISTORY_LEN` dealt blocks including the active block, the oldest
+    /// first. The randomizer has no bag, so every type may come next regardless of the history.
+    pub fn block_history(&self) -> impl Iterator<Item = BlockType> + '_ {
+        self.block_history.iter().copied()
+    }
+
+    /// Returns how many blocks were dealt since the last block of the type, or since the start
+    /// of the game if none was dealt yet.
+    pub fn drought(&self, block_type: BlockType) -> u32 {
+        self.droughts[block_type as usize]
+    }
+
+    /// Returns item stored in the item slot.
+    pub fn item(&self) -> Option<Item> {
+        self.item
+    }
+
+    /// Applies item used by the opponent.
+    pub fn receive_item(&mut self, item: Item) {
+        match item {
+            Item::ClearBottomRows => {}
+            Item::ShuffleQueue => {
+                self.next_blocks.make_contiguous().shuffle(&mut self.rng);
+                // random blocks are mixed with the given ones, so all of them are kept
+                self.random_next_blocks = 0;
+            }
+            Item::SpeedUp => self.speed_up_ticks = SPEED_UP_DURATION,
+        }
     }
 
+    /// Returns replay of the game played so far.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Captures the state of the game, which is restored by `restore`.
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot::new(self)
+    }
+
+    /// Puts the game back to the state of the snapshot taken from it, so it plays on from there
+    /// the same way for the same input. The replay has the inputs recorded up to the snapshot,
+    /// also when the game was restored to an earlier snapshot since it was taken.
+    pub fn restore(&mut self, snapshot: &GameSnapshot) {
+        snapshot.restore(self);
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Returns all blocks locked in this game in the order they were locked.
+    pub fn locked_blocks(&self) -> &SharedLog<LockedBlock> {
+        &self.locked_blocks
+    }
+
+    /// Returns total number of garbage lines produced by this game.
+    pub fn attack_total(&self) -> u32 {
+        self.attack_total
+    }
+
+    /// Returns number of garbage lines which will be added when next block is locked.
+    pub fn pending_garbage(&self) -> u32 {
+        self.pending_garbage
+    }
+
+    /// Queues garbage lines sent by the opponent. Garbage is added to the board when the next
+    /// block is locked without removing any rows, otherwise it's cancelled by own attack first.
+    pub fn receive_garbage(&mut self, lines: u32) {
+        self.pending_garbage += lines;
+    }
+
+    /// Plays a tick with the input. The partner of a co-op game doesn't play, see `tick_coop`.
     pub fn tick(&mut self, input: &dyn Input) -> Vec<TickChange> {
         let mut changes = vec![];
-        let mut block_pos = self.active_block_pos;
-        self.input.tick(input);
-
-        if self.input.move_left() {
-            if block_pos.0 > 0
-                && !self.is_block_collides(
-                    self.active_block.points_pos.values(),
-                    (block_pos.0 - 1, block_pos.1),
-                )
+        if self.is_game_over() {
+            return changes;
+        }
+
+        let _span = debug_span!("tick", tick = self.score.ticks).entered();
+        self.replay.record(input);
+        self.count_tick();
+        self.tick_pilot(input, &mut changes);
+        self.tick_finished(changes)
+    }
+
+    /// Plays a tick of the co-op game, the first input controls the pilot starting on the left
+    /// and the second one the pilot starting on the right. The pilots play one after the other,
+    /// so the first one gets to the cells both move to. The replay records only the first input.
+    pub fn tick_coop(&mut self, inputs: [&dyn Input; 2]) -> Vec<TickChange> {
+        if self.partner.is_none() {
+            return self.tick(inputs[0]);
+        }
+        let mut changes = vec![];
+        if self.is_game_over() {
+            return changes;
+        }
+
+        let _span = debug_span!("tick", tick = self.score.ticks).entered();
+        self.replay.record(inputs[0]);
+        self.count_tick();
+        self.tick_pilot(inputs[0], &mut changes);
+        if !self.is_game_over() {
+            self.swap_pilots();
+            self.fit_active_block(&mut changes);
+            if !self.is_game_over() {
+                self.tick_pilot(inputs[1], &mut changes);
+            }
+            self.swap_pilots();
+            self.fit_active_block(&mut changes);
+        }
+        self.tick_finished(changes)
+    }
+
+    fn count_tick(&mut self) {
+        self.score.ticks += 1;
+        if let Some(interval) = self.rules.drill_interval {
+            if self.score.ticks.is_multiple_of(interval) {
+                self.pending_drill_rows += 1;
+            }
+        }
+    }
+
+    /// Exchanges the pilots of the co-op game, so the partner plays as `pilot`.
+    fn swap_pilots(&mut self) {
+        if let Some(partner) = &mut self.partner {
+            mem::swap(&mut self.pilot, partner);
+        }
+    }
+
+    /// Pushes the active block towards the ceiling when the partner's lock or the cleared bottom
+    /// rows moved board points into it, the game is over if it doesn't fit anywhere.
+    fn fit_active_block(&mut self, changes: &mut Vec<TickChange>) {
+        if !self.has_active_block() || self.is_game_over() {
+            return;
+        }
+        let shape = self.pilot.active_block.shape();
+        let mut block_pos = self.pilot.active_block_pos;
+        for _ in 0..BOARD_HEIGHT {
+            if !self.is_block_collides(shape.iter(), block_pos) {
+                self.pilot.active_block_pos = block_pos;
+                return;
+            }
+            block_pos = self.rules.gravity.shifted(block_pos, -1);
+        }
+        self.end_game(changes);
+    }
+
+    /// Plays the tick of the current pilot.
+    fn tick_pilot(&mut self, input: &dyn Input, changes: &mut Vec<TickChange>) {
+        let mut block_pos = self.pilot.active_block_pos;
+        self.pilot.input.tick(input);
+
+        // held moves keep charging during the line clear and entry delays and presses are
+        // buffered for the block which spawns after them
+        if !self.has_active_block() {
+            self.pilot.buffered_input.record(&self.pilot.input);
+        }
+        match &mut self.pilot.state {
+            State::ClearingLines(line_clear) => {
+                if line_clear
+                    .timer
+                    .tick_and_restart_if_elapsed(self.rules.line_clear_delay)
+                {
+                    let State::ClearingLines(line_clear) =
+                        mem::replace(&mut self.pilot.state, State::Falling)
+                    else {
+                        unreachable!()
+                    };
+                    self.clear_rows(line_clear, changes);
+                    self.enter_next_block(input.rotate(), input.hold(), changes);
+                }
+                return;
+            }
+            State::EntryDelay(timer) => {
+                timer.tick();
+                // the next block waits until the partner's block leaves its spawn cells
+                if timer.has_elapsed(self.rules.entry_delay) && !self.is_spawn_blocked() {
+                    self.spawn_next_block(input.rotate(), input.hold(), changes);
+                }
+                return;
+            }
+            State::Spawning => self.pilot.state = State::Falling,
+            _ => {}
+        }
+
+        let buffered_input = mem::take(&mut self.pilot.buffered_input);
+
+        if (self.pilot.input.hold() || buffered_input.hold()) && !self.pilot.hold_used {
+            self.hold_block(changes);
+            if self.is_active_block_blocked() {
+                self.end_game(changes);
+            }
+            return;
+        }
+
+        if self.pilot.input.move_left() || buffered_input.move_left() {
+            if let Some(new_block_pos) =
+                self.moved_position(&self.pilot.active_block, block_pos, Move::Left)
             {
-                block_pos.0 -= 1;
+                block_pos = new_block_pos;
+                self.pilot.last_move_is_rotation = false;
+                changes.push(TickChange::BlockShifted(Move::Left));
             }
         }
-        if self.input.move_right() {
-            if block_pos.0 + self.active_block.width() < BOARD_WIDTH - 1
-                && !self.is_block_collides(
-                    self.active_block.points_pos.values(),
-                    (block_pos.0 + 1, block_pos.1),
-                )
+        if self.pilot.input.move_right() || buffered_input.move_right() {
+            if let Some(new_block_pos) =
+                self.moved_position(&self.pilot.active_block, block_pos, Move::Right)
             {
-                block_pos.0 += 1;
+                block_pos = new_block_pos;
+                self.pilot.last_move_is_rotation = false;
+                changes.push(TickChange::BlockShifted(Move::Right));
+            }
+        }
+        if self.pilot.input.use_item() {
+            self.pilot.active_block_pos = block_pos;
+            self.use_item(changes);
+            if self.is_game_over() {
+                return;
             }
+            block_pos = self.pilot.active_block_pos;
         }
-        if self.input.rotate() {
-            if let Some((new_points_pos, new_block_pos)) =
-                rotate_block(&self.active_block, block_pos, |block_points, block_pos| {
-                    !self.is_block_collides(block_points.iter(), block_pos)
-                })
+        if self.pilot.input.rotate() || buffered_input.rotate() {
+            if let Some((orientation, new_block_pos)) =
+                self.rotated_block(&self.pilot.active_block, block_pos)
             {
-                self.active_block.points_pos = new_points_pos;
+                debug!(
+                    block = ?self.pilot.active_block.block_type,
+                    from = ?block_pos,
+                    to = ?new_block_pos,
+                    "block rotated"
+                );
+                self.pilot.active_block.orientation = orientation;
                 block_pos = new_block_pos;
+                self.pilot.last_move_is_rotation = true;
+                changes.push(TickChange::BlockRotated { kick: 0 });
             }
         }
 
-        let can_drop = block_pos.1 + self.active_block.height() < BOARD_HEIGHT - 1
-            && !self.is_block_collides(
-                self.active_block.points_pos.values(),
-                (block_pos.0, block_pos.1 + 1),
-            );
+        // hard dropped block is locked on this tick
+        let hard_drop = self.pilot.input.instant_drop() || buffered_input.instant_drop();
+        if hard_drop {
+            let landing_pos = self.landing_position(&self.pilot.active_block, block_pos);
+            if landing_pos != block_pos {
+                block_pos = landing_pos;
+                self.pilot.last_move_is_rotation = false;
+            }
+        }
+
+        let drop_pos = self.moved_position(&self.pilot.active_block, block_pos, Move::Fall);
+        let can_drop = drop_pos.is_some();
 
-        if !can_drop && !self.locking {
-            self.locking = true;
-            self.lockup_timer.restart();
+        if !can_drop && !hard_drop && self.rests_on_partner(block_pos) {
+            // the partner's block moves on, so the block waits on it without the lock delay
+            self.pilot.state = State::Falling;
+        } else if !can_drop && !matches!(self.pilot.state, State::Locking(_)) {
+            self.pilot.state = State::Locking(Timer::new());
+            // hard dropped blocks lock right away
+            if !hard_drop {
+                changes.push(TickChange::BlockLanded);
+            }
         }
 
-        let drop_freq = if self.input.fast_drop() {
+        let mut drop_freq = if self.pilot.input.fast_drop() {
             self.rules.fast_drop_freq()
         } else {
             self.rules.drop_freq()
         };
+        if self.speed_up_ticks > 0 {
+            self.speed_up_ticks -= 1;
+            drop_freq = max(drop_freq / 2, 1);
+        }
 
+        let lockup_duration = self.rules.lockup_duration();
+        let locked = hard_drop
+            || match &mut self.pilot.state {
+                State::Locking(timer) => timer.tick_and_restart_if_elapsed(lockup_duration),
+                _ => false,
+            };
         //todo: instantly lockup block if it can't move anywhere (check moves to left/right)
-        if self.locking
-            && self
-                .lockup_timer
-                .tick_and_restart_if_elapsed(self.rules.lockup_duration())
-        {
+        if locked {
+            let t_spin = self.is_t_spin(block_pos);
             self.lock_active_block_to_board(block_pos);
             changes.push(TickChange::BlockLocked);
+            debug!(
+                block = ?self.pilot.active_block.block_type,
+                position = ?block_pos,
+                t_spin,
+                "block locked"
+            );
 
-            let filled_rows = self.find_filled_rows();
-            let removed_points = self.remove_rows(&filled_rows);
-            for p in removed_points {
-                changes.push(TickChange::PointRemoved(p.id));
+            self.pilot.state = State::Falling;
+            self.pilot.last_move_is_rotation = false;
+            let line_clear = LineClear {
+                rows: self.find_filled_rows(),
+                t_spin,
+                lock_tick: self.score.ticks,
+                block_pos,
+                timer: Timer::new(),
+            };
+            if !line_clear.rows.is_empty() && self.rules.line_clear_delay > 0 {
+                self.pilot.state = State::ClearingLines(line_clear);
+            } else {
+                self.clear_rows(line_clear, changes);
+                // a press on this tick already rotated the locked block
+                self.enter_next_block(
+                    input.rotate() && !self.pilot.input.rotate(),
+                    input.hold(),
+                    changes,
+                );
             }
-
-            self.spawn_block();
-            changes.push(TickChange::NewBlock);
-            self.locking = false;
-        } else if (self.locking || self.drop_timer.tick_and_restart_if_elapsed(drop_freq))
+        } else if (matches!(self.pilot.state, State::Locking(_))
+            || self.pilot.drop_timer.tick_and_restart_if_elapsed(drop_freq))
             && can_drop
         {
-            block_pos.1 += 1;
-            self.active_block_pos = block_pos;
-            self.locking = false;
+            self.pilot.active_block_pos = drop_pos.unwrap();
+            self.pilot.state = State::Falling;
+            self.pilot.last_move_is_rotation = false;
+            if self.pilot.input.fast_drop() {
+                changes.push(TickChange::SoftDropped);
+            }
+        } else {
+            self.pilot.active_block_pos = block_pos;
+        }
+    }
+
+    /// Removes the filled rows of the locked block, scores them and adds the garbage which
+    /// waited for the lock.
+    fn clear_rows(&mut self, line_clear: LineClear, changes: &mut Vec<TickChange>) {
+        let LineClear {
+            rows: filled_rows,
+            t_spin,
+            lock_tick,
+            block_pos,
+            ..
+        } = line_clear;
+        if !filled_rows.is_empty() {
+            debug!(rows = ?filled_rows, "rows cleared");
+        }
+        self.score.garbage_lines += filled_rows
+            .iter()
+            .filter(|&&y| {
+                self.board[y]
+                    .iter()
+                    .flatten()
+                    .any(|p| p.origin_block_type.is_none())
+            })
+            .count() as u32;
+        if let Some(clear_kind) = ClearKind::new(filled_rows.len(), t_spin) {
+            changes.push(TickChange::RowsCleared {
+                rows: filled_rows.clone(),
+                clear_kind,
+            });
+        }
+        let removed_points = self.remove_rows(&filled_rows);
+        if !filled_rows.is_empty() && self.points_pos.is_empty() {
+            debug!("perfect clear");
+            self.score.perfect_clears += 1;
+        }
+        for p in removed_points {
+            changes.push(TickChange::PointRemoved(p.id));
+            if let (Some(item), None) = (p.item, self.item) {
+                self.item = Some(item);
+                changes.push(TickChange::ItemGranted(item));
+            }
+        }
+
+        self.score.block_locked(filled_rows.len(), t_spin);
+        self.locked_blocks.push(LockedBlock {
+            block_type: self.pilot.active_block.block_type,
+            spawn_tick: self.pilot.active_block_spawn_tick,
+            lock_tick,
+            position: block_pos,
+            orientation: self.pilot.active_block.orientation,
+            rows: filled_rows.len(),
+            t_spin,
+            points: self.score.points,
+        });
+        let attack = self.attack.block_locked(filled_rows.len(), t_spin)
+            * self.rules.garbage_percent
+            + self.attack_remainder;
+        self.attack_remainder = attack % 100;
+        let attack = attack / 100;
+        self.attack_total += attack;
+        let cancelled = min(attack, self.pending_garbage);
+        self.pending_garbage -= cancelled;
+        if attack > cancelled {
+            debug!(lines = attack - cancelled, cancelled, "garbage sent");
+            changes.push(TickChange::GarbageSent(attack - cancelled));
+        }
+        if filled_rows.is_empty() && self.pending_garbage > 0 {
+            let lines = self.pending_garbage;
+            self.pending_garbage = 0;
+            self.add_garbage(lines as usize, changes);
+        }
+        if self.pending_drill_rows > 0 {
+            let lines = self.pending_drill_rows;
+            self.pending_drill_rows = 0;
+            self.add_garbage(lines as usize, changes);
+        }
+    }
+
+    /// Starts the entry delay, or spawns the next block right away without one or when the
+    /// garbage ended the game. The next block of a co-op pilot also waits while the partner's
+    /// block covers its spawn cells.
+    fn enter_next_block(
+        &mut self,
+        initial_rotation: bool,
+        initial_hold: bool,
+        changes: &mut Vec<TickChange>,
+    ) {
+        if (self.rules.entry_delay > 0 || self.is_spawn_blocked()) && !self.is_game_over() {
+            self.pilot.state = State::EntryDelay(Timer::new());
         } else {
-            self.active_block_pos = block_pos;
+            self.spawn_next_block(initial_rotation, initial_hold, changes);
+        }
+    }
+
+    /// Spawns the next block, swapped with the held block right away if hold is held through
+    /// the spawn and turned right away if rotation is, and ends the game if it doesn't fit.
+    fn spawn_next_block(
+        &mut self,
+        initial_rotation: bool,
+        initial_hold: bool,
+        changes: &mut Vec<TickChange>,
+    ) {
+        // the garbage may have pushed the stack out of the board
+        let topped_out = self.is_game_over();
+        self.spawn_block();
+        self.pilot.hold_used = false;
+        changes.push(TickChange::NewBlock);
+        if initial_hold {
+            // the hold held through the spawn was buffered too
+            self.pilot.buffered_input.clear_hold();
+            self.hold_block(changes);
+        }
+        if initial_rotation {
+            // the rotation held through the spawn was buffered too
+            self.pilot.buffered_input.clear_rotate();
+            self.initial_rotation(changes);
+        }
+
+        if topped_out || self.is_active_block_blocked() {
+            self.end_game(changes);
         }
+    }
 
+    /// Puts the active block to the hold and spawns the held block in its place, or the next
+    /// block when the hold is empty.
+    fn hold_block(&mut self, changes: &mut Vec<TickChange>) {
+        let block = match self.pilot.held_block.take() {
+            Some(block) => block,
+            None => self.deal_block(),
+        };
+        let mut held_block = self.place_spawned_block(block);
+        debug!(block = ?held_block.block_type, "block held");
+        held_block.orientation = 0;
+        self.pilot.held_block = Some(held_block);
+        self.pilot.hold_used = true;
+        self.pilot.last_move_is_rotation = false;
+        changes.push(TickChange::BlockHeld);
+    }
+
+    /// Returns `true` if the active block overlaps the board or the partner's block.
+    fn is_active_block_blocked(&self) -> bool {
+        self.is_block_collides(
+            self.pilot.active_block.shape().iter(),
+            self.pilot.active_block_pos,
+        )
+    }
+
+    fn end_game(&mut self, changes: &mut Vec<TickChange>) {
+        self.pilot.state = State::GameOver;
+        changes.push(TickChange::GameOver);
+        debug!(
+            score = self.score.points,
+            lines = self.score.lines,
+            "game over"
+        );
+    }
+
+    /// Returns `true` if the partner's block covers the cells where the next block of the
+    /// current pilot spawns.
+    fn is_spawn_blocked(&self) -> bool {
+        let partner = match &self.partner {
+            Some(partner) if partner.has_active_block() => partner,
+            _ => return false,
+        };
+        let spawn_shape = &orientations(self.next_blocks[0], self.rules.mirror, self.rules.big)[0];
+        let height = spawn_shape.points.iter().map(|&(_, y)| y).max().unwrap();
+        let spawn_pos = self.spawn_position(height);
+        let spawn_cells = spawn_shape
+            .points
+            .iter()
+            .map(|&pos| add_positions(spawn_pos, pos))
+            .collect::<Vec<_>>();
+        partner.block_cells().any(|pos| spawn_cells.contains(&pos))
+    }
+
+    /// Returns position where the block of the current pilot with the bottom row at
+    /// `block_height` spawns.
+    fn spawn_position(&self, block_height: i32) -> Position {
+        let (x, y) = self.rules.gravity.spawn_position(block_height);
+        (x + self.pilot.spawn_column, y)
+    }
+
+    fn tick_finished(&mut self, changes: Vec<TickChange>) -> Vec<TickChange> {
+        if changes.iter().any(|change| {
+            matches!(
+                change,
+                TickChange::BlockLocked | TickChange::PointRemoved(_) | TickChange::PointAdded(_)
+            )
+        }) {
+            self.update_metrics();
+        }
+
+        #[cfg(debug_assertions)]
+        if let Err(e) = self.check_invariants() {
+            panic!("game invariant violated: {}", e);
+        }
         changes
     }
 
+    /// Returns hash of the board, the active and next blocks and the score. Games played with
+    /// the same seed and input have the same hash, so peers of a network game can compare it.
+    pub fn state_hash(&self) -> u64 {
+        let mut bytes = vec![];
+        for row in &self.board {
+            bytes.extend(row[..self.board_width()].iter().map(|point| match point {
+                None => 0,
+                Some(point) => point.origin_block_type.map_or(1, |t| t as u8 + 2),
+            }));
+        }
+        let mut block_points = self.pilot.block_cells().collect::<Vec<_>>();
+        if let Some(partner) = &self.partner {
+            block_points.extend(partner.block_cells());
+        }
+        block_points.sort();
+        bytes.push(self.pilot.active_block.block_type as u8);
+        bytes.push(
+            self.pilot
+                .held_block
+                .as_ref()
+                .map_or(0, |block| block.block_type as u8 + 1),
+        );
+        bytes.extend(block_points.iter().flat_map(|&(x, y)| [x as u8, y as u8]));
+        bytes.extend(self.next_blocks.iter().map(|&t| t as u8));
+        for value in [
+            self.score.points,
+            self.score.lines,
+            self.score.ticks,
+            self.pending_garbage,
+            self.is_game_over() as u32,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        fnv1a(&bytes)
+    }
+
+    /// Checks that the board and the points positions agree, points don't overlap, the active
+    /// block is inside of the board and ids are generated in increasing order. Returns
+    /// description of the first violated invariant.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let next_id = self.gen_id.peek();
+
+        for (&id, &pos) in &self.points_pos {
+            match self.cell(pos) {
+                None => return Err(format!("point {} is outside of the board at {:?}", id, pos)),
+                Some(Some(point)) if point.id == id => {}
+                _ => return Err(format!("point {} is not on the board at {:?}", id, pos)),
+            }
+            if id >= next_id {
+                return Err(format!("point {} has id which wasn't generated yet", id));
+            }
+        }
+        let board_points = self.board.iter().flatten().filter(|p| p.is_some()).count();
+        if board_points != self.points_pos.len() {
+            return Err(format!(
+                "board has {} points, but {} positions are known",
+                board_points,
+                self.points_pos.len()
+            ));
+        }
+
+        for pilot in self.pilots() {
+            self.check_pilot(pilot, next_id)?;
+        }
+        if let Some(partner) = &self.partner {
+            let cells = self.pilot.block_cells().collect::<Vec<_>>();
+            let overlap = self.has_active_block()
+                && partner.has_active_block()
+                && partner.block_cells().any(|pos| cells.contains(&pos));
+            // the last block may overlap the partner's block when the game is over
+            if overlap && !self.is_game_over() {
+                return Err("blocks of the co-op pilots overlap".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_pilot(&self, pilot: &Pilot, next_id: Id) -> Result<(), String> {
+        // the locked block stays active until the next block spawns
+        if !pilot.has_active_block() {
+            return Ok(());
+        }
+        let block = &pilot.active_block;
+        if block.id >= next_id {
+            return Err(format!(
+                "block {} has id which wasn't generated yet",
+                block.id
+            ));
+        }
+        for point in block.points() {
+            if point.id <= block.id || point.id >= next_id {
+                return Err(format!(
+                    "point {} of block {} has id out of order",
+                    point.id, block.id
+                ));
+            }
+            if self.points_pos.contains_key(&point.id) {
+                return Err(format!(
+                    "point {} is both in the block and on the board",
+                    point.id
+                ));
+            }
+
+            let pos = match block.get_point_position(point.id) {
+                Some(pos) => add_positions(pilot.active_block_pos, pos),
+                None => return Err(format!("point {} of the block has no position", point.id)),
+            };
+            let cell = match self.cell(pos) {
+                Some(cell) => cell,
+                None => return Err(format!("block point {} is outside of the board", point.id)),
+            };
+            // the last block may overlap the board when the game is over
+            if !self.is_game_over() && cell.is_some() {
+                return Err(format!("block point {} overlaps board point", point.id));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn get_point_position(&self, point_id: Id) -> Option<Position> {
         self.points_pos.get(&point_id).copied()
     }
 
-    /// Returns `true` if block will collide with any of board points.
+    /// Returns position of the active block point, the co-op partner's block included.
+    pub fn block_point_position(&self, point_id: Id) -> Option<Position> {
+        self.pilots().find_map(|pilot| {
+            let point_pos = pilot.active_block.get_point_position(point_id)?;
+            Some(add_positions(pilot.active_block_pos, point_pos))
+        })
+    }
+
+    /// Returns all points locked to the board with their positions.
+    pub fn board_points(&self) -> impl Iterator<Item = (Point, Position)> + '_ {
+        self.points_pos
+            .values()
+            .map(|&pos| (self.cell(pos).unwrap().unwrap(), pos))
+    }
+
+    /// Replaces the active block with a new block of the given type at the spawn position, the
+    /// game is over if the block collides with the board there. Debug commands change the game
+    /// outside of its input, so the replay can't reproduce it.
+    pub fn debug_spawn_block(&mut self, block_type: BlockType) {
+        self.pilot.active_block = Block::new(
+            (self.gen_id)(),
+            block_type,
+            None,
+            &self.rules,
+            &mut self.gen_id,
+            &mut self.rng,
+        );
+        self.pilot.active_block_pos = self.spawn_position(self.pilot.active_block.height());
+        // the delays after a lock keep running and the ended game stays over
+        if matches!(self.pilot.state, State::Falling | State::Locking(_)) {
+            self.pilot.state = State::Spawning;
+        }
+        self.pilot.last_move_is_rotation = false;
+        if self.is_block_collides(
+            self.pilot.active_block.shape().iter(),
+            self.pilot.active_block_pos,
+        ) {
+            self.pilot.state = State::GameOver;
+        }
+    }
+
+    /// Adds garbage points to the empty board cells which are not covered by the active block,
+    /// used to set up practice boards. Replays don't record the added points.
+    pub fn prefill_board(&mut self, cells: impl IntoIterator<Item = Position>) {
+        self.prefill_board_blocks(cells.into_iter().map(|pos| (pos, None)));
+    }
+
+    /// Adds points of the block types to the empty board cells like `prefill_board`, `None`
+    /// adds garbage.
+    pub fn prefill_board_blocks(
+        &mut self,
+        cells: impl IntoIterator<Item = (Position, Option<BlockType>)>,
+    ) {
+        for (pos, block_type) in cells {
+            if self.is_covered(pos) || !matches!(self.cell(pos), Some(None)) {
+                continue;
+            }
+            let point = Point {
+                id: (self.gen_id)(),
+                origin_block_type: block_type,
+                item: None,
+            };
+            self.set_cell(pos, Some(point));
+            self.points_pos.insert(point.id, pos);
+        }
+        self.update_metrics();
+    }
+
+    /// Sets the number of cleared lines to the start of the level, which is bounded to keep the
+    /// points of the following clears from overflowing.
+    pub fn debug_set_level(&mut self, level: u32) {
+        const MAX_LEVEL: u32 = 999;
+        self.score.lines = (level.clamp(1, MAX_LEVEL) - 1) * LINES_PER_LEVEL;
+    }
+
+    /// Removes all points locked to the board.
+    pub fn debug_clear_board(&mut self) {
+        self.board = [[None; MAX_BOARD_WIDTH]; BOARD_HEIGHT];
+        self.points_pos.clear();
+        self.update_metrics();
+    }
+
+    /// Adds a garbage point to the empty board cell or removes the point from the occupied one.
+    /// Returns `false` if the cell is outside of the board or covered by the active block.
+    pub fn debug_toggle_cell(&mut self, pos: Position) -> bool {
+        if self.is_covered(pos) {
+            return false;
+        }
+
+        match self.cell(pos).copied() {
+            None => return false,
+            Some(Some(point)) => {
+                self.points_pos.remove(&point.id);
+                self.set_cell(pos, None);
+            }
+            Some(None) => {
+                let point = Point {
+                    id: (self.gen_id)(),
+                    origin_block_type: None,
+                    item: None,
+                };
+                self.set_cell(pos, Some(point));
+                self.points_pos.insert(point.id, pos);
+            }
+        }
+        self.update_metrics();
+        true
+    }
+
+    /// Returns `true` if the active block of a pilot covers the board cell.
+    fn is_covered(&self, pos: Position) -> bool {
+        self.pilots()
+            .any(|pilot| pilot.block_cells().any(|cell| cell == pos))
+    }
+
+    /// Returns the pilot and the co-op partner.
+    fn pilots(&self) -> impl Iterator<Item = &Pilot> {
+        std::iter::once(&self.pilot).chain(&self.partner)
+    }
+
+    /// Returns how many times a block point was locked to the board cell in this game.
+    pub fn placements(&self, pos: Position) -> u32 {
+        board_index(pos).map_or(0, |(x, y)| self.placements[y][x])
+    }
+
+    /// Returns board point.
+    pub fn get_point(&self, point_id: Id) -> Option<Point> {
+        let pos = self.get_point_position(point_id)?;
+        *self.cell(pos)?
+    }
+
+    /// Returns the board cell at the position, `None` outside of the board.
+    fn cell(&self, pos: Position) -> Option<&Option<Point>> {
+        let (x, y) = board_index(pos)?;
+        (x < self.board_width()).then(|| &self.board[y][x])
+    }
+
+    /// Replaces the point in the board cell, the position must be inside of the board.
+    fn set_cell(&mut self, pos: Position, point: Option<Point>) {
+        let (x, y) = board_index(pos).expect("position outside of the board");
+        self.board[y][x] = point;
+    }
+
+    /// Returns column heights, holes and other metrics of the board without the active block.
+    /// The co-op board is measured by its left half.
+    pub fn board_metrics(&self) -> BoardMetrics {
+        self.metrics
+    }
+
+    /// Returns number of the rows of the secret grade zig-zag pattern built from the floor, see
+    /// `secret_grade_name`. The pattern fits only the board of the usual width.
+    pub fn secret_grade(&self) -> usize {
+        if self.board_width() != BOARD_WIDTH {
+            return 0;
+        }
+        secret_grade::measure(|x, y| self.board[y][x].is_some(), self.rules.gravity)
+    }
+
+    fn update_metrics(&mut self) {
+        self.metrics = BoardMetrics::measure(|x, y| self.board[y][x].is_some(), self.rules.gravity);
+    }
+
+    /// Returns all placements of the active block which can be reached from its current
+    /// position, without the timing of the moves.
+    pub fn legal_placements(&self) -> Vec<Placement> {
+        placement::find_placements(self)
+    }
+
+    /// Returns position of the active block after the move, `None` if the block can't move there.
+    /// The game isn't changed, so the moves can be checked ahead.
+    pub fn try_move(&self, block_move: Move) -> Option<Position> {
+        self.moved_position(
+            &self.pilot.active_block,
+            self.pilot.active_block_pos,
+            block_move,
+        )
+    }
+
+    /// Returns position of the active block after the rotation, `None` if the block can't
+    /// rotate. The game isn't changed.
+    pub fn try_rotate(&self) -> Option<Position> {
+        self.rotated_block(&self.pilot.active_block, self.pilot.active_block_pos)
+            .map(|(_, block_pos)| block_pos)
+    }
+
+    /// Returns position of the block after the move, `None` if the block can't move there.
+    fn moved_position(
+        &self,
+        block: &Block,
+        (x, y): Position,
+        block_move: Move,
+    ) -> Option<Position> {
+        let step = self.rules.move_step();
+        let new_pos = match block_move {
+            Move::Left => (x - step, y),
+            Move::Right => (x + step, y),
+            Move::Fall => self.rules.gravity.fall((x, y), block.height())?,
+        };
+        (!self.is_block_collides(block.shape().iter(), new_pos)).then_some(new_pos)
+    }
+
+    /// Returns the orientation of the block rotated at `block_pos` and its new block position.
+    fn rotated_block(&self, block: &Block, block_pos: Position) -> Option<(usize, Position)> {
+        rotate_block(block, block_pos, |block_points, block_pos| {
+            !self.is_block_collides(block_points.iter(), block_pos)
+        })
+    }
+
+    /// Returns position where the block at `block_pos` lands when it falls to the floor, used
+    /// by hard drops, ghost blocks and bots.
+    pub fn landing_position(&self, block: &Block, block_pos: Position) -> Position {
+        self.shape_landing_position(
+            self.partner.as_ref(),
+            block.shape().iter(),
+            block.height(),
+            block_pos,
+        )
+    }
+
+    /// Returns position where the shape with the bottom row at `height` lands on the board and
+    /// the block of the `other` pilot, see `landing_position`.
+    fn shape_landing_position<'a>(
+        &self,
+        other: Option<&Pilot>,
+        points: impl Iterator<Item = &'a Position> + Clone,
+        height: i32,
+        mut pos: Position,
+    ) -> Position {
+        while let Some(next_pos) = self
+            .rules
+            .gravity
+            .fall(pos, height)
+            .filter(|&next_pos| !self.is_collides_with(other, points.clone(), next_pos))
+        {
+            pos = next_pos;
+        }
+        pos
+    }
+
+    /// Returns `true` if block will collide with any of board points or the partner's block.
     fn is_block_collides<'a>(
         &self,
         block_points: impl Iterator<Item = &'a Position>,
         block_pos: Position,
     ) -> bool {
-        for &point_pos in block_points {
-            let (x, y) = add_positions(block_pos, point_pos);
-            if self.board[y][x].is_some() {
-                return true;
+        self.is_collides_with(self.partner.as_ref(), block_points, block_pos)
+    }
+
+    /// Returns `true` if block will collide with any of board points or the active block of
+    /// the `other` pilot.
+    fn is_collides_with<'a>(
+        &self,
+        other: Option<&Pilot>,
+        block_points: impl Iterator<Item = &'a Position>,
+        block_pos: Position,
+    ) -> bool {
+        let other_cells = match other {
+            Some(other) if other.has_active_block() => other.block_cells().collect(),
+            _ => vec![],
+        };
+        // cells outside of the board collide like the walls
+        block_points
+            .map(|&point_pos| add_positions(block_pos, point_pos))
+            .any(|pos| !matches!(self.cell(pos), Some(None)) || other_cells.contains(&pos))
+    }
+
+    /// Returns `true` if the block at `block_pos` can't fall only because of the partner's block
+    /// under it.
+    fn rests_on_partner(&self, block_pos: Position) -> bool {
+        let block = &self.pilot.active_block;
+        self.partner.is_some()
+            && self
+                .rules
+                .gravity
+                .fall(block_pos, block.height())
+                .is_some_and(|pos| !self.is_collides_with(None, block.shape().iter(), pos))
+    }
+
+    fn spawn_block(&mut self) {
+        let block = self.deal_block();
+        self.place_spawned_block(block);
+    }
+
+    /// Takes the next block from the queue.
+    fn deal_block(&mut self) -> Block {
+        let block_type = self.next_blocks.pop_front().unwrap();
+        self.random_next_blocks = self.random_next_blocks.min(self.next_blocks.len());
+        self.fill_next_blocks();
+        self.block_dealt(block_type);
+
+        let item = if self.rules.items {
+            roll_item(&mut self.rng)
+        } else {
+            None
+        };
+        debug!(block = ?block_type, item = ?item, "block dealt");
+        Block::new(
+            (self.gen_id)(),
+            block_type,
+            item,
+            &self.rules,
+            &mut self.gen_id,
+            &mut self.rng,
+        )
+    }
+
+    /// Makes the block active at the spawn position and returns the replaced active block.
+    fn place_spawned_block(&mut self, block: Block) -> Block {
+        self.pilot.state = State::Spawning;
+        self.pilot.active_block_pos = self.spawn_position(block.height());
+        self.pilot.active_block_spawn_tick = self.score.ticks;
+        debug!(block = ?block.block_type, "block spawned");
+        mem::replace(&mut self.pilot.active_block, block)
+    }
+
+    /// Rotates the spawned block, lower by a row or two if it doesn't fit into the spawn rows
+    /// rotated.
+    fn initial_rotation(&mut self, changes: &mut Vec<TickChange>) {
+        let gravity = self.rules.gravity;
+        let rotated = (0..=2).find_map(|rows| {
+            let block_pos = gravity.shifted(self.pilot.active_block_pos, rows as i32);
+            Some((
+                self.rotated_block(&self.pilot.active_block, block_pos)?,
+                rows,
+            ))
+        });
+        if let Some(((orientation, block_pos), kick)) = rotated {
+            debug!(block = ?self.pilot.active_block.block_type, kick, "initial rotation");
+            self.pilot.active_block.orientation = orientation;
+            self.pilot.active_block_pos = block_pos;
+            self.pilot.input.restart_rotate();
+            changes.push(TickChange::BlockRotated { kick });
+        }
+    }
+
+    fn block_dealt(&mut self, block_type: BlockType) {
+        self.dealt_blocks[block_type as usize] += 1;
+        if self.block_history.len() == BLOCK_HISTORY_LEN {
+            self.block_history.pop_front();
+        }
+        self.block_history.push_back(block_type);
+        for drought in &mut self.droughts {
+            *drought += 1;
+        }
+        self.droughts[block_type as usize] = 0;
+    }
+
+    /// Adds the forced blocks to the next blocks, or random ones if there are none left.
+    fn fill_next_blocks(&mut self) {
+        while self.next_blocks.len() < NEXT_BLOCKS_COUNT {
+            let block_type = match self.forced_blocks.pop_front() {
+                Some(block_type) => block_type,
+                None => {
+                    self.random_next_blocks += 1;
+                    get_random_block(&mut self.rng)
+                }
+            };
+            self.next_blocks.push_back(block_type);
+        }
+    }
+
+    fn use_item(&mut self, changes: &mut Vec<TickChange>) {
+        let item = match self.item.take() {
+            Some(item) => item,
+            None => return,
+        };
+        debug!(item = ?item, "item used");
+
+        if item == Item::ClearBottomRows {
+            let rows = self
+                .rules
+                .gravity
+                .floor_rows(CLEARED_BOTTOM_ROWS)
+                .collect::<Vec<_>>();
+            for p in self.remove_rows(&rows) {
+                changes.push(TickChange::PointRemoved(p.id));
             }
+            // the stack may have moved into a block tucked under an overhang
+            self.fit_active_block(changes);
         }
-        false
+        changes.push(TickChange::ItemUsed(item));
     }
 
-    fn spawn_block(&mut self) {
-        self.active_block = Block::new((self.gen_id)(), get_random_block(), &mut self.gen_id);
-        self.active_block_pos = (4, 0);
+    /// Returns `true` if the active T block got to `block_pos` by rotation and three of four
+    /// corners around its center are occupied (walls and floor count as occupied). T-spins are
+    /// not detected for big blocks.
+    fn is_t_spin(&self, block_pos: Position) -> bool {
+        if self.pilot.active_block.block_type != BlockType::T
+            || !self.pilot.last_move_is_rotation
+            || self.pilot.active_block.big
+        {
+            return false;
+        }
+
+        let points = self.pilot.active_block.shape().iter().collect::<Vec<_>>();
+        let center = points.iter().find(|&&&(x, y)| {
+            points
+                .iter()
+                .filter(|&&&(px, py)| x.abs_diff(px) + y.abs_diff(py) == 1)
+                .count()
+                == 3
+        });
+        let (cx, cy) = match center {
+            Some(&&center) => add_positions(block_pos, center),
+            None => return false,
+        };
+
+        let occupied_corners = [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+            .iter()
+            .filter(|&&(dx, dy)| !matches!(self.cell((cx + dx, cy + dy)), Some(None)))
+            .count();
+        occupied_corners >= 3
+    }
+
+    /// Pushes the board towards the ceiling and fills rows at the floor with garbage which has
+    /// holes placed according to `GameRules::garbage_style`. The game is over if any board point
+    /// is pushed out of the board.
+    fn add_garbage(&mut self, lines: usize, changes: &mut Vec<TickChange>) {
+        let lines = min(lines, BOARD_HEIGHT);
+        debug!(lines, "garbage added");
+
+        let gravity = self.rules.gravity;
+        let width = self.board_width();
+        for y in gravity.rows_from_floor().into_iter().rev() {
+            for x in 0..width {
+                if let Some(p) = self.board[y][x].take() {
+                    match gravity.towards_ceiling(y, lines) {
+                        Some(new_y) => {
+                            self.board[new_y][x] = Some(p);
+                            self.points_pos.insert(p.id, board_position(x, new_y));
+                        }
+                        None => {
+                            self.points_pos.remove(&p.id);
+                            changes.push(TickChange::PointRemoved(p.id));
+                            self.pilot.state = State::GameOver;
+                        }
+                    }
+                }
+            }
+        }
+
+        // big blocks fit only into holes as wide as the move step
+        let step = self.rules.move_step() as usize;
+        for y in gravity.floor_rows(lines) {
+            // the wide board has a hole in each half
+            let holes = self
+                .garbage
+                .next_holes(&mut self.rng)
+                .into_iter()
+                .map(|hole| hole / step * step)
+                .collect::<Vec<_>>();
+            for x in (0..width).filter(|&x| holes.iter().all(|&h| x < h || x >= h + step)) {
+                let point = Point {
+                    id: (self.gen_id)(),
+                    origin_block_type: None,
+                    item: None,
+                };
+                self.board[y][x] = Some(point);
+                self.points_pos.insert(point.id, board_position(x, y));
+                changes.push(TickChange::PointAdded(point.id));
+            }
+        }
     }
 
     fn lock_active_block_to_board(&mut self, block_pos: Position) {
-        for point in self.active_block.points() {
+        for point in self.pilot.active_block.points() {
             let point = point.clone();
-            let (x, y) = add_positions(
+            let pos = add_positions(
                 block_pos,
-                self.active_block.get_point_position(point.id).unwrap(),
+                self.pilot
+                    .active_block
+                    .get_point_position(point.id)
+                    .unwrap(),
             );
 
+            let (x, y) = board_index(pos).expect("block point outside of the board");
             assert!(self.board[y][x].is_none());
             self.board[y][x] = Some(point);
-            self.points_pos.insert(point.id, (x, y));
+            self.placements[y][x] += 1;
+            self.points_pos.insert(point.id, pos);
         }
     }
 
     fn find_filled_rows(&self) -> Vec<usize> {
         let mut rows = vec![];
         for y in 0..BOARD_HEIGHT {
-            if self.board[y].iter().all(|p| p.is_some()) {
+            if self.board[y][..self.board_width()]
+                .iter()
+                .all(|p| p.is_some())
+            {
                 rows.push(y);
             }
         }
@@ -290,23 +1815,25 @@ impl Game {
     fn remove_rows(&mut self, rows: &[usize]) -> Vec<Point> {
         let mut removed_points = vec![];
 
+        // rows are moved towards the floor by the number of removed rows between them and the floor
+        let gravity = self.rules.gravity;
+        let width = self.board_width();
         let mut drop = 0;
-        let mut i = rows.len();
-        for y in (0..BOARD_HEIGHT).rev() {
-            if i > 0 && rows[i - 1] == y {
-                for x in 0..BOARD_WIDTH {
+        for y in gravity.rows_from_floor() {
+            if rows.contains(&y) {
+                for x in 0..width {
                     if let Some(p) = self.board[y][x].take() {
                         self.points_pos.remove(&p.id);
                         removed_points.push(p);
                     }
                 }
-                i -= 1;
                 drop += 1;
             } else if drop > 0 {
-                for x in 0..BOARD_WIDTH {
+                let new_y = gravity.towards_floor(y, drop).unwrap();
+                for x in 0..width {
                     if let Some(p) = self.board[y][x].take() {
-                        self.board[y + drop][x] = Some(p);
-                        self.points_pos.insert(p.id, (x, y + drop));
+                        self.board[new_y][x] = Some(p);
+                        self.points_pos.insert(p.id, board_position(x, new_y));
                     }
                 }
             }
@@ -315,3 +1842,581 @@ impl Game {
         removed_points
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys held on a tick.
+    struct Presses {
+        move_left: bool,
+        move_right: bool,
+        rotate: bool,
+        fast_drop: bool,
+        instant_drop: bool,
+        use_item: bool,
+        hold: bool,
+    }
+
+    const RELEASED: Presses = Presses {
+        move_left: false,
+        move_right: false,
+        rotate: false,
+        fast_drop: false,
+        instant_drop: false,
+        use_item: false,
+        hold: false,
+    };
+    /// Moves the block to the left wall and drops it.
+    const LEFT_DROP: Presses = Presses {
+        move_left: true,
+        fast_drop: true,
+        ..RELEASED
+    };
+    const HARD_DROP: Presses = Presses {
+        instant_drop: true,
+        ..RELEASED
+    };
+    /// Holds the rotation without dropping the block.
+    const ROTATE: Presses = Presses {
+        rotate: true,
+        ..RELEASED
+    };
+    const ROTATE_DROP: Presses = Presses {
+        rotate: true,
+        instant_drop: true,
+        ..RELEASED
+    };
+
+    impl Input for Presses {
+        fn move_left(&self) -> bool {
+            self.move_left
+        }
+
+        fn move_right(&self) -> bool {
+            self.move_right
+        }
+
+        fn rotate(&self) -> bool {
+            self.rotate
+        }
+
+        fn fast_drop(&self) -> bool {
+            self.fast_drop
+        }
+
+        fn instant_drop(&self) -> bool {
+            self.instant_drop
+        }
+
+        fn use_item(&self) -> bool {
+            self.use_item
+        }
+
+        fn hold(&self) -> bool {
+            self.hold
+        }
+    }
+
+    /// Fills the two bottom rows of the board except the columns, an O block dropped into two
+    /// empty columns clears both rows.
+    fn fill_rows_except(game: &mut Game, cols: impl IntoIterator<Item = usize>) {
+        let cols = cols.into_iter().collect::<Vec<_>>();
+        let width = game.board_width();
+        game.prefill_board((BOARD_HEIGHT - 2..BOARD_HEIGHT).flat_map(|y| {
+            (0..width)
+                .filter(|x| !cols.contains(x))
+                .map(move |x| board_position(x, y))
+        }));
+    }
+
+    #[test]
+    fn cleared_bottom_rows_push_tucked_block_up() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        let floor = BOARD_HEIGHT as i32 - 1;
+        // overhang two rows above the floor with the block tucked under it
+        game.prefill_board((0..4).map(|x| (x, floor - 2)));
+        game.pilot.active_block_pos = (0, floor - 1);
+        game.item = Some(Item::ClearBottomRows);
+
+        let changes = game.tick(&Presses {
+            use_item: true,
+            ..RELEASED
+        });
+        assert!(changes
+            .iter()
+            .any(|c| matches!(c, TickChange::ItemUsed(Item::ClearBottomRows))));
+        assert!(game.check_invariants().is_ok());
+        assert!(!game.is_game_over());
+        // the overhang moved to the floor, the block rests on it
+        assert_eq!((0, floor - 2), game.active_block_position());
+    }
+
+    #[test]
+    fn presses_during_entry_delay_move_next_block() {
+        let mut rules = GameRules::new();
+        rules.entry_delay = 3;
+        let mut game = Game::with_blocks(rules, &[BlockType::T, BlockType::O]);
+        game.tick(&Presses {
+            instant_drop: true,
+            ..RELEASED
+        });
+        game.tick(&Presses {
+            move_left: true,
+            ..RELEASED
+        });
+        while game.is_entry_delay() {
+            game.tick(&RELEASED);
+        }
+
+        let (x, _) = game.active_block_position();
+        game.tick(&RELEASED);
+        assert_eq!(x - 1, game.active_block_position().0);
+    }
+
+    #[test]
+    fn moves_and_rotations_are_reported() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::T]);
+        let changes = game.tick(&Presses {
+            move_left: true,
+            ..RELEASED
+        });
+        assert!(matches!(
+            changes[..],
+            [TickChange::BlockShifted(Move::Left)]
+        ));
+        // the block falls out of the ceiling to have room for the rotation
+        for _ in 0..20 {
+            game.tick(&RELEASED);
+        }
+        let changes = game.tick(&ROTATE);
+        assert!(matches!(
+            changes[..],
+            [TickChange::BlockRotated { kick: 0 }]
+        ));
+        let changes = game.tick(&ROTATE_DROP);
+        assert!(matches!(changes[0], TickChange::BlockLocked));
+    }
+
+    #[test]
+    fn held_rotation_turns_spawned_block() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::T, BlockType::I]);
+        game.tick(&ROTATE);
+        game.tick(&ROTATE_DROP);
+        assert_eq!(BlockType::I, game.active_block().block_type);
+        // the I block spawns standing, the rotation lays it down
+        let block = game.active_block();
+        assert_eq!(Some(3), block.shape().iter().map(|&(x, _)| x).max());
+        assert_eq!(0, block.height());
+    }
+
+    #[test]
+    fn hold_swaps_active_block_once_per_block() {
+        let blocks = [BlockType::T, BlockType::I, BlockType::O, BlockType::S];
+        let mut game = Game::with_blocks(GameRules::new(), &blocks);
+        let hold = Presses {
+            hold: true,
+            ..RELEASED
+        };
+        let changes = game.tick(&hold);
+        assert!(matches!(changes[..], [TickChange::BlockHeld]));
+        assert_eq!(BlockType::I, game.active_block().block_type);
+        assert_eq!(Some(BlockType::T), game.held_block());
+
+        // the swapped block can't be held again until it's locked
+        game.tick(&RELEASED);
+        assert!(game.tick(&hold).is_empty());
+        assert_eq!(BlockType::I, game.active_block().block_type);
+
+        game.tick(&HARD_DROP);
+        assert_eq!(BlockType::O, game.active_block().block_type);
+        game.tick(&RELEASED);
+        game.tick(&hold);
+        assert_eq!(BlockType::T, game.active_block().block_type);
+        assert_eq!(Some(BlockType::O), game.held_block());
+    }
+
+    #[test]
+    fn held_hold_swaps_spawned_block() {
+        let blocks = [BlockType::T, BlockType::I, BlockType::O];
+        let mut game = Game::with_blocks(GameRules::new(), &blocks);
+        let hold = Presses {
+            hold: true,
+            ..RELEASED
+        };
+        game.tick(&hold);
+        let changes = game.tick(&Presses {
+            instant_drop: true,
+            hold: true,
+            ..RELEASED
+        });
+        assert!(matches!(
+            changes[..],
+            [.., TickChange::NewBlock, TickChange::BlockHeld]
+        ));
+        assert_eq!(BlockType::T, game.active_block().block_type);
+        assert_eq!(Some(BlockType::O), game.held_block());
+        // the hold was used by the spawned block
+        game.tick(&RELEASED);
+        game.tick(&hold);
+        assert_eq!(BlockType::T, game.active_block().block_type);
+    }
+
+    #[test]
+    fn phases_follow_the_block() {
+        let mut rules = GameRules::new();
+        rules.entry_delay = 2;
+        let mut game = Game::with_blocks(rules, &[BlockType::T, BlockType::I]);
+        assert_eq!(Phase::Spawning, game.phase());
+        game.tick(&RELEASED);
+        assert_eq!(Phase::Falling, game.phase());
+        while game.phase() == Phase::Falling {
+            game.tick(&RELEASED);
+        }
+        assert_eq!(Phase::Locking, game.phase());
+        while game.phase() == Phase::Locking {
+            game.tick(&RELEASED);
+        }
+        assert_eq!(Phase::EntryDelay, game.phase());
+        game.tick(&RELEASED);
+        game.tick(&RELEASED);
+        assert_eq!(Phase::Spawning, game.phase());
+        assert_eq!(BlockType::I, game.active_block().block_type);
+    }
+
+    #[test]
+    fn entry_delay_postpones_spawn() {
+        let mut rules = GameRules::new();
+        rules.entry_delay = 3;
+        let mut game = Game::with_blocks(rules, &[BlockType::T, BlockType::I]);
+        let changes = game.tick(&HARD_DROP);
+        assert!(matches!(changes[..], [TickChange::BlockLocked]));
+        assert!(game.is_entry_delay());
+        assert!(game.view().active_block.is_empty());
+        for _ in 0..2 {
+            assert!(game.tick(&HARD_DROP).is_empty());
+        }
+        let changes = game.tick(&HARD_DROP);
+        assert!(matches!(changes[..], [TickChange::NewBlock]));
+        assert!(!game.is_entry_delay());
+        assert_eq!(BlockType::I, game.active_block().block_type);
+    }
+
+    #[test]
+    fn forced_blocks_are_dealt_first() {
+        let blocks = [
+            BlockType::T,
+            BlockType::I,
+            BlockType::O,
+            BlockType::S,
+            BlockType::Z,
+        ];
+        let game = Game::with_blocks(GameRules::new(), &blocks);
+        assert_eq!(BlockType::T, game.active_block().block_type);
+        assert_eq!(blocks[1..4], game.next_blocks().collect::<Vec<_>>());
+        assert_eq!(1, game.dealt_blocks(BlockType::T));
+    }
+
+    #[test]
+    fn preview_count_limits_next_blocks() {
+        let mut rules = GameRules::new();
+        rules.preview_count = 1;
+        let blocks = [BlockType::T, BlockType::I, BlockType::O];
+        let game = Game::with_blocks(rules, &blocks);
+        assert_eq!(vec![BlockType::I], game.next_blocks().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn history_keeps_last_blocks() {
+        use BlockType::*;
+        let blocks = [T, I, O, S, Z, J, L, I, T];
+        let mut game = Game::with_blocks(GameRules::new(), &blocks);
+        while game.locked_blocks().len() < blocks.len() - 1 {
+            game.tick(&LEFT_DROP);
+        }
+
+        assert_eq!(blocks[2..], game.block_history().collect::<Vec<_>>());
+        assert_eq!(0, game.drought(T));
+        assert_eq!(1, game.drought(I));
+        assert_eq!(6, game.drought(O));
+    }
+
+    #[test]
+    fn queue_overrides_random_blocks() {
+        let mut game = Game::new();
+        game.set_queue(&[BlockType::I, BlockType::O]);
+        game.append_queue(&[BlockType::S, BlockType::Z]);
+        game.append_queue(&[BlockType::T]);
+        let mut blocks = game.next_blocks().collect::<Vec<_>>();
+        while blocks.len() < 5 {
+            game.tick(&LEFT_DROP);
+            if game.locked_blocks().len() + NEXT_BLOCKS_COUNT > blocks.len() {
+                blocks.push(game.next_blocks().last().unwrap());
+            }
+        }
+
+        let expected = [
+            BlockType::I,
+            BlockType::O,
+            BlockType::S,
+            BlockType::Z,
+            BlockType::T,
+        ];
+        assert_eq!(expected, blocks[..]);
+    }
+
+    #[test]
+    fn positions_outside_of_board_are_rejected() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        let last = board_position(BOARD_WIDTH - 1, BOARD_HEIGHT - 1);
+        assert_eq!(Some((BOARD_WIDTH - 1, BOARD_HEIGHT - 1)), board_index(last));
+        for pos in [(-1, 0), (0, -1), (last.0 + 1, 0), (0, last.1 + 1)] {
+            assert!(!game.debug_toggle_cell(pos));
+            assert!(game.is_block_collides([(0, 0)].iter(), pos));
+        }
+        assert_eq!(None, board_index((-1, 0)));
+        assert_eq!(None, board_index((MAX_BOARD_WIDTH as i32, 0)));
+    }
+
+    #[test]
+    fn debug_level_is_bounded() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        game.debug_set_level(u32::MAX);
+        assert_eq!(999, game.score().level());
+        game.debug_set_level(0);
+        assert_eq!(1, game.score().level());
+    }
+
+    #[test]
+    fn coop_blocks_collide() {
+        let mut rules = GameRules::new();
+        rules.coop = true;
+        let mut game = Game::with_blocks(rules, &[BlockType::O]);
+        assert_eq!(MAX_BOARD_WIDTH, game.board_width());
+        let partner_left = Presses {
+            move_left: true,
+            ..RELEASED
+        };
+        for _ in 0..100 {
+            game.tick_coop([&RELEASED, &partner_left]);
+        }
+
+        // the partner's block stopped right next to the O block
+        let (_, partner_pos, _) = game.partner_block().unwrap();
+        assert_eq!(game.active_block_position().0 + 2, partner_pos.0);
+        assert!(game.check_invariants().is_ok());
+    }
+
+    #[test]
+    fn coop_pilots_share_score() {
+        let mut rules = GameRules::new();
+        rules.coop = true;
+        let mut game = Game::with_blocks(rules, &[BlockType::O]);
+        let changes = game.tick_coop([&HARD_DROP, &HARD_DROP]);
+        assert_eq!(
+            2,
+            changes
+                .iter()
+                .filter(|c| matches!(c, TickChange::BlockLocked))
+                .count()
+        );
+        assert_eq!(2, game.locked_blocks().len());
+        assert!(game
+            .board_points()
+            .any(|(_, (x, _))| x >= BOARD_WIDTH as i32));
+        assert_eq!(8, game.view().active_block.len());
+    }
+
+    #[test]
+    fn doubles_block_spawns_in_middle() {
+        let mut rules = GameRules::new();
+        rules.doubles = true;
+        let game = Game::with_blocks(rules, &[BlockType::O]);
+        let single = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        let (x, y) = single.active_block_position();
+        assert_eq!(2 * BOARD_WIDTH, game.board_width());
+        assert_eq!(
+            (x + (BOARD_WIDTH / 2) as i32, y),
+            game.active_block_position()
+        );
+    }
+
+    #[test]
+    fn doubles_rows_clear_only_when_full() {
+        let mut rules = GameRules::new();
+        rules.doubles = true;
+        let lines = |width| {
+            let mut game = Game::with_blocks(rules.clone(), &[BlockType::O]);
+            fill_rows_except(&mut game, (0..2).chain(width..2 * BOARD_WIDTH));
+            while game.locked_blocks().is_empty() {
+                game.tick(&LEFT_DROP);
+            }
+            game.score().lines
+        };
+        assert_eq!(0, lines(BOARD_WIDTH));
+        assert_eq!(2, lines(2 * BOARD_WIDTH));
+    }
+
+    #[test]
+    fn block_lands_on_stack() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        let landing_pos = |game: &Game, pos| game.landing_position(game.active_block(), pos);
+        assert_eq!(
+            board_position(0, BOARD_HEIGHT - 2),
+            landing_pos(&game, (0, 0))
+        );
+
+        game.prefill_board([board_position(1, BOARD_HEIGHT - 1)]);
+        assert_eq!(
+            board_position(0, BOARD_HEIGHT - 3),
+            landing_pos(&game, (0, 0))
+        );
+        assert_eq!(
+            board_position(2, BOARD_HEIGHT - 2),
+            landing_pos(&game, (2, 0))
+        );
+    }
+
+    #[test]
+    fn hard_drop_locks_block() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        let block = game.active_block();
+        let point_id = block.points()[0].id;
+        let point_pos = add_positions(
+            game.landing_position(block, game.active_block_position()),
+            block.get_point_position(point_id).unwrap(),
+        );
+        game.tick(&HARD_DROP);
+
+        assert_eq!(1, game.locked_blocks().len());
+        assert_eq!(Some(point_pos), game.get_point_position(point_id));
+        assert_eq!(4, game.board_metrics().aggregate_height);
+    }
+
+    #[test]
+    fn same_input_gives_same_state_hash() {
+        let mut game = Game::with_seed(GameRules::new(), 7);
+        let mut other = Game::with_seed(GameRules::new(), 7);
+        for _ in 0..100 {
+            game.tick(&LEFT_DROP);
+            other.tick(&LEFT_DROP);
+        }
+        assert_eq!(game.state_hash(), other.state_hash());
+
+        other.tick(&HARD_DROP);
+        game.tick(&LEFT_DROP);
+        assert_ne!(game.state_hash(), other.state_hash());
+    }
+
+    #[test]
+    fn restored_game_plays_on_from_snapshot() {
+        let mut game = Game::with_seed(GameRules::new(), 3);
+        for _ in 0..50 {
+            game.tick(&LEFT_DROP);
+        }
+        let snapshot = game.snapshot();
+        let hash = game.state_hash();
+        for _ in 0..200 {
+            game.tick(&HARD_DROP);
+        }
+        let played_hash = game.state_hash();
+        let played_blocks = game.locked_blocks().len();
+
+        game.restore(&snapshot);
+        assert_eq!(hash, game.state_hash());
+        assert_eq!(50, game.replay().len());
+        for _ in 0..200 {
+            game.tick(&HARD_DROP);
+        }
+        assert_eq!(played_hash, game.state_hash());
+        assert_eq!(played_blocks, game.locked_blocks().len());
+        assert_eq!(250, game.replay().len());
+    }
+
+    #[test]
+    fn snapshot_after_restored_one_keeps_its_inputs() {
+        let mut game = Game::with_seed(GameRules::new(), 3);
+        let earlier = game.snapshot();
+        for _ in 0..50 {
+            game.tick(&LEFT_DROP);
+        }
+        let later = game.snapshot();
+        let inputs = game.replay().inputs().collect::<Vec<_>>();
+
+        game.restore(&earlier);
+        game.tick(&HARD_DROP);
+        game.restore(&later);
+        assert_eq!(inputs, game.replay().inputs().collect::<Vec<_>>());
+        let mut replayed = game.replay().new_game();
+        for input in &inputs {
+            replayed.tick(input);
+        }
+        assert_eq!(game.state_hash(), replayed.state_hash());
+    }
+
+    #[test]
+    fn tried_moves_dont_change_game() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::I]);
+        let (x, y) = game.active_block_position();
+        assert_eq!(Some((x - 1, y)), game.try_move(Move::Left));
+        assert_eq!(Some((x + 1, y)), game.try_move(Move::Right));
+        assert_eq!(Some((x, y + 1)), game.try_move(Move::Fall));
+        assert!(game.try_rotate().is_some());
+        assert_eq!((x, y), game.active_block_position());
+
+        while game.try_move(Move::Left).is_some() {
+            game.tick(&LEFT_DROP);
+        }
+        assert_eq!(0, game.active_block_position().0);
+    }
+
+    #[test]
+    fn emptied_board_is_perfect_clear() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        fill_rows_except(&mut game, 0..2);
+        while game.locked_blocks().is_empty() {
+            game.tick(&LEFT_DROP);
+        }
+
+        assert_eq!(0, game.board_points().count());
+        assert_eq!(1, game.score().perfect_clears);
+    }
+
+    #[test]
+    fn cleared_rows_are_reported() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        fill_rows_except(&mut game, 0..2);
+        let mut changes = vec![];
+        while game.locked_blocks().is_empty() {
+            changes = game.tick(&LEFT_DROP);
+        }
+
+        let cleared = changes.iter().find_map(|change| match change {
+            TickChange::RowsCleared { rows, clear_kind } => Some((rows.clone(), *clear_kind)),
+            _ => None,
+        });
+        let rows = vec![BOARD_HEIGHT - 2, BOARD_HEIGHT - 1];
+        assert_eq!(Some((rows, ClearKind::Double)), cleared);
+    }
+
+    #[test]
+    fn filled_rows_stay_for_line_clear_delay() {
+        let mut rules = GameRules::new();
+        rules.line_clear_delay = 5;
+        let mut game = Game::with_blocks(rules, &[BlockType::O, BlockType::T]);
+        fill_rows_except(&mut game, 0..2);
+        while !game.is_clearing_rows() {
+            game.tick(&LEFT_DROP);
+        }
+
+        for _ in 0..4 {
+            game.tick(&LEFT_DROP);
+            assert_eq!(2 * BOARD_WIDTH, game.board_points().count());
+            assert!(game.view().active_block.is_empty());
+        }
+        game.tick(&LEFT_DROP);
+        assert_eq!(0, game.board_points().count());
+        assert_eq!(2, game.score().lines);
+        assert_eq!(BlockType::T, game.active_block().block_type);
+    }
+}
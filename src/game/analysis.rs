@@ -0,0 +1,318 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Read},
+};
+
+use super::{
+    add_positions, Block, ClearKind, Game, Input, Move, Position, Replay, ReplayInput, Score,
+    TickChange,
+};
+
+/// Block locked in the analyzed game.
+#[derive(Clone, Copy, Debug)]
+pub struct PlacedBlock {
+    pub lock_tick: u32,
+    pub clear_kind: Option<ClearKind>,
+    /// Height of the highest column after the block was locked and its rows were removed.
+    pub stack_height: usize,
+    /// Presses of the moves and the rotation from the spawn of the block to its lock, a held
+    /// input is one press.
+    pub presses: u32,
+    /// Fewest presses which put the block to the same cells by a hard drop from the spawn,
+    /// `None` for cells which can't be reached so, like soft dropped tucks and spins.
+    pub optimal_presses: Option<u32>,
+}
+
+impl PlacedBlock {
+    /// Returns whether the block was placed with more presses than needed.
+    pub fn is_finesse_fault(&self) -> bool {
+        self.optimal_presses
+            .is_some_and(|optimal| self.presses > optimal)
+    }
+}
+
+/// Statistics of a game measured by playing its replay without rendering it, for the stats
+/// screens and external tools.
+#[derive(Clone, Debug)]
+pub struct ReplayAnalysis {
+    /// Locked blocks in the order of their locks.
+    pub blocks: Vec<PlacedBlock>,
+    /// Score at the end of the replay.
+    pub score: Score,
+}
+
+impl ReplayAnalysis {
+    pub fn new(replay: &Replay) -> Self {
+        let inputs = replay.inputs().collect::<Vec<_>>();
+        let mut game = replay.new_game();
+        let mut blocks = vec![];
+        let mut finesse = Finesse::new(&game);
+        for input in &inputs {
+            let changes = game.tick(input);
+            // the rows of the locked blocks are already removed
+            let metrics = game.board_metrics();
+            let stack_height = metrics.column_heights.into_iter().max().unwrap_or(0);
+            for locked in game.locked_blocks().iter().skip(blocks.len()) {
+                let spawn_tick = locked.spawn_tick as usize;
+                blocks.push(PlacedBlock {
+                    lock_tick: locked.lock_tick,
+                    clear_kind: locked.clear_kind(),
+                    stack_height,
+                    presses: count_presses(&inputs, spawn_tick, locked.lock_tick as usize),
+                    optimal_presses: finesse.optimal_presses(locked.orientation, locked.position),
+                });
+            }
+            if changes
+                .iter()
+                .any(|c| matches!(c, TickChange::NewBlock | TickChange::BlockHeld))
+            {
+                finesse = Finesse::new(&game);
+            }
+        }
+        Self {
+            blocks,
+            score: game.score(),
+        }
+    }
+
+    /// Reads the replay file and analyzes it.
+    pub fn read(reader: impl Read) -> io::Result<Self> {
+        Ok(Self::new(&Replay::read(reader)?))
+    }
+
+    pub fn finesse_faults(&self) -> usize {
+        self.blocks.iter().filter(|b| b.is_finesse_fault()).count()
+    }
+
+    /// Returns the number of clears of every kind made in the game.
+    pub fn clear_counts(&self) -> HashMap<ClearKind, u32> {
+        let mut counts = HashMap::new();
+        for clear_kind in self.blocks.iter().filter_map(|b| b.clear_kind) {
+            *counts.entry(clear_kind).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Returns the tick of every lock with the height of the stack after it.
+    pub fn stack_heights(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        self.blocks.iter().map(|b| (b.lock_tick, b.stack_height))
+    }
+
+    /// Returns the blocks locked per second from the start of the game to the end of every
+    /// whole second, the game played `ticks_per_second` ticks in a second.
+    pub fn pps_timeline(&self, ticks_per_second: u32) -> Vec<f32> {
+        (1..=self.score.ticks / ticks_per_second)
+            .map(|second| {
+                let ticks = second * ticks_per_second;
+                let locked = self.blocks.partition_point(|b| b.lock_tick <= ticks);
+                locked as f32 / second as f32
+            })
+            .collect()
+    }
+}
+
+/// Fewest presses which bring the active block from its spawn to the cells it can be hard
+/// dropped to. A tap moves the block by a step and a held move to the wall.
+struct Finesse {
+    block: Option<Block>,
+    presses: HashMap<Vec<Position>, u32>,
+}
+
+impl Finesse {
+    fn new(game: &Game) -> Self {
+        if !game.has_active_block() {
+            return Self {
+                block: None,
+                presses: HashMap::new(),
+            };
+        }
+
+        // the presses are counted from the unrotated block, the held rotation turns it at spawn
+        let mut block = game.active_block().clone();
+        block.orientation = 0;
+        let mut presses = HashMap::new();
+        let mut visited = HashSet::new();
+        let spawn = (block.clone(), game.spawn_position(block.height()), 0);
+        let mut queue = VecDeque::from([spawn]);
+        while let Some((block, pos, count)) = queue.pop_front() {
+            if !visited.insert((block.orientation, pos)) {
+                continue;
+            }
+            let landing_pos = game.landing_position(&block, pos);
+            presses.entry(cells(&block, landing_pos)).or_insert(count);
+
+            for block_move in [Move::Left, Move::Right] {
+                if let Some(tapped_pos) = game.moved_position(&block, pos, block_move) {
+                    let mut held_pos = tapped_pos;
+                    while let Some(next_pos) = game.moved_position(&block, held_pos, block_move) {
+                        held_pos = next_pos;
+                    }
+                    queue.push_back((block.clone(), tapped_pos, count + 1));
+                    queue.push_back((block.clone(), held_pos, count + 1));
+                }
+            }
+            if let Some((orientation, rotated_pos)) = game.rotated_block(&block, pos) {
+                let mut rotated = block.clone();
+                rotated.orientation = orientation;
+                queue.push_back((rotated, rotated_pos, count + 1));
+            }
+        }
+        Self {
+            block: Some(block),
+            presses,
+        }
+    }
+
+    fn optimal_presses(&self, orientation: usize, position: Position) -> Option<u32> {
+        let mut block = self.block.clone()?;
+        block.orientation = orientation;
+        self.presses.get(&cells(&block, position)).copied()
+    }
+}
+
+/// Returns the sorted board positions of the block points.
+fn cells(block: &Block, block_pos: Position) -> Vec<Position> {
+    let mut cells = block
+        .shape()
+        .iter()
+        .map(|&pos| add_positions(block_pos, pos))
+        .collect::<Vec<_>>();
+    cells.sort();
+    cells
+}
+
+/// Counts the presses of the moves and the rotation on the ticks after `spawn_tick` up to
+/// `lock_tick`. Inputs are recorded from the first tick, which has the number 1.
+fn count_presses(inputs: &[ReplayInput], spawn_tick: usize, lock_tick: usize) -> u32 {
+    let actions: [fn(&ReplayInput) -> bool; 3] =
+        [Input::move_left, Input::move_right, Input::rotate];
+    (spawn_tick..lock_tick.min(inputs.len()))
+        .map(|i| {
+            actions
+                .iter()
+                .filter(|action| action(&inputs[i]) && (i == 0 || !action(&inputs[i - 1])))
+                .count() as u32
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Bot, GameRules};
+
+    #[derive(Default)]
+    struct Presses {
+        move_left: bool,
+        move_right: bool,
+        instant_drop: bool,
+    }
+
+    impl Input for Presses {
+        fn move_left(&self) -> bool {
+            self.move_left
+        }
+
+        fn move_right(&self) -> bool {
+            self.move_right
+        }
+
+        fn rotate(&self) -> bool {
+            false
+        }
+
+        fn fast_drop(&self) -> bool {
+            false
+        }
+
+        fn instant_drop(&self) -> bool {
+            self.instant_drop
+        }
+
+        fn use_item(&self) -> bool {
+            false
+        }
+
+        fn hold(&self) -> bool {
+            false
+        }
+    }
+
+    /// Plays the presses with a released tick after each of them.
+    fn play(game: &mut Game, presses: &[Presses]) {
+        for press in presses {
+            game.tick(press);
+            game.tick(&Presses::default());
+        }
+    }
+
+    fn left() -> Presses {
+        Presses {
+            move_left: true,
+            ..Presses::default()
+        }
+    }
+
+    fn right() -> Presses {
+        Presses {
+            move_right: true,
+            ..Presses::default()
+        }
+    }
+
+    fn drop() -> Presses {
+        Presses {
+            instant_drop: true,
+            ..Presses::default()
+        }
+    }
+
+    #[test]
+    fn wasted_presses_are_finesse_faults() {
+        let mut game = Game::with_seed(GameRules::new(), 1);
+        play(&mut game, &[left(), left(), drop()]);
+        play(&mut game, &[left(), right(), left(), left(), drop()]);
+
+        let analysis = ReplayAnalysis::new(game.replay());
+        assert_eq!(2, analysis.blocks.len());
+        assert_eq!(2, analysis.blocks[0].presses);
+        assert!(!analysis.blocks[0].is_finesse_fault());
+        assert_eq!(4, analysis.blocks[1].presses);
+        assert!(analysis.blocks[1].is_finesse_fault());
+        assert_eq!(1, analysis.finesse_faults());
+    }
+
+    #[test]
+    fn analysis_follows_game() {
+        let mut game = Game::with_seed(GameRules::new(), 7);
+        let mut bot = Bot::new();
+        while game.locked_blocks().len() < 60 && !game.is_game_over() {
+            bot.think(&game);
+            game.tick(&bot);
+        }
+
+        let analysis = ReplayAnalysis::new(game.replay());
+        assert_eq!(game.locked_blocks().len(), analysis.blocks.len());
+        assert_eq!(game.score().points, analysis.score.points);
+        let clears = analysis.clear_counts().values().sum::<u32>();
+        let expected = game.locked_blocks().iter().filter(|b| b.rows > 0).count();
+        assert_eq!(expected as u32, clears);
+        let (_, height) = analysis.stack_heights().last().unwrap();
+        let metrics = game.board_metrics();
+        assert_eq!(metrics.column_heights.into_iter().max().unwrap(), height);
+
+        let timeline = analysis.pps_timeline(60);
+        assert_eq!((game.score().ticks / 60) as usize, timeline.len());
+        // the blocks locked after the last whole second aren't in the timeline
+        let ticks = timeline.len() as u32 * 60;
+        let locked = game
+            .locked_blocks()
+            .iter()
+            .filter(|b| b.lock_tick <= ticks)
+            .count();
+        assert_eq!(
+            locked as f32 / timeline.len() as f32,
+            timeline[timeline.len() - 1]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::cmp::min;
+
+use super::ClearKind;
+
+/// Garbage lines sent for clearing 0, 1, 2, 3 and 4 rows.
+const CLEAR_ATTACK: [u32; 5] = [0, 0, 1, 2, 4];
+/// Garbage lines sent for T-spin clearing 0, 1, 2 and 3 rows.
+const T_SPIN_ATTACK: [u32; 4] = [0, 2, 4, 6];
+/// Additional garbage lines for consecutive difficult clears (tetrises and T-spins).
+const BACK_TO_BACK_BONUS: u32 = 1;
+/// Additional garbage lines for consecutive clears, indexed by combo counter.
+const COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+/// Returns number of garbage lines sent for clearing `rows` rows without a T-spin, combo or
+/// back-to-back bonus.
+pub(super) fn clear_attack(rows: usize) -> u32 {
+    CLEAR_ATTACK[min(rows, CLEAR_ATTACK.len() - 1)]
+}
+
+#[derive(Clone)]
+pub(super) struct Attack {
+    back_to_back: bool,
+    combo: Option<usize>,
+}
+
+impl Attack {
+    pub fn new() -> Self {
+        Self {
+            back_to_back: false,
+            combo: None,
+        }
+    }
+
+    /// Registers locked block which removed `rows` rows and returns number of garbage lines
+    /// to send to the opponent.
+    pub fn block_locked(&mut self, rows: usize, t_spin: bool) -> u32 {
+        if rows == 0 {
+            self.combo = None;
+            return 0;
+        }
+
+        let combo = self.combo.map_or(0, |c| c + 1);
+        self.combo = Some(combo);
+
+        let mut attack = if t_spin {
+            T_SPIN_ATTACK[min(rows, T_SPIN_ATTACK.len() - 1)]
+        } else {
+            clear_attack(rows)
+        };
+
+        let difficult = ClearKind::new(rows, t_spin).is_some_and(ClearKind::is_difficult);
+        if difficult && self.back_to_back {
+            attack += BACK_TO_BACK_BONUS;
+        }
+        self.back_to_back = difficult;
+
+        attack + COMBO_ATTACK[min(combo, COMBO_ATTACK.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_attack() {
+        let mut attack = Attack::new();
+        assert_eq!(0, attack.block_locked(1, false));
+        assert_eq!(0, attack.block_locked(0, false));
+        assert_eq!(1, attack.block_locked(2, false));
+        assert_eq!(0, attack.block_locked(0, false));
+        assert_eq!(2, attack.block_locked(3, false));
+        assert_eq!(0, attack.block_locked(0, false));
+        assert_eq!(4, attack.block_locked(4, false));
+    }
+
+    #[test]
+    fn back_to_back() {
+        let mut attack = Attack::new();
+        assert_eq!(4, attack.block_locked(4, false));
+        assert_eq!(0, attack.block_locked(0, false));
+        assert_eq!(5, attack.block_locked(2, true));
+        assert_eq!(0, attack.block_locked(0, false));
+        assert_eq!(0, attack.block_locked(1, false));
+        assert_eq!(0, attack.block_locked(0, false));
+        assert_eq!(4, attack.block_locked(4, false));
+    }
+
+    #[test]
+    fn combo() {
+        let mut attack = Attack::new();
+        assert_eq!(0, attack.block_locked(1, false));
+        assert_eq!(0, attack.block_locked(1, false));
+        assert_eq!(1, attack.block_locked(1, false));
+        assert_eq!(2, attack.block_locked(2, false));
+        assert_eq!(0, attack.block_locked(0, false));
+        assert_eq!(0, attack.block_locked(1, false));
+    }
+}
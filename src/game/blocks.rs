@@ -1,11 +1,10 @@
-use bevy::render::color::Color;
 use lazy_static::lazy_static;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use std::collections::HashMap;
 
 use super::Position;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[repr(usize)]
 pub enum BlockType {
     I = 0,
@@ -17,62 +16,104 @@ pub enum BlockType {
     Z,
 }
 
-struct BlockInfo {
-    points: Vec<Position>,
-    color: Color,
-}
-
-impl BlockInfo {
-    fn new(points: Vec<Position>, color: Color) -> Self {
-        Self { points, color }
-    }
+impl BlockType {
+    pub const ALL: [BlockType; 7] = [
+        BlockType::I,
+        BlockType::J,
+        BlockType::L,
+        BlockType::O,
+        BlockType::S,
+        BlockType::T,
+        BlockType::Z,
+    ];
 }
 
 lazy_static! {
-    static ref BLOCKS: HashMap<BlockType, BlockInfo> = HashMap::from([
-        (
-            BlockType::I,
-            BlockInfo::new(vec![(0, 0), (0, 1), (0, 2), (0, 3)], Color::CYAN)
-        ),
-        (
-            BlockType::J,
-            BlockInfo::new(vec![(0, 0), (0, 1), (1, 1), (2, 1)], Color::BLUE)
-        ),
-        (
-            BlockType::L,
-            BlockInfo::new(vec![(0, 1), (1, 1), (2, 1), (2, 0)], Color::ORANGE)
-        ),
-        (
-            BlockType::O,
-            BlockInfo::new(vec![(0, 0), (1, 0), (1, 1), (0, 1)], Color::YELLOW)
-        ),
-        (
-            BlockType::S,
-            BlockInfo::new(vec![(0, 1), (1, 1), (1, 0), (2, 0)], Color::GREEN)
-        ),
-        (
-            BlockType::T,
-            BlockInfo::new(vec![(0, 1), (1, 1), (1, 0), (2, 1)], Color::PURPLE)
-        ),
-        (
-            BlockType::Z,
-            BlockInfo::new(vec![(0, 0), (1, 0), (1, 1), (2, 1)], Color::RED)
-        ),
+    static ref BLOCKS: HashMap<BlockType, Vec<Position>> = HashMap::from([
+        (BlockType::I, vec![(0, 0), (0, 1), (0, 2), (0, 3)]),
+        (BlockType::J, vec![(0, 0), (0, 1), (1, 1), (2, 1)]),
+        (BlockType::L, vec![(0, 1), (1, 1), (2, 1), (2, 0)]),
+        (BlockType::O, vec![(0, 0), (1, 0), (1, 1), (0, 1)]),
+        (BlockType::S, vec![(0, 1), (1, 1), (1, 0), (2, 0)]),
+        (BlockType::T, vec![(0, 1), (1, 1), (1, 0), (2, 1)]),
+        (BlockType::Z, vec![(0, 0), (1, 0), (1, 1), (2, 1)]),
     ]);
 }
 
 pub fn get_block_points(block_type: BlockType) -> &'static Vec<Position> {
-    &BLOCKS[&block_type].points
+    &BLOCKS[&block_type]
+}
+
+/// Returns the shape flipped horizontally within its bounding box, in the same order.
+pub fn mirror_shape(points: &[Position]) -> Vec<Position> {
+    let width = points.iter().map(|&(x, _)| x).max().unwrap();
+    points.iter().map(|&(x, y)| (width - x, y)).collect()
 }
 
-pub fn get_block_color(block_type: BlockType) -> Color {
-    BLOCKS[&block_type].color
+/// Returns the shape with every point replaced by 2x2 points.
+pub fn scale_shape(points: &[Position]) -> Vec<Position> {
+    points
+        .iter()
+        .flat_map(|&(x, y)| {
+            [
+                (x * 2, y * 2),
+                (x * 2 + 1, y * 2),
+                (x * 2, y * 2 + 1),
+                (x * 2 + 1, y * 2 + 1),
+            ]
+        })
+        .collect()
 }
 
-pub fn get_random_block() -> BlockType {
+pub fn get_random_block(rng: &mut impl Rng) -> BlockType {
     let block_count = BLOCKS.len();
 
-    let mut rng = thread_rng();
     let block_i = rng.gen_range(0..block_count);
     unsafe { std::mem::transmute(block_i) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut points: Vec<Position>) -> Vec<Position> {
+        points.sort();
+        points
+    }
+
+    #[test]
+    fn mirrored_l_is_j() {
+        let mirrored = mirror_shape(get_block_points(BlockType::L));
+        assert_eq!(
+            sorted(get_block_points(BlockType::J).clone()),
+            sorted(mirrored)
+        );
+    }
+
+    #[test]
+    fn mirrored_s_is_z() {
+        let mirrored = mirror_shape(get_block_points(BlockType::S));
+        assert_eq!(
+            sorted(get_block_points(BlockType::Z).clone()),
+            sorted(mirrored)
+        );
+    }
+
+    #[test]
+    fn scaled_shape_has_2x2_points() {
+        let scaled = scale_shape(&[(0, 0), (1, 0)]);
+        assert_eq!(
+            vec![
+                (0, 0),
+                (0, 1),
+                (1, 0),
+                (1, 1),
+                (2, 0),
+                (2, 1),
+                (3, 0),
+                (3, 1)
+            ],
+            sorted(scaled)
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use rand::{thread_rng, Rng};
+
+use super::{
+    attack::clear_attack, board_index, rotate::rotate_shape, BoardMetrics, Game, Gravity, Id,
+    Input, Position, BOARD_HEIGHT, BOARD_WIDTH,
+};
+
+// weights of the board evaluation
+const HEIGHT_WEIGHT: f32 = -0.51;
+const LINES_WEIGHT: f32 = 0.76;
+const HOLES_WEIGHT: f32 = -0.36;
+const BUMPINESS_WEIGHT: f32 = -0.18;
+
+type Board = [[bool; BOARD_WIDTH]; BOARD_HEIGHT];
+
+struct Placement {
+    /// Sorted points of the rotated block.
+    shape: Vec<Position>,
+    x: i32,
+}
+
+/// Settings which make the bot play worse or more aggressively, the default plays as well as
+/// the bot can.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct BotDifficulty {
+    /// Up to this much is randomly added to or subtracted from the score of every placement, so
+    /// the bot misjudges the placements which look almost as good as the best one.
+    pub noise: f32,
+    /// Ticks the bot waits after the block spawns before it presses any key.
+    pub reaction_delay: u32,
+    /// Chance that the bot drops the block at a random placement instead of the best one.
+    pub misdrop_chance: f64,
+    /// Weight of the garbage sent by the cleared rows, the bot waits for clears of more rows.
+    pub aggression: f32,
+}
+
+/// Difficulty presets of the bot opponent from the easiest one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BotLevel {
+    Beginner,
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Brutal,
+}
+
+impl BotLevel {
+    pub const ALL: [BotLevel; 5] = [
+        BotLevel::Beginner,
+        BotLevel::Easy,
+        BotLevel::Medium,
+        BotLevel::Hard,
+        BotLevel::Brutal,
+    ];
+
+    /// Returns the level named by `name`, as returned by `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BotLevel::Beginner => "beginner",
+            BotLevel::Easy => "easy",
+            BotLevel::Medium => "medium",
+            BotLevel::Hard => "hard",
+            BotLevel::Brutal => "brutal",
+        }
+    }
+
+    /// Returns the level following this one in `ALL`.
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|l| l == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn difficulty(&self) -> BotDifficulty {
+        let (noise, reaction_delay, misdrop_chance, aggression) = match self {
+            BotLevel::Beginner => (1.5, 30, 0.15, 0.),
+            BotLevel::Easy => (0.8, 15, 0.08, 0.),
+            BotLevel::Medium => (0.3, 6, 0.03, 0.2),
+            BotLevel::Hard => (0.1, 2, 0.01, 0.4),
+            BotLevel::Brutal => (0., 0, 0., 0.6),
+        };
+        BotDifficulty {
+            noise,
+            reaction_delay,
+            misdrop_chance,
+            aggression,
+        }
+    }
+}
+
+/// Computer player which places every block where the board looks best after the placement.
+/// Call `Bot::think` before each game tick and pass the bot to `Game::tick` as the input.
+pub struct Bot {
+    difficulty: BotDifficulty,
+    block_id: Option<Id>,
+    target: Option<Placement>,
+    /// Ticks left until the bot reacts to the spawned block.
+    reaction_ticks: u32,
+    /// Moves and rotations are repeated only after the key is released for one tick.
+    released: bool,
+    move_left: bool,
+    move_right: bool,
+    rotate: bool,
+    fast_drop: bool,
+}
+
+impl Bot {
+    pub fn new() -> Self {
+        Self::with_difficulty(BotDifficulty::default())
+    }
+
+    pub fn with_difficulty(difficulty: BotDifficulty) -> Self {
+        Self {
+            difficulty,
+            block_id: None,
+            target: None,
+            reaction_ticks: 0,
+            released: true,
+            move_left: false,
+            move_right: false,
+            rotate: false,
+            fast_drop: false,
+        }
+    }
+
+    /// Decides which keys to press on the next tick of the game.
+    pub fn think(&mut self, game: &Game) {
+        let block = game.active_block();
+        if self.block_id != Some(block.id) {
+            self.block_id = Some(block.id);
+            self.target = find_best_placement(game, &self.difficulty);
+            self.reaction_ticks = self.difficulty.reaction_delay;
+        }
+
+        self.move_left = false;
+        self.move_right = false;
+        self.rotate = false;
+        self.fast_drop = false;
+
+        if self.reaction_ticks > 0 {
+            self.reaction_ticks -= 1;
+            return;
+        }
+
+        let target = match &self.target {
+            Some(target) => target,
+            None => {
+                self.fast_drop = true;
+                return;
+            }
+        };
+
+        if !self.released {
+            self.released = true;
+            return;
+        }
+
+        let x = game.active_block_position().0;
+        if block_shape(game) != target.shape {
+            self.rotate = true;
+            self.released = false;
+        } else if x > target.x {
+            self.move_left = true;
+            self.released = false;
+        } else if x < target.x {
+            self.move_right = true;
+            self.released = false;
+        } else {
+            self.fast_drop = true;
+        }
+    }
+
+    pub fn difficulty(&self) -> BotDifficulty {
+        self.difficulty
+    }
+}
+
+impl Input for Bot {
+    fn move_left(&self) -> bool {
+        self.move_left
+    }
+
+    fn move_right(&self) -> bool {
+        self.move_right
+    }
+
+    fn rotate(&self) -> bool {
+        self.rotate
+    }
+
+    fn fast_drop(&self) -> bool {
+        self.fast_drop
+    }
+
+    fn instant_drop(&self) -> bool {
+        false
+    }
+
+    fn use_item(&self) -> bool {
+        false
+    }
+
+    fn hold(&self) -> bool {
+        false
+    }
+}
+
+fn block_shape(game: &Game) -> Vec<Position> {
+    let block = game.active_block();
+    let mut shape = block
+        .points()
+        .iter()
+        .map(|p| block.get_point_position(p.id).unwrap())
+        .collect::<Vec<_>>();
+    shape.sort();
+    shape
+}
+
+/// Placements are evaluated on the board with the floor at the bottom, so the board is flipped
+/// vertically when blocks rise to the ceiling.
+fn find_best_placement(game: &Game, difficulty: &BotDifficulty) -> Option<Placement> {
+    let gravity = game.rules.gravity;
+    let flip = |y: usize| {
+        if gravity == Gravity::Up {
+            BOARD_HEIGHT - 1 - y
+        } else {
+            y
+        }
+    };
+    let mut board = [[false; BOARD_WIDTH]; BOARD_HEIGHT];
+    // bots plan only on the leftmost board of the wide ones, co-op games aren't played by bots
+    for (y, row) in game.board.iter().enumerate() {
+        for (x, point) in row[..BOARD_WIDTH].iter().enumerate() {
+            board[flip(y)][x] = point.is_some();
+        }
+    }
+
+    let mut rng = thread_rng();
+    let mut placements = vec![];
+    let mut shape = block_shape(game);
+    for _ in 0..4 {
+        let width = shape.iter().map(|&(x, _)| x).max().unwrap() + 1;
+        let height = shape.iter().map(|&(_, y)| y).max().unwrap();
+        let step = game.rules.move_step() as usize;
+        for x in (0..=BOARD_WIDTH as i32 - width).step_by(step) {
+            let spawn_pos = (x, gravity.spawn_position(height).1);
+            if game.is_block_collides(shape.iter(), spawn_pos) {
+                continue;
+            }
+            let (_, y) =
+                game.shape_landing_position(game.partner.as_ref(), shape.iter(), height, spawn_pos);
+
+            let mut board = board;
+            for &(px, py) in &shape {
+                let (bx, by) = board_index((x + px, y + py)).unwrap();
+                board[flip(by)][bx] = true;
+            }
+            let mut score = evaluate(&board, difficulty.aggression);
+            if difficulty.noise > 0. {
+                score += rng.gen_range(-difficulty.noise..=difficulty.noise);
+            }
+            let mut sorted_shape = shape.clone();
+            sorted_shape.sort();
+            placements.push((
+                score,
+                Placement {
+                    shape: sorted_shape,
+                    x,
+                },
+            ));
+        }
+        shape = rotate_shape(&shape);
+    }
+
+    if !placements.is_empty() && rng.gen_bool(difficulty.misdrop_chance) {
+        let misdrop = rng.gen_range(0..placements.len());
+        return Some(placements.swap_remove(misdrop).1);
+    }
+    // the first of the equally good placements is the best one
+    placements
+        .into_iter()
+        .reduce(|best, placement| {
+            if placement.0 > best.0 {
+                placement
+            } else {
+                best
+            }
+        })
+        .map(|(_, placement)| placement)
+}
+
+fn evaluate(board: &Board, aggression: f32) -> f32 {
+    let lines = board.iter().filter(|row| row.iter().all(|&p| p)).count();
+
+    let metrics = BoardMetrics::measure(|x, y| board[y][x], Gravity::Down);
+
+    HEIGHT_WEIGHT * metrics.aggregate_height as f32
+        + LINES_WEIGHT * lines as f32
+        + aggression * clear_attack(lines) as f32
+        + HOLES_WEIGHT * metrics.holes as f32
+        + BUMPINESS_WEIGHT * metrics.bumpiness as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holes_are_penalized() {
+        let mut flat = [[false; BOARD_WIDTH]; BOARD_HEIGHT];
+        let mut with_hole = flat;
+        flat[BOARD_HEIGHT - 1][0] = true;
+        flat[BOARD_HEIGHT - 1][1] = true;
+        with_hole[BOARD_HEIGHT - 2][0] = true;
+        with_hole[BOARD_HEIGHT - 1][1] = true;
+        assert!(evaluate(&flat, 0.) > evaluate(&with_hole, 0.));
+    }
+
+    #[test]
+    fn aggression_prefers_attack() {
+        let mut single = [[false; BOARD_WIDTH]; BOARD_HEIGHT];
+        let mut double = single;
+        single[BOARD_HEIGHT - 1] = [true; BOARD_WIDTH];
+        single[BOARD_HEIGHT - 2][0] = true;
+        double[BOARD_HEIGHT - 1] = [true; BOARD_WIDTH];
+        double[BOARD_HEIGHT - 2] = [true; BOARD_WIDTH];
+        let advantage = |aggression| evaluate(&double, aggression) - evaluate(&single, aggression);
+        assert!(advantage(BotLevel::Brutal.difficulty().aggression) > advantage(0.));
+    }
+}
@@ -0,0 +1,654 @@
+use super::{
+    add_positions, board_index, board_position, BlockType, Game, GameRules, Position, BOARD_WIDTH,
+};
+
+/// Characters of the encoded data, every one of them carries six bits.
+const DATA_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// Characters of the comments, four of them are packed into five data characters.
+const COMMENT_TABLE: &[u8; 95] = b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+const COMMENT_BASE: u32 = COMMENT_TABLE.len() as u32 + 1;
+/// Rows of the field of the current version above the garbage row.
+const FIELD_HEIGHT: usize = 23;
+/// The garbage row below the floor isn't a part of the board.
+const GARBAGE_ROWS: usize = 1;
+/// Value of a cell which isn't a mino of any block.
+const GRAY: u8 = 8;
+/// Prefix of the comment describing a quiz, the hold, the current and the next blocks.
+const QUIZ_PREFIX: &str = "#Q=";
+
+/// Board diagram in the fumen notation used by the community to share setups, as the board of
+/// the first page and the blocks placed on all pages.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Fumen {
+    /// Occupied cells with the column and the row counted from the floor, `None` for garbage.
+    pub cells: Vec<(Position, Option<BlockType>)>,
+    /// Blocks of the quiz, or the blocks placed on the pages if there's no quiz.
+    pub blocks: Vec<BlockType>,
+}
+
+impl Fumen {
+    /// Decodes the `v115@...` or `v110@...` string or a link ending with it, the question marks
+    /// which split long strings and any whitespace are ignored.
+    pub fn decode(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (version, data) = text
+            .split_once('@')
+            .ok_or_else(|| "missing version".to_string())?;
+        // the diagram may be pasted as a link which ends with the version
+        let height = if version.ends_with("v115") {
+            FIELD_HEIGHT
+        } else if version.ends_with("v110") {
+            21
+        } else {
+            return Err(format!("unsupported version: {}", version));
+        };
+        let mut reader = DataReader::new(data)?;
+        let field_cells = (height + GARBAGE_ROWS) * BOARD_WIDTH;
+
+        let mut field = vec![0u8; field_cells];
+        let mut first_field = None;
+        let mut page_blocks = vec![];
+        let mut quiz = None;
+        let mut repeat = 0;
+        while !reader.is_empty() {
+            if repeat > 0 {
+                repeat -= 1;
+            } else {
+                let mut index = 0;
+                while index < field_cells {
+                    let run = reader.read(2)? as usize;
+                    let diff = run / field_cells;
+                    let count = run % field_cells + 1;
+                    if diff == 8 && count == field_cells {
+                        repeat = reader.read(1)?;
+                    }
+                    for cell in field.iter_mut().skip(index).take(count) {
+                        *cell = (*cell as usize + diff)
+                            .checked_sub(8)
+                            .filter(|&v| v <= GRAY as usize)
+                            .ok_or_else(|| "invalid field".to_string())?
+                            as u8;
+                    }
+                    index += count;
+                }
+                if index != field_cells {
+                    return Err("invalid field".to_string());
+                }
+            }
+            if first_field.is_none() {
+                first_field = Some(field.clone());
+            }
+
+            let action = Action::decode(reader.read(3)?, field_cells);
+            if action.comment {
+                let len = reader.read(2)? as usize;
+                let mut comment = String::new();
+                for _ in 0..len.div_ceil(4) {
+                    let mut value = reader.read(5)?;
+                    for _ in 0..4 {
+                        let index = (value % COMMENT_BASE) as usize;
+                        comment.push(*COMMENT_TABLE.get(index).unwrap_or(&b' ') as char);
+                        value /= COMMENT_BASE;
+                    }
+                }
+                comment.truncate(len);
+                if quiz.is_none() {
+                    quiz = parse_quiz(&unescape(&comment));
+                }
+            }
+            if let Some(block_type) = block_type(action.piece) {
+                page_blocks.push(block_type);
+                if action.lock {
+                    action.place(&mut field, height)?;
+                }
+            }
+            if action.lock {
+                lock_field(&mut field, height, action.rise, action.mirror);
+            }
+        }
+
+        let field = first_field.ok_or_else(|| "no pages".to_string())?;
+        let cells = field[..height * BOARD_WIDTH]
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value != 0)
+            .map(|(index, &value)| {
+                let position =
+                    board_position(index % BOARD_WIDTH, height - 1 - index / BOARD_WIDTH);
+                (position, block_type(value))
+            })
+            .collect();
+        Ok(Self {
+            cells,
+            blocks: quiz.unwrap_or(page_blocks),
+        })
+    }
+
+    /// Returns the diagram of the board of the game, without blocks.
+    pub fn from_game(game: &Game) -> Self {
+        let rows = game.rules.gravity.rows_from_floor();
+        let cells = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, &row)| {
+                (0..BOARD_WIDTH).filter_map(move |x| {
+                    let point = game.board[row][x]?;
+                    Some((board_position(x, y), point.origin_block_type))
+                })
+            })
+            .collect();
+        Self {
+            cells,
+            blocks: vec![],
+        }
+    }
+
+    /// Encodes the board of the game as a `v115@...` diagram, with its active block as the
+    /// block of the page if `active_block` is `true`. Active blocks of the big mode aren't
+    /// tetrominoes, so they're left out.
+    pub fn encode_game(game: &Game, active_block: bool) -> String {
+        let rows = game.rules.gravity.rows_from_floor();
+        let block = active_block
+            .then(|| {
+                let points = game
+                    .active_block()
+                    .shape()
+                    .iter()
+                    .map(|&pos| {
+                        let (x, row) =
+                            board_index(add_positions(game.active_block_position(), pos))?;
+                        // blocks in the hidden rows above the field can't be shown
+                        let y = rows
+                            .iter()
+                            .position(|&r| r == row)
+                            .filter(|&y| y < FIELD_HEIGHT)?;
+                        Some((x as i32, y as i32))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                find_action(game.active_block().block_type, &points)
+            })
+            .flatten();
+        Self::from_game(game).encode_page(block)
+    }
+
+    /// Encodes the board as a `v115@...` diagram, the blocks aren't encoded.
+    pub fn encode(&self) -> String {
+        self.encode_page(None)
+    }
+
+    fn encode_page(&self, action: Option<Action>) -> String {
+        let height = FIELD_HEIGHT;
+        let field_cells = (height + GARBAGE_ROWS) * BOARD_WIDTH;
+        let mut field = vec![0u8; field_cells];
+        for &(pos, block_type) in &self.cells {
+            if let Some((x, y)) = board_index(pos).filter(|&(_, y)| y < height) {
+                field[(height - 1 - y) * BOARD_WIDTH + x] = piece_number(block_type);
+            }
+        }
+
+        let mut writer = DataWriter::default();
+        // runs of cells with the same difference from the empty field of the previous page
+        let mut index = 0;
+        while index < field_cells {
+            let value = field[index];
+            let count = field[index..].iter().take_while(|&&v| v == value).count();
+            writer.write(2, (value as usize + 8) * field_cells + count - 1);
+            index += count;
+        }
+        if field.iter().all(|&v| v == 0) {
+            // no following pages repeat the field
+            writer.write(1, 0);
+        }
+        let action = action.unwrap_or(Action {
+            piece: 0,
+            rotation: Rotation::Reverse,
+            x: 0,
+            y: 0,
+            rise: false,
+            mirror: false,
+            comment: false,
+            lock: true,
+        });
+        writer.write(3, action.encode(field_cells) as usize);
+        format!("v115@{}", writer.finish())
+    }
+
+    /// Creates the game with the board of the diagram which deals its blocks first.
+    pub fn new_game(&self, rules: GameRules) -> Game {
+        let mut game = Game::with_blocks(rules, &self.blocks);
+        let rows = game.rules().gravity.rows_from_floor();
+        let cells = self
+            .cells
+            .iter()
+            .filter_map(|&(pos, block_type)| {
+                let (x, y) = board_index(pos)?;
+                Some((board_position(x, rows[y]), block_type))
+            })
+            .collect::<Vec<_>>();
+        game.prefill_board_blocks(cells);
+        game
+    }
+}
+
+/// Reads numbers packed into the data characters, the least significant character first.
+struct DataReader {
+    values: Vec<u32>,
+    position: usize,
+}
+
+impl DataReader {
+    fn new(data: &str) -> Result<Self, String> {
+        let values = data
+            .chars()
+            .filter(|&c| c != '?' && !c.is_whitespace())
+            .map(|c| {
+                DATA_TABLE
+                    .iter()
+                    .position(|&d| d as char == c)
+                    .map(|v| v as u32)
+                    .ok_or_else(|| format!("invalid character: {}", c))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            values,
+            position: 0,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.values.len()
+    }
+
+    fn read(&mut self, len: usize) -> Result<u32, String> {
+        let values = self
+            .values
+            .get(self.position..self.position + len)
+            .ok_or_else(|| "unexpected end".to_string())?;
+        self.position += len;
+        Ok(values.iter().rev().fold(0, |value, &v| value * 64 + v))
+    }
+}
+
+/// Packs numbers into the data characters like `DataReader` reads them.
+#[derive(Default)]
+struct DataWriter {
+    data: String,
+}
+
+impl DataWriter {
+    fn write(&mut self, len: usize, mut value: usize) {
+        for _ in 0..len {
+            self.data.push(DATA_TABLE[value % 64] as char);
+            value /= 64;
+        }
+    }
+
+    /// Returns the data split by question marks like the fumen editor does, after 42 characters
+    /// and then every 47 characters.
+    fn finish(self) -> String {
+        let mut result = String::new();
+        for (i, c) in self.data.chars().enumerate() {
+            if i >= 42 && (i - 42) % 47 == 0 {
+                result.push('?');
+            }
+            result.push(c);
+        }
+        result
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Rotation {
+    Reverse,
+    Right,
+    Spawn,
+    Left,
+}
+
+/// Block shown or placed on a page with its flags.
+struct Action {
+    piece: u8,
+    rotation: Rotation,
+    /// Rotation center of the block, the row is counted from the floor.
+    x: i32,
+    y: i32,
+    rise: bool,
+    mirror: bool,
+    comment: bool,
+    lock: bool,
+}
+
+impl Action {
+    fn decode(mut value: u32, field_cells: usize) -> Self {
+        let mut take = |base: u32| {
+            let taken = value % base;
+            value /= base;
+            taken
+        };
+        let piece = take(8) as u8;
+        let rotation = [
+            Rotation::Reverse,
+            Rotation::Right,
+            Rotation::Spawn,
+            Rotation::Left,
+        ][take(4) as usize];
+        let position = take(field_cells as u32) as i32;
+        let rise = take(2) != 0;
+        let mirror = take(2) != 0;
+        let _colorize = take(2);
+        let comment = take(2) != 0;
+        let lock = take(2) == 0;
+
+        let height = (field_cells / BOARD_WIDTH - GARBAGE_ROWS) as i32;
+        let (dx, dy) = center_offset(piece, rotation);
+        let x = position % BOARD_WIDTH as i32 + dx;
+        let y = height - position / BOARD_WIDTH as i32 - 1 + dy;
+        Self {
+            piece,
+            rotation,
+            x,
+            y,
+            rise,
+            mirror,
+            comment,
+            lock,
+        }
+    }
+
+    /// Encodes the block of the first page, its guideline colors are turned on.
+    fn encode(&self, field_cells: usize) -> u32 {
+        let height = (field_cells / BOARD_WIDTH - GARBAGE_ROWS) as i32;
+        let position = if block_type(self.piece).is_some() {
+            let (dx, dy) = center_offset(self.piece, self.rotation);
+            (height - (self.y - dy) - 1) * BOARD_WIDTH as i32 + self.x - dx
+        } else {
+            0
+        };
+        let flags = [!self.lock, self.comment, true, self.mirror, self.rise]
+            .iter()
+            .fold(0, |flags, &flag| flags * 2 + flag as u32);
+        let rotation = [
+            Rotation::Reverse,
+            Rotation::Right,
+            Rotation::Spawn,
+            Rotation::Left,
+        ]
+        .iter()
+        .position(|&r| r == self.rotation)
+        .unwrap() as u32;
+        ((flags * field_cells as u32 + position as u32) * 4 + rotation) * 8 + self.piece as u32
+    }
+
+    /// Returns the cells covered by the block, the rows are counted from the floor.
+    fn cells(&self) -> Vec<(i32, i32)> {
+        let offsets: [(i32, i32); 4] = match block_type(self.piece) {
+            Some(BlockType::I) => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+            Some(BlockType::T) => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+            Some(BlockType::O) => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Some(BlockType::L) => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+            Some(BlockType::J) => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+            Some(BlockType::S) => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+            Some(BlockType::Z) => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+            None => return vec![],
+        };
+        offsets
+            .iter()
+            .map(|&(dx, dy)| match self.rotation {
+                Rotation::Spawn => (dx, dy),
+                Rotation::Right => (dy, -dx),
+                Rotation::Reverse => (-dx, -dy),
+                Rotation::Left => (-dy, dx),
+            })
+            .map(|(dx, dy)| (self.x + dx, self.y + dy))
+            .collect()
+    }
+
+    fn place(&self, field: &mut [u8], height: usize) -> Result<(), String> {
+        for (x, y) in self.cells() {
+            if !(0..BOARD_WIDTH as i32).contains(&x) || !(0..height as i32).contains(&y) {
+                return Err("block outside of the field".to_string());
+            }
+            field[(height - 1 - y as usize) * BOARD_WIDTH + x as usize] = self.piece;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the block of the type covering the points, with rows counted from the floor.
+fn find_action(block_type: BlockType, points: &[(i32, i32)]) -> Option<Action> {
+    let mut points = points.to_vec();
+    points.sort();
+    let (min_x, min_y) = (
+        points.iter().map(|p| p.0).min()?,
+        points.iter().map(|p| p.1).min()?,
+    );
+    // the rotation center is at most two cells from the corner of the block
+    let rotations = [
+        Rotation::Reverse,
+        Rotation::Right,
+        Rotation::Spawn,
+        Rotation::Left,
+    ];
+    rotations.into_iter().find_map(|rotation| {
+        (min_x - 2..=min_x + 2).find_map(|x| {
+            (min_y - 2..=min_y + 2).find_map(|y| {
+                let action = Action {
+                    piece: piece_number(Some(block_type)),
+                    rotation,
+                    x,
+                    y,
+                    rise: false,
+                    mirror: false,
+                    comment: false,
+                    lock: true,
+                };
+                let mut cells = action.cells();
+                cells.sort();
+                (cells == points).then_some(action)
+            })
+        })
+    })
+}
+
+/// Clears the filled rows of the field, then applies the flags of the locked page.
+fn lock_field(field: &mut Vec<u8>, height: usize, rise: bool, mirror: bool) {
+    let garbage = field.split_off(height * BOARD_WIDTH);
+    let rows = field
+        .chunks(BOARD_WIDTH)
+        .filter(|row| row.contains(&0))
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    field.clear();
+    field.resize(height * BOARD_WIDTH - rows.len(), 0);
+    field.extend(rows);
+    if rise {
+        // the garbage row rises and the one below it is empty
+        field.drain(..BOARD_WIDTH);
+        field.extend(&garbage);
+        field.extend([0; BOARD_WIDTH]);
+    } else {
+        field.extend(garbage);
+    }
+    if mirror {
+        for row in field[..height * BOARD_WIDTH].chunks_mut(BOARD_WIDTH) {
+            row.reverse();
+        }
+    }
+}
+
+/// Returns how far the rotation center of the block is from its encoded position, the encoded
+/// position of some blocks is off by one.
+fn center_offset(piece: u8, rotation: Rotation) -> (i32, i32) {
+    match (block_type(piece), rotation) {
+        (Some(BlockType::O), Rotation::Left) => (-1, 1),
+        (Some(BlockType::O), Rotation::Reverse) => (-1, 0),
+        (Some(BlockType::O), Rotation::Spawn) => (0, 1),
+        (Some(BlockType::I), Rotation::Reverse) => (-1, 0),
+        (Some(BlockType::I), Rotation::Left) => (0, 1),
+        (Some(BlockType::S), Rotation::Spawn) => (0, 1),
+        (Some(BlockType::S), Rotation::Right) => (1, 0),
+        (Some(BlockType::Z), Rotation::Spawn) => (0, 1),
+        (Some(BlockType::Z), Rotation::Left) => (-1, 0),
+        _ => (0, 0),
+    }
+}
+
+/// Returns the fumen piece number of the block, gray for garbage.
+fn piece_number(block_type: Option<BlockType>) -> u8 {
+    match block_type {
+        Some(BlockType::I) => 1,
+        Some(BlockType::L) => 2,
+        Some(BlockType::O) => 3,
+        Some(BlockType::Z) => 4,
+        Some(BlockType::T) => 5,
+        Some(BlockType::J) => 6,
+        Some(BlockType::S) => 7,
+        None => GRAY,
+    }
+}
+
+/// Returns the block of the fumen piece number, `None` for gray cells.
+fn block_type(piece: u8) -> Option<BlockType> {
+    use BlockType::*;
+    match piece {
+        1 => Some(I),
+        2 => Some(L),
+        3 => Some(O),
+        4 => Some(Z),
+        5 => Some(T),
+        6 => Some(J),
+        7 => Some(S),
+        _ => None,
+    }
+}
+
+/// Decodes `%XX` and `%uXXXX` escapes of the comment.
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('%') {
+        result += &rest[..start];
+        rest = &rest[start + 1..];
+        let (len, hex) = match rest.strip_prefix('u') {
+            Some(hex) => (5, hex.get(..4)),
+            None => (2, rest.get(..2)),
+        };
+        match hex
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32)
+        {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => result.push('%'),
+        }
+    }
+    result + rest
+}
+
+/// Parses the quiz `#Q=[hold](current)next`. The held block is dealt first, holding it brings
+/// up the current block, which leaves the same choice as the quiz.
+fn parse_quiz(comment: &str) -> Option<Vec<BlockType>> {
+    let quiz = comment.strip_prefix(QUIZ_PREFIX)?;
+    let quiz = quiz.split(';').next().unwrap_or_default();
+    let blocks = quiz
+        .chars()
+        .filter(|&c| !matches!(c, '[' | ']' | '(' | ')'))
+        .map(|c| {
+            BlockType::ALL
+                .into_iter()
+                .find(|t| format!("{:?}", t).eq_ignore_ascii_case(&c.to_string()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{ReplayInput, BOARD_HEIGHT};
+
+    #[test]
+    fn decodes_empty_field() {
+        let fumen = Fumen::decode("v115@vhAAgH").unwrap();
+        assert!(fumen.cells.is_empty());
+        assert!(fumen.blocks.is_empty());
+    }
+
+    #[test]
+    fn decodes_garbage_rows() {
+        // four rows with the six left columns filled
+        let fumen = Fumen::decode("v115@9gF8DeF8DeF8DeF8NeAgH").unwrap();
+        assert_eq!(24, fumen.cells.len());
+        assert!(fumen
+            .cells
+            .iter()
+            .all(|&((x, y), block_type)| x < 6 && y < 4 && block_type.is_none()));
+    }
+
+    #[test]
+    fn quiz_deals_held_block_first() {
+        use BlockType::*;
+        assert_eq!(
+            Some(vec![I, T, S, Z]),
+            parse_quiz(&unescape("%23Q=[I](T)SZ"))
+        );
+        assert_eq!(None, parse_quiz("hello"));
+    }
+
+    #[test]
+    fn encodes_decoded_board() {
+        for diagram in ["v115@vhAAgH", "v115@9gF8DeF8DeF8DeF8NeAgH"] {
+            assert_eq!(diagram, Fumen::decode(diagram).unwrap().encode());
+        }
+    }
+
+    #[test]
+    fn actions_round_trip() {
+        for piece in 1..=7 {
+            for rotation in [
+                Rotation::Reverse,
+                Rotation::Right,
+                Rotation::Spawn,
+                Rotation::Left,
+            ] {
+                let action = Action {
+                    piece,
+                    rotation,
+                    x: 4,
+                    y: 5,
+                    rise: false,
+                    mirror: false,
+                    comment: false,
+                    lock: true,
+                };
+                let decoded = Action::decode(action.encode(240), 240);
+                assert_eq!(action.cells(), decoded.cells());
+            }
+        }
+    }
+
+    #[test]
+    fn exports_active_block() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::T]);
+        game.prefill_board([board_position(0, BOARD_HEIGHT - 1)]);
+        // the block falls out of the hidden rows
+        for _ in 0..40 {
+            game.tick(&ReplayInput::default());
+        }
+
+        let fumen = Fumen::decode(&Fumen::encode_game(&game, true)).unwrap();
+        assert_eq!(vec![((0, 0), None)], fumen.cells);
+        assert_eq!(vec![BlockType::T], fumen.blocks);
+        let fumen = Fumen::decode(&Fumen::encode_game(&game, false)).unwrap();
+        assert!(fumen.blocks.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_strings() {
+        assert!(Fumen::decode("hello").is_err());
+        assert!(Fumen::decode("v115@vh").is_err());
+    }
+}
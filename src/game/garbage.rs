@@ -0,0 +1,115 @@
+use rand::Rng;
+
+use super::BOARD_WIDTH;
+
+/// Default chance for `GarbageStyle::Random` to keep the hole column of the previous row.
+pub const DEFAULT_REPEAT_CHANCE: f64 = 0.7;
+
+/// Pattern of holes in the incoming garbage rows.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "debug-ui", derive(bevy_inspector_egui::Inspectable))]
+pub enum GarbageStyle {
+    /// Every row has a hole in the same column.
+    Clean,
+    /// Hole column changes every row.
+    Cheese,
+    /// Row keeps the hole column of the previous row with the given chance.
+    Random { repeat_chance: f64 },
+}
+
+impl Default for GarbageStyle {
+    fn default() -> Self {
+        GarbageStyle::Random {
+            repeat_chance: DEFAULT_REPEAT_CHANCE,
+        }
+    }
+}
+
+impl GarbageStyle {
+    fn repeat_chance(&self) -> f64 {
+        match *self {
+            GarbageStyle::Clean => 1.,
+            GarbageStyle::Cheese => 0.,
+            GarbageStyle::Random { repeat_chance } => repeat_chance.clamp(0., 1.),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct GarbageGenerator {
+    style: GarbageStyle,
+    /// Hole column of the previous row in every board the row is wide, relative to the board.
+    holes: Vec<usize>,
+}
+
+impl GarbageGenerator {
+    /// Creates the generator of rows `boards` boards wide, every board has its own hole.
+    pub fn new(style: GarbageStyle, boards: usize, rng: &mut impl Rng) -> Self {
+        Self {
+            style,
+            holes: (0..boards).map(|_| rng.gen_range(0..BOARD_WIDTH)).collect(),
+        }
+    }
+
+    /// Returns hole columns of the next garbage row, one in every board from the left.
+    pub fn next_holes(&mut self, rng: &mut impl Rng) -> Vec<usize> {
+        for hole in &mut self.holes {
+            if !rng.gen_bool(self.style.repeat_chance()) {
+                // pick any column except the previous one
+                let next_hole = rng.gen_range(0..BOARD_WIDTH - 1);
+                *hole = if next_hole >= *hole {
+                    next_hole + 1
+                } else {
+                    next_hole
+                };
+            }
+        }
+        self.holes
+            .iter()
+            .enumerate()
+            .map(|(board, hole)| board * BOARD_WIDTH + hole)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn clean_garbage_keeps_hole() {
+        let mut rng = thread_rng();
+        let mut garbage = GarbageGenerator::new(GarbageStyle::Clean, 1, &mut rng);
+        let holes = garbage.next_holes(&mut rng);
+        for _ in 0..100 {
+            assert_eq!(holes, garbage.next_holes(&mut rng));
+        }
+    }
+
+    #[test]
+    fn cheese_garbage_moves_hole() {
+        let mut rng = thread_rng();
+        let mut garbage = GarbageGenerator::new(GarbageStyle::Cheese, 1, &mut rng);
+        let mut hole = garbage.next_holes(&mut rng)[0];
+        for _ in 0..100 {
+            let next_hole = garbage.next_holes(&mut rng)[0];
+            assert_ne!(hole, next_hole);
+            assert!(next_hole < BOARD_WIDTH);
+            hole = next_hole;
+        }
+    }
+
+    #[test]
+    fn wide_garbage_has_hole_in_every_board() {
+        let mut rng = thread_rng();
+        let mut garbage = GarbageGenerator::new(GarbageStyle::Cheese, 2, &mut rng);
+        for _ in 0..100 {
+            let holes = garbage.next_holes(&mut rng);
+            assert_eq!(2, holes.len());
+            assert!(holes[0] < BOARD_WIDTH);
+            assert!((BOARD_WIDTH..2 * BOARD_WIDTH).contains(&holes[1]));
+        }
+    }
+}
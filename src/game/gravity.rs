@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use super::{board_index, Position, BOARD_HEIGHT, HIDDEN_BOARD_TOP, VISIBLE_BOARD_HEIGHT};
+
+/// Column where blocks spawn.
+const SPAWN_X: i32 = 4;
+
+/// Direction in which blocks fall. Blocks spawn in the hidden rows at the ceiling and the stack
+/// grows from the floor towards them.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "debug-ui", derive(bevy_inspector_egui::Inspectable))]
+pub enum Gravity {
+    /// Floor is the bottom row of the board.
+    #[default]
+    Down,
+    /// Floor is the top row of the board, blocks rise from the bottom.
+    Up,
+}
+
+impl Gravity {
+    /// Returns position of the block one row closer to the floor, `None` if the block with
+    /// the bottom row at `block_height` would leave the board.
+    pub(super) fn fall(self, (x, y): Position, block_height: i32) -> Option<Position> {
+        match self {
+            Gravity::Down if y + block_height < BOARD_HEIGHT as i32 - 1 => Some((x, y + 1)),
+            Gravity::Up if y > 0 => Some((x, y - 1)),
+            _ => None,
+        }
+    }
+
+    /// Returns position where the block with the bottom row at `block_height` spawns.
+    pub(super) fn spawn_position(self, block_height: i32) -> Position {
+        match self {
+            Gravity::Down => (SPAWN_X, 0),
+            Gravity::Up => (SPAWN_X, BOARD_HEIGHT as i32 - 1 - block_height),
+        }
+    }
+
+    /// Returns the position `rows` rows closer to the floor, it may be outside of the board.
+    pub(super) fn shifted(self, (x, y): Position, rows: i32) -> Position {
+        match self {
+            Gravity::Down => (x, y + rows),
+            Gravity::Up => (x, y - rows),
+        }
+    }
+
+    /// Returns all rows of the board ordered from the floor to the ceiling.
+    pub(super) fn rows_from_floor(self) -> Vec<usize> {
+        match self {
+            Gravity::Down => (0..BOARD_HEIGHT).rev().collect(),
+            Gravity::Up => (0..BOARD_HEIGHT).collect(),
+        }
+    }
+
+    /// Returns `count` rows next to the floor.
+    pub fn floor_rows(self, count: usize) -> Range<usize> {
+        let count = count.min(BOARD_HEIGHT);
+        match self {
+            Gravity::Down => BOARD_HEIGHT - count..BOARD_HEIGHT,
+            Gravity::Up => 0..count,
+        }
+    }
+
+    /// Returns the row `count` rows closer to the floor.
+    pub(super) fn towards_floor(self, y: usize, count: usize) -> Option<usize> {
+        match self {
+            Gravity::Down => Some(y + count).filter(|&y| y < BOARD_HEIGHT),
+            Gravity::Up => y.checked_sub(count),
+        }
+    }
+
+    /// Returns the row `count` rows closer to the ceiling.
+    pub(super) fn towards_ceiling(self, y: usize, count: usize) -> Option<usize> {
+        self.flipped().towards_floor(y, count)
+    }
+
+    fn flipped(self) -> Self {
+        match self {
+            Gravity::Down => Gravity::Up,
+            Gravity::Up => Gravity::Down,
+        }
+    }
+
+    /// Returns rows of the board which are shown to the player.
+    pub fn visible_rows(self) -> Range<usize> {
+        match self {
+            Gravity::Down => HIDDEN_BOARD_TOP..BOARD_HEIGHT,
+            Gravity::Up => 0..VISIBLE_BOARD_HEIGHT,
+        }
+    }
+
+    /// Returns index of the row at `y` in the visible part of the board counted from its top,
+    /// `None` for hidden rows and rows outside of the board.
+    pub fn visible_row(self, y: i32) -> Option<usize> {
+        let y = usize::try_from(y).ok()?;
+        let rows = self.visible_rows();
+        rows.contains(&y).then(|| y - rows.start)
+    }
+
+    /// Returns column and visible row of the position, `None` for hidden rows and positions
+    /// outside of the board.
+    pub fn visible_cell(self, pos: Position) -> Option<(usize, usize)> {
+        let (x, _) = board_index(pos)?;
+        Some((x, self.visible_row(pos.1)?))
+    }
+
+    /// Returns board row of the row in the visible part of the board.
+    pub fn board_row(self, visible_row: usize) -> usize {
+        visible_row + self.visible_rows().start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameRules, Input};
+
+    struct NoInput;
+
+    impl Input for NoInput {
+        fn move_left(&self) -> bool {
+            false
+        }
+
+        fn move_right(&self) -> bool {
+            false
+        }
+
+        fn rotate(&self) -> bool {
+            false
+        }
+
+        fn fast_drop(&self) -> bool {
+            false
+        }
+
+        fn instant_drop(&self) -> bool {
+            false
+        }
+
+        fn use_item(&self) -> bool {
+            false
+        }
+
+        fn hold(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn visible_rows_map_to_board_rows() {
+        for gravity in [Gravity::Down, Gravity::Up] {
+            for y in 0..BOARD_HEIGHT {
+                if let Some(row) = gravity.visible_row(y as i32) {
+                    assert_eq!(y, gravity.board_row(row));
+                }
+            }
+            assert_eq!(
+                VISIBLE_BOARD_HEIGHT,
+                (0..BOARD_HEIGHT as i32)
+                    .filter_map(|y| gravity.visible_row(y))
+                    .count()
+            );
+        }
+    }
+
+    #[test]
+    fn blocks_stack_against_ceiling() {
+        let mut rules = GameRules::new();
+        rules.gravity = Gravity::Up;
+        let mut game = Game::with_seed(rules, 0);
+        for _ in 0..500 {
+            game.tick(&NoInput);
+        }
+
+        assert!(game.board_points().any(|(_, (_, y))| y == 0));
+        assert!(game
+            .board_points()
+            .all(|(_, (_, y))| y < BOARD_HEIGHT as i32 / 2));
+    }
+}
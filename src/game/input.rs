@@ -1,11 +1,13 @@
 use super::{utils::Timer, REPEAT_DURATION, WAIT_DURATION};
 
+#[derive(Clone)]
 enum RepeatedActionState {
     Inactive,
     Wait,
     Repeat,
 }
 
+#[derive(Clone)]
 struct RepeatedAction {
     state: RepeatedActionState,
     timer: Timer,
@@ -53,6 +55,55 @@ impl RepeatedAction {
     fn active(&self) -> bool {
         self.active
     }
+
+    /// Waits for the repeat from now on, as if the held action was activated on this tick.
+    fn restart(&mut self) {
+        self.state = RepeatedActionState::Wait;
+        self.timer.restart();
+        self.active = false;
+    }
+}
+
+/// Action which is active only on the tick when it's activated.
+#[derive(Clone)]
+struct TriggerAction {
+    pressed: bool,
+    active: bool,
+}
+
+impl TriggerAction {
+    fn new() -> Self {
+        Self {
+            pressed: false,
+            active: false,
+        }
+    }
+
+    fn tick(&mut self, pressed: bool) {
+        self.active = pressed && !self.pressed;
+        self.pressed = pressed;
+    }
+
+    fn active(&self) -> bool {
+        self.active
+    }
+}
+
+/// Timing of held moves, the move repeats after the delay (DAS) every interval (ARR), both in
+/// ticks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AutoRepeat {
+    pub delay: u32,
+    pub interval: u32,
+}
+
+impl Default for AutoRepeat {
+    fn default() -> Self {
+        Self {
+            delay: WAIT_DURATION,
+            interval: REPEAT_DURATION,
+        }
+    }
 }
 
 pub trait Input {
@@ -61,24 +112,33 @@ pub trait Input {
     fn rotate(&self) -> bool;
     fn fast_drop(&self) -> bool;
     fn instant_drop(&self) -> bool;
+    fn use_item(&self) -> bool;
+    fn hold(&self) -> bool;
 }
 
+#[derive(Clone)]
 pub struct SmartInput {
     move_left: RepeatedAction,
     move_right: RepeatedAction,
     rotate: RepeatedAction,
     fast_drop: bool,
-    instant_drop: bool,
+    instant_drop: TriggerAction,
+    use_item: TriggerAction,
+    hold: TriggerAction,
 }
 
 impl SmartInput {
-    pub fn new() -> Self {
+    /// Creates the input whose moves repeat with `auto_repeat`, the rotation keeps the default
+    /// timing.
+    pub fn new(auto_repeat: AutoRepeat) -> Self {
         Self {
-            move_left: RepeatedAction::new(WAIT_DURATION, REPEAT_DURATION),
-            move_right: RepeatedAction::new(WAIT_DURATION, REPEAT_DURATION),
+            move_left: RepeatedAction::new(auto_repeat.delay, auto_repeat.interval),
+            move_right: RepeatedAction::new(auto_repeat.delay, auto_repeat.interval),
             rotate: RepeatedAction::new(WAIT_DURATION, REPEAT_DURATION),
             fast_drop: false,
-            instant_drop: false,
+            instant_drop: TriggerAction::new(),
+            use_item: TriggerAction::new(),
+            hold: TriggerAction::new(),
         }
     }
 
@@ -87,7 +147,14 @@ impl SmartInput {
         self.move_right.tick(input.move_right());
         self.rotate.tick(input.rotate());
         self.fast_drop = input.fast_drop();
-        self.instant_drop = input.instant_drop();
+        self.instant_drop.tick(input.instant_drop());
+        self.use_item.tick(input.use_item());
+        self.hold.tick(input.hold());
+    }
+
+    /// Delays the repeat of the held rotation, used when it already rotated the new block.
+    pub fn restart_rotate(&mut self) {
+        self.rotate.restart();
     }
 }
 
@@ -108,7 +175,76 @@ impl Input for SmartInput {
         self.fast_drop
     }
 
+    fn instant_drop(&self) -> bool {
+        self.instant_drop.active()
+    }
+
+    fn use_item(&self) -> bool {
+        self.use_item.active()
+    }
+
+    fn hold(&self) -> bool {
+        self.hold.active()
+    }
+}
+
+/// Presses made while there's no active block, which are applied to the next block on its
+/// first tick.
+#[derive(Clone, Default)]
+pub struct BufferedInput {
+    move_left: bool,
+    move_right: bool,
+    rotate: bool,
+    instant_drop: bool,
+    hold: bool,
+}
+
+impl BufferedInput {
+    pub fn record(&mut self, input: &dyn Input) {
+        self.move_left |= input.move_left();
+        self.move_right |= input.move_right();
+        self.rotate |= input.rotate();
+        self.instant_drop |= input.instant_drop();
+        self.hold |= input.hold();
+    }
+
+    /// Drops the buffered rotation, used when the held rotation already turned the new block.
+    pub fn clear_rotate(&mut self) {
+        self.rotate = false;
+    }
+
+    /// Drops the buffered hold, used when the held hold already swapped the new block.
+    pub fn clear_hold(&mut self) {
+        self.hold = false;
+    }
+}
+
+impl Input for BufferedInput {
+    fn move_left(&self) -> bool {
+        self.move_left
+    }
+
+    fn move_right(&self) -> bool {
+        self.move_right
+    }
+
+    fn rotate(&self) -> bool {
+        self.rotate
+    }
+
+    fn fast_drop(&self) -> bool {
+        false
+    }
+
     fn instant_drop(&self) -> bool {
         self.instant_drop
     }
+
+    fn use_item(&self) -> bool {
+        false
+    }
+
+    fn hold(&self) -> bool {
+        self.hold
+    }
 }
@@ -0,0 +1,44 @@
+use rand::Rng;
+
+/// Chance for a new block to carry an item in one of its points.
+pub(super) const ITEM_CHANCE: f64 = 1. / 8.;
+/// How many rows at the floor are removed by `Item::ClearBottomRows`.
+pub(super) const CLEARED_BOTTOM_ROWS: usize = 2;
+/// How long (in ticks) `Item::SpeedUp` lasts.
+pub(super) const SPEED_UP_DURATION: u32 = 600;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Item {
+    /// Removes rows at the floor of own board.
+    ClearBottomRows,
+    /// Shuffles opponent's next blocks.
+    ShuffleQueue,
+    /// Temporarily increases opponent's drop speed.
+    SpeedUp,
+}
+
+impl Item {
+    const ALL: [Item; 3] = [Item::ClearBottomRows, Item::ShuffleQueue, Item::SpeedUp];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Item::ClearBottomRows => "CLEAR",
+            Item::ShuffleQueue => "SHUFFLE",
+            Item::SpeedUp => "SPEED",
+        }
+    }
+
+    /// Returns `true` if item affects opponent instead of its owner.
+    pub fn targets_opponent(&self) -> bool {
+        !matches!(self, Item::ClearBottomRows)
+    }
+}
+
+/// Returns random item if a new block should carry one.
+pub(super) fn roll_item(rng: &mut impl Rng) -> Option<Item> {
+    if rng.gen_bool(ITEM_CHANCE) {
+        Some(Item::ALL[rng.gen_range(0..Item::ALL.len())])
+    } else {
+        None
+    }
+}
@@ -0,0 +1,60 @@
+use super::{Gravity, BOARD_WIDTH};
+
+/// Shape of the stack measured from the floor, used by bots to evaluate boards.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct BoardMetrics {
+    /// Distance of the highest point of every column from the floor.
+    pub column_heights: [usize; BOARD_WIDTH],
+    /// Number of empty cells below the highest point of their column.
+    pub holes: usize,
+    /// Sum of the height differences of the neighbouring columns.
+    pub bumpiness: usize,
+    /// Sum of the column heights.
+    pub aggregate_height: usize,
+}
+
+impl BoardMetrics {
+    /// Measures the board with the cells for which `occupied` returns `true`.
+    pub fn measure(occupied: impl Fn(usize, usize) -> bool, gravity: Gravity) -> Self {
+        let rows = gravity.rows_from_floor();
+        let mut metrics = Self::default();
+        for x in 0..BOARD_WIDTH {
+            // rows are ordered from the floor, so the last occupied one is the top of the column
+            let top = rows.iter().rposition(|&y| occupied(x, y));
+            if let Some(top) = top {
+                metrics.column_heights[x] = top + 1;
+                metrics.holes += rows[..top].iter().filter(|&&y| !occupied(x, y)).count();
+            }
+        }
+        metrics.aggregate_height = metrics.column_heights.iter().sum();
+        metrics.bumpiness = metrics
+            .column_heights
+            .windows(2)
+            .map(|w| w[0].abs_diff(w[1]))
+            .sum();
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::BOARD_HEIGHT;
+
+    #[test]
+    fn holes_are_counted_below_top() {
+        // column 0 has a hole under its top, column 1 is two points high
+        let occupied = |x: usize, y: usize| {
+            (x == 0 && y == BOARD_HEIGHT - 3) || (x == 1 && y >= BOARD_HEIGHT - 2)
+        };
+        let metrics = BoardMetrics::measure(occupied, Gravity::Down);
+        assert_eq!(3, metrics.column_heights[0]);
+        assert_eq!(2, metrics.column_heights[1]);
+        assert_eq!(2, metrics.holes);
+        assert_eq!(5, metrics.aggregate_height);
+        assert_eq!(1 + 2, metrics.bumpiness);
+
+        let flipped = BoardMetrics::measure(|x, y| occupied(x, BOARD_HEIGHT - 1 - y), Gravity::Up);
+        assert_eq!(metrics, flipped);
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::{add_positions, board_index, board_position, Block, Game, Move, Position};
+
+/// Final position of the active block which can be reached by moves, rotations and falls.
+#[derive(Clone, Debug)]
+pub struct Placement {
+    /// Number of rotations from the current orientation of the active block.
+    pub rotations: usize,
+    pub position: Position,
+    /// Board positions of the block points, in the order of `Block::points`.
+    pub points: Vec<Position>,
+    /// Rows filled by the block, they are removed when it's locked.
+    pub cleared_rows: Vec<usize>,
+}
+
+/// Searches all states of the active block reachable one move at a time, every state which
+/// can't fall further is a placement. Placements with the same points are returned once, with
+/// the fewest rotations.
+pub(super) fn find_placements(game: &Game) -> Vec<Placement> {
+    let mut placements = vec![];
+    let mut visited = HashSet::new();
+    let mut placed = HashSet::new();
+    let mut queue =
+        VecDeque::from([(game.active_block().clone(), game.active_block_position(), 0)]);
+    while let Some((block, pos, rotations)) = queue.pop_front() {
+        if !visited.insert((sorted_shape(&block), pos)) {
+            continue;
+        }
+
+        for block_move in [Move::Left, Move::Right] {
+            if let Some(new_pos) = game.moved_position(&block, pos, block_move) {
+                queue.push_back((block.clone(), new_pos, rotations));
+            }
+        }
+        if let Some((orientation, new_pos)) = game.rotated_block(&block, pos) {
+            let mut rotated = block.clone();
+            rotated.orientation = orientation;
+            queue.push_back((rotated, new_pos, (rotations + 1) % 4));
+        }
+        match game.moved_position(&block, pos, Move::Fall) {
+            Some(new_pos) => queue.push_back((block, new_pos, rotations)),
+            None => {
+                let points = block
+                    .points()
+                    .iter()
+                    .map(|p| add_positions(pos, block.get_point_position(p.id).unwrap()))
+                    .collect::<Vec<_>>();
+                let mut sorted_points = points.clone();
+                sorted_points.sort();
+                if placed.insert(sorted_points) {
+                    placements.push(Placement {
+                        rotations,
+                        position: pos,
+                        cleared_rows: filled_rows(game, &points),
+                        points,
+                    });
+                }
+            }
+        }
+    }
+    placements
+}
+
+fn sorted_shape(block: &Block) -> Vec<Position> {
+    let mut shape = block.shape().to_vec();
+    shape.sort();
+    shape
+}
+
+/// Returns rows which are full with the block points added to the board.
+fn filled_rows(game: &Game, points: &[Position]) -> Vec<usize> {
+    let mut rows = points
+        .iter()
+        .filter_map(|&pos| board_index(pos))
+        .map(|(_, y)| y)
+        .collect::<Vec<_>>();
+    rows.sort();
+    rows.dedup();
+    rows.retain(|&y| {
+        (0..game.board_width())
+            .all(|x| game.board[y][x].is_some() || points.contains(&board_position(x, y)))
+    });
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{BlockType, GameRules, BOARD_HEIGHT, BOARD_WIDTH};
+
+    #[test]
+    fn placements_cover_floor() {
+        let game = Game::with_blocks(GameRules::new(), &[BlockType::O]);
+        let placements = game.legal_placements();
+        assert_eq!(BOARD_WIDTH - 1, placements.len());
+        assert!(placements
+            .iter()
+            .all(|p| p.position.1 == BOARD_HEIGHT as i32 - 2 && p.cleared_rows.is_empty()));
+    }
+
+    #[test]
+    fn placement_clears_filled_row() {
+        let mut game = Game::with_blocks(GameRules::new(), &[BlockType::I]);
+        game.prefill_board((1..BOARD_WIDTH).map(|x| board_position(x, BOARD_HEIGHT - 1)));
+        let placements = game.legal_placements();
+
+        // vertical and horizontal I block for every column it fits into
+        assert_eq!(BOARD_WIDTH + BOARD_WIDTH - 3, placements.len());
+        let clearing = placements
+            .iter()
+            .filter(|p| !p.cleared_rows.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(1, clearing.len());
+        assert_eq!(vec![BOARD_HEIGHT - 1], clearing[0].cleared_rows);
+        assert_eq!(0, clearing[0].rotations);
+    }
+}
@@ -0,0 +1,669 @@
+use std::io::{self, Read, Write};
+
+use super::{
+    utils::{fnv1a, SharedLog},
+    AutoRepeat, Game, GameRules, GarbageStyle, Gravity, Input, Score,
+};
+
+/// Starts the header of the replay files, files without it are of the first format.
+const MAGIC: [u8; 4] = *b"TRPL";
+/// Version of the file layout, increased when the layout changes. Files of older formats are
+/// still read, files of newer formats are refused.
+const FORMAT_VERSION: u8 = 3;
+/// Version of the game logic, increased when a change makes the same input play differently.
+/// Replays recorded by other versions can't be played again and are refused.
+const ENGINE_VERSION: u16 = 1;
+
+// input flags
+const MOVE_LEFT: u8 = 1 << 0;
+const MOVE_RIGHT: u8 = 1 << 1;
+const ROTATE: u8 = 1 << 2;
+const FAST_DROP: u8 = 1 << 3;
+const INSTANT_DROP: u8 = 1 << 4;
+const USE_ITEM: u8 = 1 << 5;
+const HOLD: u8 = 1 << 6;
+
+// rules flags
+const ITEMS: u8 = 1 << 0;
+const MIRROR: u8 = 1 << 1;
+const GRAVITY_UP: u8 = 1 << 2;
+const BIG: u8 = 1 << 3;
+/// Drill interval and garbage style follow the rules byte.
+const DRILL: u8 = 1 << 4;
+/// Speed level, garbage percentage and preview count follow the drill.
+const HANDICAP: u8 = 1 << 5;
+/// Entry delay, line clear delay and auto repeat delay and interval follow the handicap.
+const TIMING: u8 = 1 << 6;
+/// Added by the format 3.
+const DOUBLES: u8 = 1 << 7;
+/// Rules flags of the headerless format and the format 2.
+const FORMAT_2_RULES: u8 = ITEMS | MIRROR | GRAVITY_UP | BIG | DRILL | HANDICAP | TIMING;
+
+// garbage styles
+const CLEAN: u8 = 0;
+const CHEESE: u8 = 1;
+/// Followed by the repeat chance.
+const RANDOM: u8 = 2;
+
+/// Seed, rules and input of every tick, which are enough to play the game again. Garbage and
+/// items received from the opponent are not recorded.
+#[derive(Clone)]
+pub struct Replay {
+    /// Name of the mode the game was played in, only informative, the rules are recorded
+    /// separately.
+    preset: String,
+    seed: u64,
+    items: bool,
+    mirror: bool,
+    gravity: Gravity,
+    big: bool,
+    doubles: bool,
+    drill_interval: Option<u32>,
+    garbage_style: GarbageStyle,
+    speed_level: u32,
+    garbage_percent: u32,
+    preview_count: usize,
+    entry_delay: u32,
+    line_clear_delay: u32,
+    auto_repeat: AutoRepeat,
+    inputs: SharedLog<u8>,
+}
+
+impl Replay {
+    pub(super) fn new(seed: u64, rules: &GameRules) -> Self {
+        Self {
+            preset: String::new(),
+            seed,
+            items: rules.items,
+            mirror: rules.mirror,
+            gravity: rules.gravity,
+            big: rules.big,
+            doubles: rules.doubles,
+            drill_interval: rules.drill_interval,
+            garbage_style: rules.garbage_style,
+            speed_level: rules.speed_level,
+            garbage_percent: rules.garbage_percent,
+            preview_count: rules.preview_count,
+            entry_delay: rules.entry_delay,
+            line_clear_delay: rules.line_clear_delay,
+            auto_repeat: rules.auto_repeat,
+            inputs: SharedLog::new(),
+        }
+    }
+
+    pub(super) fn record(&mut self, input: &dyn Input) {
+        self.inputs.push(ReplayInput::from_input(input).0);
+    }
+
+    /// Returns the replay named by the mode it was played in.
+    pub fn with_preset(mut self, preset: &str) -> Self {
+        self.preset = preset.to_string();
+        self
+    }
+
+    pub fn preset(&self) -> &str {
+        &self.preset
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns number of recorded ticks.
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn rules(&self) -> GameRules {
+        let mut rules = GameRules::new();
+        rules.items = self.items;
+        rules.mirror = self.mirror;
+        rules.gravity = self.gravity;
+        rules.big = self.big;
+        rules.doubles = self.doubles;
+        rules.drill_interval = self.drill_interval;
+        rules.garbage_style = self.garbage_style;
+        rules.speed_level = self.speed_level;
+        rules.garbage_percent = self.garbage_percent;
+        rules.preview_count = self.preview_count;
+        rules.entry_delay = self.entry_delay;
+        rules.line_clear_delay = self.line_clear_delay;
+        rules.auto_repeat = self.auto_repeat;
+        rules
+    }
+
+    fn has_handicap(&self) -> bool {
+        let default = GameRules::new();
+        self.speed_level != default.speed_level
+            || self.garbage_percent != default.garbage_percent
+            || self.preview_count != default.preview_count
+    }
+
+    fn has_timing(&self) -> bool {
+        let default = GameRules::new();
+        self.entry_delay != default.entry_delay
+            || self.line_clear_delay != default.line_clear_delay
+            || self.auto_repeat != default.auto_repeat
+    }
+
+    /// Creates the game in the same state as the recorded game before the first tick.
+    pub fn new_game(&self) -> Game {
+        Game::with_seed(self.rules(), self.seed)
+    }
+
+    /// Returns input of every recorded tick.
+    pub fn inputs(&self) -> impl Iterator<Item = ReplayInput> + '_ {
+        self.inputs.iter().map(|&input| ReplayInput(input))
+    }
+
+    /// Returns input of the tick with the index, `None` past the last recorded tick.
+    pub fn input(&self, tick: usize) -> Option<ReplayInput> {
+        self.inputs.get(tick).map(|&input| ReplayInput(input))
+    }
+
+    /// Plays the first `ticks` recorded inputs on a new game, so it's in the state the recorded
+    /// game had after them.
+    pub fn game_at(&self, ticks: usize) -> Game {
+        let mut game = self.new_game();
+        for input in self.inputs().take(ticks) {
+            game.tick(&input);
+        }
+        game
+    }
+
+    /// Plays the replay on a new game and checks it ends with the points, lines and ticks of
+    /// the submitted score, so results which weren't played by the recorded input are rejected.
+    pub fn verify_score(&self, score: &Score) -> Result<(), String> {
+        let played = self.game_at(self.len()).score();
+        if (played.points, played.lines, played.ticks) != (score.points, score.lines, score.ticks) {
+            return Err(format!(
+                "the replay scores {} points and {} lines in {} ticks, not {} points and {} lines in {} ticks",
+                played.points, played.lines, played.ticks, score.points, score.lines, score.ticks
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns input of the last recorded tick.
+    pub fn last_input(&self) -> Option<ReplayInput> {
+        self.inputs.last().map(|&input| ReplayInput(input))
+    }
+
+    /// Returns FNV-1a hash of the replay file contents, which identifies the replay.
+    pub fn hash(&self) -> u64 {
+        let mut file = vec![];
+        self.write(&mut file).unwrap();
+        fnv1a(&file)
+    }
+
+    /// Writes the replay in the current format.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        writer.write_all(&ENGINE_VERSION.to_le_bytes())?;
+        // longer names are cut at a character boundary
+        let mut preset_len = self.preset.len().min(u8::MAX as usize);
+        while !self.preset.is_char_boundary(preset_len) {
+            preset_len -= 1;
+        }
+        writer.write_all(&[preset_len as u8])?;
+        writer.write_all(&self.preset.as_bytes()[..preset_len])?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        let rules = [
+            (self.items, ITEMS),
+            (self.mirror, MIRROR),
+            (self.gravity == Gravity::Up, GRAVITY_UP),
+            (self.big, BIG),
+            (self.drill_interval.is_some(), DRILL),
+            (self.has_handicap(), HANDICAP),
+            (self.has_timing(), TIMING),
+            (self.doubles, DOUBLES),
+        ]
+        .iter()
+        .filter(|(active, _)| *active)
+        .fold(0, |rules, (_, flag)| rules | flag);
+        writer.write_all(&[rules])?;
+        if let Some(interval) = self.drill_interval {
+            writer.write_all(&interval.to_le_bytes())?;
+            write_garbage_style(&mut writer, self.garbage_style)?;
+        }
+        if self.has_handicap() {
+            writer.write_all(&self.speed_level.to_le_bytes())?;
+            writer.write_all(&self.garbage_percent.to_le_bytes())?;
+            writer.write_all(&[self.preview_count as u8])?;
+        }
+        if self.has_timing() {
+            for value in [
+                self.entry_delay,
+                self.line_clear_delay,
+                self.auto_repeat.delay,
+                self.auto_repeat.interval,
+            ] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        for inputs in self.inputs.slices() {
+            writer.write_all(inputs)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the replay of the current or an older format, older formats are upgraded, so the
+    /// replay is written in the current format. Replays of newer formats or recorded by other
+    /// engine versions are refused.
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut seed = [0; 8];
+        reader.read_exact(&mut seed[..MAGIC.len()])?;
+        // a headerless file whose seed starts with the magic is misread, which is unlikely
+        let (preset, format) = if seed[..MAGIC.len()] == MAGIC {
+            let mut header = [0; 4];
+            reader.read_exact(&mut header)?;
+            let format = header[0];
+            if format > FORMAT_VERSION {
+                return Err(invalid_data(format!(
+                    "replay format {} is newer than the supported format {}",
+                    format, FORMAT_VERSION
+                )));
+            }
+            let engine_version = u16::from_le_bytes([header[1], header[2]]);
+            if engine_version != ENGINE_VERSION {
+                return Err(invalid_data(format!(
+                    "replay was recorded by engine version {}, which plays differently than \
+                     version {}",
+                    engine_version, ENGINE_VERSION
+                )));
+            }
+            let mut preset = vec![0; header[3] as usize];
+            reader.read_exact(&mut preset)?;
+            let preset = String::from_utf8(preset)
+                .map_err(|_| invalid_data("replay preset is not UTF-8".to_string()))?;
+            reader.read_exact(&mut seed)?;
+            (preset, format)
+        } else {
+            // the first format has the same layout as the current one after the header
+            reader.read_exact(&mut seed[MAGIC.len()..])?;
+            (String::new(), 1)
+        };
+        let mut rules = [0; 1];
+        reader.read_exact(&mut rules)?;
+        // rules a format doesn't know would be played by the wrong rules
+        let known_rules = if format < 3 { FORMAT_2_RULES } else { u8::MAX };
+        if rules[0] & !known_rules != 0 {
+            return Err(invalid_data(format!(
+                "replay format {} has unknown rules {:#010b}",
+                format,
+                rules[0] & !known_rules
+            )));
+        }
+        let (drill_interval, garbage_style) = if rules[0] & DRILL != 0 {
+            let mut interval = [0; 4];
+            reader.read_exact(&mut interval)?;
+            let garbage_style = read_garbage_style(&mut reader)?;
+            (Some(u32::from_le_bytes(interval)), garbage_style)
+        } else {
+            (None, GarbageStyle::default())
+        };
+        let default = GameRules::new();
+        let (speed_level, garbage_percent, preview_count) = if rules[0] & HANDICAP != 0 {
+            let mut handicap = [0; 9];
+            reader.read_exact(&mut handicap)?;
+            (
+                u32::from_le_bytes(handicap[0..4].try_into().unwrap()),
+                u32::from_le_bytes(handicap[4..8].try_into().unwrap()),
+                handicap[8] as usize,
+            )
+        } else {
+            (
+                default.speed_level,
+                default.garbage_percent,
+                default.preview_count,
+            )
+        };
+        let (entry_delay, line_clear_delay, auto_repeat) = if rules[0] & TIMING != 0 {
+            let mut timing = [0; 16];
+            reader.read_exact(&mut timing)?;
+            let value = |i: usize| u32::from_le_bytes(timing[i * 4..i * 4 + 4].try_into().unwrap());
+            (
+                value(0),
+                value(1),
+                AutoRepeat {
+                    delay: value(2),
+                    interval: value(3),
+                },
+            )
+        } else {
+            (
+                default.entry_delay,
+                default.line_clear_delay,
+                default.auto_repeat,
+            )
+        };
+        let mut inputs = vec![];
+        reader.read_to_end(&mut inputs)?;
+
+        Ok(Self {
+            preset,
+            seed: u64::from_le_bytes(seed),
+            items: rules[0] & ITEMS != 0,
+            mirror: rules[0] & MIRROR != 0,
+            gravity: if rules[0] & GRAVITY_UP != 0 {
+                Gravity::Up
+            } else {
+                Gravity::Down
+            },
+            big: rules[0] & BIG != 0,
+            doubles: rules[0] & DOUBLES != 0,
+            drill_interval,
+            garbage_style,
+            speed_level,
+            garbage_percent,
+            preview_count,
+            entry_delay,
+            line_clear_delay,
+            auto_repeat,
+            inputs: inputs.into(),
+        })
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_garbage_style(mut writer: impl Write, style: GarbageStyle) -> io::Result<()> {
+    match style {
+        GarbageStyle::Clean => writer.write_all(&[CLEAN]),
+        GarbageStyle::Cheese => writer.write_all(&[CHEESE]),
+        GarbageStyle::Random { repeat_chance } => {
+            writer.write_all(&[RANDOM])?;
+            writer.write_all(&repeat_chance.to_le_bytes())
+        }
+    }
+}
+
+fn read_garbage_style(mut reader: impl Read) -> io::Result<GarbageStyle> {
+    let mut style = [0; 1];
+    reader.read_exact(&mut style)?;
+    match style[0] {
+        CLEAN => Ok(GarbageStyle::Clean),
+        CHEESE => Ok(GarbageStyle::Cheese),
+        RANDOM => {
+            let mut repeat_chance = [0; 8];
+            reader.read_exact(&mut repeat_chance)?;
+            Ok(GarbageStyle::Random {
+                repeat_chance: f64::from_le_bytes(repeat_chance),
+            })
+        }
+        _ => Err(invalid_data("unknown garbage style".to_string())),
+    }
+}
+
+/// Recorded input of one tick.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct ReplayInput(u8);
+
+impl ReplayInput {
+    pub fn from_input(input: &dyn Input) -> Self {
+        Self::new(
+            input.move_left(),
+            input.move_right(),
+            input.rotate(),
+            input.fast_drop(),
+            input.instant_drop(),
+            input.use_item(),
+            input.hold(),
+        )
+    }
+
+    /// Returns the input of the keys held on a tick.
+    pub fn new(
+        move_left: bool,
+        move_right: bool,
+        rotate: bool,
+        fast_drop: bool,
+        instant_drop: bool,
+        use_item: bool,
+        hold: bool,
+    ) -> Self {
+        let flags = [
+            (move_left, MOVE_LEFT),
+            (move_right, MOVE_RIGHT),
+            (rotate, ROTATE),
+            (fast_drop, FAST_DROP),
+            (instant_drop, INSTANT_DROP),
+            (use_item, USE_ITEM),
+            (hold, HOLD),
+        ];
+        Self(
+            flags
+                .iter()
+                .filter(|(active, _)| *active)
+                .fold(0, |input, (_, flag)| input | flag),
+        )
+    }
+
+    /// Returns the input encoded in one byte, as it's stored in replay files.
+    pub fn to_byte(self) -> u8 {
+        self.0
+    }
+
+    pub fn from_byte(byte: u8) -> Self {
+        Self(byte)
+    }
+}
+
+impl Input for ReplayInput {
+    fn move_left(&self) -> bool {
+        self.0 & MOVE_LEFT != 0
+    }
+
+    fn move_right(&self) -> bool {
+        self.0 & MOVE_RIGHT != 0
+    }
+
+    fn rotate(&self) -> bool {
+        self.0 & ROTATE != 0
+    }
+
+    fn fast_drop(&self) -> bool {
+        self.0 & FAST_DROP != 0
+    }
+
+    fn instant_drop(&self) -> bool {
+        self.0 & INSTANT_DROP != 0
+    }
+
+    fn use_item(&self) -> bool {
+        self.0 & USE_ITEM != 0
+    }
+
+    fn hold(&self) -> bool {
+        self.0 & HOLD != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Bot;
+
+    /// Plays the game with the bot, then replays it from the written replay and checks that the
+    /// boards are the same.
+    fn assert_replay_reproduces(mut game: Game) {
+        let mut bot = Bot::new();
+        for _ in 0..3000 {
+            bot.think(&game);
+            game.tick(&bot);
+        }
+
+        let mut file = vec![];
+        game.replay().write(&mut file).unwrap();
+        let replay = Replay::read(file.as_slice()).unwrap();
+        assert_eq!(game.score().ticks as usize, replay.len());
+
+        let mut replayed = replay.new_game();
+        for input in replay.inputs() {
+            replayed.tick(&input);
+        }
+
+        let mut points = game.board_points().map(|(_, pos)| pos).collect::<Vec<_>>();
+        let mut replayed_points = replayed
+            .board_points()
+            .map(|(_, pos)| pos)
+            .collect::<Vec<_>>();
+        points.sort();
+        replayed_points.sort();
+        assert_eq!(points, replayed_points);
+        assert_eq!(
+            game.active_block_position(),
+            replayed.active_block_position()
+        );
+    }
+
+    #[test]
+    fn replay_reproduces_game() {
+        assert_replay_reproduces(Game::new());
+    }
+
+    #[test]
+    fn replay_reproduces_drill() {
+        let mut rules = GameRules::new();
+        rules.drill_interval = Some(120);
+        rules.garbage_style = GarbageStyle::Random { repeat_chance: 0.5 };
+        assert_replay_reproduces(Game::with_rules(rules));
+    }
+
+    #[test]
+    fn replay_reproduces_handicap() {
+        let mut rules = GameRules::new();
+        rules.speed_level = 5;
+        rules.garbage_percent = 50;
+        rules.preview_count = 1;
+        assert_replay_reproduces(Game::with_rules(rules));
+    }
+
+    #[test]
+    fn replay_reproduces_timing() {
+        let mut rules = GameRules::new();
+        rules.entry_delay = 10;
+        rules.line_clear_delay = 20;
+        rules.auto_repeat = AutoRepeat {
+            delay: 8,
+            interval: 1,
+        };
+        assert_replay_reproduces(Game::with_rules(rules));
+    }
+
+    #[test]
+    fn replay_reproduces_doubles() {
+        let mut rules = GameRules::new();
+        rules.doubles = true;
+        rules.drill_interval = Some(120);
+        rules.garbage_style = GarbageStyle::Cheese;
+        assert_replay_reproduces(Game::with_rules(rules));
+    }
+
+    #[test]
+    fn game_at_restores_earlier_state() {
+        let mut game = Game::new();
+        let mut bot = Bot::new();
+        let mut hashes = vec![];
+        for _ in 0..600 {
+            hashes.push(game.state_hash());
+            bot.think(&game);
+            game.tick(&bot);
+        }
+
+        let replay = game.replay();
+        for ticks in [0, 1, 250, 599] {
+            assert_eq!(hashes[ticks], replay.game_at(ticks).state_hash());
+        }
+        assert_eq!(game.state_hash(), replay.game_at(replay.len()).state_hash());
+    }
+
+    #[test]
+    fn tampered_score_is_rejected() {
+        let mut game = Game::new();
+        let mut bot = Bot::new();
+        while game.score().lines < 4 {
+            bot.think(&game);
+            game.tick(&bot);
+        }
+        let mut score = game.score();
+        assert_eq!(Ok(()), game.replay().verify_score(&score));
+
+        score.points += 100;
+        assert!(game.replay().verify_score(&score).is_err());
+    }
+
+    #[test]
+    fn headerless_replay_is_upgraded() {
+        let mut game = Game::new();
+        let bot = Bot::new();
+        for _ in 0..100 {
+            game.tick(&bot);
+        }
+        let replay = game.replay().clone().with_preset("marathon");
+        let mut file = vec![];
+        replay.write(&mut file).unwrap();
+        let header_len = MAGIC.len() + 4 + replay.preset().len();
+
+        let headerless = Replay::read(&file[header_len..]).unwrap();
+        assert_eq!(headerless.seed(), replay.seed());
+        assert_eq!(headerless.len(), replay.len());
+        let mut upgraded = vec![];
+        headerless
+            .with_preset("marathon")
+            .write(&mut upgraded)
+            .unwrap();
+        assert_eq!(upgraded, file);
+    }
+
+    #[test]
+    fn format_2_replay_is_upgraded() {
+        let mut game = Game::new();
+        let bot = Bot::new();
+        for _ in 0..100 {
+            game.tick(&bot);
+        }
+        let mut file = vec![];
+        game.replay().write(&mut file).unwrap();
+        let mut format_2 = file.clone();
+        format_2[MAGIC.len()] = 2;
+
+        let mut upgraded = vec![];
+        Replay::read(format_2.as_slice())
+            .unwrap()
+            .write(&mut upgraded)
+            .unwrap();
+        assert_eq!(upgraded, file);
+    }
+
+    #[test]
+    fn unknown_rules_of_older_format_are_refused() {
+        let mut rules = GameRules::new();
+        rules.doubles = true;
+        let mut file = vec![];
+        Game::with_rules(rules).replay().write(&mut file).unwrap();
+        assert!(Replay::read(file.as_slice()).unwrap().rules().doubles);
+
+        let mut format_2 = file;
+        format_2[MAGIC.len()] = 2;
+        assert!(Replay::read(format_2.as_slice()).is_err());
+    }
+
+    #[test]
+    fn replay_of_other_version_is_refused() {
+        let mut file = vec![];
+        Game::new().replay().write(&mut file).unwrap();
+
+        let mut newer_format = file.clone();
+        newer_format[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert!(Replay::read(newer_format.as_slice()).is_err());
+
+        let mut other_engine = file;
+        other_engine[MAGIC.len() + 1..MAGIC.len() + 3]
+            .copy_from_slice(&(ENGINE_VERSION + 1).to_le_bytes());
+        assert!(Replay::read(other_engine.as_slice()).is_err());
+    }
+}
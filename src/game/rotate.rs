@@ -1,25 +1,24 @@
 use std::collections::HashMap;
 
-use super::{Block, Id, Position, BOARD_HEIGHT, BOARD_WIDTH};
+use lazy_static::lazy_static;
 
-pub fn rotate_block<F>(
-    block: &Block,
-    block_pos: Position,
-    check_collision: F,
-) -> Option<(HashMap<Id, Position>, Position)>
-where
-    F: Fn(&[Position], Position) -> bool,
-{
-    let (block_w, block_h) = (block.width(), block.height());
-    let cx = (block_w / 2) as i32;
-    let cy = (block_h / 2) as i32;
+use super::{
+    blocks::{get_block_points, mirror_shape, scale_shape},
+    Block, BlockType, Position,
+};
+
+/// Rotates points around the center of their bounding box. Returns rotated points and
+/// position of their new bounding box relative to the old one.
+fn rotate_points(points: &[Position]) -> (Vec<Position>, (i32, i32)) {
+    let block_w = points.iter().map(|&(x, _)| x).max().unwrap();
+    let block_h = points.iter().map(|&(_, y)| y).max().unwrap();
+    let cx = block_w / 2;
+    let cy = block_h / 2;
 
-    let points = &block.points;
-    let mut rot_points = Vec::with_capacity(block.points.len());
-    for p in points {
-        let point_pos = block.get_point_position(p.id).unwrap();
-        let x = point_pos.0 as i32 - cx;
-        let y = point_pos.1 as i32 - cy;
+    let mut rot_points = Vec::with_capacity(points.len());
+    for point_pos in points {
+        let x = point_pos.0 - cx;
+        let y = point_pos.1 - cy;
 
         let rot_p = (-y + cx, x + cy);
         rot_points.push(rot_p);
@@ -28,28 +27,117 @@ where
     let min_x = rot_points.iter().map(|&(x, _)| x).min().unwrap();
     let min_y = rot_points.iter().map(|&(_, y)| y).min().unwrap();
 
-    let rot_piece_pos = (block_pos.0 as i32 + min_x, block_pos.1 as i32 + min_y);
-    if rot_piece_pos.0 < 0
-        || rot_piece_pos.0 as usize + block_h >= BOARD_WIDTH
-        || rot_piece_pos.1 < 0
-        || rot_piece_pos.1 as usize + block_w >= BOARD_HEIGHT
-    {
-        return None;
-    }
-
-    let rot_block_pos = (rot_piece_pos.0 as usize, rot_piece_pos.1 as usize);
     let rot_points = rot_points
         .into_iter()
-        .map(|(x, y)| ((x - min_x) as usize, (y - min_y) as usize))
-        .collect::<Vec<_>>();
+        .map(|(x, y)| (x - min_x, y - min_y))
+        .collect();
+    (rot_points, (min_x, min_y))
+}
+
+/// Returns points of the block shape after the rotation, in the same order.
+pub fn rotate_shape(points: &[Position]) -> Vec<Position> {
+    rotate_points(points).0
+}
+
+/// Shape of a block in one of its four orientations, each orientation is the previous one
+/// rotated.
+pub(super) struct Orientation {
+    /// Points in the order of `Block::points`.
+    pub points: Vec<Position>,
+    /// Position of the bounding box relative to the bounding box of the previous orientation.
+    offset: (i32, i32),
+}
+
+/// Orientations of the block shapes by the block type and whether the shape is mirrored and
+/// big.
+type Orientations = HashMap<(BlockType, bool, bool), [Orientation; 4]>;
+
+lazy_static! {
+    static ref ORIENTATIONS: Orientations = {
+        let mut orientations = HashMap::new();
+        for block_type in BlockType::ALL {
+            for mirror in [false, true] {
+                for big in [false, true] {
+                    let key = (block_type, mirror, big);
+                    orientations.insert(key, compute_orientations(block_type, mirror, big));
+                }
+            }
+        }
+        orientations
+    };
+}
 
-    if !check_collision(&rot_points, rot_block_pos) {
+fn compute_orientations(block_type: BlockType, mirror: bool, big: bool) -> [Orientation; 4] {
+    let mut shape = get_block_points(block_type).clone();
+    if mirror {
+        shape = mirror_shape(&shape);
+    }
+    if big {
+        shape = scale_shape(&shape);
+    }
+    let mut shapes = vec![shape];
+    let mut offsets = vec![];
+    for _ in 0..4 {
+        let (rotated, offset) = rotate_points(shapes.last().unwrap());
+        offsets.push(offset);
+        shapes.push(rotated);
+    }
+    // four rotations turn the shape back to the first orientation
+    debug_assert_eq!(shapes[0], shapes[4]);
+    [0, 1, 2, 3].map(|i| Orientation {
+        points: shapes[i].clone(),
+        offset: offsets[(i + 3) % 4],
+    })
+}
+
+/// Returns the orientations of the block shape, the first one is the spawn orientation.
+pub(super) fn orientations(
+    block_type: BlockType,
+    mirror: bool,
+    big: bool,
+) -> &'static [Orientation; 4] {
+    &ORIENTATIONS[&(block_type, mirror, big)]
+}
+
+/// Returns the next orientation of the block and its position if `check_collision` accepts the
+/// rotated block, which also keeps it inside of the board.
+pub fn rotate_block<F>(
+    block: &Block,
+    block_pos: Position,
+    check_collision: F,
+) -> Option<(usize, Position)>
+where
+    F: Fn(&[Position], Position) -> bool,
+{
+    let orientation = (block.orientation + 1) % 4;
+    let rotated = &block.orientations[orientation];
+    let (mut min_x, min_y) = rotated.offset;
+    if block.big {
+        // keep big blocks in even columns, so they fit to the holes made by other big blocks
+        min_x -= min_x.rem_euclid(2);
+    }
+
+    let rot_block_pos = (block_pos.0 + min_x, block_pos.1 + min_y);
+    if !check_collision(&rotated.points, rot_block_pos) {
         return None;
     }
+    Some((orientation, rot_block_pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut points_pos = HashMap::with_capacity(points.len());
-    for (p, pos) in points.iter().zip(rot_points.iter()) {
-        points_pos.insert(p.id, *pos);
+    #[test]
+    fn orientations_are_rotated_shapes() {
+        for block_type in BlockType::ALL {
+            let orientations = orientations(block_type, false, false);
+            for i in 0..4 {
+                let next = &orientations[(i + 1) % 4];
+                let (rotated, offset) = rotate_points(&orientations[i].points);
+                assert_eq!(rotated, next.points);
+                assert_eq!(offset, next.offset);
+            }
+        }
     }
-    Some((points_pos, rot_block_pos))
 }
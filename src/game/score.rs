@@ -0,0 +1,201 @@
+use super::{BlockType, Position};
+
+/// How many lines should be cleared to advance to the next level.
+pub(super) const LINES_PER_LEVEL: u32 = 10;
+/// How many lines should be cleared to finish the sprint.
+pub const SPRINT_LINES: u32 = 40;
+/// Lines between the split times of the sprint.
+pub const SPRINT_SPLIT_LINES: u32 = 10;
+/// Number of the split times, the last one is the sprint time.
+pub const SPRINT_SPLITS: usize = (SPRINT_LINES / SPRINT_SPLIT_LINES) as usize;
+
+/// Score points for clearing 0-4 rows with one block, multiplied by the level.
+const CLEAR_POINTS: [u32; 5] = [0, 100, 300, 500, 800];
+const T_SPIN_POINTS: [u32; 5] = [400, 800, 1200, 1600, 1600];
+
+/// Score and progress of the game.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Score {
+    pub points: u32,
+    pub lines: u32,
+    /// Number of ticks played.
+    pub ticks: u32,
+    /// Number of four row clears.
+    pub tetrises: u32,
+    /// Number of T-spins, with or without cleared rows.
+    pub t_spins: u32,
+    /// Number of ticks it took to clear `SPRINT_LINES` lines.
+    pub sprint_ticks: Option<u32>,
+    /// Number of ticks it took to clear every `SPRINT_SPLIT_LINES` lines of the sprint.
+    pub sprint_splits: [Option<u32>; SPRINT_SPLITS],
+    /// Number of consecutive blocks which removed rows, up to the last locked block.
+    pub combo: u32,
+    pub max_combo: u32,
+    /// Number of cleared lines which had garbage points in them.
+    pub garbage_lines: u32,
+    /// Number of times the board was left empty by removed rows.
+    pub perfect_clears: u32,
+}
+
+/// Kind of the clear made by one block, by the number of the cleared rows and whether the block
+/// was T-spun. Big blocks clear more than four rows, which count as tetrises.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ClearKind {
+    Single,
+    Double,
+    Triple,
+    Tetris,
+    TSpinSingle,
+    TSpinDouble,
+    TSpinTriple,
+}
+
+impl ClearKind {
+    /// Returns kind of the clear of `rows` rows, `None` if no rows were cleared.
+    pub fn new(rows: usize, t_spin: bool) -> Option<Self> {
+        let kind = match (t_spin, rows) {
+            (_, 0) => return None,
+            (false, 1) => ClearKind::Single,
+            (false, 2) => ClearKind::Double,
+            (false, 3) => ClearKind::Triple,
+            (false, _) => ClearKind::Tetris,
+            (true, 1) => ClearKind::TSpinSingle,
+            (true, 2) => ClearKind::TSpinDouble,
+            (true, _) => ClearKind::TSpinTriple,
+        };
+        Some(kind)
+    }
+
+    /// Returns `true` for tetrises and T-spins, which make back-to-back clears.
+    pub fn is_difficult(self) -> bool {
+        !matches!(
+            self,
+            ClearKind::Single | ClearKind::Double | ClearKind::Triple
+        )
+    }
+}
+
+/// Block locked to the board, the list of locked blocks is the timeline of the game.
+#[derive(Clone, Copy, Debug)]
+pub struct LockedBlock {
+    pub block_type: BlockType,
+    /// Tick when the block became active.
+    pub spawn_tick: u32,
+    pub lock_tick: u32,
+    /// Position of the block when it was locked.
+    pub position: Position,
+    /// Index of the orientation the block was locked in.
+    pub orientation: usize,
+    pub rows: usize,
+    pub t_spin: bool,
+    /// Score points after the block was locked.
+    pub points: u32,
+}
+
+impl LockedBlock {
+    /// Returns kind of the clear made by the block, `None` if it didn't clear rows.
+    pub fn clear_kind(&self) -> Option<ClearKind> {
+        ClearKind::new(self.rows, self.t_spin)
+    }
+}
+
+impl Score {
+    pub fn level(&self) -> u32 {
+        self.lines / LINES_PER_LEVEL + 1
+    }
+
+    pub(super) fn block_locked(&mut self, rows: usize, t_spin: bool) {
+        let rows = rows.min(4);
+        let points = if t_spin {
+            T_SPIN_POINTS[rows]
+        } else {
+            CLEAR_POINTS[rows]
+        };
+        self.points += points * self.level();
+        self.lines += rows as u32;
+        if rows == 4 {
+            self.tetrises += 1;
+        }
+        if t_spin {
+            self.t_spins += 1;
+        }
+        if rows > 0 {
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+        } else {
+            self.combo = 0;
+        }
+        if self.sprint_ticks.is_none() && self.lines >= SPRINT_LINES {
+            self.sprint_ticks = Some(self.ticks);
+        }
+        // a tetris can pass a split without ending exactly on it
+        let reached = (self.lines / SPRINT_SPLIT_LINES) as usize;
+        for split in self.sprint_splits.iter_mut().take(reached) {
+            split.get_or_insert(self.ticks);
+        }
+    }
+
+    /// Returns the index and ticks of the last reached sprint split.
+    pub fn last_split(&self) -> Option<(usize, u32)> {
+        self.sprint_splits
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, split)| Some((i, (*split)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_are_multiplied_by_level() {
+        let mut score = Score::default();
+        score.block_locked(4, false);
+        score.block_locked(4, false);
+        score.block_locked(2, false);
+        assert_eq!(2, score.level());
+        score.block_locked(1, true);
+        assert_eq!(800 + 800 + 300 + 800 * 2, score.points);
+        assert_eq!(11, score.lines);
+    }
+
+    #[test]
+    fn combo_is_broken_by_block_without_rows() {
+        let mut score = Score::default();
+        score.block_locked(1, false);
+        score.block_locked(2, false);
+        score.block_locked(1, false);
+        assert_eq!(3, score.combo);
+        score.block_locked(0, false);
+        score.block_locked(1, false);
+        assert_eq!(1, score.combo);
+        assert_eq!(3, score.max_combo);
+    }
+
+    #[test]
+    fn sprint_splits_are_recorded() {
+        let mut score = Score::default();
+        for ticks in 1..=12 {
+            score.ticks = ticks * 10;
+            score.block_locked(4, false);
+        }
+        assert_eq!(
+            [Some(30), Some(50), Some(80), Some(100)],
+            score.sprint_splits
+        );
+        assert_eq!(score.sprint_ticks, score.sprint_splits[SPRINT_SPLITS - 1]);
+        assert_eq!(Some((SPRINT_SPLITS - 1, 100)), score.last_split());
+    }
+
+    #[test]
+    fn clear_kinds() {
+        assert_eq!(None, ClearKind::new(0, true));
+        assert_eq!(Some(ClearKind::Triple), ClearKind::new(3, false));
+        assert_eq!(Some(ClearKind::Tetris), ClearKind::new(6, false));
+        assert_eq!(Some(ClearKind::TSpinDouble), ClearKind::new(2, true));
+        assert!(!ClearKind::Triple.is_difficult());
+        assert!(ClearKind::TSpinSingle.is_difficult());
+    }
+}
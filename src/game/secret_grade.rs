@@ -0,0 +1,79 @@
+use super::{Gravity, BOARD_WIDTH};
+
+/// Number of rows of the complete pattern, its hole goes from the left wall to the right one
+/// and back.
+pub const SECRET_GRADE_ROWS: usize = 2 * BOARD_WIDTH - 1;
+
+/// Names of the grades by the number of the pattern rows, starting with a single row.
+const GRADE_NAMES: [&str; SECRET_GRADE_ROWS] = [
+    "9", "8", "7", "6", "5", "4", "3", "2", "1", "S1", "S2", "S3", "S4", "S5", "S6", "S7", "S8",
+    "S9", "GM",
+];
+
+/// Returns the hole column of the pattern row, rows are counted from the floor.
+fn hole_column(row: usize) -> usize {
+    if row < BOARD_WIDTH {
+        row
+    } else {
+        2 * (BOARD_WIDTH - 1) - row
+    }
+}
+
+/// Returns number of the rows of the secret grade pattern built from the floor of the board
+/// with the cells for which `occupied` returns `true`. Every pattern row is filled except the
+/// hole, which moves by one column each row, rows above the first wrong one don't count.
+pub fn measure(occupied: impl Fn(usize, usize) -> bool, gravity: Gravity) -> usize {
+    gravity
+        .rows_from_floor()
+        .into_iter()
+        .take(SECRET_GRADE_ROWS)
+        .enumerate()
+        .take_while(|&(row, y)| (0..BOARD_WIDTH).all(|x| occupied(x, y) != (x == hole_column(row))))
+        .count()
+}
+
+/// Returns name of the grade achieved by the pattern `rows` rows high, `None` without a
+/// pattern row.
+pub fn grade_name(rows: usize) -> Option<&'static str> {
+    rows.checked_sub(1)
+        .and_then(|index| GRADE_NAMES.get(index).copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::BOARD_HEIGHT;
+
+    /// Returns whether the cell is occupied by the pattern `rows` rows high on the board with
+    /// the floor at the bottom.
+    fn pattern(rows: usize) -> impl Fn(usize, usize) -> bool {
+        move |x, y| {
+            let row = BOARD_HEIGHT - 1 - y;
+            row < rows && x != hole_column(row)
+        }
+    }
+
+    #[test]
+    fn complete_pattern_is_grand_master() {
+        let rows = measure(pattern(SECRET_GRADE_ROWS), Gravity::Down);
+        assert_eq!(SECRET_GRADE_ROWS, rows);
+        assert_eq!(Some("GM"), grade_name(rows));
+
+        let flipped = measure(
+            |x, y| pattern(SECRET_GRADE_ROWS)(x, BOARD_HEIGHT - 1 - y),
+            Gravity::Up,
+        );
+        assert_eq!(SECRET_GRADE_ROWS, flipped);
+    }
+
+    #[test]
+    fn partial_pattern_is_graded_to_first_wrong_row() {
+        assert_eq!(Some("9"), grade_name(measure(pattern(1), Gravity::Down)));
+        assert_eq!(Some("S2"), grade_name(measure(pattern(11), Gravity::Down)));
+
+        // the filled hole of the fifth row breaks the pattern
+        let filled_hole = |x, y| pattern(12)(x, y) || (x == 4 && y == BOARD_HEIGHT - 5);
+        assert_eq!(4, measure(filled_hole, Gravity::Down));
+        assert_eq!(None, grade_name(measure(|_, _| false, Gravity::Down)));
+    }
+}
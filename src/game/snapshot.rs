@@ -0,0 +1,28 @@
+use super::Game;
+
+/// State of the game after a tick with everything which decides how it plays on: the board,
+/// the active and next blocks, the random generator and the timers, and the inputs recorded up
+/// to the tick, so any snapshot of the game can be restored, also one taken after the current
+/// tick. The locked blocks and the inputs are kept in `SharedLog`s which share all but the last
+/// items with the game, so taking a snapshot doesn't get slower as the game goes on.
+#[derive(Clone)]
+pub struct GameSnapshot {
+    game: Game,
+}
+
+impl GameSnapshot {
+    pub(super) fn new(game: &Game) -> Self {
+        Self { game: game.clone() }
+    }
+
+    /// Returns the number of ticks the game played before the snapshot.
+    pub fn ticks(&self) -> u32 {
+        self.game.score.ticks
+    }
+
+    /// Puts the game to the state of the snapshot, its replay has the inputs recorded up to
+    /// the snapshot.
+    pub(super) fn restore(&self, game: &mut Game) {
+        *game = self.game.clone();
+    }
+}
@@ -1,5 +1,11 @@
+use std::sync::Arc;
+
 use super::Id;
 
+/// Number of items in the shared chunks of a `SharedLog`.
+const LOG_CHUNK_LEN: usize = 1024;
+
+#[derive(Clone)]
 pub struct Timer {
     tick: u32,
 }
@@ -32,6 +38,14 @@ impl Timer {
     }
 }
 
+/// Returns FNV-1a hash of the bytes, which is the same on all platforms and builds.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[derive(Clone)]
 pub struct IdGenerator {
     next_id: Id,
 }
@@ -42,6 +56,11 @@ impl IdGenerator {
             next_id: Id::new(1).unwrap(),
         }
     }
+
+    /// Returns the id which will be generated next.
+    pub fn peek(&self) -> Id {
+        self.next_id
+    }
 }
 
 impl FnOnce<()> for IdGenerator {
@@ -60,10 +79,114 @@ impl FnMut<()> for IdGenerator {
     }
 }
 
+/// Append-only list whose clones share the filled chunks of items, so a clone copies only the
+/// items pushed after the last filled chunk and the list of chunks.
+#[derive(Clone)]
+pub struct SharedLog<T> {
+    chunks: Vec<Arc<[T]>>,
+    tail: Vec<T>,
+}
+
+impl<T> SharedLog<T> {
+    pub fn new() -> Self {
+        Self {
+            chunks: vec![],
+            tail: Vec::with_capacity(LOG_CHUNK_LEN),
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.tail.push(item);
+        if self.tail.len() == LOG_CHUNK_LEN {
+            let chunk = std::mem::replace(&mut self.tail, Vec::with_capacity(LOG_CHUNK_LEN));
+            self.chunks.push(chunk.into());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len() * LOG_CHUNK_LEN + self.tail.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        match self.chunks.get(index / LOG_CHUNK_LEN) {
+            Some(chunk) => chunk.get(index % LOG_CHUNK_LEN),
+            None => self.tail.get(index - self.chunks.len() * LOG_CHUNK_LEN),
+        }
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.tail
+            .last()
+            .or_else(|| self.chunks.last().and_then(|chunk| chunk.last()))
+    }
+
+    /// Returns the items in consecutive slices, in the order they were pushed.
+    pub fn slices(&self) -> impl DoubleEndedIterator<Item = &[T]> {
+        self.chunks
+            .iter()
+            .map(|chunk| &chunk[..])
+            .chain([&self.tail[..]])
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.slices().flatten()
+    }
+}
+
+impl<T> Default for SharedLog<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Vec<T>> for SharedLog<T> {
+    fn from(items: Vec<T>) -> Self {
+        let mut log = Self::new();
+        for item in items {
+            log.push(item);
+        }
+        log
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SharedLog<T> {
+    type Item = &'a T;
+    type IntoIter = Box<dyn DoubleEndedIterator<Item = &'a T> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn shared_log_clones_keep_their_items() {
+        let mut log = SharedLog::new();
+        for i in 0..LOG_CHUNK_LEN + 10 {
+            log.push(i);
+        }
+        let mut clone = log.clone();
+        log.push(0);
+        clone.push(1);
+
+        assert_eq!(LOG_CHUNK_LEN + 11, log.len());
+        assert_eq!(Some(&0), log.last());
+        assert_eq!(Some(&1), clone.last());
+        assert_eq!(Some(&LOG_CHUNK_LEN), clone.get(LOG_CHUNK_LEN));
+        assert!(log
+            .iter()
+            .take(LOG_CHUNK_LEN + 10)
+            .eq(clone.iter().take(LOG_CHUNK_LEN + 10)));
+        assert_eq!(None, log.get(log.len()));
+    }
+
     #[test]
     fn generate_ids() {
         let mut gen_id = IdGenerator::new();
@@ -0,0 +1,101 @@
+use super::{
+    add_positions, BlockType, Game, Gravity, Id, Item, Point, Position, Score, TickChange,
+};
+
+/// Values shown next to the board.
+#[derive(Clone, Copy, Debug)]
+pub struct HudValues {
+    pub score: Score,
+    pub item: Option<Item>,
+    pub attack_total: u32,
+    pub pending_garbage: u32,
+}
+
+/// Snapshot of the game state renderers draw from. Positions are board positions including
+/// the hidden rows above the visible board.
+#[derive(Clone)]
+pub struct GameView {
+    pub board: Vec<(Point, Position)>,
+    pub active_block_id: Id,
+    pub active_block_type: BlockType,
+    /// Points of the active block, followed by the points of the co-op partner's block.
+    pub active_block: Vec<(Point, Position)>,
+    /// Positions of the active block points where the block would land, followed by the ones
+    /// of the partner's block.
+    pub ghost: Vec<Position>,
+    pub queue: Vec<BlockType>,
+    pub held_block: Option<BlockType>,
+    pub hud: HudValues,
+    pub game_over: bool,
+    /// Decides which rows are visible.
+    pub gravity: Gravity,
+}
+
+impl GameView {
+    pub(super) fn new(game: &Game) -> Self {
+        let block = game.active_block();
+        let block_pos = game.active_block_position();
+        let landing_pos = game.landing_position(block, block_pos);
+        // the locked block is already on the board until the next block spawns
+        let mut blocks = vec![];
+        if game.has_active_block() {
+            blocks.push((block, block_pos, landing_pos));
+        }
+        blocks.extend(game.partner_block());
+        Self {
+            board: game.board_points().collect(),
+            active_block_id: block.id,
+            active_block_type: block.block_type,
+            active_block: blocks
+                .iter()
+                .flat_map(|&(block, block_pos, _)| {
+                    block.points().iter().map(move |p| {
+                        let point_pos = block.get_point_position(p.id).unwrap();
+                        (*p, add_positions(block_pos, point_pos))
+                    })
+                })
+                .collect(),
+            ghost: blocks
+                .iter()
+                .flat_map(|&(block, _, landing_pos)| {
+                    block.points().iter().map(move |p| {
+                        add_positions(landing_pos, block.get_point_position(p.id).unwrap())
+                    })
+                })
+                .collect(),
+            queue: game.next_blocks().collect(),
+            held_block: game.held_block(),
+            hud: HudValues {
+                score: game.score(),
+                item: game.item(),
+                attack_total: game.attack_total(),
+                pending_garbage: game.pending_garbage(),
+            },
+            game_over: game.is_game_over(),
+            gravity: game.rules.gravity,
+        }
+    }
+
+    /// Returns points of the board and the active block.
+    pub fn points(&self) -> impl Iterator<Item = (Point, Position)> + '_ {
+        self.board.iter().chain(self.active_block.iter()).copied()
+    }
+
+    /// Returns the board or active block point with its position.
+    pub fn point(&self, point_id: Id) -> Option<(Point, Position)> {
+        self.points().find(|(p, _)| p.id == point_id)
+    }
+}
+
+/// Frontend drawing the game. Renderers which redraw the whole state need only `draw`,
+/// the ones keeping their own state can follow the changes instead.
+pub trait Renderer {
+    /// Draws the whole game state.
+    fn draw(&mut self, view: &GameView);
+
+    /// Called after every tick with the state after the tick and the changes made by it.
+    fn apply_changes(&mut self, view: &GameView, changes: &[TickChange]) {
+        let _ = changes;
+        self.draw(view);
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Instant;
+
+use crate::{
+    game::{Bot, Game, GameRules, Replay},
+    TICK_DURATION,
+};
+
+/// Minutes of the game the bot plays at most, the bot which doesn't top out is stopped then.
+const MAX_BOT_MINUTES: f32 = 10.;
+
+/// Plays the game without the window and prints its result with the speed of the simulation.
+/// The replay is played again if given, otherwise the bot plays a game by the rules.
+pub fn run(rules: GameRules, seed: Option<u64>, replay: Option<&Replay>) {
+    let start = Instant::now();
+    let game = match replay {
+        Some(replay) => replay.game_at(replay.len()),
+        None => play_bot(rules, seed),
+    };
+    let elapsed = start.elapsed().as_secs_f32();
+
+    let score = game.score();
+    let sprint = score.sprint_ticks.map_or("-".to_string(), |ticks| {
+        format!("{:.2}", ticks as f32 * TICK_DURATION)
+    });
+    println!(
+        "score: {}\nlines: {}\ntime: {:.2}\nsprint: {}\nticks per second: {:.0}",
+        score.points,
+        score.lines,
+        score.ticks as f32 * TICK_DURATION,
+        sprint,
+        score.ticks as f32 / elapsed.max(f32::EPSILON)
+    );
+}
+
+fn play_bot(rules: GameRules, seed: Option<u64>) -> Game {
+    let mut game = match seed {
+        Some(seed) => Game::with_seed(rules, seed),
+        None => Game::with_rules(rules),
+    };
+    let mut bot = Bot::new();
+    let max_ticks = (MAX_BOT_MINUTES * 60. / TICK_DURATION) as u32;
+    while !game.is_game_over() && game.score().ticks < max_ticks {
+        bot.think(&game);
+        game.tick(&bot);
+    }
+    game
+}
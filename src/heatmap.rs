@@ -0,0 +1,73 @@
+use bevy::{math::vec3, prelude::*, sprite::Anchor};
+
+use crate::{game, player::Player, pos_to_vec3, UNIT_PX};
+
+const TOGGLE_KEY: KeyCode = KeyCode::H;
+const HEATMAP_COLOR: Color = Color::rgb(1., 0.3, 0.);
+/// Heatmap is drawn over the board points.
+const HEATMAP_Z: f32 = 1.;
+
+/// Cell of the heatmap showing how often blocks were locked to it.
+#[derive(Component)]
+pub struct HeatmapCell;
+
+/// Shows or hides heatmap of block placements over the boards of players whose game is over.
+pub fn toggle_heatmap(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    players: Query<&Player>,
+    cells: Query<Entity, With<HeatmapCell>>,
+) {
+    // hide the heatmap when a new game is started
+    let restarted = players.iter().all(|p| !p.game.is_game_over());
+    if !keys.just_pressed(TOGGLE_KEY) && !restarted {
+        return;
+    }
+    if !cells.is_empty() || restarted {
+        for cell in cells.iter() {
+            commands.entity(cell).despawn();
+        }
+        return;
+    }
+
+    for player in players.iter().filter(|p| p.game.is_game_over()) {
+        let visible_rows = player.game.rules().gravity.visible_rows();
+        let board_width = player.game.board_width();
+        let max_count = visible_rows
+            .clone()
+            .flat_map(|y| (0..board_width).map(move |x| game::board_position(x, y)))
+            .map(|pos| player.game.placements(pos))
+            .max()
+            .unwrap_or_default();
+        if max_count == 0 {
+            continue;
+        }
+
+        for (row, y) in visible_rows.enumerate() {
+            for x in 0..board_width {
+                let count = player.game.placements(game::board_position(x, y));
+                let mut translation = pos_to_vec3(game::board_position(x, row));
+                translation.z = HEATMAP_Z;
+                let mut color = HEATMAP_COLOR;
+                color.set_a(count as f32 / max_count as f32);
+                let cell = commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            anchor: Anchor::BottomLeft,
+                            ..default()
+                        },
+                        transform: Transform {
+                            translation,
+                            scale: vec3(UNIT_PX, UNIT_PX, 1.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(HeatmapCell)
+                    .id();
+                commands.entity(player.board).add_child(cell);
+            }
+        }
+    }
+}
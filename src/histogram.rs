@@ -0,0 +1,61 @@
+use bevy::{math::vec3, prelude::*, sprite::Anchor};
+
+use crate::{game::BlockType, get_block_color, player::Player};
+
+/// Height of one bar including the gap between bars.
+pub const ROW_HEIGHT: f32 = 8.;
+const BAR_HEIGHT: f32 = 6.;
+const MAX_BAR_WIDTH: f32 = 50.;
+
+/// Bar showing how many blocks of the type were dealt to the player.
+#[derive(Component)]
+pub struct HistogramBar {
+    player: Entity,
+    block_type: BlockType,
+}
+
+/// Spawns bars of all block types as children of `parent`, with top left corner at the
+/// given position.
+pub fn spawn_histogram(
+    commands: &mut Commands,
+    parent: Entity,
+    player: Entity,
+    left: f32,
+    top: f32,
+) {
+    for (i, &block_type) in BlockType::ALL.iter().enumerate() {
+        let bar = commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: get_block_color(block_type),
+                    anchor: Anchor::BottomLeft,
+                    ..default()
+                },
+                transform: Transform {
+                    translation: vec3(left, top + ROW_HEIGHT * i as f32, 0.),
+                    scale: vec3(0., BAR_HEIGHT, 1.),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(HistogramBar { player, block_type })
+            .id();
+        commands.entity(parent).add_child(bar);
+    }
+}
+
+/// Scales the bars relative to the most dealt block type.
+pub fn update_histogram(players: Query<&Player>, mut bars: Query<(&HistogramBar, &mut Transform)>) {
+    for (bar, mut transform) in bars.iter_mut() {
+        if let Ok(player) = players.get(bar.player) {
+            let max_count = BlockType::ALL
+                .iter()
+                .map(|&block_type| player.game.dealt_blocks(block_type))
+                .max()
+                .unwrap_or_default()
+                .max(1);
+            let count = player.game.dealt_blocks(bar.block_type);
+            transform.scale.x = MAX_BAR_WIDTH * count as f32 / max_count as f32;
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{secret_grade_name, SPRINT_SPLIT_LINES},
+    i18n::{Key, Language},
+    pb_ghost::PbGhost,
+    player::Player,
+    practice::PracticeSession,
+    records::BestSplits,
+    TICK_DURATION, UI,
+};
+
+const FONT_SIZE: f32 = 14.;
+const LABEL_COLOR: Color = Color::GRAY;
+const VALUE_COLOR: Color = Color::WHITE;
+
+/// Player's value shown next to the board.
+#[derive(Clone, Copy)]
+pub enum HudValue {
+    /// Item stored in the item slot.
+    Item,
+    /// Total number of garbage lines sent to the opponent.
+    Attack,
+    Score,
+    Lines,
+    Level,
+    /// Time played.
+    Time,
+    /// Number of cleared lines with garbage.
+    GarbageLines,
+    /// Number of consecutive blocks which removed rows.
+    Combo,
+    /// Longest combo of the practice session.
+    BestCombo,
+    /// Number of successful and all attempts of the practice session.
+    PerfectClears,
+    SuccessRate,
+    /// Last dealt blocks, the newest on the right.
+    BlockHistory,
+    /// Next blocks shown to the player, the first on the left.
+    Next,
+    /// Block in the hold.
+    Hold,
+    /// Grade of the zig-zag pattern built from the floor.
+    SecretGrade,
+    /// Time of the last reached sprint split and its difference to the best sprint.
+    Split,
+    /// Lines cleared ahead or behind the personal best run at the same time.
+    Pace,
+}
+
+impl HudValue {
+    fn label(&self, language: Language) -> String {
+        let key = match self {
+            HudValue::Item => Key::Item,
+            HudValue::Attack => Key::Attack,
+            HudValue::Score => Key::Score,
+            HudValue::Lines => Key::Lines,
+            HudValue::Level => Key::Level,
+            HudValue::Time => Key::Time,
+            HudValue::GarbageLines => Key::Dug,
+            HudValue::Combo => Key::Combo,
+            HudValue::BestCombo => Key::BestCombo,
+            HudValue::PerfectClears => Key::PerfectClears,
+            HudValue::SuccessRate => Key::SuccessRate,
+            HudValue::BlockHistory => Key::BlockHistory,
+            HudValue::Next => Key::Next,
+            HudValue::Hold => Key::Hold,
+            HudValue::SecretGrade => Key::SecretGrade,
+            HudValue::Split => Key::Split,
+            HudValue::Pace => Key::Pace,
+        };
+        format!("{}\n", language.get(key))
+    }
+
+    fn value(
+        &self,
+        player: &Player,
+        session: Option<&PracticeSession>,
+        best_splits: Option<&BestSplits>,
+        ghost: Option<&PbGhost>,
+        language: Language,
+    ) -> String {
+        match self {
+            HudValue::Item => player
+                .game
+                .item()
+                .map_or("-", |item| language.item_name(item))
+                .to_string(),
+            HudValue::Attack => player.game.attack_total().to_string(),
+            HudValue::Score => player.game.score().points.to_string(),
+            HudValue::Lines => player.game.score().lines.to_string(),
+            HudValue::Level => player.game.score().level().to_string(),
+            HudValue::Time => {
+                let seconds = (player.game.score().ticks as f32 * TICK_DURATION) as u32;
+                format!("{}:{:02}", seconds / 60, seconds % 60)
+            }
+            HudValue::GarbageLines => player.game.score().garbage_lines.to_string(),
+            HudValue::Combo => player.game.score().combo.to_string(),
+            HudValue::BestCombo => session
+                .map_or(0, |s| s.best_combo)
+                .max(player.game.score().max_combo)
+                .to_string(),
+            HudValue::PerfectClears => session.map_or("-".to_string(), |s| {
+                format!("{}/{}", s.perfect_clears, s.attempts)
+            }),
+            HudValue::SuccessRate => match session {
+                Some(s) if s.attempts > 0 => {
+                    format!("{}%", s.perfect_clears * 100 / s.attempts)
+                }
+                _ => "-".to_string(),
+            },
+            HudValue::BlockHistory => player
+                .game
+                .block_history()
+                .map(|block_type| format!("{:?}", block_type))
+                .collect(),
+            HudValue::Next => player
+                .game
+                .next_blocks()
+                .map(|block_type| format!("{:?}", block_type))
+                .collect(),
+            HudValue::Hold => player
+                .game
+                .held_block()
+                .map_or("-".to_string(), |block_type| format!("{:?}", block_type)),
+            HudValue::SecretGrade => secret_grade_name(player.game.secret_grade())
+                .unwrap_or("-")
+                .to_string(),
+            HudValue::Split => match player.game.score().last_split() {
+                Some((split, ticks)) => {
+                    let time = ticks as f32 * TICK_DURATION;
+                    // negative differences are ahead of the best sprint
+                    let pace = best_splits
+                        .and_then(|best| best.0.get(split))
+                        .map_or("-".to_string(), |best| format!("{:+.2}", time - best));
+                    format!(
+                        "{} {}:{:05.2}\n{}",
+                        (split as u32 + 1) * SPRINT_SPLIT_LINES,
+                        time as u32 / 60,
+                        time % 60.,
+                        pace
+                    )
+                }
+                None => "-".to_string(),
+            },
+            HudValue::Pace => ghost.map_or("-".to_string(), |ghost| {
+                format!("{:+}", ghost.pace(&player.game))
+            }),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct HudText {
+    player: Entity,
+    value: HudValue,
+}
+
+pub fn spawn_hud_text(
+    commands: &mut Commands,
+    ui: &UI,
+    player: Entity,
+    value: HudValue,
+    left: f32,
+    top: f32,
+) -> Entity {
+    commands
+        .spawn_bundle(
+            TextBundle::from_sections([
+                TextSection::new("", ui.text_style(FONT_SIZE, LABEL_COLOR)),
+                TextSection::new("", ui.text_style(FONT_SIZE, VALUE_COLOR)),
+            ])
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(left),
+                    top: Val::Px(top),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(HudText { player, value })
+        .id()
+}
+
+pub fn update_hud(
+    players: Query<&Player>,
+    language: Res<Language>,
+    session: Option<Res<PracticeSession>>,
+    best_splits: Option<Res<BestSplits>>,
+    ghost: Option<Res<PbGhost>>,
+    mut texts: Query<(&HudText, &mut Text)>,
+) {
+    for (hud_text, mut text) in texts.iter_mut() {
+        if let Ok(player) = players.get(hud_text.player) {
+            text.sections[0].value = hud_text.value.label(*language);
+            text.sections[1].value = hud_text.value.value(
+                player,
+                session.as_deref(),
+                best_splits.as_deref(),
+                ghost.as_deref(),
+                *language,
+            );
+        }
+    }
+}
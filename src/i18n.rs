@@ -0,0 +1,371 @@
+use std::fmt::Display;
+
+use crate::{
+    game::{BotLevel, Item},
+    settings::Settings,
+};
+
+/// Language of the texts shown to the player, selected by the `language` setting and switched
+/// in the menu.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Language {
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    pub fn from_settings(settings: &Settings) -> Self {
+        settings
+            .get("language")
+            .and_then(|code| Self::ALL.into_iter().find(|l| l.code() == code))
+            .unwrap_or(Language::English)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    /// Returns the language following this one in `ALL`.
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|l| l == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Returns the text in this language, `{}` in the text are placeholders for arguments
+    /// filled by `format`.
+    pub fn get(&self, key: Key) -> &'static str {
+        key.texts()[*self as usize]
+    }
+
+    /// Returns the text with placeholders replaced by `args` in order.
+    pub fn format(&self, key: Key, args: &[&dyn Display]) -> String {
+        let mut args = args.iter();
+        let mut parts = self.get(key).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                text += &arg.to_string();
+            }
+            text += part;
+        }
+        text
+    }
+
+    pub fn bot_level_name(&self, level: BotLevel) -> &'static str {
+        self.get(match level {
+            BotLevel::Beginner => Key::BotBeginner,
+            BotLevel::Easy => Key::BotEasy,
+            BotLevel::Medium => Key::BotMedium,
+            BotLevel::Hard => Key::BotHard,
+            BotLevel::Brutal => Key::BotBrutal,
+        })
+    }
+
+    pub fn item_name(&self, item: Item) -> &'static str {
+        self.get(match item {
+            Item::ClearBottomRows => Key::ItemClear,
+            Item::ShuffleQueue => Key::ItemShuffle,
+            Item::SpeedUp => Key::ItemSpeed,
+        })
+    }
+}
+
+/// Identifies a text shown to the player.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Key {
+    Title,
+    DailyChallenge,
+    Best,
+    PressEnter,
+    FumenHint,
+    FumenFailed,
+    ProfileHint,
+    VersusProfileHint,
+    OnlineHint,
+    StatisticsHint,
+    SavesHint,
+    LanguageHint,
+    LanguageName,
+    Demo,
+    PressAnyKey,
+    Statistics,
+    Games,
+    Lines,
+    Tetrises,
+    TSpins,
+    Playtime,
+    Sprint,
+    Item,
+    Attack,
+    Score,
+    Level,
+    Time,
+    Dug,
+    Combo,
+    BestCombo,
+    PerfectClears,
+    SuccessRate,
+    BlockHistory,
+    Next,
+    Hold,
+    PerfectClear,
+    Missed,
+    ResetHint,
+    ItemClear,
+    ItemShuffle,
+    ItemSpeed,
+    Kos,
+    MatchWinner,
+    GameNumber,
+    GameWinner,
+    Draw,
+    Handicap,
+    HandicapHint,
+    Online,
+    HostHint,
+    JoinPrompt,
+    JoinHint,
+    RoomCode,
+    PlayerNumber,
+    You,
+    Ready,
+    NotReady,
+    Waiting,
+    Connecting,
+    ReadyHint,
+    LobbyHandicapHint,
+    StartingIn,
+    Desync,
+    Disconnected,
+    Loading,
+    EditorOn,
+    EditorOff,
+    Saved,
+    ScreenshotFailed,
+    FumenCopied,
+    FumenCopyFailed,
+    SummaryExportFailed,
+    SavedGames,
+    NewSave,
+    LoadHint,
+    SaveHint,
+    SaveFailed,
+    OtherModeSave,
+    Paused,
+    Resume,
+    Restart,
+    SettingsEntry,
+    QuitToMenu,
+    LanguageEntry,
+    ControlsEntry,
+    VersusControlsEntry,
+    Back,
+    QuitPrompt,
+    QuitHint,
+    ResumeHint,
+    VsyncEntry,
+    FpsCapEntry,
+    On,
+    Off,
+    ReduceMotionEntry,
+    HighContrastEntry,
+    MasterVolumeEntry,
+    MusicVolumeEntry,
+    SfxVolumeEntry,
+    Muted,
+    SoundPackEntry,
+    DefaultSoundPack,
+    VoiceVolumeEntry,
+    AnnouncerEntry,
+    SettingsReloaded,
+    BotLevelHint,
+    BotBeginner,
+    BotEasy,
+    BotMedium,
+    BotHard,
+    BotBrutal,
+    CampaignStage,
+    StageHint,
+    GoalLines,
+    GoalDig,
+    GoalDefeat,
+    StageCleared,
+    StageFailed,
+    SecretGrade,
+    SecretGradeResult,
+    Split,
+    Pace,
+    PersonalBest,
+    NewRecord,
+    ReplayPosition,
+    ReplayHint,
+    ViewedReplay,
+    CheckpointHint,
+    CheckpointSet,
+}
+
+impl Key {
+    /// Returns the text in all languages in the order of `Language::ALL`.
+    fn texts(&self) -> [&'static str; 2] {
+        match self {
+            Key::Title => ["TETRIS", "TETRIS"],
+            Key::DailyChallenge => ["DAILY CHALLENGE", "TAGESCHALLENGE"],
+            Key::Best => ["BEST {}", "BESTWERT {}"],
+            Key::PressEnter => ["PRESS ENTER", "ENTER DRÜCKEN"],
+            Key::FumenHint => ["F - PASTE FUMEN", "F - FUMEN EINFÜGEN"],
+            Key::FumenFailed => ["INVALID FUMEN: {}", "UNGÜLTIGES FUMEN: {}"],
+            Key::ProfileHint => ["P - CONTROLS: {}", "P - STEUERUNG: {}"],
+            Key::VersusProfileHint => [
+                "P / SHIFT+P - CONTROLS: {} / {}",
+                "P / UMSCHALT+P - STEUERUNG: {} / {}",
+            ],
+            Key::OnlineHint => ["O - ONLINE", "O - ONLINE"],
+            Key::StatisticsHint => ["S - STATISTICS", "S - STATISTIK"],
+            Key::SavesHint => ["V - SAVED GAMES", "V - SPIELSTÄNDE"],
+            Key::LanguageHint => ["L - LANGUAGE: {}", "L - SPRACHE: {}"],
+            Key::LanguageName => ["ENGLISH", "DEUTSCH"],
+            Key::Demo => ["DEMO", "DEMO"],
+            Key::PressAnyKey => ["PRESS ANY KEY", "BELIEBIGE TASTE DRÜCKEN"],
+            Key::Statistics => ["STATISTICS", "STATISTIK"],
+            Key::Games => ["GAMES", "SPIELE"],
+            Key::Lines => ["LINES", "REIHEN"],
+            Key::Tetrises => ["TETRISES", "TETRISE"],
+            Key::TSpins => ["T-SPINS", "T-SPINS"],
+            Key::Playtime => ["PLAYTIME", "SPIELZEIT"],
+            Key::Sprint => ["SPRINT {}", "SPRINT {}"],
+            Key::Item => ["ITEM", "ITEM"],
+            Key::Attack => ["ATTACK", "ANGRIFF"],
+            Key::Score => ["SCORE", "PUNKTE"],
+            Key::Level => ["LEVEL", "LEVEL"],
+            Key::Time => ["TIME", "ZEIT"],
+            Key::Dug => ["DUG", "GEGRABEN"],
+            Key::Combo => ["COMBO", "COMBO"],
+            Key::BestCombo => ["BEST", "REKORD"],
+            Key::PerfectClears => ["PC", "PC"],
+            Key::SuccessRate => ["RATE", "QUOTE"],
+            Key::BlockHistory => ["HISTORY", "VERLAUF"],
+            Key::Next => ["NEXT", "NÄCHSTE"],
+            Key::Hold => ["HOLD", "HALTEN"],
+            Key::PerfectClear => ["PERFECT CLEAR", "PERFECT CLEAR"],
+            Key::Missed => ["MISSED", "VERFEHLT"],
+            Key::ResetHint => [
+                "R - RESET  BACKSPACE - REWIND",
+                "R - NEUSTART  RÜCKTASTE - ZURÜCKSPULEN",
+            ],
+            Key::ItemClear => ["CLEAR", "LEEREN"],
+            Key::ItemShuffle => ["SHUFFLE", "MISCHEN"],
+            Key::ItemSpeed => ["SPEED", "TEMPO"],
+            Key::Kos => ["P{} KO: {}", "S{} KO: {}"],
+            Key::MatchWinner => ["P{} WINS THE MATCH", "S{} GEWINNT DAS MATCH"],
+            Key::GameNumber => ["GAME {}", "SPIEL {}"],
+            Key::GameWinner => ["P{} WINS", "S{} GEWINNT"],
+            Key::Draw => ["DRAW", "UNENTSCHIEDEN"],
+            Key::Handicap => ["SPD {} GRB {}% NEXT {}", "TMP {} MÜLL {}% NÄCHSTE {}"],
+            Key::HandicapHint => [
+                "1/2/3 - P1 HANDICAP  8/9/0 - P2 HANDICAP",
+                "1/2/3 - S1 HANDICAP  8/9/0 - S2 HANDICAP",
+            ],
+            Key::Online => ["ONLINE", "ONLINE"],
+            Key::HostHint => ["TAB - HOST GAME", "TAB - SPIEL HOSTEN"],
+            Key::JoinPrompt => ["ROOM CODE OR ADDRESS", "RAUMCODE ODER ADRESSE"],
+            Key::JoinHint => ["ENTER - JOIN", "ENTER - BEITRETEN"],
+            Key::RoomCode => ["ROOM CODE {}", "RAUMCODE {}"],
+            Key::PlayerNumber => ["P{}", "S{}"],
+            Key::You => ["YOU", "DU"],
+            Key::Ready => ["READY", "BEREIT"],
+            Key::NotReady => ["NOT READY", "NICHT BEREIT"],
+            Key::Waiting => ["WAITING...", "WARTEN..."],
+            Key::Connecting => ["CONNECTING...", "VERBINDEN..."],
+            Key::ReadyHint => ["ENTER - READY", "ENTER - BEREIT"],
+            Key::LobbyHandicapHint => ["1/2/3 - HANDICAP", "1/2/3 - HANDICAP"],
+            Key::StartingIn => ["STARTING IN {}", "START IN {}"],
+            Key::Desync => ["GAMES OUT OF SYNC", "SPIELE NICHT SYNCHRON"],
+            Key::Disconnected => ["CONNECTION LOST", "VERBINDUNG VERLOREN"],
+            Key::Loading => ["LOADING...", "LADEN..."],
+            Key::EditorOn => ["EDITOR ON", "EDITOR AN"],
+            Key::EditorOff => ["EDITOR OFF", "EDITOR AUS"],
+            Key::Saved => ["SAVED {}", "GESPEICHERT {}"],
+            Key::ScreenshotFailed => ["SCREENSHOT FAILED", "SCREENSHOT FEHLGESCHLAGEN"],
+            Key::FumenCopied => ["FUMEN COPIED", "FUMEN KOPIERT"],
+            Key::FumenCopyFailed => ["FUMEN COPY FAILED", "FUMEN KOPIEREN FEHLGESCHLAGEN"],
+            Key::SummaryExportFailed => [
+                "SUMMARY EXPORT FAILED",
+                "EXPORT DER ZUSAMMENFASSUNG FEHLGESCHLAGEN",
+            ],
+            Key::SavedGames => ["SAVED GAMES", "SPIELSTÄNDE"],
+            Key::NewSave => ["NEW: {}_", "NEU: {}_"],
+            Key::LoadHint => [
+                "ENTER - LOAD  DEL - DELETE\nTAB - BACK",
+                "ENTER - LADEN  ENTF - LÖSCHEN\nTAB - ZURÜCK",
+            ],
+            Key::SaveHint => [
+                "ENTER - SAVE  DEL - DELETE\nTAB - RESUME",
+                "ENTER - SPEICHERN  ENTF - LÖSCHEN\nTAB - FORTSETZEN",
+            ],
+            Key::SaveFailed => ["SAVE FAILED", "SPEICHERN FEHLGESCHLAGEN"],
+            Key::OtherModeSave => ["SAVED IN {}", "GESPEICHERT IN {}"],
+            Key::Paused => ["PAUSED", "PAUSE"],
+            Key::Resume => ["RESUME", "FORTSETZEN"],
+            Key::Restart => ["RESTART", "NEUSTART"],
+            Key::SettingsEntry => ["SETTINGS", "EINSTELLUNGEN"],
+            Key::QuitToMenu => ["QUIT TO MENU", "ZUM MENÜ"],
+            Key::LanguageEntry => ["LANGUAGE: {}", "SPRACHE: {}"],
+            Key::ControlsEntry => ["CONTROLS: {}", "STEUERUNG: {}"],
+            Key::VersusControlsEntry => ["CONTROLS: {} / {}", "STEUERUNG: {} / {}"],
+            Key::Back => ["BACK", "ZURÜCK"],
+            Key::QuitPrompt => ["QUIT THE GAME?", "SPIEL BEENDEN?"],
+            Key::QuitHint => [
+                "ENTER - QUIT  ESC - CANCEL",
+                "ENTER - BEENDEN  ESC - ABBRECHEN",
+            ],
+            Key::ResumeHint => ["R - RESUME LAST RUN", "R - LETZTES SPIEL FORTSETZEN"],
+            Key::VsyncEntry => ["VSYNC: {}", "VSYNC: {}"],
+            Key::FpsCapEntry => ["FPS LIMIT: {}", "FPS-LIMIT: {}"],
+            Key::On => ["ON", "AN"],
+            Key::Off => ["OFF", "AUS"],
+            Key::ReduceMotionEntry => ["REDUCE MOTION: {}", "WENIGER BEWEGUNG: {}"],
+            Key::HighContrastEntry => ["HIGH CONTRAST: {}", "HOHER KONTRAST: {}"],
+            Key::MasterVolumeEntry => ["VOLUME: {}%", "LAUTSTÄRKE: {}%"],
+            Key::MusicVolumeEntry => ["MUSIC: {}%", "MUSIK: {}%"],
+            Key::SfxVolumeEntry => ["SOUND EFFECTS: {}%", "SOUNDEFFEKTE: {}%"],
+            Key::Muted => ["MUTED (M)", "STUMM (M)"],
+            Key::SoundPackEntry => ["SOUNDS: {}", "KLÄNGE: {}"],
+            Key::DefaultSoundPack => ["DEFAULT", "STANDARD"],
+            Key::VoiceVolumeEntry => ["ANNOUNCER VOLUME: {}%", "ANSAGER-LAUTSTÄRKE: {}%"],
+            Key::AnnouncerEntry => ["ANNOUNCER: {}", "ANSAGER: {}"],
+            Key::SettingsReloaded => ["SETTINGS RELOADED", "EINSTELLUNGEN NEU GELADEN"],
+            Key::BotLevelHint => ["A - AI LEVEL: {}", "A - KI-STUFE: {}"],
+            Key::BotBeginner => ["BEGINNER", "ANFÄNGER"],
+            Key::BotEasy => ["EASY", "LEICHT"],
+            Key::BotMedium => ["MEDIUM", "MITTEL"],
+            Key::BotHard => ["HARD", "SCHWER"],
+            Key::BotBrutal => ["BRUTAL", "BRUTAL"],
+            Key::CampaignStage => ["STAGE {}/{}", "STUFE {}/{}"],
+            Key::StageHint => ["LEFT/RIGHT - STAGE", "LINKS/RECHTS - STUFE"],
+            Key::GoalLines => ["CLEAR {} LINES", "{} REIHEN ABRÄUMEN"],
+            Key::GoalDig => ["DIG {} GARBAGE LINES", "{} MÜLLREIHEN ABGRABEN"],
+            Key::GoalDefeat => ["BEAT THE {} AI", "BESIEGE DIE KI ({})"],
+            Key::StageCleared => ["STAGE CLEARED", "STUFE GESCHAFFT"],
+            Key::StageFailed => ["STAGE FAILED", "STUFE VERLOREN"],
+            Key::SecretGrade => ["GRADE", "RANG"],
+            Key::SecretGradeResult => ["SECRET GRADE {}", "GEHEIMER RANG {}"],
+            Key::Split => ["SPLIT", "ZWISCHENZEIT"],
+            Key::Pace => ["PACE", "TEMPO"],
+            Key::PersonalBest => ["PERSONAL BEST", "PERSÖNLICHE BESTLEISTUNG"],
+            Key::NewRecord => ["NEW RECORD", "NEUER REKORD"],
+            Key::ReplayPosition => ["REPLAY {} / {}  x{}", "REPLAY {} / {}  x{}"],
+            Key::ReplayHint => [
+                "SPACE - PLAY/PAUSE  UP/DOWN - SPEED  ,/. - STEP  LEFT/RIGHT - JUMP",
+                "LEERTASTE - ABSPIELEN/PAUSE  HOCH/RUNTER - TEMPO  ,/. - SCHRITT  LINKS/RECHTS - SPRINGEN",
+            ],
+            Key::ViewedReplay => ["REPLAY: {}", "REPLAY: {}"],
+            Key::CheckpointHint => ["C - CHECKPOINT", "C - CHECKPUNKT"],
+            Key::CheckpointSet => ["CHECKPOINT SET", "CHECKPUNKT GESETZT"],
+        }
+    }
+}
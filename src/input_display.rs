@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    game::{Input as _, ReplayInput},
+    player::Player,
+    GameEntity, InputPlayed, MARGIN_SIZE, PLAYER_WIDTH, UI,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::I;
+const FONT_SIZE: f32 = 14.;
+const PRESSED_COLOR: Color = Color::WHITE;
+const RELEASED_COLOR: Color = Color::DARK_GRAY;
+const HISTORY_COLOR: Color = Color::GRAY;
+/// Number of the last presses shown in the history.
+const HISTORY_LEN: usize = 12;
+
+/// Symbols of the actions in the order they are shown.
+const ACTIONS: [(&str, fn(&ReplayInput) -> bool); 7] = [
+    ("←", ReplayInput::move_left),
+    ("→", ReplayInput::move_right),
+    ("↓", ReplayInput::fast_drop),
+    ("↻", ReplayInput::rotate),
+    ("⇓", ReplayInput::instant_drop),
+    ("◆", ReplayInput::use_item),
+    ("⇄", ReplayInput::hold),
+];
+
+/// Text over the bottom of the board showing the actions pressed on the last tick and the
+/// history of the presses, the newest on the left.
+#[derive(Component)]
+pub struct InputDisplay {
+    player: Entity,
+    last_input: ReplayInput,
+    history: VecDeque<&'static str>,
+}
+
+/// Shows or hides the input display of the players controlled by keys.
+pub fn toggle_input_display(
+    mut commands: Commands,
+    ui: Res<UI>,
+    keys: Res<Input<KeyCode>>,
+    players: Query<(Entity, &Player)>,
+    displays: Query<Entity, With<InputDisplay>>,
+) {
+    if !keys.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    if !displays.is_empty() {
+        for display in displays.iter() {
+            commands.entity(display).despawn();
+        }
+        return;
+    }
+
+    for (player_entity, player) in players.iter().filter(|(_, p)| p.bot.is_none()) {
+        let mut sections = ACTIONS
+            .iter()
+            .map(|(symbol, _)| {
+                TextSection::new(
+                    format!("{} ", symbol),
+                    ui.text_style(FONT_SIZE, RELEASED_COLOR),
+                )
+            })
+            .collect::<Vec<_>>();
+        sections.push(TextSection::new(
+            "\n",
+            ui.text_style(FONT_SIZE, HISTORY_COLOR),
+        ));
+        commands
+            .spawn_bundle(TextBundle::from_sections(sections).with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(PLAYER_WIDTH * player.index as f32 + MARGIN_SIZE * 1.5),
+                    bottom: Val::Px(MARGIN_SIZE * 1.5),
+                    ..default()
+                },
+                ..default()
+            }))
+            .insert(InputDisplay {
+                player: player_entity,
+                last_input: ReplayInput::default(),
+                history: VecDeque::with_capacity(HISTORY_LEN),
+            })
+            .insert(GameEntity);
+    }
+}
+
+/// Updates the input displays with the inputs played by the games.
+pub fn update_input_display(
+    mut inputs_played: EventReader<InputPlayed>,
+    mut displays: Query<(&mut InputDisplay, &mut Text)>,
+) {
+    for input_played in inputs_played.iter() {
+        for (mut display, mut text) in displays.iter_mut() {
+            if display.player != input_played.player {
+                continue;
+            }
+            let input = input_played.input;
+            for (i, (symbol, active)) in ACTIONS.iter().enumerate() {
+                let pressed = active(&input);
+                if pressed && !active(&display.last_input) {
+                    if display.history.len() == HISTORY_LEN {
+                        display.history.pop_back();
+                    }
+                    display.history.push_front(symbol);
+                }
+                text.sections[i].style.color = if pressed {
+                    PRESSED_COLOR
+                } else {
+                    RELEASED_COLOR
+                };
+            }
+            display.last_input = input;
+            let history = display.history.iter().copied().collect::<Vec<_>>();
+            text.sections[ACTIONS.len()].value = format!("\n{}", history.join(" "));
+        }
+    }
+}
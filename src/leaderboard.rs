@@ -0,0 +1,164 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::Cli,
+    game::GameRules,
+    i18n::{Key, Language},
+    menu::MenuEntity,
+    mode_name,
+    player::Player,
+    stack_visibility::StackVisibility,
+    RunFinished, MARGIN_SIZE, TICK_DURATION, UI,
+};
+
+const TOP_ENTRIES_COUNT: usize = 5;
+const DEFAULT_PLAYER_NAME: &str = "anonymous";
+const FONT_SIZE: f32 = 14.;
+const TEXT_COLOR: Color = Color::GRAY;
+
+/// Result of a finished single player game.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Entry {
+    pub player: String,
+    pub mode: String,
+    pub score: u32,
+    /// Duration of the game in seconds.
+    pub time: f32,
+    /// Hash of the replay, the server may ask for the replay file to verify the result.
+    pub replay_hash: String,
+}
+
+/// Online leaderboard enabled by `--leaderboard=<url>`. Requests are sent from background threads
+/// so the game doesn't stall while waiting for the server.
+pub struct Leaderboard {
+    url: String,
+    player: String,
+    /// Top entries of the current mode, `None` until they are fetched.
+    top: Arc<Mutex<Option<Vec<Entry>>>>,
+}
+
+impl Leaderboard {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        let url = cli.leaderboard.as_ref()?;
+        let player = cli.player.as_deref().unwrap_or(DEFAULT_PLAYER_NAME);
+
+        Some(Self {
+            url: url.trim_end_matches('/').to_string(),
+            player: player.to_string(),
+            top: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    fn submit(&self, entry: Entry) {
+        let url = format!("{}/scores", self.url);
+        thread::spawn(move || {
+            if let Err(e) = ureq::post(&url).send_json(&entry) {
+                error!("failed to submit the result to the leaderboard: {}", e);
+            }
+        });
+    }
+
+    fn fetch_top(&self, mode: String) {
+        let url = format!("{}/scores", self.url);
+        let top = self.top.clone();
+        *top.lock().unwrap() = None;
+        thread::spawn(move || {
+            let entries = ureq::get(&url)
+                .query("mode", &mode)
+                .query("limit", &TOP_ENTRIES_COUNT.to_string())
+                .call()
+                .map_err(|e| e.to_string())
+                .and_then(|response| {
+                    response
+                        .into_json::<Vec<Entry>>()
+                        .map_err(|e| e.to_string())
+                });
+            let entries = entries.unwrap_or_else(|e| {
+                error!("failed to fetch the leaderboard: {}", e);
+                vec![]
+            });
+            *top.lock().unwrap() = Some(entries);
+        });
+    }
+}
+
+pub fn submit_finished_runs(
+    mut runs_finished: EventReader<RunFinished>,
+    players: Query<&Player>,
+    leaderboard: Res<Leaderboard>,
+    rules: Res<GameRules>,
+    visibility: Res<StackVisibility>,
+) {
+    for run in runs_finished.iter() {
+        if let Ok(player) = players.get(run.player) {
+            let score = player.game.score();
+            // results which the replay doesn't play again were tampered with
+            if let Err(e) = player.game.replay().verify_score(&score) {
+                error!("rejected the leaderboard result: {}", e);
+                continue;
+            }
+            let mode = mode_name(&rules, &visibility);
+            // the hash of the replay file saved with the mode as the preset
+            let replay_hash = player.game.replay().clone().with_preset(&mode).hash();
+            leaderboard.submit(Entry {
+                player: leaderboard.player.clone(),
+                mode,
+                score: score.points,
+                time: score.ticks as f32 * TICK_DURATION,
+                replay_hash: format!("{:016x}", replay_hash),
+            });
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct TopText;
+
+pub fn spawn_top_text(
+    mut commands: Commands,
+    ui: Res<UI>,
+    leaderboard: Res<Leaderboard>,
+    rules: Res<GameRules>,
+    visibility: Res<StackVisibility>,
+) {
+    leaderboard.fetch_top(mode_name(&rules, &visibility));
+    commands
+        .spawn_bundle(
+            TextBundle::from_section("", ui.text_style(FONT_SIZE, TEXT_COLOR)).with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(MARGIN_SIZE),
+                    bottom: Val::Px(MARGIN_SIZE),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(TopText)
+        .insert(MenuEntity);
+}
+
+pub fn update_top_text(
+    leaderboard: Res<Leaderboard>,
+    language: Res<Language>,
+    mut texts: Query<&mut Text, With<TopText>>,
+) {
+    let top = leaderboard.top.lock().unwrap();
+    let value = match top.as_ref() {
+        Some(entries) => entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| format!("{}. {:<12} {:>8}\n", i + 1, entry.player, entry.score))
+            .collect(),
+        None => language.get(Key::Loading).to_string(),
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
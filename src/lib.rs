@@ -0,0 +1,7 @@
+#![feature(unboxed_closures, fn_traits)]
+
+//! Game logic independent of the Bevy frontend, shared by the game and terminal binaries, the
+//! benchmarks and the Python bindings. It builds without Bevy when the default `frontend`
+//! feature is turned off.
+
+pub mod game;
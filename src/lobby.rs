@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::GameRules,
+    i18n::{Key, Language},
+    menu::spawn_menu_text,
+    netplay::{Netplay, DEFAULT_PORT},
+    profiles::Profiles,
+    versus::Handicap,
+    window_width, AppState, GameOptions, UI, WINDOW_HEIGHT,
+};
+
+const HOST_KEY: KeyCode = KeyCode::Tab;
+/// Joins the game on the typed code or address, then toggles whether the player is ready.
+const CONFIRM_KEY: KeyCode = KeyCode::Return;
+/// Keys cycling the speed level, garbage percentage and preview count of the local player.
+const HANDICAP_KEYS: [KeyCode; 3] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+const COUNTDOWN_DURATION: f32 = 3.;
+const MAX_LINE_LEN: usize = 64;
+
+/// Screen where online games are hosted or joined and players get ready, the connection itself
+/// is the `Netplay` resource.
+#[derive(Default)]
+pub struct Lobby {
+    /// Room code or address typed by the player.
+    line: String,
+    /// Why the last attempt to host or join failed.
+    error: Option<String>,
+    countdown: Option<Timer>,
+}
+
+#[derive(Component)]
+pub struct LobbyText;
+
+pub fn setup_lobby(
+    mut commands: Commands,
+    ui: Res<UI>,
+    mut chars: ResMut<Events<ReceivedCharacter>>,
+) {
+    // the key which opened the lobby isn't a part of the address
+    chars.clear();
+    let text = spawn_menu_text(&mut commands, &ui, "");
+    commands.entity(text).insert(LobbyText);
+    commands.insert_resource(Lobby::default());
+}
+
+pub fn update_lobby(
+    mut commands: Commands,
+    mut lobby: ResMut<Lobby>,
+    netplay: Option<ResMut<Netplay>>,
+    keys: Res<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut options: ResMut<GameOptions>,
+    rules: Res<GameRules>,
+    profiles: Res<Profiles>,
+    mut windows: ResMut<Windows>,
+    mut state: ResMut<State<AppState>>,
+    language: Res<Language>,
+    time: Res<Time>,
+    mut texts: Query<&mut Text, With<LobbyText>>,
+) {
+    let mut netplay = match netplay {
+        Some(netplay) => netplay,
+        None => {
+            for c in chars.iter() {
+                if c.char == '\u{8}' {
+                    // backspace on the empty line leaves the lobby
+                    if lobby.line.pop().is_none() {
+                        state.set(AppState::Menu).unwrap();
+                        return;
+                    }
+                } else if !c.char.is_control() && lobby.line.len() < MAX_LINE_LEN {
+                    lobby.line.push(c.char);
+                }
+            }
+            let result = if keys.just_pressed(HOST_KEY) {
+                Some(Netplay::host(DEFAULT_PORT))
+            } else if keys.just_pressed(CONFIRM_KEY) && !lobby.line.trim().is_empty() {
+                Some(Netplay::join(lobby.line.trim()))
+            } else {
+                None
+            };
+            match result {
+                Some(Ok(netplay)) => commands.insert_resource(netplay),
+                Some(Err(e)) => lobby.error = Some(e.to_string()),
+                None => {}
+            }
+
+            let text = format!(
+                "{}\n\n{}\n\n{}\n> {}_\n{}\n\n{}",
+                language.get(Key::Online),
+                language.get(Key::HostHint),
+                language.get(Key::JoinPrompt),
+                lobby.line,
+                language.get(Key::JoinHint),
+                lobby.error.as_deref().unwrap_or_default()
+            );
+            for mut lobby_text in texts.iter_mut() {
+                lobby_text.sections[0].value = text.clone();
+            }
+            return;
+        }
+    };
+
+    // the local player plays with the single player controls
+    netplay.set_auto_repeat(profiles.single().auto_repeat);
+    if keys.just_pressed(CONFIRM_KEY) && netplay.is_connected() {
+        let ready = !netplay.readiness().0;
+        netplay.set_ready(ready);
+    }
+    if let Some(key) = HANDICAP_KEYS.iter().position(|&k| keys.just_pressed(k)) {
+        let mut handicap = netplay.local_handicap();
+        match key {
+            0 => handicap.next_speed_level(),
+            1 => handicap.next_garbage_percent(),
+            _ => handicap.next_preview_count(),
+        }
+        netplay.set_handicap(handicap);
+    }
+    if netplay.is_started() && lobby.countdown.is_none() {
+        lobby.countdown = Some(Timer::from_seconds(COUNTDOWN_DURATION, false));
+    }
+    if let Some(countdown) = &mut lobby.countdown {
+        if countdown.tick(time.delta()).finished() {
+            // the match is always between the two peers
+            options.player_count = 2;
+            options.handicaps.resize(2, Handicap::default());
+            options.daily = None;
+            options.practice = None;
+            if let Some(window) = windows.get_primary_mut() {
+                window.set_resolution(window_width(options.player_count, &rules), WINDOW_HEIGHT);
+            }
+            state.set(AppState::Game).unwrap();
+            return;
+        }
+    }
+
+    let mut text = format!("{}\n\n", language.get(Key::Online));
+    if let Some(code) = netplay.room_code() {
+        text += &format!("{}\n\n", language.format(Key::RoomCode, &[&code]));
+    }
+    let (local_ready, remote_ready) = netplay.readiness();
+    let handicaps = netplay.handicaps();
+    for index in 0..2 {
+        let local = index == netplay.local_index();
+        let status = if !netplay.is_connected() {
+            match (local, netplay.is_host()) {
+                (true, _) => language.get(Key::NotReady),
+                (false, true) => language.get(Key::Waiting),
+                (false, false) => language.get(Key::Connecting),
+            }
+        } else if (local && local_ready) || (!local && remote_ready) {
+            language.get(Key::Ready)
+        } else {
+            language.get(Key::NotReady)
+        };
+        let name = match netplay.peer_address() {
+            _ if local => language.get(Key::You).to_string(),
+            Some(address) => address.to_string(),
+            None => "-".to_string(),
+        };
+        text += &format!(
+            "{} {}  {}\n{}\n",
+            language.format(Key::PlayerNumber, &[&(index + 1)]),
+            name,
+            status,
+            handicaps[index].describe(*language)
+        );
+    }
+    text += "\n";
+    match &lobby.countdown {
+        Some(countdown) => {
+            let seconds_left = (countdown.duration() - countdown.elapsed())
+                .as_secs_f32()
+                .ceil();
+            text += &language.format(Key::StartingIn, &[&seconds_left]);
+        }
+        None if netplay.is_connected() => {
+            text += &format!(
+                "{}\n{}",
+                language.get(Key::ReadyHint),
+                language.get(Key::LobbyHandicapHint)
+            );
+        }
+        None => {}
+    }
+    for mut lobby_text in texts.iter_mut() {
+        lobby_text.sections[0].value = text.clone();
+    }
+}
@@ -1,97 +1,646 @@
-#![feature(unboxed_closures, fn_traits)]
-
 use std::collections::HashMap;
 
 use bevy::{math::vec3, prelude::*, sprite::Anchor, time::FixedTimestep};
-use game::Id;
+use game::Renderer;
+use hud::HudValue;
+use player::{Player, PointEntities, RawInput};
+use renderer::BevyRenderer;
+use settings::Settings;
+use stack_visibility::{LockedAt, StackVisibility};
+use tetris::game;
+use versus::Match;
 
-mod game;
+mod accessibility;
+mod actions;
+mod announcer;
+mod archive;
+mod audio;
+mod background;
+mod bot_protocol;
+mod campaign;
+mod cells;
+mod chat;
+mod cli;
+mod clipboard;
+mod console;
+mod daily;
+mod diagnostics;
+#[cfg(feature = "discord")]
+mod discord;
+mod editor;
+mod frame_rate;
+mod fumen;
+mod headless;
+mod heatmap;
+mod histogram;
+mod hud;
+mod i18n;
+mod input_display;
+#[cfg(feature = "leaderboard")]
+mod leaderboard;
+mod lobby;
+mod menu;
+mod netplay;
+mod paths;
+mod pause;
+mod pb_ghost;
+mod player;
+mod practice;
+mod profiles;
+mod quit;
+mod raster;
+mod records;
+mod reload;
+mod renderer;
+mod replay_viewer;
+mod replays;
+mod saves;
+mod scaling;
+mod screenshot;
+mod settings;
+mod stack_visibility;
+#[cfg(feature = "state-server")]
+mod state_server;
+mod stats;
+mod summary;
+mod toast;
+mod versus;
 
 const UNIT_PX: f32 = 20.;
 const BORDER_SIZE: f32 = 2.;
 const WINDOW_HEIGHT: f32 = 440.;
-const WINDOW_WIDTH: f32 = 320.;
+/// Width of the window area occupied by one player.
+const PLAYER_WIDTH: f32 = 320.;
 const MARGIN_SIZE: f32 = 20.;
+const HUD_LINE_HEIGHT: f32 = 60.;
+
+const FONTS_DIR: &str = "fonts";
+const DEFAULT_FONT: &str = "DejaVuSansMono-Bold.ttf";
+/// Size of the small texts like the HUD, larger texts are scaled with it.
+const DEFAULT_FONT_SIZE: f32 = 14.;
 
 // colors
 const BG_COLOR: Color = Color::BLACK;
 const BORDER_COLOR: Color = Color::WHITE;
+const ITEM_POINT_COLOR: Color = Color::WHITE;
+const GARBAGE_POINT_COLOR: Color = Color::GRAY;
 
 const TICK_DURATION: f32 = 1. / 60.;
 
-#[derive(Default)]
-struct RawInput {
-    move_left: bool,
-    move_right: bool,
-    rotate: bool,
-    fast_drop: bool,
-    instant_drop: bool,
-}
+const DEFAULT_BEST_OF: usize = 3;
+/// Seconds between garbage rows of the drill.
+const DEFAULT_DRILL_INTERVAL: f32 = 3.;
 
-impl RawInput {
-    fn reset(&mut self) {
-        *self = RawInput::default();
-    }
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum AppState {
+    Menu,
+    Game,
+    /// Computer plays the game while the menu is idle.
+    Demo,
+    /// Lifetime statistics screen.
+    Stats,
+    /// Hosting or joining an online game.
+    Lobby,
+    /// Save slots, a suspended game is saved to one of them, otherwise one is loaded.
+    Saves,
 }
 
-impl game::Input for RawInput {
-    fn move_left(&self) -> bool {
-        self.move_left
-    }
+struct UI {
+    font: Handle<Font>,
+    /// Scale of all font sizes, set by the `font_size` setting.
+    font_scale: f32,
+}
 
-    fn move_right(&self) -> bool {
-        self.move_right
+impl UI {
+    fn text_style(&self, font_size: f32, color: Color) -> TextStyle {
+        TextStyle {
+            font: self.font.clone(),
+            font_size: font_size * self.font_scale,
+            color,
+        }
     }
+}
 
-    fn rotate(&self) -> bool {
-        self.rotate
-    }
+struct GameOptions {
+    /// Number of players playing against each other.
+    player_count: usize,
+    /// Number of games in the versus match.
+    best_of: usize,
+    /// Day of the daily challenge, all players get the same blocks on the same day.
+    daily: Option<u64>,
+    /// Left and right keys are swapped, meant to be used with mirrored blocks.
+    mirror_input: bool,
+    /// Single player practice preset.
+    practice: Option<practice::Practice>,
+    /// Handicaps of the local versus players, indexed by player.
+    handicaps: Vec<versus::Handicap>,
+    /// The second versus player is the bot, a sparring partner of the single local player.
+    bot_opponent: bool,
+    /// Difficulty of the bot opponent.
+    bot_level: game::BotLevel,
+    /// Single player campaign, its stages set the rules and the bot opponent of every game.
+    campaign: Option<campaign::Campaign>,
+    /// Replay played in the viewer instead of the single player game.
+    viewed_replay: Option<game::Replay>,
+    /// Seed of the games given by `--seed`, they are random otherwise.
+    seed: Option<u64>,
+}
 
-    fn fast_drop(&self) -> bool {
-        self.fast_drop
-    }
+/// Entity which is despawned when the game is left.
+#[derive(Component)]
+struct GameEntity;
 
-    fn instant_drop(&self) -> bool {
-        self.instant_drop
-    }
+#[derive(Component)]
+struct PointComponent {
+    player: Entity,
+    id: game::Id,
+    /// Color of the point before the display options are applied.
+    color: Color,
 }
 
-type PointEntities = HashMap<Id, Entity>;
+#[derive(Component)]
+struct BlockComponent(game::Id);
 
-struct UI {
-    board: Entity,
-    points: PointEntities,
+/// Sent when a single player game of a human player is over.
+struct RunFinished {
+    player: Entity,
 }
 
-#[derive(Component)]
-struct PointComponent(game::Id);
+/// Sent for every tick with the input the player's game played on it.
+struct InputPlayed {
+    player: Entity,
+    input: game::ReplayInput,
+}
 
-#[derive(Component)]
-struct BlockComponent(game::Id);
+/// Sent for every change of the players' games on a tick.
+struct TickChanged {
+    /// Player whose game changed.
+    player: Entity,
+    change: game::TickChange,
+}
 
 fn main() {
-    App::new()
-        .insert_resource(ClearColor(BG_COLOR))
+    let cli = cli::Cli::parse_args();
+
+    let export = (cli.export_gif.as_ref())
+        .map(|path| (path, replays::ExportFormat::Gif))
+        .or_else(|| {
+            (cli.export_png.as_ref()).map(|path| (path, replays::ExportFormat::PngSequence))
+        });
+    if let Some((path, format)) = export {
+        match replays::export_replay(path, format) {
+            Ok(output) => println!("exported to {}", output.display()),
+            Err(e) => eprintln!("failed to export {}: {}", path.display(), e),
+        }
+        return;
+    }
+    if let Some(path) = &cli.export_profile {
+        match archive::export_profile(path) {
+            Ok(()) => println!("exported to {}", path.display()),
+            Err(e) => eprintln!("failed to export the profile: {}", e),
+        }
+        return;
+    }
+    if let Some(path) = &cli.import_profile {
+        match archive::import_profile(path) {
+            Ok(files) => println!("imported {}", files.join(", ")),
+            Err(e) => eprintln!("failed to import {}: {}", path.display(), e),
+        }
+        return;
+    }
+    if let Some(path) = &cli.upgrade_replay {
+        match replays::upgrade_replay(path) {
+            Ok(()) => println!("upgraded {}", path.display()),
+            Err(e) => eprintln!("failed to upgrade {}: {}", path.display(), e),
+        }
+        return;
+    }
+    if let Some(path) = &cli.analyze_replay {
+        match replays::analyze_replay(path) {
+            Ok(report) => print!("{}", report),
+            Err(e) => eprintln!("failed to analyze {}: {}", path.display(), e),
+        }
+        return;
+    }
+
+    let netplay = match netplay::Netplay::from_cli(&cli) {
+        Some(Ok(netplay)) => Some(netplay),
+        Some(Err(e)) => {
+            eprintln!("failed to start the network game: {}", e);
+            return;
+        }
+        None => None,
+    };
+    // the bot opponent plays only local matches
+    let bot_opponent = netplay.is_none() && cli.vs_ai;
+    let versus = netplay.is_some() || bot_opponent || cli.versus;
+    let player_count = if versus { 2 } else { 1 };
+    let best_of = cli.best_of.unwrap_or(DEFAULT_BEST_OF);
+    // the viewed replay is played by its own rules instead of a game of any mode
+    let viewed_replay = match &cli.replay {
+        Some(path) if player_count == 1 => match replays::load_replay(path) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                eprintln!("failed to load {}: {}", path.display(), e);
+                return;
+            }
+        },
+        _ => None,
+    };
+    let single = player_count == 1 && viewed_replay.is_none();
+    let daily = if single && cli.daily {
+        Some(daily::today())
+    } else {
+        None
+    };
+    let practice = if single {
+        practice::Practice::from_cli(&cli)
+    } else {
+        None
+    };
+    // the campaign is a mode of its own, its stages set the rules
+    let campaign = (single && daily.is_none() && practice.is_none() && cli.campaign)
+        .then(campaign::Campaign::load);
+    let mut rules = match &viewed_replay {
+        Some(replay) => replay.rules(),
+        None => game_rules_from_cli(&cli),
+    };
+    // co-op pilots share the board of a single player
+    rules.coop &= player_count == 1;
+
+    if cli.bot_protocol {
+        if let Err(e) = bot_protocol::run(rules, daily.map(daily::seed).or(cli.seed)) {
+            eprintln!("failed to communicate with the bot: {}", e);
+        }
+        return;
+    }
+    if cli.headless {
+        headless::run(
+            rules,
+            daily.map(daily::seed).or(cli.seed),
+            viewed_replay.as_ref(),
+        );
+        return;
+    }
+
+    if let Err(e) = paths::create_dirs() {
+        eprintln!("failed to create the config and data directories: {}", e);
+    }
+    let errors = paths::migrate_old_install(
+        settings::SETTINGS_FILE,
+        &[settings::SETTINGS_FILE],
+        &[
+            records::RECORDS_FILE,
+            stats::STATS_FILE,
+            campaign::CAMPAIGN_FILE,
+            replays::REPLAYS_DIR,
+            saves::SAVES_DIR,
+            screenshot::SCREENSHOTS_DIR,
+            summary::SUMMARIES_DIR,
+        ],
+    );
+    for e in errors {
+        eprintln!("{}", e);
+    }
+
+    let settings = settings::Settings::load(
+        &cli.settings,
+        cli.config
+            .clone()
+            .unwrap_or_else(|| paths::config_path(settings::SETTINGS_FILE)),
+    );
+    let profiles = profiles::Profiles::from_settings(&settings);
+
+    let frame_rate = frame_rate::FrameRate::from_settings(&settings);
+
+    let mut app = App::new();
+    // the asset server reads its settings when the default plugins are added
+    add_asset_hot_reload(&mut app);
+    app.insert_resource(ClearColor(BG_COLOR))
         .insert_resource(WindowDescriptor {
-            width: WINDOW_WIDTH,
+            width: window_width(player_count, &rules),
             height: WINDOW_HEIGHT,
             resizable: false,
+            present_mode: frame_rate.present_mode(),
             ..default()
         })
-        .init_resource::<RawInput>()
+        .insert_resource(frame_rate)
+        .insert_resource(accessibility::ReduceMotion::from_settings(&settings))
+        .insert_resource(accessibility::HighContrast::from_settings(&settings))
+        .insert_resource(cells::CellStyle::from_settings(&settings))
+        .insert_resource(cells::CellTexture::default())
+        .insert_resource(accessibility::HighContrastTextures::default())
+        .insert_resource(audio::Volume::from_settings(&settings))
+        .insert_resource(audio::SoundPack::from_settings(&settings))
+        .insert_resource(audio::Sounds::default())
+        .insert_resource(announcer::Announcer::from_settings(&settings))
+        .insert_resource(i18n::Language::from_settings(&settings))
+        .insert_resource(settings)
+        .insert_resource(scaling::UiScale::from_cli(&cli))
+        .insert_resource(StackVisibility::from_cli(&cli))
+        .insert_resource(background::BackgroundStyle::from_cli(&cli))
+        .insert_resource(rules)
+        .insert_resource(GameOptions {
+            player_count,
+            best_of,
+            daily,
+            mirror_input: cli.mirror_input,
+            practice,
+            handicaps: vec![versus::Handicap::default(); player_count],
+            bot_opponent,
+            bot_level: cli.ai_level.unwrap_or_default(),
+            campaign,
+            viewed_replay,
+            seed: cli.seed,
+        })
         .add_plugins(DefaultPlugins)
+        .add_plugin(bevy::diagnostic::FrameTimeDiagnosticsPlugin)
+        .add_plugin(bevy::diagnostic::EntityCountDiagnosticsPlugin)
+        .add_event::<RunFinished>()
+        .add_event::<InputPlayed>()
+        .add_event::<TickChanged>()
+        .add_event::<reload::SettingsReloaded>()
+        .insert_resource(stats::LifetimeStats::load())
+        // games hosted or joined by the arguments start in the lobby
+        .add_state(if netplay.is_some() {
+            AppState::Lobby
+        } else {
+            AppState::Menu
+        })
+        .insert_resource(profiles.action_maps())
+        .insert_resource(profiles)
+        .insert_resource(console::Console::default())
+        .insert_resource(chat::Chat::default())
+        .insert_resource(editor::Editor::default())
+        .insert_resource(pause::Pause::default())
+        .insert_resource(quit::QuitDialog::default())
         .add_startup_system(setup)
-        .add_system_to_stage(CoreStage::PreUpdate, check_input)
+        .add_startup_system(diagnostics::setup_diagnostics)
+        .add_startup_system_to_stage(StartupStage::PostStartup, console::setup_console)
+        .add_system_set(SystemSet::on_enter(AppState::Menu).with_system(menu::setup_menu))
+        .add_system_set(SystemSet::on_update(AppState::Menu).with_system(menu::update_menu))
+        .add_system_set(SystemSet::on_exit(AppState::Menu).with_system(menu::cleanup_menu))
+        .add_system_set(
+            SystemSet::on_enter(AppState::Game)
+                .with_system(setup_game)
+                .with_system(background::spawn_background),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(versus::update_match)
+                .with_system(chat::update_chat)
+                .with_system(practice::update_practice)
+                .with_system(campaign::update_campaign)
+                .with_system(saves::suspend_game)
+                .with_system(saves::resume_saved_game)
+                .with_system(replay_viewer::update_replay_viewer)
+                .with_system(pause::update_pause)
+                .with_system(pause::pause_on_focus_loss),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Game)
+                .with_system(cleanup_game)
+                .with_system(pause::cleanup_pause),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Demo).with_system(menu::setup_demo))
+        .add_system_set(SystemSet::on_update(AppState::Demo).with_system(menu::update_demo))
+        .add_system_set(
+            SystemSet::on_exit(AppState::Demo)
+                .with_system(menu::cleanup_menu)
+                .with_system(cleanup_game),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Stats).with_system(stats::setup_stats_screen))
+        .add_system_set(
+            SystemSet::on_update(AppState::Stats).with_system(stats::update_stats_screen),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Stats).with_system(menu::cleanup_menu))
+        .add_system_set(SystemSet::on_enter(AppState::Lobby).with_system(lobby::setup_lobby))
+        .add_system_set(SystemSet::on_update(AppState::Lobby).with_system(lobby::update_lobby))
+        .add_system_set(SystemSet::on_exit(AppState::Lobby).with_system(menu::cleanup_menu))
+        .add_system_set(SystemSet::on_enter(AppState::Saves).with_system(saves::setup_save_screen))
+        .add_system_set(
+            SystemSet::on_update(AppState::Saves).with_system(saves::update_save_screen),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Saves)
+                .with_system(menu::cleanup_menu)
+                .with_system(saves::cleanup_save_screen),
+        )
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            console::update_console.before(player::check_input),
+        )
+        .add_system_to_stage(CoreStage::PreUpdate, player::check_input)
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            quit::update_quit_dialog.after(bevy::input::InputSystem),
+        )
         .add_system_set_to_stage(
             CoreStage::PreUpdate,
             SystemSet::new()
                 .with_run_criteria(FixedTimestep::step(TICK_DURATION as f64))
+                .with_system(practice::rewind_practice.before(tick))
                 .with_system(tick),
         )
         .add_system(update_block_points)
         .add_system(update_board_points)
-        .add_system(bevy::window::close_on_esc)
-        .run()
+        .add_system(hud::update_hud)
+        .add_system(background::update_background)
+        .add_system(cells::update_cell_texture)
+        .add_system(accessibility::update_high_contrast_textures)
+        .add_system(
+            accessibility::style_points
+                .after(cells::update_cell_texture)
+                .after(accessibility::update_high_contrast_textures),
+        )
+        .add_system(accessibility::update_ghosts)
+        .add_system(background::fit_background_image)
+        .add_system(histogram::update_histogram)
+        .add_system(heatmap::toggle_heatmap)
+        .add_system(pb_ghost::update_pb_ghost)
+        .add_system(input_display::toggle_input_display)
+        .add_system(input_display::update_input_display)
+        .add_system(diagnostics::measure_tick_rate)
+        .add_system(diagnostics::toggle_diagnostics)
+        .add_system(diagnostics::update_diagnostics)
+        .add_system(summary::export_summary)
+        .add_system(editor::update_editor)
+        .add_system(replays::save_finished_runs)
+        .add_system(records::save_finished_runs)
+        .add_system(records::hide_new_records)
+        .add_system(stats::update_stats)
+        .add_system(screenshot::take_screenshot)
+        .add_system(fumen::export_fumen)
+        .add_system(toast::update_toasts)
+        .add_system(netplay::update_netplay)
+        .add_system(scaling::apply_ui_scale)
+        .add_system(reload::watch_settings)
+        .add_system(reload::apply_reloaded_settings.after(reload::watch_settings))
+        .add_system(frame_rate::apply_present_mode)
+        .add_system(audio::load_sound_pack)
+        .add_system(audio::apply_volume.after(audio::load_sound_pack))
+        .add_system(audio::restart_reloaded_music)
+        .add_system(audio::play_sound_effects)
+        .add_system(audio::toggle_mute)
+        .add_system(announcer::load_voices)
+        .add_system(announcer::announce)
+        .add_system_to_stage(CoreStage::Last, frame_rate::limit_frame_rate)
+        .add_system_to_stage(CoreStage::Last, saves::autosave_on_exit);
+    add_leaderboard(&mut app, &cli);
+    add_state_server(&mut app, &cli);
+    add_discord_presence(&mut app);
+    add_debug_ui(&mut app);
+    if let Some(netplay) = netplay {
+        app.insert_resource(netplay);
+    }
+    app.run();
+}
+
+#[cfg(feature = "leaderboard")]
+fn add_leaderboard(app: &mut App, cli: &cli::Cli) {
+    if let Some(leaderboard) = leaderboard::Leaderboard::from_cli(cli) {
+        app.insert_resource(leaderboard)
+            .add_system_set(
+                SystemSet::on_enter(AppState::Menu).with_system(leaderboard::spawn_top_text),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Menu).with_system(leaderboard::update_top_text),
+            )
+            .add_system(leaderboard::submit_finished_runs);
+    }
+}
+
+#[cfg(not(feature = "leaderboard"))]
+fn add_leaderboard(_app: &mut App, _cli: &cli::Cli) {}
+
+#[cfg(feature = "state-server")]
+fn add_state_server(app: &mut App, cli: &cli::Cli) {
+    if let Some(server) = state_server::StateServer::from_cli(cli) {
+        app.insert_resource(server)
+            .add_system(state_server::broadcast_state);
+    }
+}
+
+#[cfg(not(feature = "state-server"))]
+fn add_state_server(_app: &mut App, _cli: &cli::Cli) {}
+
+/// Watches the asset folder, so edited skins, backgrounds and sounds are used right away.
+#[cfg(feature = "hot-reload")]
+fn add_asset_hot_reload(app: &mut App) {
+    app.insert_resource(bevy::asset::AssetServerSettings {
+        watch_for_changes: true,
+        ..default()
+    });
+}
+
+#[cfg(not(feature = "hot-reload"))]
+fn add_asset_hot_reload(_app: &mut App) {}
+
+#[cfg(feature = "discord")]
+fn add_discord_presence(app: &mut App) {
+    if let Some(presence) = discord::Presence::connect() {
+        app.insert_non_send_resource(presence)
+            .add_system(discord::update_presence);
+    }
+}
+
+#[cfg(not(feature = "discord"))]
+fn add_discord_presence(_app: &mut App) {}
+
+/// Adds inspectors of the world and the resources the new games are created from, changed rules
+/// apply to the next started game.
+#[cfg(feature = "debug-ui")]
+fn add_debug_ui(app: &mut App) {
+    use bevy_inspector_egui::{InspectorPlugin, RegisterInspectable, WorldInspectorPlugin};
+
+    app.add_plugin(WorldInspectorPlugin::new())
+        .add_plugin(InspectorPlugin::<game::GameRules>::new())
+        .add_plugin(InspectorPlugin::<StackVisibility>::new())
+        .register_inspectable::<LockedAt>();
+}
+
+#[cfg(not(feature = "debug-ui"))]
+fn add_debug_ui(_app: &mut App) {}
+
+fn game_rules_from_cli(cli: &cli::Cli) -> game::GameRules {
+    let mut rules = game::GameRules::new();
+    rules.items = cli.items;
+    rules.mirror = cli.mirror;
+    rules.big = cli.big;
+    rules.coop = cli.coop;
+    rules.doubles = cli.doubles;
+    if cli.flipped_gravity {
+        rules.gravity = game::Gravity::Up;
+    }
+    rules.drill_interval = cli.drill.map(|seconds| {
+        let seconds = seconds.unwrap_or(DEFAULT_DRILL_INTERVAL);
+        (seconds / TICK_DURATION).round().max(1.) as u32
+    });
+    if let Some(entry_delay) = cli.are {
+        rules.entry_delay = entry_delay;
+    }
+    if let Some(line_clear_delay) = cli.line_clear {
+        rules.line_clear_delay = line_clear_delay;
+    }
+    if let Some(garbage_style) = cli.garbage {
+        rules.garbage_style = garbage_style;
+    }
+    rules
+}
+
+/// Returns name of the single player mode, results of games with different rules are not
+/// compared.
+fn mode_name(rules: &game::GameRules, visibility: &StackVisibility) -> String {
+    let mut name = match rules.drill_interval {
+        // the garbage style matters only in the drill, single player games don't receive garbage
+        Some(interval) => {
+            let style = match rules.garbage_style {
+                game::GarbageStyle::Clean => "clean".to_string(),
+                game::GarbageStyle::Cheese => "cheese".to_string(),
+                game::GarbageStyle::Random { repeat_chance } => format!("random{}", repeat_chance),
+            };
+            format!("drill{:.1}s+{}", interval as f32 * TICK_DURATION, style)
+        }
+        None => "marathon".to_string(),
+    };
+    if rules.items {
+        name += "+items";
+    }
+    if rules.mirror {
+        name += "+mirror";
+    }
+    if rules.big {
+        name += "+big";
+    }
+    if rules.coop {
+        name += "+coop";
+    } else if rules.doubles {
+        name += "+doubles";
+    }
+    if rules.gravity == game::Gravity::Up {
+        name += "+flipped";
+    }
+    if rules.entry_delay > 0 {
+        name += &format!("+are{}", rules.entry_delay);
+    }
+    if rules.line_clear_delay > 0 {
+        name += &format!("+clear{}", rules.line_clear_delay);
+    }
+    match visibility {
+        StackVisibility::Visible => {}
+        StackVisibility::Invisible => name += "+invisible",
+        StackVisibility::Fade(_) => name += "+fade",
+    }
+    name
+}
+
+/// Returns width of the playfield of one player, the co-op board is wider by a board.
+fn player_width(rules: &game::GameRules) -> f32 {
+    PLAYER_WIDTH + units_to_px(rules.board_width() - game::BOARD_WIDTH)
+}
+
+fn window_width(player_count: usize, rules: &game::GameRules) -> f32 {
+    player_width(rules) * player_count as f32
 }
 
 fn units_to_px(units: usize) -> f32 {
@@ -99,31 +648,234 @@ fn units_to_px(units: usize) -> f32 {
 }
 
 fn pos_to_vec3(pos: game::Position) -> Vec3 {
-    vec3(units_to_px(pos.0), units_to_px(pos.1), 0.)
+    vec3(pos.0 as f32 * UNIT_PX, pos.1 as f32 * UNIT_PX, 0.)
+}
+
+/// Fonts are loaded from the fonts asset folder, the `font` setting selects the file.
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+    commands.spawn_bundle(Camera2dBundle::default());
+    let font = settings.get("font").unwrap_or(DEFAULT_FONT);
+    let font_size = settings
+        .get("font_size")
+        .and_then(|size| size.parse().ok())
+        .filter(|&size: &f32| size > 0.)
+        .unwrap_or(DEFAULT_FONT_SIZE);
+    commands.insert_resource(UI {
+        font: asset_server.load(&format!("{}/{}", FONTS_DIR, font)),
+        font_scale: font_size / DEFAULT_FONT_SIZE,
+    });
 }
 
-fn setup(mut commands: Commands) {
-    let mut ui = setup_ui(&mut commands);
-    let game = setup_game(&mut commands, &mut ui);
+fn setup_game(
+    mut commands: Commands,
+    ui: Res<UI>,
+    mut rules: ResMut<game::GameRules>,
+    mut options: ResMut<GameOptions>,
+    profiles: Res<profiles::Profiles>,
+    netplay: Option<Res<netplay::Netplay>>,
+    visibility: Res<StackVisibility>,
+    mut windows: ResMut<Windows>,
+) {
+    // the boss stages of the campaign are played in the versus window
+    if let Some(campaign) = options.campaign.clone() {
+        campaign.apply(&mut options, &mut rules);
+        resize_window(&mut windows, window_width(options.player_count, &rules));
+        commands.insert_resource(campaign::CampaignSession::default());
+    }
+    // the bot opponent doesn't need the controls of the second player
+    let versus = options.player_count > 1 && !options.bot_opponent;
+    let (seed, handicaps, auto_repeats) = match &netplay {
+        Some(netplay) => (
+            Some(netplay.game_seed(0)),
+            netplay.handicaps().to_vec(),
+            netplay.auto_repeats().to_vec(),
+        ),
+        None => (
+            options.daily.map(daily::seed).or(options.seed),
+            options.handicaps.clone(),
+            (0..options.player_count)
+                .map(|i| profiles.player(i, versus).auto_repeat)
+                .collect(),
+        ),
+    };
+    let player_rules = (0..options.player_count)
+        .map(|i| {
+            let rules = handicaps
+                .get(i)
+                .map_or_else(|| rules.clone(), |handicap| handicap.apply(&rules));
+            game::GameRules {
+                auto_repeat: auto_repeats[i],
+                ..rules
+            }
+        })
+        .collect::<Vec<_>>();
+    spawn_playfield(
+        &mut commands,
+        &ui,
+        &rules,
+        window_width(options.player_count, &rules),
+        options.player_count,
+        options.bot_opponent.then(|| options.bot_level.difficulty()),
+        seed,
+        options.practice.clone(),
+        &player_rules,
+    );
+
+    if options.player_count > 1 {
+        commands.insert_resource(Match::new(
+            options.player_count,
+            options.best_of,
+            &handicaps,
+        ));
+        let match_text = versus::spawn_match_text(&mut commands, &ui);
+        commands.entity(match_text).insert(GameEntity);
+    }
+    if let Some(practice) = &options.practice {
+        commands.insert_resource(practice::PracticeSession::new(practice.clone()));
+    }
+    if let Some(replay) = &options.viewed_replay {
+        commands.insert_resource(replay_viewer::ReplayViewer::new(replay.clone()));
+        let viewer_text = replay_viewer::spawn_viewer_text(&mut commands, &ui);
+        commands.entity(viewer_text).insert(GameEntity);
+    } else if options.player_count == 1 {
+        let mode = mode_name(&rules, &visibility);
+        let best_splits = records::best_sprint_splits(&records::load_records(), &mode);
+        commands.insert_resource(records::BestSplits(best_splits.unwrap_or_default()));
+        // practice games are prepared, unlike the run of the ghost
+        if options.practice.is_none() && options.campaign.is_none() {
+            if let Some(ghost) = pb_ghost::PbGhost::load(&mode) {
+                commands.insert_resource(ghost);
+            }
+        }
+    }
+    if netplay.is_some() {
+        let chat_text = chat::spawn_chat_text(&mut commands, &ui);
+        commands.entity(chat_text).insert(GameEntity);
+    }
+}
 
-    commands.insert_resource(ui);
-    commands.insert_resource(game);
+fn cleanup_game(
+    mut commands: Commands,
+    entities: Query<Entity, With<GameEntity>>,
+    rules: Res<game::GameRules>,
+    mut options: ResMut<GameOptions>,
+    mut windows: ResMut<Windows>,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Match>();
+    commands.remove_resource::<practice::PracticeSession>();
+    commands.remove_resource::<campaign::CampaignSession>();
+    commands.remove_resource::<records::BestSplits>();
+    commands.remove_resource::<pb_ghost::PbGhost>();
+    commands.remove_resource::<replay_viewer::ReplayViewer>();
+    // the campaign menu and demo are single player
+    if options.campaign.is_some() {
+        options.player_count = 1;
+        options.bot_opponent = false;
+        resize_window(&mut windows, window_width(1, &rules));
+    }
 }
 
-fn setup_ui(commands: &mut Commands) -> UI {
-    commands.spawn_bundle(Camera2dBundle::default());
+fn resize_window(windows: &mut Windows, width: f32) {
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_resolution(width, WINDOW_HEIGHT);
+    }
+}
 
+/// Spawns boards, players and their HUD. The game of the last player is controlled by a bot of
+/// the `bot` difficulty if given. Games are started with `seed` if given, otherwise with a
+/// random one. The HUD of the `practice` shows its values instead of the score. Players play by
+/// the rules at their index in `player_rules`, if any.
+fn spawn_playfield(
+    commands: &mut Commands,
+    ui: &UI,
+    rules: &game::GameRules,
+    window_width: f32,
+    player_count: usize,
+    bot: Option<game::BotDifficulty>,
+    seed: Option<u64>,
+    practice: Option<practice::Practice>,
+    player_rules: &[game::GameRules],
+) {
     // move (0, 0) to top / left and flip y axis
     let canvas = commands
         .spawn_bundle(SpatialBundle::from_transform(Transform {
-            translation: vec3(-WINDOW_WIDTH / 2., WINDOW_HEIGHT / 2., 0.),
+            translation: vec3(-window_width / 2., WINDOW_HEIGHT / 2., 0.),
             scale: vec3(1., -1., 1.),
             ..default()
         }))
+        .insert(GameEntity)
         .id();
 
-    // board
-    let board_width = units_to_px(game::BOARD_WIDTH);
+    let versus = player_count > 1;
+    for i in 0..player_count {
+        let offset = player_width(rules) * i as f32;
+        let board = setup_board(commands, canvas, offset, rules.board_width());
+        let bot = bot
+            .filter(|_| i + 1 == player_count)
+            .map(game::Bot::with_difficulty);
+        let player_rules = player_rules
+            .get(i)
+            .cloned()
+            .unwrap_or_else(|| rules.clone());
+        let game = match seed {
+            Some(seed) => game::Game::with_seed(player_rules, seed),
+            None => game::Game::with_rules(player_rules),
+        };
+        let player = setup_player(commands, i, board, bot, game);
+
+        let hud_left =
+            offset + MARGIN_SIZE * 2. + units_to_px(rules.board_width()) + BORDER_SIZE * 2.;
+        let mut hud_values = vec![];
+        if let Some(practice) = &practice {
+            hud_values.extend(practice.hud_values());
+        } else if rules.drill_interval.is_some() {
+            hud_values.extend([HudValue::Time, HudValue::GarbageLines]);
+        } else if !versus {
+            hud_values.extend([
+                HudValue::Score,
+                HudValue::Lines,
+                HudValue::Level,
+                HudValue::Split,
+                HudValue::Pace,
+            ]);
+        }
+        hud_values.push(HudValue::Hold);
+        if rules.items {
+            hud_values.push(HudValue::Item);
+        }
+        if versus {
+            hud_values.extend([HudValue::Attack, HudValue::Next]);
+        }
+        for (line, value) in hud_values.into_iter().enumerate() {
+            let hud_text = hud::spawn_hud_text(
+                commands,
+                ui,
+                player,
+                value,
+                hud_left,
+                MARGIN_SIZE + HUD_LINE_HEIGHT * line as f32,
+            );
+            commands.entity(hud_text).insert(GameEntity);
+        }
+
+        let histogram_height = histogram::ROW_HEIGHT * game::BlockType::ALL.len() as f32;
+        histogram::spawn_histogram(
+            commands,
+            canvas,
+            player,
+            hud_left,
+            WINDOW_HEIGHT - MARGIN_SIZE - histogram_height,
+        );
+    }
+}
+
+/// Spawns board `board_width` columns wide with its border and returns the entity which should
+/// be a parent of the board points.
+fn setup_board(commands: &mut Commands, canvas: Entity, offset: f32, board_width: usize) -> Entity {
+    let board_width = units_to_px(board_width);
     let board_height = units_to_px(game::VISIBLE_BOARD_HEIGHT);
     let board_with_border_width = board_width + BORDER_SIZE * 2.;
     let board_with_border_height = board_height + BORDER_SIZE * 2.;
@@ -137,7 +889,7 @@ fn setup_ui(commands: &mut Commands) -> UI {
                 ..default()
             },
             transform: Transform {
-                translation: vec3(MARGIN_SIZE, MARGIN_SIZE, 0.),
+                translation: vec3(offset + MARGIN_SIZE, MARGIN_SIZE, 0.),
                 scale: vec3(board_with_border_width, board_with_border_height, 1.),
                 ..default()
             },
@@ -154,7 +906,11 @@ fn setup_ui(commands: &mut Commands) -> UI {
                 ..default()
             },
             transform: Transform {
-                translation: vec3(MARGIN_SIZE + BORDER_SIZE, MARGIN_SIZE + BORDER_SIZE, 0.),
+                translation: vec3(
+                    offset + MARGIN_SIZE + BORDER_SIZE,
+                    MARGIN_SIZE + BORDER_SIZE,
+                    0.,
+                ),
                 scale: vec3(board_width, board_height, 1.),
                 ..default()
             },
@@ -164,7 +920,7 @@ fn setup_ui(commands: &mut Commands) -> UI {
 
     let board = commands
         .spawn_bundle(SpatialBundle::from_transform(Transform::from_xyz(
-            MARGIN_SIZE + BORDER_SIZE,
+            offset + MARGIN_SIZE + BORDER_SIZE,
             MARGIN_SIZE + BORDER_SIZE,
             0.,
         )))
@@ -174,43 +930,70 @@ fn setup_ui(commands: &mut Commands) -> UI {
         .entity(canvas)
         .push_children(&[board_border, board_bg, board]);
 
-    UI {
-        board,
-        points: HashMap::new(),
-    }
+    board
 }
 
-fn setup_game(commands: &mut Commands, ui: &mut UI) -> game::Game {
-    let game = game::Game::new();
-    spawn_block(
-        commands,
-        game.active_block(),
-        game.active_block_position(),
-        ui.board,
-        &mut ui.points,
-    );
-    game
+fn setup_player(
+    commands: &mut Commands,
+    index: usize,
+    board: Entity,
+    bot: Option<game::Bot>,
+    game: game::Game,
+) -> Entity {
+    let player_entity = commands.spawn().insert(GameEntity).id();
+    let partner_input = game.rules().coop.then(RawInput::default);
+    let mut player = Player {
+        index,
+        game,
+        input: RawInput::default(),
+        partner_input,
+        bot,
+        board,
+        points: HashMap::new(),
+    };
+    // a new game has no locked points, so their lock time doesn't matter
+    respawn_points(commands, player_entity, &mut player, 0.);
+    commands.entity(player_entity).insert(player);
+    player_entity
 }
 
-fn spawn_block(
+/// Replaces player's game with a new one.
+fn restart_player(
     commands: &mut Commands,
-    block: &game::Block,
-    block_pos: game::Position,
-    parent: Entity,
-    point_entities: &mut PointEntities,
+    player_entity: Entity,
+    player: &mut Player,
+    game: game::Game,
 ) {
-    for point in block.points() {
-        let point_pos = block.get_point_position(point.id).unwrap();
-        let point_pos = game::add_positions(block_pos, point_pos);
-        let point_entity = spawn_point(commands, point, point_pos, parent, point_entities);
-        commands
-            .entity(point_entity)
-            .insert(BlockComponent(block.id));
+    player.game = game;
+    player.input.reset();
+    // the bot would keep its target if the new block had the same id
+    if let Some(bot) = &mut player.bot {
+        *bot = game::Bot::with_difficulty(bot.difficulty());
+    }
+    if let Some(partner_input) = &mut player.partner_input {
+        partner_input.reset();
     }
+    respawn_points(commands, player_entity, player, 0.);
+}
+
+/// Replaces entities of the player's points with the ones of the current game state, used when
+/// the game was changed outside of its tick.
+fn respawn_points(commands: &mut Commands, player_entity: Entity, player: &mut Player, now: f64) {
+    let view = player.game.view();
+    BevyRenderer {
+        commands,
+        player_entity,
+        board: player.board,
+        points: &mut player.points,
+        block_points: vec![],
+        now,
+    }
+    .draw(&view);
 }
 
 fn spawn_point(
     commands: &mut Commands,
+    player: Entity,
     point: &game::Point,
     point_pos: game::Position,
     parent: Entity,
@@ -218,10 +1001,14 @@ fn spawn_point(
 ) -> Entity {
     let point_entity = commands
         .spawn()
-        .insert(PointComponent(point.id))
+        .insert(PointComponent {
+            player,
+            id: point.id,
+            color: point_color(point),
+        })
         .insert_bundle(SpriteBundle {
             sprite: Sprite {
-                color: game::get_block_color(point.origin_block_type),
+                color: point_color(point),
                 anchor: Anchor::BottomLeft,
                 ..default()
             },
@@ -239,95 +1026,241 @@ fn spawn_point(
     point_entity
 }
 
-fn check_input(bevy_input: Res<Input<KeyCode>>, mut input: ResMut<RawInput>) {
-    if bevy_input.pressed(KeyCode::Left) {
-        input.move_left = true;
-    }
-    if bevy_input.pressed(KeyCode::Right) {
-        input.move_right = true;
+pub fn get_block_color(block_type: game::BlockType) -> Color {
+    match block_type {
+        game::BlockType::I => Color::CYAN,
+        game::BlockType::J => Color::BLUE,
+        game::BlockType::L => Color::ORANGE,
+        game::BlockType::O => Color::YELLOW,
+        game::BlockType::S => Color::GREEN,
+        game::BlockType::T => Color::PURPLE,
+        game::BlockType::Z => Color::RED,
     }
-    if bevy_input.pressed(KeyCode::Down) {
-        input.fast_drop = true;
-    }
-    if bevy_input.pressed(KeyCode::Up) {
-        input.rotate = true;
+}
+
+fn point_color(point: &game::Point) -> Color {
+    match (point.item, point.origin_block_type) {
+        (Some(_), _) => ITEM_POINT_COLOR,
+        (None, Some(block_type)) => get_block_color(block_type),
+        (None, None) => GARBAGE_POINT_COLOR,
     }
 }
 
 fn tick(
     mut commands: Commands,
-    mut game: ResMut<game::Game>,
-    mut ui: ResMut<UI>,
-    mut input: ResMut<RawInput>,
-    block_points: Query<Entity, With<BlockComponent>>,
+    mut players: Query<(Entity, &mut Player)>,
+    mut runs_finished: EventWriter<RunFinished>,
+    mut inputs_played: EventWriter<InputPlayed>,
+    mut tick_changes: EventWriter<TickChanged>,
+    versus_match: Option<Res<Match>>,
+    practice: Option<Res<practice::PracticeSession>>,
+    campaign: Option<Res<campaign::CampaignSession>>,
+    netplay: Option<ResMut<netplay::Netplay>>,
+    editor: Res<editor::Editor>,
+    pause: Res<pause::Pause>,
+    viewer: Option<Res<replay_viewer::ReplayViewer>>,
+    time: Res<Time>,
+    block_points: Query<(Entity, &PointComponent), With<BlockComponent>>,
 ) {
-    let input = input.as_mut();
-    let changes = game.tick(input);
-    input.reset();
-
-    for change in changes {
-        use crate::game::TickChange::*;
-        match change {
-            BlockLocked => {
-                for point_entity in block_points.iter() {
-                    commands.entity(point_entity).remove::<BlockComponent>();
+    // the viewer plays the replay at its own pace and the rewound practice game goes backwards
+    if editor.is_active()
+        || pause.is_active()
+        || viewer.is_some()
+        || matches!(&practice, Some(p) if p.is_rewinding())
+        || matches!(versus_match, Some(m) if !m.is_playing())
+    {
+        return;
+    }
+
+    // the demo in the menu is played locally
+    let mut netplay = netplay.filter(|_| versus_match.is_some());
+    let net_inputs = match netplay.as_mut() {
+        Some(netplay) => {
+            // both peers stop at the same tick, the match notices the game over after the tick
+            if players.iter().any(|(_, player)| player.game.is_game_over()) {
+                return;
+            }
+            netplay.receive();
+            let local = players
+                .iter()
+                .find(|(_, player)| player.index == netplay.local_index())
+                .map(|(_, player)| game::ReplayInput::from_input(&player.input))
+                .unwrap_or_default();
+            let inputs = netplay.advance(local);
+            netplay.send_inputs();
+            // the local input is kept until the tick can be played
+            match inputs {
+                Some(inputs) => Some(inputs),
+                None => return,
+            }
+        }
+        None => None,
+    };
+
+    let mut sent_garbage = vec![];
+    let mut used_items = vec![];
+
+    for (player_entity, mut player) in players.iter_mut() {
+        let player = &mut *player;
+        let played = !player.game.is_game_over();
+        let changes = match (&net_inputs, player.bot.as_mut()) {
+            (Some(inputs), _) => player.game.tick(&inputs[player.index]),
+            (None, Some(bot)) => {
+                bot.think(&player.game);
+                player.game.tick(&*bot)
+            }
+            (None, None) => match &player.partner_input {
+                Some(partner_input) => player.game.tick_coop([&player.input, partner_input]),
+                None => player.game.tick(&player.input),
+            },
+        };
+        player.input.advance();
+        if let Some(partner_input) = &mut player.partner_input {
+            partner_input.advance();
+        }
+        if let Some(input) = player.game.replay().last_input().filter(|_| played) {
+            inputs_played.send(InputPlayed {
+                player: player_entity,
+                input,
+            });
+        }
+
+        BevyRenderer {
+            commands: &mut commands,
+            player_entity,
+            board: player.board,
+            points: &mut player.points,
+            block_points: block_points
+                .iter()
+                .filter(|(_, point)| point.player == player_entity)
+                .map(|(point_entity, point)| (point_entity, point.id))
+                .collect(),
+            now: time.seconds_since_startup(),
+        }
+        .apply_changes(&player.game.view(), &changes);
+
+        for change in changes {
+            use crate::game::TickChange::*;
+            tick_changes.send(TickChanged {
+                player: player_entity,
+                change: change.clone(),
+            });
+            match change {
+                GarbageSent(lines) => sent_garbage.push((player_entity, lines)),
+                ItemUsed(item) if item.targets_opponent() => used_items.push((player_entity, item)),
+                // replays don't record garbage and items received from the opponent, so only
+                // single player games are saved, neither do they record practice boards or the
+                // co-op partner, campaign stages aren't runs of any mode
+                GameOver
+                    if versus_match.is_none()
+                        && practice.is_none()
+                        && campaign.is_none()
+                        && player.bot.is_none()
+                        && player.partner_input.is_none() =>
+                {
+                    runs_finished.send(RunFinished {
+                        player: player_entity,
+                    });
                 }
+                _ => {}
             }
-            NewBlock => {
-                spawn_block(
-                    &mut commands,
-                    game.active_block(),
-                    game.active_block_position(),
-                    ui.board,
-                    &mut ui.points,
-                );
+        }
+    }
+
+    // garbage and items go to every opponent of the sender
+    for (sender, lines) in sent_garbage {
+        for (player_entity, mut player) in players.iter_mut() {
+            if player_entity != sender {
+                player.game.receive_garbage(lines);
             }
-            PointRemoved(point_id) => {
-                let point_entity = ui.points.remove(&point_id).unwrap();
-                commands.entity(point_entity).despawn();
+        }
+    }
+    for (sender, item) in used_items {
+        for (player_entity, mut player) in players.iter_mut() {
+            if player_entity != sender {
+                player.game.receive_item(item);
             }
         }
     }
+
+    if let Some(netplay) = netplay.as_mut().filter(|netplay| netplay.is_hash_tick()) {
+        let mut hashes = players
+            .iter()
+            .map(|(_, player)| (player.index, player.game.state_hash()))
+            .collect::<Vec<_>>();
+        hashes.sort();
+        let hash = hashes.iter().fold(0, |hash: u64, &(_, player_hash)| {
+            hash.rotate_left(1) ^ player_hash
+        });
+        netplay.check_state(hash);
+    }
 }
 
 fn update_board_points(
-    game: Res<game::Game>,
+    players: Query<&Player>,
+    stack_visibility: Res<StackVisibility>,
+    time: Res<Time>,
     mut board_points: Query<
-        (&PointComponent, &mut Transform, &mut Visibility),
+        (
+            &PointComponent,
+            &LockedAt,
+            &mut Transform,
+            &mut Visibility,
+            &mut Sprite,
+        ),
         Without<BlockComponent>,
     >,
 ) {
-    for (point, mut transform, mut visibility) in board_points.iter_mut() {
-        let point_pos = game.get_point_position(point.0).unwrap();
-        update_point_view(point_pos, &mut transform, &mut visibility);
+    let now = time.seconds_since_startup();
+    for (point, locked_at, mut transform, mut visibility, mut sprite) in board_points.iter_mut() {
+        let game = &players.get(point.player).unwrap().game;
+        let point_pos = game.get_point_position(point.id).unwrap();
+        update_point_view(
+            game.rules().gravity,
+            point_pos,
+            &mut transform,
+            &mut visibility,
+        );
+
+        let locked_for = (now - locked_at.0) as f32;
+        let alpha = stack_visibility.point_alpha(locked_for, game.is_game_over());
+        sprite.color.set_a(alpha);
+        if alpha <= 0. {
+            visibility.is_visible = false;
+        }
     }
 }
 
 fn update_block_points(
-    game: Res<game::Game>,
+    players: Query<&Player>,
     mut board_points: Query<
         (&PointComponent, &mut Transform, &mut Visibility),
         With<BlockComponent>,
     >,
 ) {
-    let block = game.active_block();
-    let block_pos = game.active_block_position();
     for (point, mut transform, mut visibility) in board_points.iter_mut() {
-        let point_pos = block.get_point_position(point.0).unwrap();
-        let point_pos = game::add_positions(block_pos, point_pos);
-        update_point_view(point_pos, &mut transform, &mut visibility);
+        let game = &players.get(point.player).unwrap().game;
+        let point_pos = game.block_point_position(point.id).unwrap();
+        update_point_view(
+            game.rules().gravity,
+            point_pos,
+            &mut transform,
+            &mut visibility,
+        );
     }
 }
 
 fn update_point_view(
+    gravity: game::Gravity,
     point_pos: game::Position,
     transform: &mut Transform,
     visibility: &mut Visibility,
 ) {
-    if point_pos.1 >= game::HIDDEN_BOARD_TOP {
-        transform.translation = pos_to_vec3((point_pos.0, point_pos.1 - game::HIDDEN_BOARD_TOP));
-        visibility.is_visible = true;
-    } else {
-        visibility.is_visible = false;
+    match gravity.visible_row(point_pos.1) {
+        Some(row) => {
+            transform.translation = pos_to_vec3((point_pos.0, row as i32));
+            visibility.is_visible = true;
+        }
+        None => visibility.is_visible = false,
     }
 }
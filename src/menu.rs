@@ -0,0 +1,323 @@
+use bevy::prelude::*;
+
+use crate::{
+    actions::ActionMaps,
+    campaign, clipboard, daily,
+    game::{BotDifficulty, GameRules},
+    i18n::{Key, Language},
+    mode_name,
+    player::Player,
+    practice::{self, Practice},
+    profiles::Profiles,
+    records,
+    saves::{self, ResumedGame},
+    settings::Settings,
+    spawn_playfield,
+    stack_visibility::StackVisibility,
+    toast, window_width, AppState, GameOptions, UI,
+};
+
+/// How long the menu should be idle before the demo starts, in seconds.
+const DEMO_DELAY: f32 = 15.;
+const FONT_SIZE: f32 = 24.;
+/// Keys cycling the speed level, garbage percentage and preview count of the first two players.
+const HANDICAP_KEYS: [[KeyCode; 3]; 2] = [
+    [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3],
+    [KeyCode::Key8, KeyCode::Key9, KeyCode::Key0],
+];
+/// Cycles the control profile of the single player, also the one playing against the bot, or
+/// the first versus player, with shift of the second versus player.
+const PROFILE_KEY: KeyCode = KeyCode::P;
+const SHIFT_KEYS: [KeyCode; 2] = [KeyCode::LShift, KeyCode::RShift];
+const TEXT_COLOR: Color = Color::WHITE;
+
+struct IdleTimer(Timer);
+
+/// Entity which is despawned when the menu or the demo is left.
+#[derive(Component)]
+pub struct MenuEntity;
+
+/// Spawns centered text and returns the text entity.
+pub fn spawn_menu_text(commands: &mut Commands, ui: &UI, text: &str) -> Entity {
+    let mut text_entity = None;
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(MenuEntity)
+        .with_children(|parent| {
+            let entity = parent
+                .spawn_bundle(
+                    TextBundle::from_section(text, ui.text_style(FONT_SIZE, TEXT_COLOR))
+                        .with_text_alignment(TextAlignment::CENTER),
+                )
+                .id();
+            text_entity = Some(entity);
+        });
+    text_entity.unwrap()
+}
+
+pub fn setup_menu(
+    mut commands: Commands,
+    ui: Res<UI>,
+    language: Res<Language>,
+    options: Res<GameOptions>,
+    profiles: Res<Profiles>,
+    rules: Res<GameRules>,
+    visibility: Res<StackVisibility>,
+) {
+    // the campaign plays only its own stages, the viewer only its replay
+    let single =
+        options.player_count == 1 && options.campaign.is_none() && options.viewed_replay.is_none();
+    let mut text = format!("{}\n\n", language.get(Key::Title));
+    if let Some(day) = options.daily {
+        let best = records::daily_best(&records::load_records(), day)
+            .map_or("-".to_string(), |score| score.to_string());
+        text += &format!(
+            "{}\n{}\n{}\n\n",
+            language.get(Key::DailyChallenge),
+            daily::format_day(day),
+            language.format(Key::Best, &[&best])
+        );
+    } else if single {
+        let mode = mode_name(&rules, &visibility);
+        if let Some(best) = records::PersonalBest::of_mode(&records::load_records(), &mode) {
+            text += &format!(
+                "{}\n{}\n\n",
+                language.get(Key::PersonalBest),
+                best.describe(*language)
+            );
+        }
+    }
+    if options.player_count > 1 {
+        for (i, handicap) in options.handicaps.iter().enumerate() {
+            text += &format!(
+                "{} {}\n",
+                language.format(Key::PlayerNumber, &[&(i + 1)]),
+                handicap.describe(*language)
+            );
+        }
+        text += &format!("{}\n\n", language.get(Key::HandicapHint));
+    }
+    if let Some(replay) = &options.viewed_replay {
+        text += &format!(
+            "{}\n\n",
+            language.format(Key::ViewedReplay, &[&replay.preset()])
+        );
+    }
+    if let Some(campaign) = &options.campaign {
+        text += &format!(
+            "{}\n{}\n\n",
+            campaign.describe(*language),
+            language.get(Key::StageHint)
+        );
+    }
+    text += &format!("{}\n", language.get(Key::PressEnter));
+    if single && saves::has_autosave() {
+        text += &format!("{}\n", language.get(Key::ResumeHint));
+    }
+    if single {
+        text += &format!(
+            "{}\n{}\n",
+            language.get(Key::FumenHint),
+            language.get(Key::SavesHint)
+        );
+    }
+    if options.bot_opponent {
+        text += &format!(
+            "{}\n",
+            language.format(
+                Key::BotLevelHint,
+                &[&language.bot_level_name(options.bot_level)]
+            )
+        );
+    }
+    if options.player_count > 1 && !options.bot_opponent {
+        text += &format!(
+            "{}\n",
+            language.format(
+                Key::VersusProfileHint,
+                &[&profiles.versus(0).name, &profiles.versus(1).name]
+            )
+        );
+    } else {
+        text += &format!(
+            "{}\n",
+            language.format(Key::ProfileHint, &[&profiles.single().name])
+        );
+    }
+    text += &format!(
+        "{}\n{}\n{}",
+        language.get(Key::OnlineHint),
+        language.get(Key::StatisticsHint),
+        language.format(Key::LanguageHint, &[&language.get(Key::LanguageName)])
+    );
+    spawn_menu_text(&mut commands, &ui, &text);
+    commands.insert_resource(IdleTimer(Timer::from_seconds(DEMO_DELAY, false)));
+}
+
+pub fn update_menu(
+    mut commands: Commands,
+    ui: Res<UI>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut language: ResMut<Language>,
+    mut options: ResMut<GameOptions>,
+    mut profiles: ResMut<Profiles>,
+    mut action_maps: ResMut<ActionMaps>,
+    mut settings: ResMut<Settings>,
+    rules: Res<GameRules>,
+    visibility: Res<StackVisibility>,
+    time: Res<Time>,
+    mut idle_timer: ResMut<IdleTimer>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let single =
+        options.player_count == 1 && options.campaign.is_none() && options.viewed_replay.is_none();
+    if keys.just_pressed(KeyCode::Return) {
+        state.set(AppState::Game).unwrap();
+    } else if options
+        .campaign
+        .as_mut()
+        .is_some_and(|campaign| campaign::select_stage(campaign, &keys))
+    {
+        state.restart().unwrap();
+    } else if keys.just_pressed(KeyCode::F) && single {
+        // the diagram is pasted from the clipboard
+        match clipboard::get_text().and_then(|text| practice::load_fumen(&text)) {
+            Ok(fumen) => {
+                options.practice = Some(Practice::Setup(fumen));
+                state.set(AppState::Game).unwrap();
+            }
+            Err(e) => {
+                toast::spawn_toast(&mut commands, &ui, language.format(Key::FumenFailed, &[&e]))
+            }
+        }
+    } else if keys.just_pressed(KeyCode::R) && single {
+        match saves::load_autosave() {
+            // the game is played by the rules of the mode it was saved in
+            Ok(replay) if replay.preset() != mode_name(&rules, &visibility) => {
+                let message = language.format(Key::OtherModeSave, &[&replay.preset()]);
+                toast::spawn_toast(&mut commands, &ui, message);
+            }
+            Ok(replay) => {
+                // the run is resumed only once
+                if let Err(e) = saves::remove_autosave() {
+                    warn!("failed to remove the autosave: {}", e);
+                }
+                commands.insert_resource(ResumedGame(replay));
+                state.set(AppState::Game).unwrap();
+            }
+            Err(e) => warn!("failed to load the autosave: {}", e),
+        }
+    } else if keys.just_pressed(KeyCode::O) {
+        keys.clear();
+        state.set(AppState::Lobby).unwrap();
+    } else if keys.just_pressed(KeyCode::S) {
+        keys.clear();
+        state.set(AppState::Stats).unwrap();
+    } else if keys.just_pressed(KeyCode::V) && single {
+        keys.clear();
+        state.set(AppState::Saves).unwrap();
+    } else if keys.just_pressed(PROFILE_KEY) {
+        if options.player_count == 1 || options.bot_opponent {
+            profiles.next_single(&mut settings);
+        } else {
+            let player = if keys.any_pressed(SHIFT_KEYS) { 1 } else { 0 };
+            profiles.next_versus(player, &mut settings);
+        }
+        *action_maps = profiles.action_maps();
+        state.restart().unwrap();
+    } else if keys.just_pressed(KeyCode::A) && options.bot_opponent {
+        options.bot_level = options.bot_level.next();
+        state.restart().unwrap();
+    } else if keys.just_pressed(KeyCode::L) {
+        *language = language.next();
+        // spawn the menu again in the new language
+        state.restart().unwrap();
+    } else if let Some((player, key)) = pressed_handicap_key(&keys, options.player_count) {
+        let handicap = &mut options.handicaps[player];
+        match key {
+            0 => handicap.next_speed_level(),
+            1 => handicap.next_garbage_percent(),
+            _ => handicap.next_preview_count(),
+        }
+        state.restart().unwrap();
+    } else if keys.get_just_pressed().next().is_some() {
+        idle_timer.0.reset();
+    } else if idle_timer.0.tick(time.delta()).just_finished() {
+        state.set(AppState::Demo).unwrap();
+    }
+}
+
+/// Returns the player and the index of the pressed handicap key, in versus only.
+fn pressed_handicap_key(keys: &Input<KeyCode>, player_count: usize) -> Option<(usize, usize)> {
+    if player_count < 2 {
+        return None;
+    }
+    HANDICAP_KEYS
+        .iter()
+        .enumerate()
+        .find_map(|(player, player_keys)| {
+            let key = player_keys.iter().position(|&k| keys.just_pressed(k))?;
+            Some((player, key))
+        })
+}
+
+pub fn cleanup_menu(mut commands: Commands, entities: Query<Entity, With<MenuEntity>>) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn setup_demo(
+    mut commands: Commands,
+    ui: Res<UI>,
+    language: Res<Language>,
+    rules: Res<GameRules>,
+    options: Res<GameOptions>,
+) {
+    let window_width = window_width(options.player_count, &rules);
+    // the bot plays only single games on a board of the usual width
+    let rules = GameRules {
+        coop: false,
+        doubles: false,
+        ..rules.clone()
+    };
+    spawn_playfield(
+        &mut commands,
+        &ui,
+        &rules,
+        window_width,
+        1,
+        Some(BotDifficulty::default()),
+        None,
+        None,
+        &[],
+    );
+    let text = format!(
+        "{}\n\n{}",
+        language.get(Key::Demo),
+        language.get(Key::PressAnyKey)
+    );
+    spawn_menu_text(&mut commands, &ui, &text);
+}
+
+/// Returns to the menu when any key is pressed or the bot loses.
+pub fn update_demo(
+    keys: Res<Input<KeyCode>>,
+    players: Query<&Player>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let game_over = !players.is_empty() && players.iter().all(|p| p.game.is_game_over());
+    if keys.get_just_pressed().next().is_some() || game_over {
+        state.set(AppState::Menu).unwrap();
+    }
+}
@@ -0,0 +1,564 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+use crate::{
+    cli::Cli,
+    game::{AutoRepeat, ReplayInput},
+    i18n::{Key, Language},
+    toast,
+    versus::Handicap,
+    UI,
+};
+
+/// Number of ticks between the local input and the tick it's played on, it hides the latency
+/// of the connection as long as the inputs arrive in time.
+const INPUT_DELAY: u32 = 3;
+/// Peers compare hashes of the games every this many ticks.
+const HASH_INTERVAL: u32 = 60;
+/// Port hosted on if `--host` doesn't give one.
+pub const DEFAULT_PORT: u16 = 7878;
+const HELLO_INTERVAL: Duration = Duration::from_millis(500);
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Inputs are sent again until they are acknowledged, at most this many in one packet.
+const MAX_PACKET_INPUTS: usize = 255;
+/// Chat messages are cut to this many bytes.
+const MAX_CHAT_LEN: usize = 200;
+/// Crockford's base 32 alphabet of room codes, without letters which look like digits.
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LEN: usize = 10;
+
+// packet kinds
+const HELLO: u8 = 0;
+/// Followed by the seed.
+const WELCOME: u8 = 1;
+/// Followed by the acknowledged tick, the tick of the first input, the count and the inputs.
+const INPUTS: u8 = 2;
+/// Followed by the tick and the hash of the games.
+const STATE_HASH: u8 = 3;
+/// Followed by the lobby flags, the number of the next chat message expected from the peer, the
+/// handicap and the auto repeat of the moves.
+const LOBBY: u8 = 4;
+/// Followed by the number of the message and the text.
+const CHAT: u8 = 5;
+
+// lobby flags
+const READY: u8 = 1 << 0;
+/// Sent by the host when both players are ready.
+const START: u8 = 1 << 1;
+
+/// Lockstep versus game over UDP, created in the lobby or by `--host[=<port>]` and
+/// `--connect=<room code or address>`. Both peers play both games with the local and the
+/// received inputs, every tick waits until the input of the opponent for it arrives. The host
+/// is the first player on both peers.
+pub struct Netplay {
+    socket: UdpSocket,
+    host: bool,
+    /// Set when the welcome is sent or received, the seed comes with it.
+    connected: bool,
+    /// Code of the hosted room, `None` when joining or if the local address is unknown.
+    room_code: Option<String>,
+    seed: u64,
+    local_ready: bool,
+    remote_ready: bool,
+    local_handicap: Handicap,
+    remote_handicap: Handicap,
+    local_auto_repeat: AutoRepeat,
+    remote_auto_repeat: AutoRepeat,
+    started: bool,
+    /// Number of ticks played.
+    tick: u32,
+    /// Local inputs from `local_first` on, the ones played or acknowledged by the opponent are
+    /// dropped.
+    local_inputs: VecDeque<ReplayInput>,
+    local_first: u32,
+    /// First tick which wasn't received by the opponent yet.
+    acknowledged: u32,
+    /// Received inputs of the opponent from `tick` on.
+    remote_inputs: VecDeque<ReplayInput>,
+    local_hashes: HashMap<u32, u64>,
+    remote_hashes: HashMap<u32, u64>,
+    /// First tick at which the games of the peers differed.
+    desync: Option<u32>,
+    /// Sent chat messages with their numbers, they are sent again until acknowledged.
+    outgoing_chat: VecDeque<(u32, String)>,
+    sent_chat: u32,
+    /// Number of chat messages received from the opponent.
+    received_chat: u32,
+    incoming_chat: Vec<String>,
+    last_received: Instant,
+    last_hello: Option<Instant>,
+    reported_desync: bool,
+    reported_disconnect: bool,
+}
+
+impl Netplay {
+    /// Hosts or joins a game if the command line asks for it.
+    pub fn from_cli(cli: &Cli) -> Option<io::Result<Self>> {
+        if let Some(port) = cli.host {
+            return Some(Self::host(port.unwrap_or(DEFAULT_PORT)));
+        }
+        let address = cli.connect.as_ref()?;
+        Some(Self::join(address))
+    }
+
+    /// Waits for the opponent on the port.
+    pub fn host(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        let mut netplay = Self::new(socket, true, thread_rng().gen())?;
+        netplay.room_code = local_address(port).map(room_code);
+        Ok(netplay)
+    }
+
+    /// Connects to the host given by the room code or the address, the port may be left out.
+    pub fn join(code_or_address: &str) -> io::Result<Self> {
+        let address = match parse_room_code(code_or_address) {
+            Some(address) => address,
+            None if code_or_address.contains(':') => resolve(code_or_address)?,
+            None => resolve((code_or_address, DEFAULT_PORT))?,
+        };
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(address)?;
+        // the seed comes with the welcome
+        Self::new(socket, false, 0)
+    }
+
+    fn new(socket: UdpSocket, host: bool, seed: u64) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        // nothing is pressed during the delay at the start
+        let delay = vec![ReplayInput::default(); INPUT_DELAY as usize];
+        Ok(Self {
+            socket,
+            host,
+            connected: false,
+            room_code: None,
+            seed,
+            local_ready: false,
+            remote_ready: false,
+            local_handicap: Handicap::default(),
+            remote_handicap: Handicap::default(),
+            local_auto_repeat: AutoRepeat::default(),
+            remote_auto_repeat: AutoRepeat::default(),
+            started: false,
+            tick: 0,
+            local_inputs: delay.iter().copied().collect(),
+            local_first: 0,
+            acknowledged: INPUT_DELAY,
+            remote_inputs: delay.into_iter().collect(),
+            local_hashes: HashMap::new(),
+            remote_hashes: HashMap::new(),
+            desync: None,
+            outgoing_chat: VecDeque::new(),
+            sent_chat: 0,
+            received_chat: 0,
+            incoming_chat: vec![],
+            last_received: Instant::now(),
+            last_hello: None,
+            reported_desync: false,
+            reported_disconnect: false,
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.host
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Returns index of the player controlled on this peer.
+    pub fn local_index(&self) -> usize {
+        if self.host {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Returns the seed of the `game`-th game of the match, the same on both peers.
+    pub fn game_seed(&self, game: usize) -> u64 {
+        self.seed.wrapping_add(game as u64)
+    }
+
+    pub fn peer_address(&self) -> Option<SocketAddr> {
+        self.socket.peer_addr().ok()
+    }
+
+    /// Returns the room code of the hosted game, it encodes the address of this computer in the
+    /// local network.
+    pub fn room_code(&self) -> Option<&str> {
+        self.room_code.as_deref()
+    }
+
+    /// Returns whether the local and the remote player are ready.
+    pub fn readiness(&self) -> (bool, bool) {
+        (self.local_ready, self.remote_ready)
+    }
+
+    pub fn set_ready(&mut self, ready: bool) {
+        // the countdown can't be stopped once the host started it
+        if !self.started {
+            self.local_ready = ready;
+        }
+    }
+
+    /// Returns handicaps of both players, indexed by player.
+    pub fn handicaps(&self) -> [Handicap; 2] {
+        if self.host {
+            [self.local_handicap, self.remote_handicap]
+        } else {
+            [self.remote_handicap, self.local_handicap]
+        }
+    }
+
+    /// Changes handicap of the local player, it can't be changed while the player is ready.
+    pub fn set_handicap(&mut self, handicap: Handicap) {
+        if !self.local_ready && !self.started {
+            self.local_handicap = handicap;
+        }
+    }
+
+    pub fn local_handicap(&self) -> Handicap {
+        self.local_handicap
+    }
+
+    /// Returns auto repeat of the moves of both players, indexed by player.
+    pub fn auto_repeats(&self) -> [AutoRepeat; 2] {
+        if self.host {
+            [self.local_auto_repeat, self.remote_auto_repeat]
+        } else {
+            [self.remote_auto_repeat, self.local_auto_repeat]
+        }
+    }
+
+    /// Changes auto repeat of the local player's moves, it's final once the game started.
+    pub fn set_auto_repeat(&mut self, auto_repeat: AutoRepeat) {
+        if !self.started {
+            self.local_auto_repeat = auto_repeat;
+        }
+    }
+
+    /// Returns `true` once both players are ready and the host started the countdown.
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    /// Reads all received packets and sends what the opponent should know. Called every frame,
+    /// so the packets keep the connection alive.
+    pub fn update(&mut self) {
+        self.receive();
+        if !self.connected {
+            let hello_due = self
+                .last_hello
+                .is_none_or(|last| last.elapsed() >= HELLO_INTERVAL);
+            if !self.host && hello_due {
+                self.send(&[HELLO]);
+                self.last_hello = Some(Instant::now());
+            }
+            return;
+        }
+        if self.host && self.local_ready && self.remote_ready {
+            self.started = true;
+        }
+        let flags = [(self.local_ready, READY), (self.started, START)]
+            .iter()
+            .filter(|(active, _)| *active)
+            .fold(0, |flags, (_, flag)| flags | flag);
+        let mut packet = vec![LOBBY, flags];
+        packet.extend(self.received_chat.to_le_bytes());
+        packet.push(self.local_handicap.speed_level as u8);
+        packet.extend((self.local_handicap.garbage_percent as u16).to_le_bytes());
+        packet.push(self.local_handicap.preview_count as u8);
+        packet.push(self.local_auto_repeat.delay.min(u8::MAX as u32) as u8);
+        packet.push(self.local_auto_repeat.interval.min(u8::MAX as u32) as u8);
+        self.send(&packet);
+        for (number, text) in &self.outgoing_chat {
+            let mut packet = vec![CHAT];
+            packet.extend(number.to_le_bytes());
+            packet.extend(text.as_bytes());
+            self.send(&packet);
+        }
+        self.send_inputs();
+    }
+
+    /// Sends the chat message to the opponent, it arrives even if some packets are lost.
+    pub fn send_chat(&mut self, text: &str) {
+        let mut len = text.len().min(MAX_CHAT_LEN);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.outgoing_chat
+            .push_back((self.sent_chat, text[..len].to_string()));
+        self.sent_chat += 1;
+    }
+
+    /// Returns chat messages received since the last call.
+    pub fn take_chat(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.incoming_chat)
+    }
+
+    /// Reads all received packets.
+    pub fn receive(&mut self) {
+        let mut packet = [0; 1 + 4 + 4 + 1 + MAX_PACKET_INPUTS + MAX_CHAT_LEN];
+        loop {
+            let (len, sender) = match self.socket.recv_from(&mut packet) {
+                Ok(received) => received,
+                // refused connections are reported until the opponent comes back, they are
+                // handled like no packets
+                Err(_) => break,
+            };
+            let packet = &packet[..len];
+            if !self.connected && !matches!(packet.first(), Some(&HELLO | &WELCOME)) {
+                continue;
+            }
+            match packet.first() {
+                Some(&HELLO) if self.host => {
+                    // the first player who says hello joins, the socket ignores the others
+                    if !self.connected {
+                        if self.socket.connect(sender).is_err() {
+                            continue;
+                        }
+                        self.connected = true;
+                    }
+                    // the welcome may have been lost, the opponent keeps asking for it
+                    self.send_welcome();
+                }
+                Some(&WELCOME) if !self.host && len == 9 => {
+                    self.seed = u64::from_le_bytes(packet[1..].try_into().unwrap());
+                    self.connected = true;
+                }
+                Some(&LOBBY) if len == 12 => {
+                    // the handicap is final once the game started
+                    if !self.started {
+                        self.remote_handicap = Handicap {
+                            speed_level: packet[6] as u32,
+                            garbage_percent: u16::from_le_bytes([packet[7], packet[8]]) as u32,
+                            preview_count: packet[9] as usize,
+                        };
+                        self.remote_auto_repeat = AutoRepeat {
+                            delay: packet[10] as u32,
+                            interval: packet[11] as u32,
+                        };
+                    }
+                    self.remote_ready = packet[1] & READY != 0;
+                    if packet[1] & START != 0 {
+                        self.started = true;
+                    }
+                    let received_chat = read_u32(&packet[2..]);
+                    self.outgoing_chat
+                        .retain(|&(number, _)| number >= received_chat);
+                }
+                Some(&CHAT) if len >= 5 => {
+                    // messages are shown in order, the later ones come again
+                    if read_u32(&packet[1..]) == self.received_chat {
+                        let text = String::from_utf8_lossy(&packet[5..]);
+                        self.incoming_chat.push(text.into_owned());
+                        self.received_chat += 1;
+                    }
+                }
+                Some(&INPUTS) if len >= 10 => {
+                    let acknowledged = read_u32(&packet[1..]);
+                    let first = read_u32(&packet[5..]);
+                    let inputs = &packet[10..(10 + packet[9] as usize).min(len)];
+                    self.inputs_received(acknowledged, first, inputs);
+                }
+                Some(&STATE_HASH) if len == 13 => {
+                    let tick = read_u32(&packet[1..]);
+                    let hash = u64::from_le_bytes(packet[5..13].try_into().unwrap());
+                    self.remote_hashes.insert(tick, hash);
+                    self.compare_hashes(tick);
+                }
+                _ => continue,
+            }
+            self.last_received = Instant::now();
+        }
+    }
+
+    fn inputs_received(&mut self, acknowledged: u32, first: u32, inputs: &[u8]) {
+        self.acknowledged = self.acknowledged.max(acknowledged);
+        self.drop_old_inputs();
+        let mut next = self.tick + self.remote_inputs.len() as u32;
+        for (tick, &input) in (first..).zip(inputs) {
+            if tick == next {
+                self.remote_inputs.push_back(ReplayInput::from_byte(input));
+                next += 1;
+            }
+        }
+    }
+
+    /// Returns inputs of both players for the next tick, or `None` if the input of the opponent
+    /// didn't arrive yet. The `local` input is played after the input delay.
+    pub fn advance(&mut self, local: ReplayInput) -> Option<[ReplayInput; 2]> {
+        let remote = self.remote_inputs.pop_front()?;
+        self.local_inputs.push_back(local);
+        let local = self.local_inputs[(self.tick - self.local_first) as usize];
+        self.tick += 1;
+        self.drop_old_inputs();
+        Some(if self.host {
+            [local, remote]
+        } else {
+            [remote, local]
+        })
+    }
+
+    fn drop_old_inputs(&mut self) {
+        while self.local_first < self.acknowledged.min(self.tick) {
+            self.local_inputs.pop_front();
+            self.local_first += 1;
+        }
+    }
+
+    /// Sends local inputs which the opponent didn't acknowledge yet, they are sent every tick
+    /// so lost packets don't matter.
+    pub fn send_inputs(&self) {
+        let received = self.tick + self.remote_inputs.len() as u32;
+        let unacknowledged = self.acknowledged.saturating_sub(self.local_first) as usize;
+        let inputs = self
+            .local_inputs
+            .iter()
+            .skip(unacknowledged)
+            .take(MAX_PACKET_INPUTS)
+            .map(|input| input.to_byte())
+            .collect::<Vec<_>>();
+        let mut packet = vec![INPUTS];
+        packet.extend(received.to_le_bytes());
+        packet.extend((self.local_first + unacknowledged as u32).to_le_bytes());
+        packet.push(inputs.len() as u8);
+        packet.extend(inputs);
+        self.send(&packet);
+    }
+
+    fn send_welcome(&self) {
+        let mut packet = vec![WELCOME];
+        packet.extend(self.seed.to_le_bytes());
+        self.send(&packet);
+    }
+
+    fn send(&self, packet: &[u8]) {
+        // lost packets are sent again, so errors are ignored too
+        let _ = self.socket.send(packet);
+    }
+
+    /// Returns `true` if the hash of the games should be compared after the last tick.
+    pub fn is_hash_tick(&self) -> bool {
+        self.tick.is_multiple_of(HASH_INTERVAL)
+    }
+
+    /// Sends the hash of the games after the last tick to the opponent.
+    pub fn check_state(&mut self, hash: u64) {
+        let mut packet = vec![STATE_HASH];
+        packet.extend(self.tick.to_le_bytes());
+        packet.extend(hash.to_le_bytes());
+        self.send(&packet);
+        self.local_hashes.insert(self.tick, hash);
+        self.compare_hashes(self.tick);
+    }
+
+    fn compare_hashes(&mut self, tick: u32) {
+        if let (Some(local), Some(remote)) =
+            (self.local_hashes.get(&tick), self.remote_hashes.get(&tick))
+        {
+            if local != remote && self.desync.is_none() {
+                self.desync = Some(tick);
+            }
+            self.local_hashes.remove(&tick);
+            self.remote_hashes.remove(&tick);
+        }
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.connected && self.last_received.elapsed() > DISCONNECT_TIMEOUT
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn resolve(address: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+    address
+        .to_socket_addrs()?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no IPv4 address"))
+}
+
+/// Returns the address of this computer in the local network with the port.
+fn local_address(port: u16) -> Option<SocketAddr> {
+    // connecting a UDP socket sends nothing, it only picks the interface of the route
+    let probe = UdpSocket::bind("0.0.0.0:0").ok()?;
+    probe.connect(("192.0.2.1", 9)).ok()?;
+    let ip = probe.local_addr().ok()?.ip();
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Returns the IPv4 address and the port encoded in 10 characters, like `3J9QW-0YV2F`.
+fn room_code(address: SocketAddr) -> String {
+    let ip = match address {
+        SocketAddr::V4(address) => u32::from(*address.ip()),
+        SocketAddr::V6(_) => 0,
+    };
+    let value = (ip as u64) << 16 | address.port() as u64;
+    let code = (0..CODE_LEN)
+        .rev()
+        .map(|i| CODE_ALPHABET[(value >> (i * 5)) as usize & 31] as char)
+        .collect::<String>();
+    format!("{}-{}", &code[..CODE_LEN / 2], &code[CODE_LEN / 2..])
+}
+
+/// Returns the address of the room code, dashes and the case don't matter.
+fn parse_room_code(code: &str) -> Option<SocketAddr> {
+    let code = code
+        .chars()
+        .filter(|&c| c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<Vec<_>>();
+    if code.len() != CODE_LEN {
+        return None;
+    }
+    let mut value = 0u64;
+    for c in code {
+        let digit = CODE_ALPHABET.iter().position(|&a| a as char == c)?;
+        value = value << 5 | digit as u64;
+    }
+    if value >> 48 != 0 {
+        return None;
+    }
+    let ip = std::net::Ipv4Addr::from((value >> 16) as u32);
+    Some(SocketAddr::new(ip.into(), value as u16))
+}
+
+/// Keeps the connection alive and reports desynchronized games and the lost
+/// connection.
+pub fn update_netplay(
+    mut commands: Commands,
+    netplay: Option<ResMut<Netplay>>,
+    ui: Res<UI>,
+    language: Res<Language>,
+) {
+    let mut netplay = match netplay {
+        Some(netplay) => netplay,
+        None => return,
+    };
+    netplay.update();
+
+    if let Some(tick) = netplay.desync {
+        if !netplay.reported_desync {
+            netplay.reported_desync = true;
+            error!("games of the peers differ since tick {}", tick);
+            toast::spawn_toast(&mut commands, &ui, language.get(Key::Desync));
+        }
+    }
+    let disconnected = netplay.is_disconnected();
+    if disconnected && !netplay.reported_disconnect {
+        warn!("no packets from {:?}", netplay.peer_address());
+        toast::spawn_toast(&mut commands, &ui, language.get(Key::Disconnected));
+    }
+    netplay.reported_disconnect = disconnected;
+}
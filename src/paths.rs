@@ -0,0 +1,86 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Directory of the game in the platform's config and data directories.
+const APP_DIR: &str = "tetris";
+
+/// Returns the path of the config file in `~/.config/tetris` on Linux, `%APPDATA%\tetris` on
+/// Windows and `~/Library/Application Support/tetris` on macOS. Platforms without such a
+/// directory keep the file in the working directory.
+pub fn config_path(name: &str) -> PathBuf {
+    app_dir(dirs::config_dir()).join(name)
+}
+
+/// Returns the path of the data file or directory in `~/.local/share/tetris` on Linux, on
+/// Windows and macOS it's the same directory as the config one.
+pub fn data_path(name: &str) -> PathBuf {
+    app_dir(dirs::data_dir()).join(name)
+}
+
+fn app_dir(platform_dir: Option<PathBuf>) -> PathBuf {
+    platform_dir.map_or_else(PathBuf::new, |dir| dir.join(APP_DIR))
+}
+
+/// Creates the config and data directories.
+pub fn create_dirs() -> io::Result<()> {
+    fs::create_dir_all(config_path(""))?;
+    fs::create_dir_all(data_path(""))
+}
+
+/// Copies the files and directories of an old install from the directory of the executable,
+/// where older versions kept them, to the config and data directories. The old install is
+/// recognized by the `marker` config file, which is copied last, so the migration is done once
+/// it's in the config directory. Files which are in the new directories already are left
+/// alone, the old ones are kept. Returns the errors of the files which weren't copied.
+pub fn migrate_old_install(
+    marker: &str,
+    config_files: &[&str],
+    data_files: &[&str],
+) -> Vec<String> {
+    let old_dir = match env::current_exe() {
+        Ok(exe) => exe.parent().map_or_else(PathBuf::new, Path::to_path_buf),
+        Err(e) => return vec![format!("failed to find the executable: {}", e)],
+    };
+    let new_marker = config_path(marker);
+    if !old_dir.join(marker).is_file() || new_marker.exists() || old_dir.join(marker) == new_marker
+    {
+        return vec![];
+    }
+
+    let copied = config_files
+        .iter()
+        .filter(|&&name| name != marker)
+        .map(|name| (name, config_path(name)))
+        .chain(data_files.iter().map(|name| (name, data_path(name))));
+    let mut errors = vec![];
+    for (name, new) in copied {
+        let old = old_dir.join(name);
+        if old.exists() && !new.exists() {
+            if let Err(e) = copy_all(&old, &new) {
+                errors.push(format!("failed to copy {}: {}", old.display(), e));
+            }
+        }
+    }
+    // an interrupted migration is tried again on the next launch
+    if errors.is_empty() {
+        if let Err(e) = fs::copy(old_dir.join(marker), &new_marker) {
+            errors.push(format!("failed to copy {}: {}", marker, e));
+        }
+    }
+    errors
+}
+
+/// Copies the file or the directory with everything in it.
+fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_all(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
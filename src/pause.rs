@@ -0,0 +1,324 @@
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::SystemParam, prelude::*, window::WindowFocused};
+
+use crate::{
+    accessibility::{HighContrast, ReduceMotion},
+    actions::ActionMaps,
+    announcer::Announcer,
+    audio::{Channel, SoundPack, Volume, MAX_VOLUME, VOLUME_STEP},
+    frame_rate::FrameRate,
+    i18n::{Key, Language},
+    menu::spawn_menu_text,
+    netplay::Netplay,
+    profiles::Profiles,
+    settings::Settings,
+    AppState, GameOptions, UI,
+};
+
+/// Escape also asks whether to quit in the other screens and in online games.
+const TOGGLE_KEYS: [KeyCode; 2] = [KeyCode::P, KeyCode::Escape];
+
+/// Entries of the pause menu in the order they are listed.
+const MAIN_ENTRIES: [Entry; 4] = [Entry::Resume, Entry::Restart, Entry::Settings, Entry::Quit];
+const SETTINGS_ENTRIES: [Entry; 13] = [
+    Entry::Language,
+    Entry::Controls,
+    Entry::Volume(Channel::Master),
+    Entry::Volume(Channel::Music),
+    Entry::Volume(Channel::Sfx),
+    Entry::Volume(Channel::Voice),
+    Entry::Announcer,
+    Entry::SoundPack,
+    Entry::Vsync,
+    Entry::FpsCap,
+    Entry::ReduceMotion,
+    Entry::HighContrast,
+    Entry::Back,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    Resume,
+    Restart,
+    /// Opens the settings page.
+    Settings,
+    /// Returns to the main menu.
+    Quit,
+    Language,
+    Controls,
+    /// Slider changed by left and right.
+    Volume(Channel),
+    SoundPack,
+    Announcer,
+    Vsync,
+    FpsCap,
+    ReduceMotion,
+    HighContrast,
+    /// Returns to the first page.
+    Back,
+}
+
+/// Preferences changed in the settings page.
+#[derive(SystemParam)]
+pub struct Preferences<'w, 's> {
+    frame_rate: ResMut<'w, FrameRate>,
+    reduce_motion: ResMut<'w, ReduceMotion>,
+    high_contrast: ResMut<'w, HighContrast>,
+    volume: ResMut<'w, Volume>,
+    sound_pack: ResMut<'w, SoundPack>,
+    announcer: ResMut<'w, Announcer>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+/// Menu over the paused game. While it's open the games don't tick and the players' keys don't
+/// reach them.
+#[derive(Default)]
+pub struct Pause {
+    open: bool,
+    settings_page: bool,
+    selected: usize,
+}
+
+impl Pause {
+    pub fn is_active(&self) -> bool {
+        self.open
+    }
+
+    fn entries(&self) -> &'static [Entry] {
+        if self.settings_page {
+            &SETTINGS_ENTRIES
+        } else {
+            &MAIN_ENTRIES
+        }
+    }
+
+    fn to_text(
+        &self,
+        language: Language,
+        profiles: &Profiles,
+        preferences: &Preferences,
+        versus: bool,
+    ) -> String {
+        let mut text = format!("{}\n\n", language.get(Key::Paused));
+        for (i, entry) in self.entries().iter().enumerate() {
+            let label = match entry {
+                Entry::Resume => language.get(Key::Resume).to_string(),
+                Entry::Restart => language.get(Key::Restart).to_string(),
+                Entry::Settings => language.get(Key::SettingsEntry).to_string(),
+                Entry::Quit => language.get(Key::QuitToMenu).to_string(),
+                Entry::Language => {
+                    language.format(Key::LanguageEntry, &[&language.get(Key::LanguageName)])
+                }
+                Entry::Controls if versus => language.format(
+                    Key::VersusControlsEntry,
+                    &[&profiles.versus(0).name, &profiles.versus(1).name],
+                ),
+                Entry::Controls => language.format(Key::ControlsEntry, &[&profiles.single().name]),
+                Entry::Volume(channel) => {
+                    let key = match channel {
+                        Channel::Master => Key::MasterVolumeEntry,
+                        Channel::Music => Key::MusicVolumeEntry,
+                        Channel::Sfx => Key::SfxVolumeEntry,
+                        Channel::Voice => Key::VoiceVolumeEntry,
+                    };
+                    language.format(key, &[&preferences.volume.get(*channel).to_string()])
+                }
+                Entry::SoundPack => {
+                    let default = language.get(Key::DefaultSoundPack);
+                    let name = preferences.sound_pack.name().unwrap_or(default);
+                    language.format(Key::SoundPackEntry, &[&name])
+                }
+                Entry::Announcer => {
+                    let value = on_off(preferences.announcer.enabled);
+                    language.format(Key::AnnouncerEntry, &[&language.get(value)])
+                }
+                Entry::Vsync => {
+                    let value = on_off(preferences.frame_rate.vsync);
+                    language.format(Key::VsyncEntry, &[&language.get(value)])
+                }
+                Entry::FpsCap => {
+                    let value = match preferences.frame_rate.fps_cap {
+                        Some(cap) => cap.to_string(),
+                        None => language.get(Key::Off).to_string(),
+                    };
+                    language.format(Key::FpsCapEntry, &[&value])
+                }
+                Entry::ReduceMotion => {
+                    let value = on_off(preferences.reduce_motion.0);
+                    language.format(Key::ReduceMotionEntry, &[&language.get(value)])
+                }
+                Entry::HighContrast => {
+                    let value = on_off(preferences.high_contrast.0);
+                    language.format(Key::HighContrastEntry, &[&language.get(value)])
+                }
+                Entry::Back => language.get(Key::Back).to_string(),
+            };
+            if i == self.selected {
+                text += &format!("> {} <\n", label);
+            } else {
+                text += &format!("{}\n", label);
+            }
+        }
+        text
+    }
+}
+
+fn on_off(value: bool) -> Key {
+    if value {
+        Key::On
+    } else {
+        Key::Off
+    }
+}
+
+/// Text of the pause menu.
+#[derive(Component)]
+pub struct PauseText;
+
+/// Opens the pause menu by the toggle key and applies the selected entry. Online games can't be
+/// paused.
+pub fn update_pause(
+    mut commands: Commands,
+    ui: Res<UI>,
+    mut pause: ResMut<Pause>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut language: ResMut<Language>,
+    mut profiles: ResMut<Profiles>,
+    mut action_maps: ResMut<ActionMaps>,
+    mut settings: ResMut<Settings>,
+    mut preferences: Preferences,
+    game_options: Res<GameOptions>,
+    netplay: Option<Res<Netplay>>,
+    mut state: ResMut<State<AppState>>,
+    mut texts: Query<(&Parent, &mut Text), With<PauseText>>,
+) {
+    if !pause.open {
+        if keys.any_just_pressed(TOGGLE_KEYS) && netplay.is_none() {
+            keys.clear();
+            open_pause_menu(&mut commands, &ui, &mut pause);
+        }
+        return;
+    }
+
+    let versus = game_options.player_count > 1;
+    let entry_count = pause.entries().len();
+    if keys.just_pressed(KeyCode::Up) {
+        pause.selected = (pause.selected + entry_count - 1) % entry_count;
+    } else if keys.just_pressed(KeyCode::Down) {
+        pause.selected = (pause.selected + 1) % entry_count;
+    } else if let (Entry::Volume(channel), true) = (
+        pause.entries()[pause.selected],
+        keys.any_just_pressed([KeyCode::Left, KeyCode::Right]),
+    ) {
+        let step = VOLUME_STEP as i32;
+        let delta = if keys.just_pressed(KeyCode::Left) {
+            -step
+        } else {
+            step
+        };
+        preferences.volume.adjust(channel, delta, &mut settings);
+    } else if keys.any_just_pressed(TOGGLE_KEYS) {
+        close_pause_menu(&mut commands, &mut pause, &texts);
+        return;
+    } else if keys.just_pressed(KeyCode::Return) {
+        match pause.entries()[pause.selected] {
+            Entry::Resume => {
+                close_pause_menu(&mut commands, &mut pause, &texts);
+                return;
+            }
+            // the menu is closed when the game is left
+            Entry::Restart => {
+                state.restart().unwrap();
+                return;
+            }
+            Entry::Quit => {
+                state.set(AppState::Menu).unwrap();
+                return;
+            }
+            Entry::Settings | Entry::Back => {
+                pause.settings_page = !pause.settings_page;
+                pause.selected = 0;
+            }
+            Entry::Language => *language = language.next(),
+            Entry::Controls => {
+                // shift selects the profile of the second versus player, as in the menu
+                if !versus {
+                    profiles.next_single(&mut settings);
+                } else if keys.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+                    profiles.next_versus(1, &mut settings);
+                } else {
+                    profiles.next_versus(0, &mut settings);
+                }
+                *action_maps = profiles.action_maps();
+            }
+            // enter raises the volume, from the maximum back to silence
+            Entry::Volume(channel) => {
+                let step = if preferences.volume.get(channel) == MAX_VOLUME {
+                    -(MAX_VOLUME as i32)
+                } else {
+                    VOLUME_STEP as i32
+                };
+                preferences.volume.adjust(channel, step, &mut settings);
+            }
+            Entry::SoundPack => preferences.sound_pack.next(&mut settings),
+            Entry::Announcer => preferences.announcer.toggle(&mut settings),
+            Entry::Vsync => preferences.frame_rate.toggle_vsync(&mut settings),
+            Entry::FpsCap => preferences.frame_rate.next_fps_cap(&mut settings),
+            Entry::ReduceMotion => preferences.reduce_motion.toggle(&mut settings),
+            Entry::HighContrast => preferences.high_contrast.toggle(&mut settings),
+        }
+    }
+    // keys used in the menu shouldn't trigger other hotkeys
+    keys.clear();
+
+    for (_, mut text) in texts.iter_mut() {
+        text.sections[0].value = pause.to_text(*language, &profiles, &preferences, versus);
+    }
+}
+
+fn open_pause_menu(commands: &mut Commands, ui: &UI, pause: &mut Pause) {
+    *pause = Pause {
+        open: true,
+        ..default()
+    };
+    let text = spawn_menu_text(commands, ui, "");
+    commands.entity(text).insert(PauseText);
+}
+
+/// Pauses the game when the window loses focus, unless the `auto_pause` setting is `false`.
+pub fn pause_on_focus_loss(
+    mut commands: Commands,
+    ui: Res<UI>,
+    mut pause: ResMut<Pause>,
+    mut focus_events: EventReader<WindowFocused>,
+    settings: Res<Settings>,
+    netplay: Option<Res<Netplay>>,
+) {
+    let focus_lost = focus_events.iter().any(|event| !event.focused);
+    let enabled = settings.get("auto_pause") != Some("false");
+    if focus_lost && enabled && !pause.open && netplay.is_none() {
+        open_pause_menu(&mut commands, &ui, &mut pause);
+    }
+}
+
+fn close_pause_menu(
+    commands: &mut Commands,
+    pause: &mut Pause,
+    texts: &Query<(&Parent, &mut Text), With<PauseText>>,
+) {
+    *pause = Pause::default();
+    for (parent, _) in texts.iter() {
+        commands.entity(parent.get()).despawn_recursive();
+    }
+}
+
+/// Closes the pause menu when the game is left.
+pub fn cleanup_pause(
+    mut commands: Commands,
+    mut pause: ResMut<Pause>,
+    texts: Query<(&Parent, &mut Text), With<PauseText>>,
+) {
+    close_pause_menu(&mut commands, &mut pause, &texts);
+}
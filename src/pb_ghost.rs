@@ -0,0 +1,150 @@
+use bevy::{math::vec3, prelude::*, sprite::Anchor};
+
+use crate::{
+    cells::CellStyle,
+    game::{self, Game, Replay, ReplayInput},
+    player::Player,
+    pos_to_vec3, replays,
+    settings::Settings,
+    UNIT_PX,
+};
+
+const OUTLINE_COLOR: Color = Color::rgba(1., 1., 1., 0.35);
+/// Pixels of the cell edges drawn by the outline.
+const OUTLINE_WIDTH: u32 = 2;
+/// Outline is drawn over the board points.
+const OUTLINE_Z: f32 = 1.;
+
+/// Personal best run of the mode played again in the background, in step with the game of the
+/// single player. The pace of the game is compared with it and the `pb_ghost_outline` setting
+/// shows its board over the player's one.
+pub struct PbGhost {
+    replay: Replay,
+    inputs: Vec<ReplayInput>,
+    game: Game,
+    /// Texture of the outline cells, drawn when the outline is shown for the first time.
+    texture: Option<Handle<Image>>,
+    /// Visible cells of the ghost's board which are drawn.
+    outline: Vec<(usize, usize)>,
+}
+
+impl PbGhost {
+    /// Loads the replay of the personal best run of the mode, if there is one.
+    pub fn load(mode: &str) -> Option<Self> {
+        let replay = match replays::load_replay(&replays::best_replay_path(mode)) {
+            Ok(replay) => replay,
+            Err(e) => {
+                info!("no personal best ghost of {}: {}", mode, e);
+                return None;
+            }
+        };
+        Some(Self {
+            inputs: replay.inputs().collect(),
+            game: replay.new_game(),
+            replay,
+            texture: None,
+            outline: vec![],
+        })
+    }
+
+    /// Returns how many lines the game cleared more than the personal best run at the same
+    /// time, negative when it's behind.
+    pub fn pace(&self, game: &Game) -> i64 {
+        game.score().lines as i64 - self.game.score().lines as i64
+    }
+
+    /// Plays the ghost's game up to the tick of the player's game, a restarted game starts the
+    /// run of the ghost again.
+    fn catch_up(&mut self, ticks: u32) {
+        if self.game.score().ticks > ticks {
+            self.game = self.replay.new_game();
+        }
+        while self.game.score().ticks < ticks && !self.game.is_game_over() {
+            // the replay has an input for every tick until the game is over
+            match self.inputs.get(self.game.score().ticks as usize) {
+                Some(input) => self.game.tick(input),
+                None => break,
+            };
+        }
+    }
+}
+
+/// Cell of the outline of the ghost's board.
+#[derive(Component)]
+pub struct OutlineCell;
+
+pub fn update_pb_ghost(
+    mut commands: Commands,
+    ghost: Option<ResMut<PbGhost>>,
+    players: Query<&Player>,
+    cells: Query<Entity, With<OutlineCell>>,
+    settings: Res<Settings>,
+    style: Res<CellStyle>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    // the ghost is loaded on entering single player games
+    let mut ghost = match ghost {
+        Some(ghost) => ghost,
+        None => return,
+    };
+    let player = match players.iter().find(|player| player.index == 0) {
+        Some(player) => player,
+        None => return,
+    };
+    ghost.catch_up(player.game.score().ticks);
+
+    let outline = if settings.get("pb_ghost_outline") == Some("true") {
+        let gravity = ghost.game.rules().gravity;
+        let mut outline = ghost
+            .game
+            .board_points()
+            .filter_map(|(_, pos)| gravity.visible_cell(pos))
+            .collect::<Vec<_>>();
+        outline.sort_unstable();
+        outline
+    } else {
+        vec![]
+    };
+    if outline == ghost.outline {
+        return;
+    }
+
+    for cell in cells.iter() {
+        commands.entity(cell).despawn();
+    }
+    if !outline.is_empty() && ghost.texture.is_none() {
+        let image = style.cell_image(|edges| {
+            if edges.into_iter().min().unwrap_or_default() < OUTLINE_WIDTH {
+                [255; 4]
+            } else {
+                [0; 4]
+            }
+        });
+        ghost.texture = Some(images.add(image));
+    }
+    for &(x, row) in &outline {
+        let mut translation = pos_to_vec3(game::board_position(x, row));
+        translation.z = OUTLINE_Z;
+        let cell = commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: OUTLINE_COLOR,
+                    anchor: Anchor::BottomLeft,
+                    // the unit sized sprite is scaled to the cell size
+                    custom_size: Some(Vec2::ONE),
+                    ..default()
+                },
+                texture: ghost.texture.clone().unwrap_or_default(),
+                transform: Transform {
+                    translation,
+                    scale: vec3(UNIT_PX, UNIT_PX, 1.),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(OutlineCell)
+            .id();
+        commands.entity(player.board).add_child(cell);
+    }
+    ghost.outline = outline;
+}
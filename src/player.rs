@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use bevy::{
+    input::{
+        gamepad::{GamepadEvent, GamepadEventType},
+        keyboard::KeyboardInput,
+        ButtonState,
+    },
+    prelude::*,
+};
+
+use crate::{
+    actions::{Action, ActionMap, ActionMaps, Binding},
+    chat::Chat,
+    console::Console,
+    game::{self, Id},
+    netplay::Netplay,
+    pause::Pause,
+    quit::QuitDialog,
+    GameOptions,
+};
+
+/// Presses and releases of the bindings of one action, accumulated between ticks.
+#[derive(Default)]
+struct ActionKey {
+    held: bool,
+    /// Presses which weren't played yet.
+    presses: u32,
+    /// Whether the action was active on the last tick.
+    active: bool,
+}
+
+impl ActionKey {
+    fn press(&mut self) {
+        // repeated press events of a held key are not new presses
+        if !self.held {
+            self.held = true;
+            self.presses += 1;
+        }
+    }
+
+    fn release(&mut self) {
+        self.held = false;
+    }
+
+    /// Returns whether the action is active on the next tick. Every press is active for at
+    /// least one tick and presses waiting for the same tick are separated by an inactive one,
+    /// so each of them registers.
+    fn peek(&self) -> bool {
+        match (self.presses, self.active) {
+            (0, _) => self.held,
+            (_, true) => false,
+            (_, false) => true,
+        }
+    }
+
+    fn advance(&mut self) {
+        let active = self.peek();
+        if active && self.presses > 0 {
+            self.presses -= 1;
+        }
+        self.active = active;
+    }
+
+    fn reset(&mut self) {
+        self.presses = 0;
+        self.active = false;
+    }
+}
+
+/// Input of the player's keys, the next tick plays it and calls `advance`.
+#[derive(Default)]
+pub struct RawInput {
+    move_left: ActionKey,
+    move_right: ActionKey,
+    rotate: ActionKey,
+    fast_drop: ActionKey,
+    instant_drop: ActionKey,
+    use_item: ActionKey,
+    hold: ActionKey,
+}
+
+impl RawInput {
+    /// Applies the press or release to the actions bound to it, `ignore_presses` drops the
+    /// presses but keeps the releases, so no action is stuck.
+    fn apply(&mut self, actions: &ActionMap, event: &BindingEvent, ignore_presses: bool) {
+        for action in actions.actions(event.binding, event.gamepad) {
+            let action = self.action_mut(action);
+            if !event.pressed {
+                action.release();
+            } else if !ignore_presses {
+                action.press();
+            }
+        }
+    }
+
+    fn action_mut(&mut self, action: Action) -> &mut ActionKey {
+        match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::FastDrop => &mut self.fast_drop,
+            Action::Rotate => &mut self.rotate,
+            Action::InstantDrop => &mut self.instant_drop,
+            Action::UseItem => &mut self.use_item,
+            Action::Hold => &mut self.hold,
+        }
+    }
+
+    fn actions_mut(&mut self) -> [&mut ActionKey; 7] {
+        [
+            &mut self.move_left,
+            &mut self.move_right,
+            &mut self.rotate,
+            &mut self.fast_drop,
+            &mut self.instant_drop,
+            &mut self.use_item,
+            &mut self.hold,
+        ]
+    }
+
+    /// Moves to the next tick, the presses played on this one are consumed.
+    pub fn advance(&mut self) {
+        for action in self.actions_mut() {
+            action.advance();
+        }
+    }
+
+    /// Drops the presses which weren't played, the keys held down stay held.
+    pub fn reset(&mut self) {
+        for action in self.actions_mut() {
+            action.reset();
+        }
+    }
+}
+
+impl game::Input for RawInput {
+    fn move_left(&self) -> bool {
+        self.move_left.peek()
+    }
+
+    fn move_right(&self) -> bool {
+        self.move_right.peek()
+    }
+
+    fn rotate(&self) -> bool {
+        self.rotate.peek()
+    }
+
+    fn fast_drop(&self) -> bool {
+        self.fast_drop.peek()
+    }
+
+    fn instant_drop(&self) -> bool {
+        self.instant_drop.peek()
+    }
+
+    fn use_item(&self) -> bool {
+        self.use_item.peek()
+    }
+
+    fn hold(&self) -> bool {
+        self.hold.peek()
+    }
+}
+
+pub type PointEntities = HashMap<Id, Entity>;
+
+#[derive(Component)]
+pub struct Player {
+    pub index: usize,
+    pub game: game::Game,
+    pub input: RawInput,
+    /// Input of the second pilot of a co-op game.
+    pub partner_input: Option<RawInput>,
+    /// Computer player which controls the game instead of keys.
+    pub bot: Option<game::Bot>,
+    /// Parent entity of the player's point entities.
+    pub board: Entity,
+    pub points: PointEntities,
+}
+
+/// Press or release of a key or a gamepad button.
+struct BindingEvent {
+    binding: Binding,
+    gamepad: Option<Gamepad>,
+    pressed: bool,
+}
+
+/// Gamepad buttons pressed further than this are pressed.
+const BUTTON_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Accumulates the key and gamepad events of every frame through the action maps, so taps
+/// shorter than a tick are not lost.
+pub fn check_input(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+    console: Res<Console>,
+    chat: Res<Chat>,
+    pause: Res<Pause>,
+    quit_dialog: Res<QuitDialog>,
+    options: Res<GameOptions>,
+    action_maps: Res<ActionMaps>,
+    netplay: Option<Res<Netplay>>,
+    mut players: Query<&mut Player>,
+) {
+    // keys of the menus don't reach the games
+    let typing = console.is_open() || chat.is_open() || pause.is_active() || quit_dialog.is_open();
+    let key_events = keyboard_events.iter().filter_map(|event| {
+        Some(BindingEvent {
+            binding: Binding::Key(event.key_code?),
+            gamepad: None,
+            pressed: event.state == ButtonState::Pressed,
+        })
+    });
+    let button_events = gamepad_events
+        .iter()
+        .filter_map(|event| match event.event_type {
+            GamepadEventType::ButtonChanged(button, value) => Some(BindingEvent {
+                binding: Binding::Button(button),
+                gamepad: Some(event.gamepad),
+                pressed: value >= BUTTON_PRESS_THRESHOLD,
+            }),
+            _ => None,
+        });
+    let events = key_events.chain(button_events).collect::<Vec<_>>();
+    // the player sparring with the bot uses the single player controls
+    let versus = players.iter().filter(|player| player.bot.is_none()).count() > 1;
+
+    for mut player in players.iter_mut() {
+        let player = &mut *player;
+        if player.bot.is_some() {
+            continue;
+        }
+        // the opponent's inputs come from the network, the local player uses the single
+        // player controls and the co-op pilots use the versus controls of the first two players
+        let coop = player.partner_input.is_some();
+        let actions = match &netplay {
+            Some(netplay) if player.index != netplay.local_index() => continue,
+            Some(_) => Some(&action_maps.single),
+            None => action_maps.player(player.index, versus || coop),
+        };
+        let mirrored = |actions: Option<&ActionMap>| match actions {
+            Some(actions) if options.mirror_input => Some(actions.mirrored()),
+            actions => actions.cloned(),
+        };
+        if let Some(actions) = mirrored(actions) {
+            for event in &events {
+                player.input.apply(&actions, event, typing);
+            }
+        }
+        if let (Some(partner_input), Some(actions)) = (
+            &mut player.partner_input,
+            mirrored(action_maps.player(1, true)),
+        ) {
+            for event in &events {
+                partner_input.apply(&actions, event, typing);
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use std::{collections::VecDeque, ops::Range};
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, thread_rng};
+
+use crate::{
+    cli::Cli,
+    editor::Editor,
+    game::{
+        board_position, secret_grade_name, BlockType, Fumen, Game, GameRules, GameSnapshot,
+        BOARD_WIDTH, SECRET_GRADE_ROWS,
+    },
+    hud::HudValue,
+    i18n::{Key, Language},
+    pause::Pause,
+    player::Player,
+    respawn_points, restart_player, toast, TICK_DURATION, UI,
+};
+
+const RESET_KEY: KeyCode = KeyCode::R;
+const CHECKPOINT_KEY: KeyCode = KeyCode::C;
+const REWIND_KEY: KeyCode = KeyCode::Back;
+/// Seconds of the game which can be rewound.
+const REWIND_SECONDS: f32 = 3.;
+
+/// Columns of the well left empty on the combo practice board.
+const COMBO_WELL: Range<usize> = 3..7;
+const COMBO_STACK_HEIGHT: usize = 12;
+/// Well columns filled in the floor row, so any block can start the combo.
+const COMBO_RESIDUE: [usize; 3] = [3, 4, 5];
+
+/// Number of blocks which fill the four rows of the perfect clear.
+const OPENER_BLOCKS: usize = 10;
+const OPENER_ROWS: usize = 4;
+/// First bag and the start of the second one, each of them allows a perfect clear of the four
+/// bottom rows by dropping the blocks without tucks or spins.
+const PC_OPENERS: [[BlockType; OPENER_BLOCKS]; 8] = {
+    use BlockType::*;
+    [
+        [I, J, T, O, S, Z, L, T, Z, L],
+        [L, O, J, I, Z, S, T, L, T, I],
+        [T, O, S, J, I, Z, L, I, O, T],
+        [T, L, J, S, I, Z, O, T, S, I],
+        [I, J, T, L, S, O, Z, T, O, L],
+        [O, T, J, Z, L, I, S, T, Z, J],
+        [L, O, J, Z, I, T, S, T, O, L],
+        [J, T, I, S, O, L, Z, S, T, L],
+    ]
+};
+
+/// Single player preset with a prepared game, the game is reset to it instantly by the reset
+/// key or when the attempt is over.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Practice {
+    /// Stack with a 4-wide well for drilling combos.
+    Combo,
+    /// Blocks of a perfect clear opener, the attempt succeeds if the board is cleared with them.
+    PerfectClear,
+    /// Board and blocks of a fumen diagram, the attempt is over when its blocks are placed.
+    Setup(Fumen),
+    /// Zig-zag pattern of holes built from the floor, graded by its height when the attempt is
+    /// over.
+    SecretGrade,
+}
+
+impl Practice {
+    /// Returns the preset given by `--combo-practice`, `--pc-practice`, `--secret-grade` or
+    /// `--fumen=<diagram or file>`.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        if cli.combo_practice {
+            return Some(Practice::Combo);
+        }
+        if cli.pc_practice {
+            return Some(Practice::PerfectClear);
+        }
+        if cli.secret_grade {
+            return Some(Practice::SecretGrade);
+        }
+        let fumen = cli.fumen.as_ref()?;
+        match load_fumen(fumen) {
+            Ok(fumen) => Some(Practice::Setup(fumen)),
+            Err(e) => {
+                eprintln!("failed to load the fumen {}: {}", fumen, e);
+                None
+            }
+        }
+    }
+
+    /// Returns values shown in the HUD, the block history helps to train awareness of the dealt
+    /// blocks.
+    pub fn hud_values(&self) -> Vec<HudValue> {
+        let mut values = match self {
+            Practice::Combo => vec![HudValue::Combo, HudValue::BestCombo],
+            Practice::PerfectClear => vec![HudValue::PerfectClears, HudValue::SuccessRate],
+            Practice::Setup(_) => vec![HudValue::Lines, HudValue::Combo],
+            Practice::SecretGrade => vec![HudValue::SecretGrade, HudValue::Lines],
+        };
+        values.push(HudValue::BlockHistory);
+        values
+    }
+
+    fn new_game(&self, rules: &GameRules) -> Game {
+        match self {
+            Practice::Combo => {
+                let mut game = Game::with_rules(rules.clone());
+                let gravity = game.rules().gravity;
+                let floor = gravity.floor_rows(1).start;
+                let cells = gravity
+                    .floor_rows(COMBO_STACK_HEIGHT)
+                    .flat_map(|y| (0..BOARD_WIDTH).map(move |x| (x, y)))
+                    .filter(|&(x, y)| {
+                        !COMBO_WELL.contains(&x) || (y == floor && COMBO_RESIDUE.contains(&x))
+                    })
+                    .map(|(x, y)| board_position(x, y))
+                    .collect::<Vec<_>>();
+                game.prefill_board(cells);
+                game
+            }
+            Practice::PerfectClear => {
+                let opener = PC_OPENERS.choose(&mut thread_rng()).unwrap();
+                Game::with_blocks(rules.clone(), opener)
+            }
+            Practice::Setup(fumen) => fumen.new_game(rules.clone()),
+            Practice::SecretGrade => Game::with_rules(rules.clone()),
+        }
+    }
+
+    /// Returns whether the last placement keeps the attempt solvable, so it can be a
+    /// checkpoint. Only the presets with a given sequence of blocks have checkpoints.
+    fn is_correct_placement(&self, game: &Game) -> bool {
+        match self {
+            Practice::PerfectClear => {
+                let metrics = game.board_metrics();
+                !game.is_game_over()
+                    && metrics.holes == 0
+                    && metrics.column_heights.into_iter().max().unwrap_or(0) <= OPENER_ROWS
+            }
+            Practice::Setup(fumen) => !fumen.blocks.is_empty() && !game.is_game_over(),
+            Practice::Combo | Practice::SecretGrade => false,
+        }
+    }
+
+    /// Returns whether the attempt is over because its goal was reached.
+    fn is_solved(&self, game: &Game) -> bool {
+        match self {
+            Practice::PerfectClear => game.score().perfect_clears > 0,
+            Practice::Setup(fumen) => {
+                !game.is_game_over()
+                    && !fumen.blocks.is_empty()
+                    && game.locked_blocks().len() >= fumen.blocks.len()
+            }
+            Practice::Combo | Practice::SecretGrade => false,
+        }
+    }
+
+    fn is_attempt_over(&self, game: &Game) -> bool {
+        match self {
+            Practice::Combo => game.is_game_over(),
+            Practice::PerfectClear => {
+                game.is_game_over()
+                    || game.score().perfect_clears > 0
+                    || game.locked_blocks().len() >= OPENER_BLOCKS
+            }
+            // diagrams without blocks are played until the game is over
+            Practice::Setup(fumen) => {
+                game.is_game_over()
+                    || (!fumen.blocks.is_empty()
+                        && game.locked_blocks().len() >= fumen.blocks.len())
+            }
+            Practice::SecretGrade => {
+                game.is_game_over() || game.secret_grade() == SECRET_GRADE_ROWS
+            }
+        }
+    }
+}
+
+/// Decodes the fumen diagram, or the one in the file if the text isn't a diagram.
+pub fn load_fumen(text: &str) -> Result<Fumen, String> {
+    if text.contains('@') {
+        return Fumen::decode(text);
+    }
+    let diagram = std::fs::read_to_string(text).map_err(|e| e.to_string())?;
+    Fumen::decode(&diagram)
+}
+
+/// State of the attempt after a correct placement, which can be retried instead of the start.
+#[derive(Clone)]
+struct Checkpoint {
+    snapshot: GameSnapshot,
+    /// Number of blocks locked up to the checkpoint.
+    locked_blocks: usize,
+}
+
+/// Results of the practice kept across resets.
+pub struct PracticeSession {
+    practice: Practice,
+    pub best_combo: u32,
+    /// Number of finished or reset attempts with at least one locked block.
+    pub attempts: u32,
+    /// Number of attempts with a perfect clear.
+    pub perfect_clears: u32,
+    started: bool,
+    /// States of the attempt before its last ticks, the oldest first.
+    history: VecDeque<GameSnapshot>,
+    rewinding: bool,
+    /// Number of blocks locked before the last tick.
+    locked_blocks: usize,
+    /// State after the last placement if it was correct, the checkpoint key makes it the
+    /// checkpoint.
+    placement: Option<Checkpoint>,
+    /// Failed attempts and resets are retried from here.
+    checkpoint: Option<Checkpoint>,
+}
+
+impl PracticeSession {
+    pub fn new(practice: Practice) -> Self {
+        Self {
+            practice,
+            best_combo: 0,
+            attempts: 0,
+            perfect_clears: 0,
+            started: false,
+            history: VecDeque::new(),
+            rewinding: false,
+            locked_blocks: 0,
+            placement: None,
+            checkpoint: None,
+        }
+    }
+
+    /// Returns `true` while the game is played backwards instead of ticking.
+    pub fn is_rewinding(&self) -> bool {
+        self.rewinding
+    }
+}
+
+pub fn update_practice(
+    mut commands: Commands,
+    session: Option<ResMut<PracticeSession>>,
+    mut players: Query<(Entity, &mut Player)>,
+    rules: Res<GameRules>,
+    keys: Res<Input<KeyCode>>,
+    ui: Res<UI>,
+    language: Res<Language>,
+) {
+    // the session is created on entering the game
+    let mut session = match session {
+        Some(session) => session,
+        None => return,
+    };
+
+    for (player_entity, mut player) in players.iter_mut() {
+        let score = player.game.score();
+        session.best_combo = session.best_combo.max(score.combo);
+
+        // players are spawned on entering the game, so their games are prepared here
+        if !session.started {
+            let mut hint = language.get(Key::ResetHint).to_string();
+            if matches!(
+                session.practice,
+                Practice::PerfectClear | Practice::Setup(_)
+            ) {
+                hint += &format!("  {}", language.get(Key::CheckpointHint));
+            }
+            toast::spawn_toast(&mut commands, &ui, hint);
+            session.started = true;
+        } else {
+            if keys.just_pressed(CHECKPOINT_KEY) {
+                if let Some(placement) = session.placement.clone() {
+                    session.checkpoint = Some(placement);
+                    toast::spawn_toast(&mut commands, &ui, language.get(Key::CheckpointSet));
+                }
+            }
+            let attempt_over = session.practice.is_attempt_over(&player.game);
+            if !attempt_over && !keys.just_pressed(RESET_KEY) {
+                continue;
+            }
+            if !player.game.locked_blocks().is_empty() {
+                session.attempts += 1;
+                if score.perfect_clears > 0 {
+                    session.perfect_clears += 1;
+                }
+            }
+            if attempt_over && session.practice == Practice::PerfectClear {
+                let key = if score.perfect_clears > 0 {
+                    Key::PerfectClear
+                } else {
+                    Key::Missed
+                };
+                toast::spawn_toast(&mut commands, &ui, language.get(key));
+            }
+            if attempt_over && session.practice == Practice::SecretGrade {
+                let grade = secret_grade_name(player.game.secret_grade()).unwrap_or("-");
+                toast::spawn_toast(
+                    &mut commands,
+                    &ui,
+                    language.format(Key::SecretGradeResult, &[&grade]),
+                );
+            }
+        }
+
+        session.history.clear();
+        // failed attempts are retried from the checkpoint, so is the reset unless the game is
+        // at the checkpoint already
+        let solved = session.practice.is_solved(&player.game);
+        let retried = session.checkpoint.clone().filter(|checkpoint| {
+            !solved && player.game.locked_blocks().len() > checkpoint.locked_blocks
+        });
+        if let Some(checkpoint) = retried {
+            player.game.restore(&checkpoint.snapshot);
+            player.input.reset();
+            // the locked points are drawn as the ones locked long ago
+            respawn_points(&mut commands, player_entity, &mut player, 0.);
+            session.locked_blocks = checkpoint.locked_blocks;
+            session.placement = Some(checkpoint);
+            continue;
+        }
+
+        // the player keeps the controls of the first game
+        let rules = GameRules {
+            auto_repeat: player.game.rules().auto_repeat,
+            ..rules.clone()
+        };
+        let game = session.practice.new_game(&rules);
+        restart_player(&mut commands, player_entity, &mut player, game);
+        session.locked_blocks = 0;
+        session.placement = None;
+        session.checkpoint = None;
+    }
+}
+
+/// Keeps the state of the game before every tick, they are restored backwards while the rewind
+/// key is held, so a misdrop can be undone. The state after a correct placement is kept for the
+/// checkpoint.
+pub fn rewind_practice(
+    mut commands: Commands,
+    session: Option<ResMut<PracticeSession>>,
+    mut players: Query<(Entity, &mut Player)>,
+    keys: Res<Input<KeyCode>>,
+    editor: Res<Editor>,
+    pause: Res<Pause>,
+    time: Res<Time>,
+) {
+    let mut session = match session {
+        Some(session) => session,
+        None => return,
+    };
+    // the game doesn't tick either
+    if editor.is_active() || pause.is_active() {
+        return;
+    }
+    let (player_entity, mut player) = match players.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    session.rewinding = keys.pressed(REWIND_KEY);
+    if session.rewinding {
+        if let Some(snapshot) = session.history.pop_back() {
+            player.game.restore(&snapshot);
+            respawn_points(
+                &mut commands,
+                player_entity,
+                &mut player,
+                time.seconds_since_startup(),
+            );
+        }
+    } else {
+        if session.history.len() >= (REWIND_SECONDS / TICK_DURATION) as usize {
+            session.history.pop_front();
+        }
+        let snapshot = player.game.snapshot();
+        let locked_blocks = player.game.locked_blocks().len();
+        if locked_blocks > session.locked_blocks {
+            session.placement = session
+                .practice
+                .is_correct_placement(&player.game)
+                .then(|| Checkpoint {
+                    snapshot: snapshot.clone(),
+                    locked_blocks,
+                });
+        }
+        session.history.push_back(snapshot);
+    }
+    session.locked_blocks = player.game.locked_blocks().len();
+}
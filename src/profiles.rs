@@ -0,0 +1,285 @@
+use bevy::{
+    input::gamepad::GamepadButtonType,
+    log::warn,
+    prelude::{Gamepad, KeyCode},
+};
+
+use crate::{
+    actions::{Action, ActionMap, ActionMaps, Binding},
+    game::AutoRepeat,
+    settings::Settings,
+};
+
+/// Keys which can be bound in the settings file, by their names.
+const KEYS: [KeyCode; 80] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Left,
+    KeyCode::Up,
+    KeyCode::Right,
+    KeyCode::Down,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Back,
+    KeyCode::Tab,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LAlt,
+    KeyCode::RAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Backslash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::LBracket,
+    KeyCode::RBracket,
+    KeyCode::Minus,
+    KeyCode::Equals,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::NumpadEnter,
+    KeyCode::NumpadAdd,
+    KeyCode::NumpadSubtract,
+    KeyCode::NumpadDecimal,
+];
+
+/// Gamepad buttons which can be bound in the settings file, by their names prefixed with
+/// `Gamepad`.
+const BUTTONS: [GamepadButtonType; 19] = [
+    GamepadButtonType::South,
+    GamepadButtonType::East,
+    GamepadButtonType::North,
+    GamepadButtonType::West,
+    GamepadButtonType::C,
+    GamepadButtonType::Z,
+    GamepadButtonType::LeftTrigger,
+    GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger,
+    GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select,
+    GamepadButtonType::Start,
+    GamepadButtonType::Mode,
+    GamepadButtonType::LeftThumb,
+    GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp,
+    GamepadButtonType::DPadDown,
+    GamepadButtonType::DPadLeft,
+    GamepadButtonType::DPadRight,
+];
+
+/// Named controls of a player.
+#[derive(Clone)]
+pub struct Profile {
+    pub name: String,
+    pub actions: ActionMap,
+    pub auto_repeat: AutoRepeat,
+}
+
+/// Control profiles and the ones selected by the players. Profiles are defined by
+/// `profile.<name>.<setting>=<value>` lines of the settings file, where the setting is an
+/// action like `move_left` bound to comma separated keys like `Left` and gamepad buttons like
+/// `GamepadSouth`, or `das` and `arr` in ticks. A profile starts from the bindings of the
+/// built-in profile of the same name, `arrows` or `wasd`, or from the arrow keys.
+pub struct Profiles {
+    profiles: Vec<Profile>,
+    /// Index of the profile of the single player, selected by the `profile` setting.
+    single: usize,
+    /// Indexes of the profiles of the versus players, selected by the `profile_p<number>`
+    /// settings.
+    versus: [usize; 2],
+}
+
+impl Profiles {
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut profiles = vec![
+            Profile {
+                name: "arrows".to_string(),
+                actions: ActionMap::arrows(),
+                auto_repeat: AutoRepeat::default(),
+            },
+            Profile {
+                name: "wasd".to_string(),
+                actions: ActionMap::wasd(),
+                auto_repeat: AutoRepeat::default(),
+            },
+        ];
+        let mut lines = settings
+            .with_prefix("profile.")
+            .filter_map(|(name, value)| {
+                let (profile, setting) = name.rsplit_once('.')?;
+                Some((profile, setting, value))
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        // actions bound by the settings replace the built-in bindings
+        let mut bound_actions = vec![];
+        for (profile_name, setting, value) in lines {
+            let index = match profiles.iter().position(|p| p.name == profile_name) {
+                Some(index) => index,
+                None => {
+                    profiles.push(Profile {
+                        name: profile_name.to_string(),
+                        actions: ActionMap::arrows(),
+                        auto_repeat: AutoRepeat::default(),
+                    });
+                    profiles.len() - 1
+                }
+            };
+            let profile = &mut profiles[index];
+            match setting {
+                "das" => {
+                    if let Ok(delay) = value.parse() {
+                        profile.auto_repeat.delay = delay;
+                    }
+                }
+                "arr" => {
+                    if let Ok(interval) = value.parse() {
+                        profile.auto_repeat.interval = interval;
+                    }
+                }
+                _ => {
+                    let action = match Action::ALL.into_iter().find(|a| a.name() == setting) {
+                        Some(action) => action,
+                        None => continue,
+                    };
+                    if !bound_actions.contains(&(index, action)) {
+                        profile.actions.unbind(action);
+                        bound_actions.push((index, action));
+                    }
+                    for binding in value.split(',').filter_map(parse_binding) {
+                        profile.actions.bind(action, binding);
+                    }
+                }
+            }
+        }
+
+        let selected = |setting: &str, default: &str| {
+            let name = settings.get(setting).unwrap_or(default);
+            profiles.iter().position(|p| p.name == name).unwrap_or(0)
+        };
+        let single = selected("profile", "arrows");
+        let versus = [
+            selected("profile_p1", "wasd"),
+            selected("profile_p2", "arrows"),
+        ];
+        Self {
+            profiles,
+            single,
+            versus,
+        }
+    }
+
+    pub fn single(&self) -> &Profile {
+        &self.profiles[self.single]
+    }
+
+    /// Returns the profile of the versus player.
+    pub fn versus(&self, player: usize) -> &Profile {
+        &self.profiles[self.versus[player]]
+    }
+
+    /// Returns the profile of the player of a single player game or a versus match.
+    pub fn player(&self, index: usize, versus: bool) -> &Profile {
+        if versus {
+            self.versus(index.min(self.versus.len() - 1))
+        } else {
+            self.single()
+        }
+    }
+
+    /// Selects the next profile of the single player and saves the selection.
+    pub fn next_single(&mut self, settings: &mut Settings) {
+        self.single = (self.single + 1) % self.profiles.len();
+        save_selection(settings, "profile", self.single());
+    }
+
+    /// Selects the next profile of the versus player and saves the selection.
+    pub fn next_versus(&mut self, player: usize, settings: &mut Settings) {
+        self.versus[player] = (self.versus[player] + 1) % self.profiles.len();
+        let setting = format!("profile_p{}", player + 1);
+        save_selection(settings, &setting, self.versus(player));
+    }
+
+    /// Returns the action maps of the selected profiles, versus players read the gamepad of
+    /// their index.
+    pub fn action_maps(&self) -> ActionMaps {
+        ActionMaps {
+            single: self.single().actions.clone(),
+            versus: (0..self.versus.len())
+                .map(|i| self.versus(i).actions.with_gamepad(Some(Gamepad::new(i))))
+                .collect(),
+        }
+    }
+}
+
+fn save_selection(settings: &mut Settings, setting: &str, profile: &Profile) {
+    // the selection still applies to this session
+    if let Err(e) = settings.set(setting, &profile.name) {
+        warn!("failed to save the control profile: {}", e);
+    }
+}
+
+/// Parses a key name like `Left` or a gamepad button name like `GamepadSouth`.
+fn parse_binding(name: &str) -> Option<Binding> {
+    let name = name.trim();
+    if let Some(button) = name.strip_prefix("Gamepad") {
+        return BUTTONS
+            .into_iter()
+            .find(|b| format!("{:?}", b) == button)
+            .map(Binding::Button);
+    }
+    KEYS.into_iter()
+        .find(|k| format!("{:?}", k) == name)
+        .map(Binding::Key)
+}
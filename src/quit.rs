@@ -0,0 +1,89 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    i18n::{Key, Language},
+    netplay::Netplay,
+    AppState, UI,
+};
+
+const QUIT_KEY: KeyCode = KeyCode::Escape;
+const CONFIRM_KEY: KeyCode = KeyCode::Return;
+const FONT_SIZE: f32 = 24.;
+const TEXT_COLOR: Color = Color::WHITE;
+const OVERLAY_COLOR: Color = Color::rgba(0., 0., 0., 0.8);
+
+/// Dialog asking whether to close the window, opened by the quit key. During local games the
+/// key pauses the game instead.
+#[derive(Default)]
+pub struct QuitDialog {
+    open: bool,
+}
+
+impl QuitDialog {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+#[derive(Component)]
+pub struct QuitOverlay;
+
+/// Runs before the other systems, which don't see the keys while the dialog is open.
+pub fn update_quit_dialog(
+    mut commands: Commands,
+    ui: Res<UI>,
+    language: Res<Language>,
+    mut dialog: ResMut<QuitDialog>,
+    mut keys: ResMut<Input<KeyCode>>,
+    state: Res<State<AppState>>,
+    netplay: Option<Res<Netplay>>,
+    overlays: Query<Entity, With<QuitOverlay>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if !dialog.open {
+        let paused_instead = *state.current() == AppState::Game && netplay.is_none();
+        if keys.just_pressed(QUIT_KEY) && !paused_instead {
+            dialog.open = true;
+            keys.clear();
+            spawn_overlay(&mut commands, &ui, &language);
+        }
+        return;
+    }
+
+    if keys.just_pressed(CONFIRM_KEY) {
+        exit.send(AppExit);
+    } else if keys.just_pressed(QUIT_KEY) {
+        dialog.open = false;
+        for overlay in overlays.iter() {
+            commands.entity(overlay).despawn_recursive();
+        }
+    }
+    keys.clear();
+}
+
+fn spawn_overlay(commands: &mut Commands, ui: &UI, language: &Language) {
+    let text = format!(
+        "{}\n\n{}",
+        language.get(Key::QuitPrompt),
+        language.get(Key::QuitHint)
+    );
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(OVERLAY_COLOR),
+            ..default()
+        })
+        .insert(QuitOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(text, ui.text_style(FONT_SIZE, TEXT_COLOR))
+                    .with_text_alignment(TextAlignment::CENTER),
+            );
+        });
+}
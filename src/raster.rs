@@ -0,0 +1,76 @@
+use bevy::prelude::Color;
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    game::{self, Game},
+    player_width, point_color, BG_COLOR, BORDER_COLOR, BORDER_SIZE, MARGIN_SIZE, UNIT_PX,
+    WINDOW_HEIGHT,
+};
+
+/// Draws boards of the games the same way they are laid out in the window. Text is not drawn.
+pub fn rasterize(games: &[&Game]) -> RgbaImage {
+    let window_width = games
+        .iter()
+        .map(|game| player_width(game.rules()))
+        .sum::<f32>();
+    let mut image =
+        RgbaImage::from_pixel(window_width as u32, WINDOW_HEIGHT as u32, to_rgba(BG_COLOR));
+
+    for (i, game) in games.iter().enumerate() {
+        let offset = player_width(game.rules()) * i as f32;
+        let board_width = game.board_width() as f32 * UNIT_PX;
+        let board_height = game::VISIBLE_BOARD_HEIGHT as f32 * UNIT_PX;
+
+        // board border and bg
+        fill_rect(
+            &mut image,
+            offset + MARGIN_SIZE,
+            MARGIN_SIZE,
+            board_width + BORDER_SIZE * 2.,
+            board_height + BORDER_SIZE * 2.,
+            BORDER_COLOR,
+        );
+        fill_rect(
+            &mut image,
+            offset + MARGIN_SIZE + BORDER_SIZE,
+            MARGIN_SIZE + BORDER_SIZE,
+            board_width,
+            board_height,
+            BG_COLOR,
+        );
+
+        // the view has the active blocks of both co-op pilots
+        for (point, (x, y)) in game.view().points() {
+            let row = match game.rules().gravity.visible_row(y) {
+                Some(row) => row,
+                None => continue,
+            };
+            fill_rect(
+                &mut image,
+                offset + MARGIN_SIZE + BORDER_SIZE + x as f32 * UNIT_PX,
+                MARGIN_SIZE + BORDER_SIZE + row as f32 * UNIT_PX,
+                UNIT_PX,
+                UNIT_PX,
+                point_color(&point),
+            );
+        }
+    }
+
+    image
+}
+
+fn to_rgba(color: Color) -> Rgba<u8> {
+    let [r, g, b, a] = color.as_rgba_f32();
+    Rgba([r, g, b, a].map(|c| (c.clamp(0., 1.) * 255.).round() as u8))
+}
+
+fn fill_rect(image: &mut RgbaImage, left: f32, top: f32, width: f32, height: f32, color: Color) {
+    let color = to_rgba(color);
+    let right = ((left + width) as u32).min(image.width());
+    let bottom = ((top + height) as u32).min(image.height());
+    for y in top as u32..bottom {
+        for x in left as u32..right {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
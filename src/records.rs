@@ -0,0 +1,284 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    game::{GameRules, SPRINT_LINES, SPRINT_SPLITS},
+    i18n::{Key, Language},
+    mode_name, paths,
+    player::Player,
+    stack_visibility::StackVisibility,
+    GameEntity, GameOptions, RunFinished, TICK_DURATION, UI,
+};
+
+/// Name of the records file in the data directory.
+pub const RECORDS_FILE: &str = "records.txt";
+const FONT_SIZE: f32 = 24.;
+const NEW_RECORD_COLOR: Color = Color::GOLD;
+
+/// Result of a finished single player game.
+pub struct Record {
+    pub mode: String,
+    pub score: u32,
+    pub lines: u32,
+    /// Duration of the game in seconds.
+    pub time: f32,
+    /// Day of the daily challenge if the game was one.
+    pub daily: Option<u64>,
+    /// Number of cleared lines with garbage, the result of the drill.
+    pub garbage_lines: u32,
+    /// Seconds it took to reach every reached split of the sprint.
+    pub splits: Vec<f32>,
+}
+
+impl Record {
+    /// Records are stored one per line as tab separated values.
+    fn to_line(&self) -> String {
+        let daily = self.daily.map_or("-".to_string(), |day| day.to_string());
+        let splits = if self.splits.is_empty() {
+            "-".to_string()
+        } else {
+            self.splits
+                .iter()
+                .map(|split| split.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.mode, self.score, self.lines, self.time, daily, self.garbage_lines, splits
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut values = line.split('\t');
+        let mode = values.next()?.to_string();
+        let score = values.next()?.parse().ok()?;
+        let lines = values.next()?.parse().ok()?;
+        let time = values.next()?.parse().ok()?;
+        let daily = match values.next()? {
+            "-" => None,
+            day => Some(day.parse().ok()?),
+        };
+        // missing in records saved before the drill was added
+        let garbage_lines = values.next().and_then(|v| v.parse().ok()).unwrap_or(0);
+        // missing in records saved before the sprint splits were added
+        let splits = match values.next() {
+            None | Some("-") => vec![],
+            Some(splits) => splits
+                .split(',')
+                .map(|split| split.parse().ok())
+                .collect::<Option<_>>()?,
+        };
+        Some(Self {
+            mode,
+            score,
+            lines,
+            time,
+            daily,
+            garbage_lines,
+            splits,
+        })
+    }
+
+    /// Returns the time of the finished sprint in seconds.
+    fn sprint_time(&self) -> Option<f32> {
+        (self.splits.len() == SPRINT_SPLITS).then(|| self.splits[SPRINT_SPLITS - 1])
+    }
+}
+
+/// Best results of the finished games of a mode, every combination of the rules is a mode of
+/// its own.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct PersonalBest {
+    score: u32,
+    lines: u32,
+    /// Fastest sprint in seconds.
+    sprint: Option<f32>,
+}
+
+impl PersonalBest {
+    /// Returns the best results of the records of the mode, `None` if no game of the mode was
+    /// finished yet.
+    pub fn of_mode(records: &[Record], mode: &str) -> Option<Self> {
+        records
+            .iter()
+            .filter(|r| r.mode == mode)
+            .fold(None, |best: Option<Self>, record| {
+                let best = best.unwrap_or_default();
+                Some(Self {
+                    score: best.score.max(record.score),
+                    lines: best.lines.max(record.lines),
+                    sprint: match (best.sprint, record.sprint_time()) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    },
+                })
+            })
+    }
+
+    pub fn describe(&self, language: Language) -> String {
+        let sprint = self
+            .sprint
+            .map_or("-".to_string(), |time| format!("{:.2}s", time));
+        format!(
+            "{} {}  {} {}  {} {}",
+            language.get(Key::Score),
+            self.score,
+            language.get(Key::Lines),
+            self.lines,
+            language.format(Key::Sprint, &[&SPRINT_LINES]),
+            sprint
+        )
+    }
+
+    /// Returns a line for every result of the record which beats these ones.
+    fn beaten_by(&self, record: &Record, language: Language) -> Vec<String> {
+        let mut lines = vec![];
+        if record.score > self.score {
+            lines.push(format!("{} {}", language.get(Key::Score), record.score));
+        }
+        if record.lines > self.lines {
+            lines.push(format!("{} {}", language.get(Key::Lines), record.lines));
+        }
+        match (record.sprint_time(), self.sprint) {
+            (Some(time), Some(best)) if time >= best => {}
+            (Some(time), _) => lines.push(format!(
+                "{} {:.2}s",
+                language.format(Key::Sprint, &[&SPRINT_LINES]),
+                time
+            )),
+            (None, _) => {}
+        }
+        lines
+    }
+}
+
+/// Highlighted text of the results which beat the personal best of the mode, shown over the
+/// board until the game is restarted or left.
+#[derive(Component)]
+pub struct NewRecordText;
+
+fn spawn_new_record_text(commands: &mut Commands, ui: &UI, text: &str) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(NewRecordText)
+        .insert(GameEntity)
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(text, ui.text_style(FONT_SIZE, NEW_RECORD_COLOR))
+                    .with_text_alignment(TextAlignment::CENTER),
+            );
+        });
+}
+
+pub fn hide_new_records(
+    mut commands: Commands,
+    players: Query<&Player>,
+    texts: Query<Entity, With<NewRecordText>>,
+) {
+    if players.iter().all(|p| !p.game.is_game_over()) {
+        for text in texts.iter() {
+            commands.entity(text).despawn_recursive();
+        }
+    }
+}
+
+/// Returns all saved records, unreadable lines are skipped.
+pub fn load_records() -> Vec<Record> {
+    fs::read_to_string(paths::data_path(RECORDS_FILE))
+        .map(|file| file.lines().filter_map(Record::from_line).collect())
+        .unwrap_or_default()
+}
+
+fn append_record(record: &Record) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(paths::data_path(RECORDS_FILE))?;
+    writeln!(file, "{}", record.to_line())
+}
+
+/// Returns the split times of the fastest finished sprint of the mode.
+pub fn best_sprint_splits(records: &[Record], mode: &str) -> Option<Vec<f32>> {
+    records
+        .iter()
+        .filter(|r| r.mode == mode)
+        .filter_map(|r| Some((r.sprint_time()?, r)))
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, r)| r.splits.clone())
+}
+
+/// Split times of the player's fastest sprint in the mode of the game, the pace of the game is
+/// compared with them.
+pub struct BestSplits(pub Vec<f32>);
+
+/// Returns the best score of the daily challenge of the given day.
+pub fn daily_best(records: &[Record], day: u64) -> Option<u32> {
+    records
+        .iter()
+        .filter(|r| r.daily == Some(day))
+        .map(|r| r.score)
+        .max()
+}
+
+/// Saves the records of the finished games, the results which beat the personal best of the
+/// mode are highlighted.
+pub fn save_finished_runs(
+    mut commands: Commands,
+    mut runs_finished: EventReader<RunFinished>,
+    players: Query<&Player>,
+    options: Res<GameOptions>,
+    rules: Res<GameRules>,
+    visibility: Res<StackVisibility>,
+    ui: Res<UI>,
+    language: Res<Language>,
+) {
+    for run in runs_finished.iter() {
+        if let Ok(player) = players.get(run.player) {
+            let score = player.game.score();
+            // results which the replay doesn't play again were tampered with
+            if let Err(e) = player.game.replay().verify_score(&score) {
+                error!("rejected the record: {}", e);
+                continue;
+            }
+            let record = Record {
+                mode: mode_name(&rules, &visibility),
+                score: score.points,
+                lines: score.lines,
+                time: score.ticks as f32 * TICK_DURATION,
+                daily: options.daily,
+                garbage_lines: score.garbage_lines,
+                splits: score
+                    .sprint_splits
+                    .iter()
+                    .map_while(|split| Some(*split? as f32 * TICK_DURATION))
+                    .collect(),
+            };
+            // the first game of the mode has nothing to beat
+            let best = PersonalBest::of_mode(&load_records(), &record.mode);
+            if let Err(e) = append_record(&record) {
+                error!("failed to save the record: {}", e);
+            }
+
+            let beaten = best.map_or(vec![], |best| best.beaten_by(&record, *language));
+            if !beaten.is_empty() {
+                let text = format!("{}\n{}", language.get(Key::NewRecord), beaten.join("\n"));
+                spawn_new_record_text(&mut commands, &ui, &text);
+            }
+        }
+    }
+}
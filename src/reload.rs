@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::{
+    accessibility::{HighContrast, ReduceMotion},
+    actions::ActionMaps,
+    announcer::Announcer,
+    audio::{SoundPack, Volume},
+    cells::CellStyle,
+    frame_rate::FrameRate,
+    i18n::{Key, Language},
+    profiles::Profiles,
+    settings::Settings,
+    toast::spawn_toast,
+    UI,
+};
+
+/// Seconds between the checks of the settings file.
+const POLL_INTERVAL: f64 = 1.;
+
+/// Sent when the settings file was edited and read again.
+pub struct SettingsReloaded;
+
+/// Reads the settings file again when it's edited while the game runs.
+pub fn watch_settings(
+    mut commands: Commands,
+    ui: Res<UI>,
+    time: Res<Time>,
+    mut settings: ResMut<Settings>,
+    mut last_poll: Local<f64>,
+    mut reloaded: EventWriter<SettingsReloaded>,
+) {
+    let now = time.seconds_since_startup();
+    if now - *last_poll < POLL_INTERVAL {
+        return;
+    }
+    *last_poll = now;
+    if !settings.is_outdated() {
+        return;
+    }
+    settings.reload();
+    reloaded.send(SettingsReloaded);
+    info!("settings reloaded");
+    // the toast is in the reloaded language
+    let language = Language::from_settings(&settings);
+    spawn_toast(&mut commands, &ui, language.get(Key::SettingsReloaded));
+}
+
+/// Applies the reloaded settings. The auto repeat of the profiles is a rule of the game, so it
+/// applies from the next game, the bindings apply right away.
+pub fn apply_reloaded_settings(
+    mut reloaded: EventReader<SettingsReloaded>,
+    settings: Res<Settings>,
+    mut profiles: ResMut<Profiles>,
+    mut action_maps: ResMut<ActionMaps>,
+    mut language: ResMut<Language>,
+    mut frame_rate: ResMut<FrameRate>,
+    mut reduce_motion: ResMut<ReduceMotion>,
+    mut high_contrast: ResMut<HighContrast>,
+    mut cell_style: ResMut<CellStyle>,
+    mut volume: ResMut<Volume>,
+    mut sound_pack: ResMut<SoundPack>,
+    mut announcer: ResMut<Announcer>,
+) {
+    if reloaded.iter().count() == 0 {
+        return;
+    }
+    *profiles = Profiles::from_settings(&settings);
+    *action_maps = profiles.action_maps();
+    *language = Language::from_settings(&settings);
+    *frame_rate = FrameRate::from_settings(&settings);
+    *reduce_motion = ReduceMotion::from_settings(&settings);
+    *high_contrast = HighContrast::from_settings(&settings);
+    volume.reload(&settings);
+    announcer.enabled = Announcer::from_settings(&settings).enabled;
+    // the textures and sounds are only loaded again when they change
+    let new_style = CellStyle::from_settings(&settings);
+    if *cell_style != new_style {
+        *cell_style = new_style;
+    }
+    let new_pack = SoundPack::from_settings(&settings);
+    if *sound_pack != new_pack {
+        *sound_pack = new_pack;
+    }
+}
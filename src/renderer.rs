@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{self, GameView, TickChange},
+    player::PointEntities,
+    spawn_point,
+    stack_visibility::LockedAt,
+    BlockComponent,
+};
+
+/// Keeps point entities of one player's board in sync with the game.
+pub struct BevyRenderer<'a, 'w, 's> {
+    pub commands: &'a mut Commands<'w, 's>,
+    pub player_entity: Entity,
+    /// Parent entity of the point entities.
+    pub board: Entity,
+    pub points: &'a mut PointEntities,
+    /// Entities and ids of the active block points before the tick.
+    pub block_points: Vec<(Entity, game::Id)>,
+    /// Time in seconds since startup, points locked now fade from this time.
+    pub now: f64,
+}
+
+impl BevyRenderer<'_, '_, '_> {
+    fn spawn_point(&mut self, point: &game::Point, point_pos: game::Position) -> Entity {
+        spawn_point(
+            self.commands,
+            self.player_entity,
+            point,
+            point_pos,
+            self.board,
+            self.points,
+        )
+    }
+
+    /// Spawns the active block points which have no entities yet, the co-op partner's block
+    /// keeps falling when a new block arrives.
+    fn spawn_active_block(&mut self, view: &GameView) {
+        for (point, point_pos) in &view.active_block {
+            if self.points.contains_key(&point.id) {
+                continue;
+            }
+            let point_entity = self.spawn_point(point, *point_pos);
+            self.commands
+                .entity(point_entity)
+                .insert(BlockComponent(view.active_block_id));
+        }
+    }
+}
+
+impl game::Renderer for BevyRenderer<'_, '_, '_> {
+    fn draw(&mut self, view: &GameView) {
+        for (_, point_entity) in self.points.drain() {
+            self.commands.entity(point_entity).despawn();
+        }
+        for (point, point_pos) in &view.board {
+            let point_entity = self.spawn_point(point, *point_pos);
+            self.commands
+                .entity(point_entity)
+                .insert(LockedAt(self.now));
+        }
+        self.spawn_active_block(view);
+    }
+
+    fn apply_changes(&mut self, view: &GameView, changes: &[TickChange]) {
+        for change in changes {
+            match *change {
+                TickChange::BlockLocked => {
+                    // only the points of the locked block are on the board
+                    let locked = self
+                        .block_points
+                        .iter()
+                        .filter(|(_, point_id)| view.board.iter().any(|(p, _)| p.id == *point_id));
+                    for &(point_entity, _) in locked {
+                        self.commands
+                            .entity(point_entity)
+                            .remove::<BlockComponent>()
+                            .insert(LockedAt(self.now));
+                    }
+                }
+                TickChange::NewBlock => self.spawn_active_block(view),
+                TickChange::BlockHeld => {
+                    // the points of the block put to the hold are neither on the board nor
+                    // in the active blocks anymore
+                    let held = self
+                        .points
+                        .keys()
+                        .filter(|&&point_id| view.points().all(|(p, _)| p.id != point_id))
+                        .copied()
+                        .collect::<Vec<_>>();
+                    for point_id in held {
+                        let point_entity = self.points.remove(&point_id).unwrap();
+                        self.commands.entity(point_entity).despawn();
+                    }
+                    self.spawn_active_block(view);
+                }
+                TickChange::PointRemoved(point_id) => {
+                    let point_entity = self.points.remove(&point_id).unwrap();
+                    self.commands.entity(point_entity).despawn();
+                }
+                TickChange::PointAdded(point_id) => {
+                    let (point, point_pos) = view.point(point_id).unwrap();
+                    let point_entity = self.spawn_point(&point, point_pos);
+                    self.commands
+                        .entity(point_entity)
+                        .insert(LockedAt(self.now));
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{Game, GameSnapshot, Replay},
+    i18n::{Key, Language},
+    pause::Pause,
+    player::Player,
+    respawn_points, TICK_DURATION, UI,
+};
+
+const PLAY_KEY: KeyCode = KeyCode::Space;
+const SLOWER_KEY: KeyCode = KeyCode::Down;
+const FASTER_KEY: KeyCode = KeyCode::Up;
+const STEP_BACK_KEY: KeyCode = KeyCode::Comma;
+const STEP_KEY: KeyCode = KeyCode::Period;
+const JUMP_BACK_KEY: KeyCode = KeyCode::Left;
+const JUMP_KEY: KeyCode = KeyCode::Right;
+/// Seconds of the replay skipped by a jump.
+const JUMP_SECONDS: f32 = 5.;
+/// Playback speeds from the slowest, the last one is the real time.
+const SPEEDS: [f32; 3] = [0.25, 0.5, 1.];
+/// Ticks between the snapshots of the played replay, seeking backward plays the game on from
+/// the last snapshot before the position.
+const SNAPSHOT_TICKS: usize = 60;
+const FONT_SIZE: f32 = 14.;
+const TEXT_COLOR: Color = Color::WHITE;
+const MARGIN_SIZE: f32 = 4.;
+
+/// Plays the replay given by `--replay <path>` on the board of the single player instead
+/// of the player's input. The playback can be paused, slowed down, stepped by ticks and moved
+/// forward and backward.
+pub struct ReplayViewer {
+    replay: Replay,
+    /// Ticks of the replay played so far, fractional while the playback is slowed down.
+    position: f32,
+    /// Ticks played by the shown game, `None` until the player's game is replaced by the
+    /// replayed one.
+    shown: Option<usize>,
+    /// Index of the playback speed in `SPEEDS`.
+    speed: usize,
+    paused: bool,
+    /// Snapshots of the shown game taken every `SNAPSHOT_TICKS` ticks.
+    snapshots: Vec<GameSnapshot>,
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            position: 0.,
+            shown: None,
+            speed: SPEEDS.len() - 1,
+            paused: false,
+            snapshots: vec![],
+        }
+    }
+
+    /// Returns the number of whole ticks played.
+    fn tick(&self) -> usize {
+        self.position as usize
+    }
+
+    fn seek(&mut self, position: f32) {
+        self.position = position.clamp(0., self.replay.len() as f32);
+    }
+
+    fn control(&mut self, keys: &Input<KeyCode>) {
+        let jump = JUMP_SECONDS / TICK_DURATION;
+        if keys.just_pressed(PLAY_KEY) {
+            // the finished replay is played again from the start
+            if self.paused && self.tick() == self.replay.len() {
+                self.seek(0.);
+            }
+            self.paused = !self.paused;
+        } else if keys.just_pressed(SLOWER_KEY) {
+            self.speed = self.speed.saturating_sub(1);
+        } else if keys.just_pressed(FASTER_KEY) {
+            self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+        } else if keys.just_pressed(STEP_KEY) {
+            // steps pause the playback on whole ticks
+            self.paused = true;
+            self.seek((self.tick() + 1) as f32);
+        } else if keys.just_pressed(STEP_BACK_KEY) {
+            self.paused = true;
+            self.seek(self.tick().saturating_sub(1) as f32);
+        } else if keys.just_pressed(JUMP_KEY) {
+            self.seek(self.position + jump);
+        } else if keys.just_pressed(JUMP_BACK_KEY) {
+            self.seek(self.position - jump);
+        }
+    }
+
+    /// Plays the shown game from the tick `from` up to the tick `to`, snapshots of the ticks
+    /// played for the first time are kept.
+    fn play(&mut self, game: &mut Game, from: usize, to: usize) {
+        for tick in from..to {
+            if tick % SNAPSHOT_TICKS == 0 && tick / SNAPSHOT_TICKS == self.snapshots.len() {
+                self.snapshots.push(game.snapshot());
+            }
+            if let Some(input) = self.replay.input(tick) {
+                game.tick(&input);
+            }
+        }
+    }
+
+    fn describe(&self, language: Language) -> String {
+        let mut text = language.format(
+            Key::ReplayPosition,
+            &[
+                &format_time(self.tick()),
+                &format_time(self.replay.len()),
+                &SPEEDS[self.speed],
+            ],
+        );
+        if self.paused {
+            text += &format!("  {}", language.get(Key::Paused));
+        }
+        format!("{}\n{}", text, language.get(Key::ReplayHint))
+    }
+}
+
+fn format_time(ticks: usize) -> String {
+    let time = ticks as f32 * TICK_DURATION;
+    format!("{}:{:05.2}", time as u32 / 60, time % 60.)
+}
+
+/// Position of the playback and the controls of the viewer.
+#[derive(Component)]
+pub struct ViewerText;
+
+pub fn spawn_viewer_text(commands: &mut Commands, ui: &UI) -> Entity {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section("", ui.text_style(FONT_SIZE, TEXT_COLOR)).with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(MARGIN_SIZE),
+                    bottom: Val::Px(MARGIN_SIZE),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ViewerText)
+        .id()
+}
+
+pub fn update_replay_viewer(
+    mut commands: Commands,
+    viewer: Option<ResMut<ReplayViewer>>,
+    keys: Res<Input<KeyCode>>,
+    pause: Res<Pause>,
+    time: Res<Time>,
+    language: Res<Language>,
+    mut players: Query<(Entity, &mut Player)>,
+    mut texts: Query<&mut Text, With<ViewerText>>,
+) {
+    // the viewer is created on entering the game with a viewed replay
+    let mut viewer = match viewer {
+        Some(viewer) => viewer,
+        None => return,
+    };
+    if !pause.is_active() {
+        viewer.control(&keys);
+        if !viewer.paused {
+            let ticks = time.delta_seconds() / TICK_DURATION * SPEEDS[viewer.speed];
+            viewer.seek(viewer.position + ticks);
+            viewer.paused = viewer.tick() == viewer.replay.len();
+        }
+    }
+
+    // the players are spawned by commands of the game setup
+    let (player_entity, mut player) = match players.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let tick = viewer.tick();
+    if viewer.shown != Some(tick) {
+        let from = match viewer.shown {
+            Some(shown) if shown < tick => shown,
+            // earlier states are restored from the last snapshot before them
+            _ => match viewer.snapshots.get(tick / SNAPSHOT_TICKS) {
+                Some(snapshot) => {
+                    player.game.restore(snapshot);
+                    tick / SNAPSHOT_TICKS * SNAPSHOT_TICKS
+                }
+                None => {
+                    player.game = viewer.replay.new_game();
+                    0
+                }
+            },
+        };
+        viewer.play(&mut player.game, from, tick);
+        respawn_points(
+            &mut commands,
+            player_entity,
+            &mut player,
+            time.seconds_since_startup(),
+        );
+        viewer.shown = Some(tick);
+    }
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = viewer.describe(*language);
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame,
+};
+
+use crate::{
+    game::{ClearKind, GameRules, Replay, ReplayAnalysis},
+    mode_name, paths,
+    pb_ghost::PbGhost,
+    player::Player,
+    raster,
+    records::BestSplits,
+    stack_visibility::StackVisibility,
+    RunFinished, TICK_DURATION,
+};
+
+pub const REPLAYS_DIR: &str = "replays";
+/// Every n-th tick of the replay is exported as a frame.
+const EXPORT_FRAME_STEP: usize = 3;
+const EXPORT_FRAME_DELAY_MS: u32 = 50;
+
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Gif,
+    /// Numbered PNG files in a directory.
+    PngSequence,
+}
+
+/// Saves the replay to a timestamped file in the replays directory.
+pub fn save_replay(replay: &Replay, mode: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(paths::data_path(REPLAYS_DIR))?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = paths::data_path(REPLAYS_DIR).join(format!("{}-{}.replay", mode, timestamp));
+    write_replay(&path, replay, mode)?;
+    Ok(path)
+}
+
+fn write_replay(path: &Path, replay: &Replay, mode: &str) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    replay.clone().with_preset(mode).write(&mut writer)?;
+    writer.flush()
+}
+
+/// Returns path of the replay of the fastest sprint of the mode, which is played by the
+/// personal best ghost.
+pub fn best_replay_path(mode: &str) -> PathBuf {
+    paths::data_path(REPLAYS_DIR).join(format!("{}-best.replay", mode))
+}
+
+/// Returns whether the game finished the sprint faster than the best one of the mode. The first
+/// finished sprint after the ghost was added replaces the missing replay of an earlier best.
+fn is_best_sprint(sprint_ticks: Option<u32>, best: &[f32], path: &Path) -> bool {
+    let time = match sprint_ticks {
+        Some(ticks) => ticks as f32 * TICK_DURATION,
+        None => return false,
+    };
+    match best.last() {
+        Some(&best) => time < best || !path.exists(),
+        None => true,
+    }
+}
+
+pub fn load_replay(path: &Path) -> io::Result<Replay> {
+    Replay::read(BufReader::new(File::open(path)?))
+}
+
+/// Rewrites the replay file in the current format.
+pub fn upgrade_replay(path: &Path) -> io::Result<()> {
+    let replay = load_replay(path)?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    replay.write(&mut writer)?;
+    writer.flush()
+}
+
+/// Analyzes the replay file and returns the report of its statistics, one per line.
+pub fn analyze_replay(path: &Path) -> io::Result<String> {
+    let analysis = ReplayAnalysis::read(BufReader::new(File::open(path)?))?;
+    let ticks_per_second = (1. / TICK_DURATION).round() as u32;
+    let pps = analysis
+        .pps_timeline(ticks_per_second)
+        .iter()
+        .map(|pps| format!("{:.2}", pps))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let heights = analysis
+        .stack_heights()
+        .map(|(_, height)| height.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let clear_counts = analysis.clear_counts();
+    let clears = [
+        ClearKind::Single,
+        ClearKind::Double,
+        ClearKind::Triple,
+        ClearKind::Tetris,
+        ClearKind::TSpinSingle,
+        ClearKind::TSpinDouble,
+        ClearKind::TSpinTriple,
+    ]
+    .iter()
+    .map(|kind| format!("{:?}={}", kind, clear_counts.get(kind).unwrap_or(&0)))
+    .collect::<Vec<_>>()
+    .join(" ");
+    Ok(format!(
+        "score: {}\nlines: {}\nblocks: {}\nfinesse faults: {}\nclears: {}\n\
+        pps by second: {}\nstack height by block: {}\n",
+        analysis.score.points,
+        analysis.score.lines,
+        analysis.blocks.len(),
+        analysis.finesse_faults(),
+        clears,
+        pps,
+        heights
+    ))
+}
+
+/// Plays the replay without the window and writes its frames next to the replay file. Returns
+/// path of the exported GIF file or PNG directory.
+pub fn export_replay(path: &Path, format: ExportFormat) -> Result<PathBuf, String> {
+    let replay = load_replay(path).map_err(|e| e.to_string())?;
+    let output = match format {
+        ExportFormat::Gif => path.with_extension("gif"),
+        ExportFormat::PngSequence => path.with_extension(""),
+    };
+
+    let mut gif = match format {
+        ExportFormat::Gif => {
+            let file = File::create(&output).map_err(|e| e.to_string())?;
+            let mut encoder = GifEncoder::new(BufWriter::new(file));
+            encoder
+                .set_repeat(Repeat::Infinite)
+                .map_err(|e| e.to_string())?;
+            Some(encoder)
+        }
+        ExportFormat::PngSequence => {
+            fs::create_dir_all(&output).map_err(|e| e.to_string())?;
+            None
+        }
+    };
+
+    let mut game = replay.new_game();
+    let mut frame_index = 0;
+    for (tick, input) in replay.inputs().enumerate() {
+        game.tick(&input);
+        if tick % EXPORT_FRAME_STEP != 0 && tick + 1 != replay.len() {
+            continue;
+        }
+
+        let image = raster::rasterize(&[&game]);
+        match gif.as_mut() {
+            Some(encoder) => {
+                let delay = Delay::from_numer_denom_ms(EXPORT_FRAME_DELAY_MS, 1);
+                encoder
+                    .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                    .map_err(|e| e.to_string())?;
+            }
+            None => {
+                let frame_path = output.join(format!("{:06}.png", frame_index));
+                image.save(frame_path).map_err(|e| e.to_string())?;
+            }
+        }
+        frame_index += 1;
+    }
+
+    Ok(output)
+}
+
+pub fn save_finished_runs(
+    mut commands: Commands,
+    mut runs_finished: EventReader<RunFinished>,
+    players: Query<&Player>,
+    rules: Res<GameRules>,
+    visibility: Res<StackVisibility>,
+    mut best_splits: Option<ResMut<BestSplits>>,
+) {
+    for run in runs_finished.iter() {
+        if let Ok(player) = players.get(run.player) {
+            let mode = mode_name(&rules, &visibility);
+            let replay = player.game.replay();
+            match save_replay(replay, &mode) {
+                Ok(path) => info!("replay saved to {}", path.display()),
+                Err(e) => error!("failed to save replay: {}", e),
+            }
+
+            let score = player.game.score();
+            let best = best_splits
+                .as_ref()
+                .map_or(&[][..], |best| best.0.as_slice());
+            let best_path = best_replay_path(&mode);
+            if !is_best_sprint(score.sprint_ticks, best, &best_path) {
+                continue;
+            }
+            match write_replay(&best_path, replay, &mode) {
+                Ok(()) => {
+                    // the restarted game is compared with the new best
+                    if let Some(best_splits) = &mut best_splits {
+                        best_splits.0 = score
+                            .sprint_splits
+                            .iter()
+                            .map_while(|split| Some((*split)? as f32 * TICK_DURATION))
+                            .collect();
+                    }
+                    if let Some(ghost) = PbGhost::load(&mode) {
+                        commands.insert_resource(ghost);
+                    }
+                }
+                Err(e) => error!("failed to save the personal best replay: {}", e),
+            }
+        }
+    }
+}
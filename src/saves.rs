@@ -0,0 +1,506 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    window::WindowCloseRequested,
+};
+use image::imageops;
+
+use crate::{
+    game::{self, Game, Replay},
+    i18n::{Key, Language},
+    menu::MenuEntity,
+    mode_name,
+    netplay::Netplay,
+    paths,
+    player::Player,
+    raster, respawn_points,
+    settings::Settings,
+    stack_visibility::StackVisibility,
+    toast, units_to_px, AppState, GameOptions, BORDER_SIZE, MARGIN_SIZE, UI,
+};
+
+pub const SAVES_DIR: &str = "saves";
+const SAVE_EXTENSION: &str = "replay";
+/// Slot of the game suspended when the window was closed, it's offered in the menu.
+const AUTOSAVE_NAME: &str = "autosave";
+const SUSPEND_KEY: KeyCode = KeyCode::F5;
+const BACK_KEY: KeyCode = KeyCode::Tab;
+const MAX_SLOTS: usize = 4;
+const MAX_NAME_LEN: usize = 16;
+const THUMBNAIL_SCALE: f32 = 0.15;
+const FONT_SIZE: f32 = 14.;
+const TEXT_COLOR: Color = Color::GRAY;
+const SELECTED_COLOR: Color = Color::WHITE;
+
+/// Game suspended by the player, which is saved to a slot or resumed.
+pub struct SuspendedGame(Replay);
+
+/// Game loaded from a slot, which replaces the game of the player when the game starts.
+pub struct ResumedGame(pub Replay);
+
+/// Suspended game saved under a name, with its preview.
+struct SaveSlot {
+    name: String,
+    replay: Replay,
+    score: u32,
+    thumbnail: Handle<Image>,
+}
+
+/// Save slots listed on the screen. The selected slot is loaded, or overwritten if a game is
+/// suspended, in which case the first entry is a new slot if there's space for it.
+pub struct SaveScreen {
+    slots: Vec<SaveSlot>,
+    selected: usize,
+    /// Name of the new slot typed by the player.
+    name: String,
+}
+
+impl SaveScreen {
+    fn has_new_slot(&self, suspended: bool) -> bool {
+        suspended && self.slots.len() < MAX_SLOTS
+    }
+
+    /// Returns the slot of the selected entry, `None` if the new slot is selected.
+    fn selected_slot(&self, suspended: bool) -> Option<&SaveSlot> {
+        let offset = self.has_new_slot(suspended) as usize;
+        self.selected
+            .checked_sub(offset)
+            .and_then(|i| self.slots.get(i))
+    }
+
+    fn entry_count(&self, suspended: bool) -> usize {
+        self.slots.len() + self.has_new_slot(suspended) as usize
+    }
+}
+
+/// Text of the entry at the index in the list of the save screen.
+#[derive(Component)]
+pub struct EntryText(usize);
+
+fn slot_path(name: &str) -> PathBuf {
+    paths::data_path(SAVES_DIR).join(format!("{}.{}", name, SAVE_EXTENSION))
+}
+
+/// Loads the slots sorted by name, slots which can't be read are skipped.
+fn load_slots(images: &mut Assets<Image>) -> Vec<SaveSlot> {
+    let entries = match fs::read_dir(paths::data_path(SAVES_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut slots = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != SAVE_EXTENSION {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_string();
+            let replay = match File::open(&path).and_then(|f| Replay::read(BufReader::new(f))) {
+                Ok(replay) => replay,
+                Err(e) => {
+                    warn!("failed to load the save {}: {}", path.display(), e);
+                    return None;
+                }
+            };
+            let game = resume(&replay);
+            Some(SaveSlot {
+                name,
+                score: game.score().points,
+                thumbnail: images.add(thumbnail(&game)),
+                replay,
+            })
+        })
+        .collect::<Vec<_>>();
+    slots.sort_by(|a, b| a.name.cmp(&b.name));
+    slots
+}
+
+fn save_slot(name: &str, replay: &Replay) -> io::Result<()> {
+    fs::create_dir_all(paths::data_path(SAVES_DIR))?;
+    let mut writer = BufWriter::new(File::create(slot_path(name))?);
+    replay.write(&mut writer)?;
+    writer.flush()
+}
+
+/// Plays the recorded input, so the game is in the state it was suspended in.
+fn resume(replay: &Replay) -> Game {
+    replay.game_at(replay.len())
+}
+
+/// Draws the board of the game.
+fn thumbnail(game: &Game) -> Image {
+    let image = raster::rasterize(&[game]);
+    let width = units_to_px(game.board_width()) + BORDER_SIZE * 2.;
+    let height = units_to_px(game::VISIBLE_BOARD_HEIGHT) + BORDER_SIZE * 2.;
+    let board = imageops::crop_imm(
+        &image,
+        MARGIN_SIZE as u32,
+        MARGIN_SIZE as u32,
+        width as u32,
+        height as u32,
+    )
+    .to_image();
+    Image::new(
+        Extent3d {
+            width: board.width(),
+            height: board.height(),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        board.into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Returns the replay of the single player game in progress, which can be resumed from it.
+fn suspendable_replay(
+    players: &Query<&Player>,
+    rules: &game::GameRules,
+    visibility: &StackVisibility,
+    options: &GameOptions,
+    online: bool,
+) -> Option<Replay> {
+    // practice games are prepared, so they can't be played again from the seed, replays don't
+    // record the co-op partner, campaign stages are played in one go and viewed replays are
+    // saved already
+    if options.practice.is_some()
+        || options.campaign.is_some()
+        || options.viewed_replay.is_some()
+        || online
+        || rules.coop
+    {
+        return None;
+    }
+    let player = players
+        .get_single()
+        .ok()
+        .filter(|p| p.bot.is_none() && !p.game.is_game_over())?;
+    Some(
+        player
+            .game
+            .replay()
+            .clone()
+            .with_preset(&mode_name(rules, visibility)),
+    )
+}
+
+/// Suspends the single player game, which is then saved on the save screen.
+pub fn suspend_game(
+    mut commands: Commands,
+    mut keys: ResMut<Input<KeyCode>>,
+    players: Query<&Player>,
+    rules: Res<game::GameRules>,
+    visibility: Res<StackVisibility>,
+    options: Res<GameOptions>,
+    netplay: Option<Res<Netplay>>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if !keys.just_pressed(SUSPEND_KEY) {
+        return;
+    }
+    let replay =
+        match suspendable_replay(&players, &rules, &visibility, &options, netplay.is_some()) {
+            Some(replay) => replay,
+            None => return,
+        };
+    commands.insert_resource(SuspendedGame(replay));
+    keys.clear();
+    state.set(AppState::Saves).unwrap();
+}
+
+/// Saves the game in progress or the suspended one to the autosave slot and the language
+/// switched in the menu to the settings when the window is closed.
+pub fn autosave_on_exit(
+    mut exits: EventReader<AppExit>,
+    mut close_requests: EventReader<WindowCloseRequested>,
+    players: Query<&Player>,
+    suspended: Option<Res<SuspendedGame>>,
+    rules: Res<game::GameRules>,
+    visibility: Res<StackVisibility>,
+    options: Res<GameOptions>,
+    netplay: Option<Res<Netplay>>,
+    language: Res<Language>,
+    mut settings: ResMut<Settings>,
+) {
+    // both are read, so neither is left for the next frame
+    let exiting = exits.iter().count() > 0;
+    if close_requests.iter().count() == 0 && !exiting {
+        return;
+    }
+
+    let replay = match &suspended {
+        Some(suspended) => Some(suspended.0.clone()),
+        None => suspendable_replay(&players, &rules, &visibility, &options, netplay.is_some()),
+    };
+    if let Some(replay) = replay {
+        match save_slot(AUTOSAVE_NAME, &replay) {
+            Ok(()) => info!("game saved to the {} slot", AUTOSAVE_NAME),
+            Err(e) => error!("failed to save the game: {}", e),
+        }
+    }
+    if settings.get("language") != Some(language.code()) {
+        if let Err(e) = settings.set("language", language.code()) {
+            error!("failed to save the language: {}", e);
+        }
+    }
+}
+
+pub fn has_autosave() -> bool {
+    slot_path(AUTOSAVE_NAME).exists()
+}
+
+pub fn load_autosave() -> io::Result<Replay> {
+    Replay::read(BufReader::new(File::open(slot_path(AUTOSAVE_NAME))?))
+}
+
+/// Deletes the autosave slot once its game is resumed.
+pub fn remove_autosave() -> io::Result<()> {
+    fs::remove_file(slot_path(AUTOSAVE_NAME))
+}
+
+/// Replaces the game of the player by the loaded one.
+pub fn resume_saved_game(
+    mut commands: Commands,
+    resumed: Option<Res<ResumedGame>>,
+    mut players: Query<(Entity, &mut Player)>,
+    time: Res<Time>,
+) {
+    let resumed = match resumed {
+        Some(resumed) => resumed,
+        None => return,
+    };
+    // the players are spawned by commands of the game setup
+    let (player_entity, mut player) = match players.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    player.game = resume(&resumed.0);
+    respawn_points(
+        &mut commands,
+        player_entity,
+        &mut player,
+        time.seconds_since_startup(),
+    );
+    commands.remove_resource::<ResumedGame>();
+}
+
+pub fn setup_save_screen(
+    mut commands: Commands,
+    ui: Res<UI>,
+    language: Res<Language>,
+    mut images: ResMut<Assets<Image>>,
+    suspended: Option<Res<SuspendedGame>>,
+) {
+    let screen = SaveScreen {
+        slots: load_slots(&mut images),
+        selected: 0,
+        name: String::new(),
+    };
+    let suspended = suspended.is_some();
+    let text_style = ui.text_style(FONT_SIZE, TEXT_COLOR);
+    // doubles boards are twice as wide
+    let thumbnail_size = |board_width| {
+        Size::new(
+            Val::Px((units_to_px(board_width) + BORDER_SIZE * 2.) * THUMBNAIL_SCALE),
+            Val::Px((units_to_px(game::VISIBLE_BOARD_HEIGHT) + BORDER_SIZE * 2.) * THUMBNAIL_SCALE),
+        )
+    };
+    let hint = if suspended {
+        Key::SaveHint
+    } else {
+        Key::LoadHint
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: UiRect::all(Val::Px(MARGIN_SIZE)),
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(MenuEntity)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                format!("{}\n", language.get(Key::SavedGames)),
+                ui.text_style(FONT_SIZE, SELECTED_COLOR),
+            ));
+            if screen.has_new_slot(suspended) {
+                parent
+                    .spawn_bundle(TextBundle::from_section("", text_style.clone()))
+                    .insert(EntryText(0));
+            }
+            let offset = screen.has_new_slot(suspended) as usize;
+            for (i, slot) in screen.slots.iter().enumerate() {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            margin: UiRect::new(
+                                Val::Px(0.),
+                                Val::Px(0.),
+                                Val::Px(BORDER_SIZE * 2.),
+                                Val::Px(BORDER_SIZE * 2.),
+                            ),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: UiColor(Color::NONE),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn_bundle(ImageBundle {
+                            style: Style {
+                                size: thumbnail_size(slot.replay.rules().board_width()),
+                                margin: UiRect::new(
+                                    Val::Px(0.),
+                                    Val::Px(MARGIN_SIZE / 2.),
+                                    Val::Px(0.),
+                                    Val::Px(0.),
+                                ),
+                                ..default()
+                            },
+                            image: UiImage(slot.thumbnail.clone()),
+                            ..default()
+                        });
+                        row.spawn_bundle(TextBundle::from_section(
+                            format!(
+                                "{}\n{}\n{} {}",
+                                slot.name,
+                                slot.replay.preset(),
+                                language.get(Key::Score),
+                                slot.score
+                            ),
+                            text_style.clone(),
+                        ))
+                        .insert(EntryText(offset + i));
+                    });
+            }
+            parent.spawn_bundle(TextBundle::from_section(
+                format!("\n{}", language.get(hint)),
+                text_style.clone(),
+            ));
+        });
+    commands.insert_resource(screen);
+}
+
+pub fn update_save_screen(
+    mut commands: Commands,
+    ui: Res<UI>,
+    language: Res<Language>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut screen: ResMut<SaveScreen>,
+    suspended: Option<Res<SuspendedGame>>,
+    rules: Res<game::GameRules>,
+    visibility: Res<StackVisibility>,
+    mut texts: Query<(&EntryText, &mut Text)>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let is_suspended = suspended.is_some();
+    let entry_count = screen.entry_count(is_suspended);
+    if screen.has_new_slot(is_suspended) && screen.selected == 0 {
+        for c in chars.iter() {
+            if c.char == '\u{8}' {
+                screen.name.pop();
+            } else if (c.char.is_ascii_alphanumeric() || c.char == '-' || c.char == '_')
+                && screen.name.len() < MAX_NAME_LEN
+            {
+                screen.name.push(c.char);
+            }
+        }
+    } else {
+        chars.clear();
+    }
+
+    if keys.just_pressed(KeyCode::Up) && screen.selected > 0 {
+        screen.selected -= 1;
+    } else if keys.just_pressed(KeyCode::Down) && screen.selected + 1 < entry_count {
+        screen.selected += 1;
+    } else if keys.just_pressed(KeyCode::Delete) {
+        if let Some(slot) = screen.selected_slot(is_suspended) {
+            match fs::remove_file(slot_path(&slot.name)) {
+                Ok(()) => state.restart().unwrap(),
+                Err(e) => error!("failed to delete the save {}: {}", slot.name, e),
+            }
+        }
+    } else if keys.just_pressed(KeyCode::Return) {
+        match &suspended {
+            Some(suspended) => {
+                let name = match screen.selected_slot(true) {
+                    // the selected slot is overwritten
+                    Some(slot) => slot.name.clone(),
+                    None if !screen.name.is_empty() => screen.name.clone(),
+                    None => return,
+                };
+                match save_slot(&name, &suspended.0) {
+                    Ok(()) => {
+                        toast::spawn_toast(
+                            &mut commands,
+                            &ui,
+                            language.format(Key::Saved, &[&name]),
+                        );
+                        commands.remove_resource::<SuspendedGame>();
+                        state.set(AppState::Menu).unwrap();
+                    }
+                    Err(e) => {
+                        error!("failed to save the game: {}", e);
+                        toast::spawn_toast(&mut commands, &ui, language.get(Key::SaveFailed));
+                    }
+                }
+            }
+            None => {
+                if let Some(slot) = screen.selected_slot(false) {
+                    // the game is played by the rules of the mode it was saved in
+                    let mode = mode_name(&rules, &visibility);
+                    if slot.replay.preset() != mode {
+                        toast::spawn_toast(
+                            &mut commands,
+                            &ui,
+                            language.format(Key::OtherModeSave, &[&slot.replay.preset()]),
+                        );
+                        return;
+                    }
+                    commands.insert_resource(ResumedGame(slot.replay.clone()));
+                    state.set(AppState::Game).unwrap();
+                }
+            }
+        }
+    } else if keys.just_pressed(BACK_KEY) {
+        match &suspended {
+            Some(suspended) => {
+                commands.insert_resource(ResumedGame(suspended.0.clone()));
+                commands.remove_resource::<SuspendedGame>();
+                state.set(AppState::Game).unwrap();
+            }
+            None => state.set(AppState::Menu).unwrap(),
+        }
+    }
+    // keys typed to the name shouldn't trigger other hotkeys
+    keys.clear();
+
+    for (entry, mut text) in texts.iter_mut() {
+        let section = &mut text.sections[0];
+        section.style.color = if entry.0 == screen.selected {
+            SELECTED_COLOR
+        } else {
+            TEXT_COLOR
+        };
+        if entry.0 == 0 && screen.has_new_slot(is_suspended) {
+            section.value = language.format(Key::NewSave, &[&screen.name]);
+        }
+    }
+}
+
+pub fn cleanup_save_screen(mut commands: Commands) {
+    commands.remove_resource::<SaveScreen>();
+}
@@ -0,0 +1,67 @@
+use bevy::{prelude::*, window::WindowBackendScaleFactorChanged, winit::WinitWindows};
+
+use crate::{cli::Cli, WINDOW_HEIGHT};
+
+/// Part of the monitor height the window may take when the scale is chosen automatically.
+const AUTO_MAX_HEIGHT_RATIO: f64 = 0.5;
+
+/// Scale of everything drawn to the window. Layout constants are in logical pixels of the
+/// unscaled window, the scale multiplies them on top of the monitor scale factor.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UiScale {
+    /// Largest whole scale which keeps the window within the part of the monitor height.
+    Auto,
+    Fixed(f64),
+}
+
+impl UiScale {
+    pub fn from_cli(cli: &Cli) -> Self {
+        match cli.ui_scale {
+            Some(scale) if scale > 0. => UiScale::Fixed(scale),
+            _ => UiScale::Auto,
+        }
+    }
+
+    /// Returns the scale for a monitor `monitor_height` logical pixels high.
+    fn scale(&self, monitor_height: Option<f64>) -> f64 {
+        match *self {
+            UiScale::Auto => monitor_height
+                .map(|height| {
+                    (height * AUTO_MAX_HEIGHT_RATIO / WINDOW_HEIGHT as f64)
+                        .floor()
+                        .max(1.)
+                })
+                .unwrap_or(1.),
+            UiScale::Fixed(scale) => scale,
+        }
+    }
+}
+
+/// Overrides scale factor of the window so its contents are scaled by the UI scale, the
+/// logical size of the window stays the same. Applied on the first run and whenever the window
+/// is moved to a monitor with a different scale factor.
+pub fn apply_ui_scale(
+    ui_scale: Res<UiScale>,
+    mut windows: ResMut<Windows>,
+    winit_windows: NonSend<WinitWindows>,
+    mut scale_changes: EventReader<WindowBackendScaleFactorChanged>,
+    mut applied: Local<bool>,
+) {
+    if scale_changes.iter().count() == 0 && *applied {
+        return;
+    }
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+    *applied = true;
+    let backend_scale = window.backend_scale_factor();
+    let monitor_height = winit_windows
+        .get_window(window.id())
+        .and_then(|w| w.current_monitor())
+        .map(|monitor| monitor.size().height as f64 / backend_scale);
+
+    let scale = ui_scale.scale(monitor_height);
+    info!("ui scale {}, monitor scale factor {}", scale, backend_scale);
+    window.set_scale_factor_override(Some(backend_scale * scale));
+}
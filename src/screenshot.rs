@@ -0,0 +1,53 @@
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    i18n::{Key, Language},
+    paths,
+    player::Player,
+    raster, toast, UI,
+};
+
+const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+pub const SCREENSHOTS_DIR: &str = "screenshots";
+
+/// Saves boards of all players to a timestamped PNG file. Bevy doesn't provide a way to read the
+/// rendered frame back, so the boards are drawn again from the game state by `raster::rasterize`.
+pub fn take_screenshot(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    ui: Res<UI>,
+    language: Res<Language>,
+    players: Query<&Player>,
+) {
+    if !keys.just_pressed(SCREENSHOT_KEY) || players.is_empty() {
+        return;
+    }
+
+    let mut players = players.iter().collect::<Vec<_>>();
+    players.sort_by_key(|p| p.index);
+    let games = players.iter().map(|p| &p.game).collect::<Vec<_>>();
+    let image = raster::rasterize(&games);
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = paths::data_path(SCREENSHOTS_DIR).join(format!("tetris-{}.png", timestamp));
+
+    let message = match fs::create_dir_all(paths::data_path(SCREENSHOTS_DIR))
+        .map_err(|e| e.to_string())
+        .and_then(|_| image.save(&path).map_err(|e| e.to_string()))
+    {
+        Ok(()) => language.format(Key::Saved, &[&path.display()]),
+        Err(e) => {
+            error!("failed to save screenshot {}: {}", path.display(), e);
+            language.get(Key::ScreenshotFailed).to_string()
+        }
+    };
+    toast::spawn_toast(&mut commands, &ui, message);
+}
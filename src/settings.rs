@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Name of the settings file in the config directory.
+pub const SETTINGS_FILE: &str = "settings.txt";
+
+/// Player settings stored as `name=value` lines in the settings file, every setting can be
+/// overridden by a `--set name=value` argument.
+#[derive(Default)]
+pub struct Settings {
+    values: HashMap<String, String>,
+    /// Settings given by the command line, they override the file.
+    overrides: Vec<(String, String)>,
+    path: PathBuf,
+    /// Modification time of the settings file when it was last read or written.
+    modified: Option<SystemTime>,
+}
+
+impl Settings {
+    /// Loads the settings from the file at `path`, which is `SETTINGS_FILE` in the config
+    /// directory unless `--config` gives another one.
+    pub fn load(overrides: &[(String, String)], path: PathBuf) -> Self {
+        let modified = file_modified(&path);
+        let file = fs::read_to_string(&path).unwrap_or_default();
+        let file_values = file.lines().filter_map(|line| line.split_once('='));
+        let values = file_values
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .chain(overrides.iter().cloned())
+            .collect();
+        Self {
+            values,
+            overrides: overrides.to_vec(),
+            path,
+            modified,
+        }
+    }
+
+    /// Returns whether the settings file was changed by someone else since it was read.
+    pub fn is_outdated(&self) -> bool {
+        file_modified(&self.path) != self.modified
+    }
+
+    /// Reads the settings file again, the arguments still override it.
+    pub fn reload(&mut self) {
+        *self = Self::load(&self.overrides, self.path.clone());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .get(name)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Returns the settings whose names start with `prefix`, the prefix is stripped from the
+    /// returned names.
+    pub fn with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.values
+            .iter()
+            .filter_map(move |(name, value)| Some((name.strip_prefix(prefix)?, value.as_str())))
+    }
+
+    /// Changes the setting and saves it to the settings file, other lines of the file are kept.
+    pub fn set(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.values.insert(name.to_string(), value.to_string());
+        let outdated = self.is_outdated();
+        let file = fs::read_to_string(&self.path).unwrap_or_default();
+        let line = format!("{}={}", name, value);
+        let mut found = false;
+        let mut lines = file
+            .lines()
+            .map(|l| match l.split_once('=') {
+                Some((n, _)) if n.trim() == name => {
+                    found = true;
+                    line.clone()
+                }
+                _ => l.to_string(),
+            })
+            .collect::<Vec<_>>();
+        if !found {
+            lines.push(line);
+        }
+        fs::write(&self.path, lines.join("\n") + "\n")?;
+        // the own changes don't need to be reloaded, unlike the earlier changes of others
+        if !outdated {
+            self.modified = file_modified(&self.path);
+        }
+        Ok(())
+    }
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
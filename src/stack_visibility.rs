@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+
+use crate::cli::Cli;
+
+/// How points locked to the board are shown.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "debug-ui", derive(bevy_inspector_egui::Inspectable))]
+pub enum StackVisibility {
+    /// Locked points are always visible.
+    Visible,
+    /// Locked points disappear as soon as they are locked.
+    Invisible,
+    /// Locked points fade out during the given number of seconds.
+    Fade(f32),
+}
+
+impl Default for StackVisibility {
+    fn default() -> Self {
+        StackVisibility::Visible
+    }
+}
+
+impl StackVisibility {
+    pub fn from_cli(cli: &Cli) -> Self {
+        if cli.invisible {
+            StackVisibility::Invisible
+        } else if cli.fade {
+            StackVisibility::Fade(1.)
+        } else {
+            StackVisibility::Visible
+        }
+    }
+
+    /// Returns opacity of the point which was locked `locked_for` seconds ago.
+    /// The whole stack is revealed when the game is over.
+    pub fn point_alpha(&self, locked_for: f32, game_over: bool) -> f32 {
+        if game_over {
+            return 1.;
+        }
+        match *self {
+            StackVisibility::Visible => 1.,
+            StackVisibility::Invisible => 0.,
+            StackVisibility::Fade(duration) => (1. - locked_for / duration).clamp(0., 1.),
+        }
+    }
+}
+
+/// Time (in seconds since startup) when the point was locked to the board.
+#[derive(Component)]
+#[cfg_attr(feature = "debug-ui", derive(bevy_inspector_egui::Inspectable))]
+pub struct LockedAt(pub f64);
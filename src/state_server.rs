@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use tungstenite::{Message, WebSocket};
+
+use crate::{
+    bot_protocol::state_json,
+    cli::Cli,
+    game::{Gravity, Move, TickChange},
+    player::Player,
+    TickChanged,
+};
+
+/// How long a client's thread waits for a message of the client before it sends the queued
+/// states.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// WebSocket server enabled by `--state-server=<address>`, every connected client gets a JSON
+/// message with the state of a player's game and the changes of its last ticks, so overlays,
+/// coaching tools and bots can watch the running game. Every client is served by a background
+/// thread, so a slow client doesn't stall the game.
+pub struct StateServer {
+    /// Queues of the messages of the connected clients.
+    clients: Arc<Mutex<Vec<mpsc::Sender<String>>>>,
+}
+
+impl StateServer {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        let address = cli.state_server.as_ref()?;
+        let listener = match TcpListener::bind(address) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("failed to start the state server on {}: {}", address, e);
+                return None;
+            }
+        };
+        info!("state server listening on {}", address);
+
+        let clients = Arc::new(Mutex::new(vec![]));
+        let accepted = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let socket = stream
+                    .map_err(|e| e.to_string())
+                    .and_then(|stream| tungstenite::accept(stream).map_err(|e| e.to_string()));
+                let mut socket = match socket {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        warn!("failed to accept a state server client: {}", e);
+                        continue;
+                    }
+                };
+                if let Err(e) = socket.get_mut().set_read_timeout(Some(POLL_INTERVAL)) {
+                    warn!("failed to set up a state server client: {}", e);
+                    continue;
+                }
+                let (sender, receiver) = mpsc::channel::<String>();
+                accepted.lock().unwrap().push(sender);
+                // the receiver is dropped with the disconnected client, which removes its queue
+                thread::spawn(move || serve_client(&mut socket, &receiver));
+            }
+        });
+        Some(Self { clients })
+    }
+
+    fn has_clients(&self) -> bool {
+        !self.clients.lock().unwrap().is_empty()
+    }
+
+    fn broadcast(&self, message: &str) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| client.send(message.to_string()).is_ok());
+    }
+}
+
+/// Sends the queued messages to the client until it disconnects. The socket is read in between,
+/// which answers the pings and the close of the client, its messages are ignored.
+fn serve_client(socket: &mut WebSocket<TcpStream>, receiver: &mpsc::Receiver<String>) {
+    loop {
+        match socket.read() {
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => return,
+        }
+        loop {
+            match receiver.try_recv() {
+                Ok(message) => {
+                    if socket.send(Message::text(message)).is_err() {
+                        return;
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// Returns the JSON object of the change, its `type` is the name of the `TickChange` variant
+/// followed by the values of the variant. Rows are counted from the top visible row like in the
+/// state.
+fn change_json(change: &TickChange, gravity: Gravity) -> String {
+    let (change_type, values) = match change {
+        TickChange::BlockShifted(direction) => {
+            let direction = match direction {
+                Move::Left => "left",
+                Move::Right => "right",
+                Move::Fall => "down",
+            };
+            ("BlockShifted", format!(",\"direction\":\"{}\"", direction))
+        }
+        TickChange::BlockRotated { kick } => ("BlockRotated", format!(",\"kick\":{}", kick)),
+        TickChange::SoftDropped => ("SoftDropped", String::new()),
+        TickChange::BlockLanded => ("BlockLanded", String::new()),
+        TickChange::BlockLocked => ("BlockLocked", String::new()),
+        TickChange::NewBlock => ("NewBlock", String::new()),
+        TickChange::BlockHeld => ("BlockHeld", String::new()),
+        TickChange::RowsCleared { rows, clear_kind } => {
+            let top = gravity.visible_rows().start as i32;
+            let rows = rows
+                .iter()
+                .map(|&y| (y as i32 - top).to_string())
+                .collect::<Vec<_>>();
+            (
+                "RowsCleared",
+                format!(
+                    ",\"rows\":[{}],\"clear_kind\":\"{:?}\"",
+                    rows.join(","),
+                    clear_kind
+                ),
+            )
+        }
+        TickChange::PointRemoved(id) => ("PointRemoved", format!(",\"point\":{}", id)),
+        TickChange::GameOver => ("GameOver", String::new()),
+        TickChange::ItemGranted(item) => ("ItemGranted", format!(",\"item\":\"{:?}\"", item)),
+        TickChange::ItemUsed(item) => ("ItemUsed", format!(",\"item\":\"{:?}\"", item)),
+        TickChange::PointAdded(id) => ("PointAdded", format!(",\"point\":{}", id)),
+        TickChange::GarbageSent(lines) => ("GarbageSent", format!(",\"lines\":{}", lines)),
+    };
+    format!("{{\"type\":\"{}\"{}}}", change_type, values)
+}
+
+/// Sends the state of every game which ticked since the last frame with its changes.
+pub fn broadcast_state(
+    server: Res<StateServer>,
+    players: Query<(Entity, &Player)>,
+    mut changes: EventReader<TickChanged>,
+    mut sent_ticks: Local<HashMap<Entity, u32>>,
+) {
+    let changes = changes.iter().collect::<Vec<_>>();
+    if !server.has_clients() {
+        return;
+    }
+    for (player_entity, player) in players.iter() {
+        let ticks = player.game.score().ticks;
+        if sent_ticks.insert(player_entity, ticks) == Some(ticks) {
+            continue;
+        }
+        let events = changes
+            .iter()
+            .filter(|event| event.player == player_entity)
+            .map(|event| change_json(&event.change, player.game.rules().gravity))
+            .collect::<Vec<_>>();
+        server.broadcast(&format!(
+            "{{\"player\":{},\"events\":[{}],\"state\":{}}}",
+            player.index,
+            events.join(","),
+            state_json(&player.game)
+        ));
+    }
+}
@@ -0,0 +1,145 @@
+use std::{fs, io};
+
+use bevy::prelude::*;
+
+use crate::{
+    game::SPRINT_LINES,
+    i18n::{Key, Language},
+    menu::spawn_menu_text,
+    paths,
+    player::Player,
+    AppState, RunFinished, TICK_DURATION, UI,
+};
+
+/// Name of the stats file in the data directory.
+pub const STATS_FILE: &str = "stats.txt";
+
+/// Career statistics accumulated over all single player games.
+#[derive(Default)]
+pub struct LifetimeStats {
+    games: u32,
+    lines: u32,
+    tetrises: u32,
+    t_spins: u32,
+    /// Total time played in seconds.
+    playtime: f32,
+    /// Fastest time to clear `SPRINT_LINES` lines in seconds.
+    best_sprint: Option<f32>,
+}
+
+impl LifetimeStats {
+    /// Loads stats from the stats file which has a `name=value` pair per line. Missing values
+    /// are left at zero.
+    pub fn load() -> Self {
+        let mut stats = Self::default();
+        let file = fs::read_to_string(paths::data_path(STATS_FILE)).unwrap_or_default();
+        for (name, value) in file.lines().filter_map(|line| line.split_once('=')) {
+            match name {
+                "games" => stats.games = value.parse().unwrap_or_default(),
+                "lines" => stats.lines = value.parse().unwrap_or_default(),
+                "tetrises" => stats.tetrises = value.parse().unwrap_or_default(),
+                "t_spins" => stats.t_spins = value.parse().unwrap_or_default(),
+                "playtime" => stats.playtime = value.parse().unwrap_or_default(),
+                "best_sprint" => stats.best_sprint = value.parse().ok(),
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let mut file = format!(
+            "games={}\nlines={}\ntetrises={}\nt_spins={}\nplaytime={}\n",
+            self.games, self.lines, self.tetrises, self.t_spins, self.playtime
+        );
+        if let Some(best_sprint) = self.best_sprint {
+            file += &format!("best_sprint={}\n", best_sprint);
+        }
+        fs::write(paths::data_path(STATS_FILE), file)
+    }
+
+    fn to_text(&self, language: Language) -> String {
+        let playtime = self.playtime as u32;
+        let best_sprint = self
+            .best_sprint
+            .map_or("-".to_string(), |time| format!("{:.2}s", time));
+        let rows = [
+            (language.get(Key::Games).to_string(), self.games.to_string()),
+            (language.get(Key::Lines).to_string(), self.lines.to_string()),
+            (
+                language.get(Key::Tetrises).to_string(),
+                self.tetrises.to_string(),
+            ),
+            (
+                language.get(Key::TSpins).to_string(),
+                self.t_spins.to_string(),
+            ),
+            (
+                language.get(Key::Playtime).to_string(),
+                format!(
+                    "{}:{:02}:{:02}",
+                    playtime / 3600,
+                    playtime / 60 % 60,
+                    playtime % 60
+                ),
+            ),
+            (language.format(Key::Sprint, &[&SPRINT_LINES]), best_sprint),
+        ];
+        let rows = rows
+            .iter()
+            .map(|(label, value)| format!("{:<10}{:>8}\n", label, value))
+            .collect::<String>();
+        format!(
+            "{}\n\n{}\n{}",
+            language.get(Key::Statistics),
+            rows,
+            language.get(Key::PressAnyKey)
+        )
+    }
+}
+
+pub fn update_stats(
+    mut runs_finished: EventReader<RunFinished>,
+    players: Query<&Player>,
+    mut stats: ResMut<LifetimeStats>,
+) {
+    let mut changed = false;
+    for run in runs_finished.iter() {
+        if let Ok(player) = players.get(run.player) {
+            let score = player.game.score();
+            stats.games += 1;
+            stats.lines += score.lines;
+            stats.tetrises += score.tetrises;
+            stats.t_spins += score.t_spins;
+            stats.playtime += score.ticks as f32 * TICK_DURATION;
+            if let Some(sprint_ticks) = score.sprint_ticks {
+                let time = sprint_ticks as f32 * TICK_DURATION;
+                stats.best_sprint = Some(stats.best_sprint.map_or(time, |best| best.min(time)));
+            }
+            changed = true;
+        }
+    }
+
+    if changed {
+        if let Err(e) = stats.save() {
+            error!("failed to save stats: {}", e);
+        }
+    }
+}
+
+pub fn setup_stats_screen(
+    mut commands: Commands,
+    ui: Res<UI>,
+    language: Res<Language>,
+    stats: Res<LifetimeStats>,
+) {
+    spawn_menu_text(&mut commands, &ui, &stats.to_text(*language));
+}
+
+pub fn update_stats_screen(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keys.get_just_pressed().next().is_some() {
+        // don't let the menu handle the same key press
+        keys.clear();
+        state.set(AppState::Menu).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    game::{Game, GameRules},
+    i18n::{Key, Language},
+    mode_name, paths,
+    player::Player,
+    stack_visibility::StackVisibility,
+    toast::spawn_toast,
+    versus::Match,
+    TICK_DURATION, UI,
+};
+
+pub const SUMMARIES_DIR: &str = "summaries";
+const JSON_KEY: KeyCode = KeyCode::J;
+const CSV_KEY: KeyCode = KeyCode::C;
+
+fn ticks_to_secs(ticks: u32) -> f32 {
+    ticks as f32 * TICK_DURATION
+}
+
+/// Returns statistics of the game and all its locked blocks as a JSON object.
+fn to_json(game: &Game, mode: &str) -> String {
+    let score = game.score();
+    let blocks = game
+        .locked_blocks()
+        .iter()
+        .map(|block| {
+            format!(
+                "    {{\"type\": \"{:?}\", \"spawn_time\": {}, \"lock_time\": {}, \
+                \"rows\": {}, \"t_spin\": {}, \"score\": {}}}",
+                block.block_type,
+                ticks_to_secs(block.spawn_tick),
+                ticks_to_secs(block.lock_tick),
+                block.rows,
+                block.t_spin,
+                block.points
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    format!(
+        "{{\n  \"mode\": \"{}\",\n  \"score\": {},\n  \"lines\": {},\n  \"level\": {},\n  \
+        \"time\": {},\n  \"tetrises\": {},\n  \"t_spins\": {},\n  \"blocks\": [\n{}\n  ]\n}}\n",
+        mode,
+        score.points,
+        score.lines,
+        score.level(),
+        ticks_to_secs(score.ticks),
+        score.tetrises,
+        score.t_spins,
+        blocks
+    )
+}
+
+/// Returns a row for every locked block, totals of the game can be taken from the last row.
+fn to_csv(game: &Game) -> String {
+    let mut csv = "type,spawn_time,lock_time,rows,t_spin,score\n".to_string();
+    for block in game.locked_blocks() {
+        csv += &format!(
+            "{:?},{},{},{},{},{}\n",
+            block.block_type,
+            ticks_to_secs(block.spawn_tick),
+            ticks_to_secs(block.lock_tick),
+            block.rows,
+            block.t_spin,
+            block.points
+        );
+    }
+    csv
+}
+
+fn save_summary(contents: &str, mode: &str, extension: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(paths::data_path(SUMMARIES_DIR))?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path =
+        paths::data_path(SUMMARIES_DIR).join(format!("{}-{}.{}", mode, timestamp, extension));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Exports statistics of the finished single player game when the export key is pressed on
+/// the game over screen.
+pub fn export_summary(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    ui: Res<UI>,
+    language: Res<Language>,
+    players: Query<&Player>,
+    versus_match: Option<Res<Match>>,
+    rules: Res<GameRules>,
+    visibility: Res<StackVisibility>,
+) {
+    let json = keys.just_pressed(JSON_KEY);
+    if versus_match.is_some() || !(json || keys.just_pressed(CSV_KEY)) {
+        return;
+    }
+
+    let mode = mode_name(&rules, &visibility);
+    for player in players
+        .iter()
+        .filter(|p| p.bot.is_none() && p.game.is_game_over())
+    {
+        let saved = if json {
+            save_summary(&to_json(&player.game, &mode), &mode, "json")
+        } else {
+            save_summary(&to_csv(&player.game), &mode, "csv")
+        };
+        let message = match saved {
+            Ok(path) => language.format(Key::Saved, &[&path.display()]),
+            Err(e) => {
+                error!("failed to save summary: {}", e);
+                language.get(Key::SummaryExportFailed).to_string()
+            }
+        };
+        spawn_toast(&mut commands, &ui, message);
+    }
+}
@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+
+use crate::UI;
+
+const TOAST_DURATION: f32 = 2.;
+const FONT_SIZE: f32 = 14.;
+const TEXT_COLOR: Color = Color::WHITE;
+const MARGIN_SIZE: f32 = 4.;
+
+/// Short message shown at the bottom of the window.
+#[derive(Component)]
+pub struct Toast(Timer);
+
+pub fn spawn_toast(commands: &mut Commands, ui: &UI, text: impl Into<String>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(text, ui.text_style(FONT_SIZE, TEXT_COLOR)).with_style(
+                Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(MARGIN_SIZE),
+                        bottom: Val::Px(MARGIN_SIZE),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ),
+        )
+        .insert(Toast(Timer::from_seconds(TOAST_DURATION, false)));
+}
+
+pub fn update_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut Toast)>,
+) {
+    for (entity, mut toast) in toasts.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{Game, GameRules, NEXT_BLOCKS_COUNT},
+    i18n::{Key, Language},
+    netplay::Netplay,
+    player::Player,
+    restart_player, UI,
+};
+
+const INTERMISSION_DURATION: f32 = 3.;
+const FONT_SIZE: f32 = 24.;
+const TEXT_COLOR: Color = Color::WHITE;
+const MAX_SPEED_LEVEL: u32 = 10;
+const GARBAGE_PERCENTS: [u32; 7] = [25, 50, 75, 100, 125, 150, 200];
+
+/// Settings of a single player which make the game harder or easier for them, chosen before the
+/// match.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Handicap {
+    pub speed_level: u32,
+    pub garbage_percent: u32,
+    pub preview_count: usize,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        let rules = GameRules::new();
+        Self {
+            speed_level: rules.speed_level,
+            garbage_percent: rules.garbage_percent,
+            preview_count: rules.preview_count,
+        }
+    }
+}
+
+impl Handicap {
+    /// Returns `rules` of the player's game.
+    pub fn apply(&self, rules: &GameRules) -> GameRules {
+        GameRules {
+            speed_level: self.speed_level,
+            garbage_percent: self.garbage_percent,
+            preview_count: self.preview_count,
+            ..rules.clone()
+        }
+    }
+
+    pub fn next_speed_level(&mut self) {
+        self.speed_level = self.speed_level % MAX_SPEED_LEVEL + 1;
+    }
+
+    pub fn next_garbage_percent(&mut self) {
+        let index = GARBAGE_PERCENTS
+            .iter()
+            .position(|&p| p == self.garbage_percent)
+            .map_or(0, |i| (i + 1) % GARBAGE_PERCENTS.len());
+        self.garbage_percent = GARBAGE_PERCENTS[index];
+    }
+
+    pub fn next_preview_count(&mut self) {
+        self.preview_count = (self.preview_count + 1) % (NEXT_BLOCKS_COUNT + 1);
+    }
+
+    pub fn describe(&self, language: Language) -> String {
+        language.format(
+            Key::Handicap,
+            &[
+                &self.speed_level,
+                &self.garbage_percent,
+                &self.preview_count,
+            ],
+        )
+    }
+}
+
+enum MatchState {
+    Playing,
+    /// Pause between two games of the set.
+    Intermission(Timer),
+    Finished,
+}
+
+/// Best-of-N set of versus games.
+pub struct Match {
+    state: MatchState,
+    games_to_win: usize,
+    games_played: usize,
+    wins: Vec<usize>,
+    kos: Vec<usize>,
+    /// Handicap of every player, applied to each of their games.
+    handicaps: Vec<Handicap>,
+}
+
+impl Match {
+    /// Creates the match, players without a handicap in `handicaps` play with the default one.
+    pub fn new(player_count: usize, best_of: usize, handicaps: &[Handicap]) -> Self {
+        Self {
+            state: MatchState::Playing,
+            games_to_win: best_of / 2 + 1,
+            games_played: 0,
+            wins: vec![0; player_count],
+            kos: vec![0; player_count],
+            handicaps: (0..player_count)
+                .map(|i| handicaps.get(i).copied().unwrap_or_default())
+                .collect(),
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, MatchState::Playing)
+    }
+
+    /// Returns the player who won the match, if it's finished.
+    pub fn winner(&self) -> Option<usize> {
+        self.wins.iter().position(|&w| w >= self.games_to_win)
+    }
+
+    /// Registers finished game, `winner` is `None` if all players topped out at once.
+    fn game_finished(&mut self, winner: Option<usize>, topped_out: &[usize]) {
+        self.games_played += 1;
+        for i in 0..self.kos.len() {
+            if !topped_out.contains(&i) {
+                self.kos[i] += topped_out.len();
+            }
+        }
+        if let Some(winner) = winner {
+            self.wins[winner] += 1;
+        }
+
+        self.state = if self.winner().is_some() {
+            MatchState::Finished
+        } else {
+            MatchState::Intermission(Timer::from_seconds(INTERMISSION_DURATION, false))
+        };
+    }
+
+    fn score(&self) -> String {
+        self.wins
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>()
+            .join(" - ")
+    }
+
+    fn kos(&self, language: Language) -> String {
+        self.kos
+            .iter()
+            .enumerate()
+            .map(|(i, ko)| language.format(Key::Kos, &[&(i + 1), ko]))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn message(&self, last_winner: Option<usize>, language: Language) -> String {
+        match (&self.state, self.winner()) {
+            (MatchState::Finished, Some(winner)) => format!(
+                "{}\n{}\n\n{}",
+                language.format(Key::MatchWinner, &[&(winner + 1)]),
+                self.score(),
+                self.kos(language)
+            ),
+            _ => {
+                let result = match last_winner {
+                    Some(winner) => language.format(Key::GameWinner, &[&(winner + 1)]),
+                    None => language.get(Key::Draw).to_string(),
+                };
+                format!(
+                    "{}\n{}\n{}",
+                    language.format(Key::GameNumber, &[&self.games_played]),
+                    result,
+                    self.score()
+                )
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MatchText;
+
+pub fn spawn_match_text(commands: &mut Commands, ui: &UI) -> Entity {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section("", ui.text_style(FONT_SIZE, TEXT_COLOR))
+                        .with_text_alignment(TextAlignment::CENTER),
+                )
+                .insert(MatchText);
+        })
+        .id()
+}
+
+pub fn update_match(
+    mut commands: Commands,
+    versus_match: Option<ResMut<Match>>,
+    mut players: Query<(Entity, &mut Player)>,
+    rules: Res<GameRules>,
+    netplay: Option<Res<Netplay>>,
+    language: Res<Language>,
+    time: Res<Time>,
+    mut texts: Query<&mut Text, With<MatchText>>,
+) {
+    // the match is created on entering the game
+    let mut versus_match = match versus_match {
+        Some(versus_match) => versus_match,
+        None => return,
+    };
+
+    match &mut versus_match.state {
+        MatchState::Playing => {
+            let topped_out = players
+                .iter()
+                .filter(|(_, p)| p.game.is_game_over())
+                .map(|(_, p)| p.index)
+                .collect::<Vec<_>>();
+            if topped_out.is_empty() {
+                return;
+            }
+
+            let mut survivors = players
+                .iter()
+                .filter(|(_, p)| !p.game.is_game_over())
+                .map(|(_, p)| p.index);
+            let winner = match (survivors.next(), survivors.next()) {
+                (Some(winner), None) => Some(winner),
+                (None, _) => None,
+                // the game goes on while there are at least two players left
+                (Some(_), Some(_)) => return,
+            };
+
+            versus_match.game_finished(winner, &topped_out);
+            let message = versus_match.message(winner, *language);
+            for mut text in texts.iter_mut() {
+                text.sections[0].value = message.clone();
+            }
+        }
+        MatchState::Intermission(timer) => {
+            if timer.tick(time.delta()).finished() {
+                for (player_entity, mut player) in players.iter_mut() {
+                    // the player keeps the controls of the first game
+                    let rules = GameRules {
+                        auto_repeat: player.game.rules().auto_repeat,
+                        ..versus_match.handicaps[player.index].apply(&rules)
+                    };
+                    // games of the network peers must be the same
+                    let game = match &netplay {
+                        Some(netplay) => {
+                            Game::with_seed(rules, netplay.game_seed(versus_match.games_played))
+                        }
+                        None => Game::with_rules(rules),
+                    };
+                    restart_player(&mut commands, player_entity, &mut player, game);
+                }
+                for mut text in texts.iter_mut() {
+                    text.sections[0].value.clear();
+                }
+                versus_match.state = MatchState::Playing;
+            }
+        }
+        MatchState::Finished => {}
+    }
+}